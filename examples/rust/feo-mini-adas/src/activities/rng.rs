@@ -0,0 +1,89 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Seedable pseudo-random number generation for the example activities
+
+use feo::prelude::ActivityId;
+use std::env;
+use std::hash::{BuildHasher as _, Hasher as _, RandomState};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Seed shared by all activities of the process
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// Whether activities sleep for a random time in each step
+static SLEEP: AtomicBool = AtomicBool::new(true);
+
+/// Environment variable with the seed of the activities, making their outputs reproducible
+///
+/// Without a seed, a random one is chosen.
+pub const SEED_VAR: &str = "FEO_EXAMPLE_SEED";
+
+/// Environment variable disabling the random sleep in each step of the activities if set to
+/// `0`, e.g. for fast tests
+pub const SLEEP_VAR: &str = "FEO_EXAMPLE_SLEEP";
+
+/// Set the seed and the random sleep of the activities from the environment
+///
+/// See [`SEED_VAR`] and [`SLEEP_VAR`]. Must be called before creating activities.
+pub fn init_from_env() {
+    if let Ok(seed) = env::var(SEED_VAR) {
+        let seed = seed
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid {SEED_VAR}: {seed}"));
+        SEED.set(seed).expect("seed already set");
+    }
+    if let Ok(sleep) = env::var(SLEEP_VAR) {
+        SLEEP.store(sleep != "0", Ordering::Relaxed);
+    }
+}
+
+/// Check whether activities sleep for a random time in each step
+pub(crate) fn sleep_enabled() -> bool {
+    SLEEP.load(Ordering::Relaxed)
+}
+
+/// Seed of the process
+fn seed() -> u64 {
+    *SEED.get_or_init(|| RandomState::new().build_hasher().finish())
+}
+
+/// Xorshift pseudo-random number generator
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a generator from the given seed
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero, spread the seed with splitmix64 instead
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+        Self(if state == 0 { 1 } else { state })
+    }
+
+    /// Create a generator for the activity with the given id from the seed of the process
+    pub fn for_activity(activity_id: ActivityId) -> Self {
+        Self::new(seed() ^ (usize::from(activity_id) as u64).rotate_left(32))
+    }
+
+    /// Generate the next pseudo-random number
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Generate a pseudo-random number in the specified range, including its end
+    pub fn gen_range(&mut self, range: Range<i64>) -> i64 {
+        let rand = (self.next_u64() % (i64::MAX as u64)) as i64;
+        rand % (range.end - range.start + 1) + range.start
+    }
+}
@@ -9,8 +9,8 @@ use crate::activities::components::{
 use crate::activities::messages::{BrakeInstruction, CameraImage, RadarScan, Scene, Steering};
 use configuration::topics::Direction;
 use feo::activity::ActivityIdAndBuilder;
-use feo::com::{init_topic, TopicHandle};
-use feo::configuration::topics::TopicSpecification;
+use feo::com::{init_topic_with_qos, ComError, TopicHandle};
+use feo::configuration::topics::{TopicQos, TopicSpecification, TopicWiring};
 use feo::prelude::*;
 use std::collections::HashMap;
 
@@ -150,7 +150,7 @@ pub fn activity_dependencies() -> ActivityDependencies {
     dependencies.into()
 }
 
-pub fn initialize_topics() -> Vec<TopicHandle> {
+pub fn initialize_topics() -> Result<Vec<TopicHandle>, ComError> {
     topic_dependencies()
         .into_iter()
         .map(|spec| {
@@ -165,38 +165,66 @@ pub fn initialize_topics() -> Vec<TopicHandle> {
                 .filter(|(_, dir)| matches!(dir, Direction::Incoming))
                 .count();
 
-            (spec.init_fn)(writers, readers)
+            (spec.init_fn)(writers, readers, spec.qos)
         })
         .collect()
 }
 
+/// Wiring of all topics, e.g. to describe the configuration
+pub fn topic_wiring() -> Vec<TopicWiring> {
+    topic_dependencies()
+        .iter()
+        .map(TopicSpecification::wiring)
+        .collect()
+}
+
 fn topic_dependencies() -> Vec<TopicSpecification> {
     use Direction::*;
     vec![
         TopicSpecification {
+            topic: TOPIC_CAMERA_FRONT,
             peers: vec![(0.into(), Outgoing), (2.into(), Incoming)],
-            init_fn: Box::new(|w, r| init_topic::<CameraImage>(TOPIC_CAMERA_FRONT, w, r)),
+            qos: TopicQos::default(),
+            init_fn: Box::new(|w, r, q| {
+                init_topic_with_qos::<CameraImage>(TOPIC_CAMERA_FRONT, w, r, q)
+            }),
         },
         TopicSpecification {
+            topic: TOPIC_RADAR_FRONT,
             peers: vec![(1.into(), Outgoing), (2.into(), Incoming)],
-            init_fn: Box::new(|w, r| init_topic::<RadarScan>(TOPIC_RADAR_FRONT, w, r)),
+            qos: TopicQos::default(),
+            init_fn: Box::new(|w, r, q| {
+                init_topic_with_qos::<RadarScan>(TOPIC_RADAR_FRONT, w, r, q)
+            }),
         },
         TopicSpecification {
+            topic: TOPIC_INFERRED_SCENE,
             peers: vec![
                 (2.into(), Outgoing),
                 (3.into(), Incoming),
                 (4.into(), Incoming),
                 (5.into(), Incoming),
             ],
-            init_fn: Box::new(|w, r| init_topic::<Scene>(TOPIC_INFERRED_SCENE, w, r)),
+            qos: TopicQos::default(),
+            init_fn: Box::new(|w, r, q| {
+                init_topic_with_qos::<Scene>(TOPIC_INFERRED_SCENE, w, r, q)
+            }),
         },
         TopicSpecification {
+            topic: TOPIC_CONTROL_BRAKES,
             peers: vec![(4.into(), Outgoing), (6.into(), Incoming)],
-            init_fn: Box::new(|w, r| init_topic::<BrakeInstruction>(TOPIC_CONTROL_BRAKES, w, r)),
+            qos: TopicQos::default(),
+            init_fn: Box::new(|w, r, q| {
+                init_topic_with_qos::<BrakeInstruction>(TOPIC_CONTROL_BRAKES, w, r, q)
+            }),
         },
         TopicSpecification {
+            topic: TOPIC_CONTROL_STEERING,
             peers: vec![(5.into(), Outgoing), (7.into(), Incoming)],
-            init_fn: Box::new(|w, r| init_topic::<Steering>(TOPIC_CONTROL_STEERING, w, r)),
+            qos: TopicQos::default(),
+            init_fn: Box::new(|w, r, q| {
+                init_topic_with_qos::<Steering>(TOPIC_CONTROL_STEERING, w, r, q)
+            }),
         },
     ]
 }
@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::activities::messages::{BrakeInstruction, CameraImage, RadarScan, Scene, Steering};
+use crate::activities::rng::{self, Rng};
 use crate::ffi::{
     create_lane_assist, free_lane_assist, shutdown_lane_assist, startup_lane_assist,
     step_lane_assist,
@@ -13,7 +14,6 @@ use feo_log::debug;
 use feo_tracing::{instrument, tracing};
 use std::ffi::c_void;
 use std::hash::{BuildHasher as _, Hasher as _, RandomState};
-use std::mem::MaybeUninit;
 use std::ops::Range;
 use std::thread;
 use std::time::Duration;
@@ -29,6 +29,24 @@ pub struct Camera {
     activity_id: ActivityId,
     /// Image output
     output_image: ActivityOutput<CameraImage>,
+    /// Pseudo-random image generator
+    generator: ImageGenerator,
+}
+
+impl Camera {
+    pub fn build(activity_id: ActivityId, image_topic: &str) -> Box<dyn Activity> {
+        Box::new(Self {
+            activity_id,
+            output_image: ActivityOutput::get_or_panic(image_topic),
+            generator: ImageGenerator::new(Rng::for_activity(activity_id)),
+        })
+    }
+}
+
+/// Generator of pseudo-random camera images
+#[derive(Debug)]
+struct ImageGenerator {
+    rng: Rng,
 
     // Local state for pseudo-random output generation
     num_people: usize,
@@ -36,25 +54,25 @@ pub struct Camera {
     distance_obstacle: f64,
 }
 
-impl Camera {
-    pub fn build(activity_id: ActivityId, image_topic: &str) -> Box<dyn Activity> {
-        Box::new(Self {
-            activity_id,
-            output_image: ActivityOutput::get(image_topic),
+impl ImageGenerator {
+    fn new(rng: Rng) -> Self {
+        Self {
+            rng,
             num_people: 4,
             num_cars: 10,
             distance_obstacle: 40.0,
-        })
+        }
     }
 
-    fn get_image(&mut self) -> CameraImage {
+    fn next_image(&mut self) -> CameraImage {
         const PEOPLE_CHANGE_PROP: f64 = 0.8;
         const CAR_CHANGE_PROP: f64 = 0.8;
         const DISTANCE_CHANGE_PROP: f64 = 1.0;
 
-        self.num_people = random_walk_integer(self.num_people, PEOPLE_CHANGE_PROP, 1);
-        self.num_cars = random_walk_integer(self.num_people, CAR_CHANGE_PROP, 2);
-        let sample = random_walk_float(self.distance_obstacle, DISTANCE_CHANGE_PROP, 5.0);
+        let rng = &mut self.rng;
+        self.num_people = random_walk_integer(rng, self.num_people, PEOPLE_CHANGE_PROP, 1);
+        self.num_cars = random_walk_integer(rng, self.num_people, CAR_CHANGE_PROP, 2);
+        let sample = random_walk_float(rng, self.distance_obstacle, DISTANCE_CHANGE_PROP, 5.0);
         self.distance_obstacle = sample.clamp(20.0, 50.0);
 
         CameraImage {
@@ -70,23 +88,23 @@ impl Activity for Camera {
         self.activity_id
     }
 
-    #[instrument(name = "Camera startup")]
+    #[instrument(name = "Camera startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "Camera")]
+    #[instrument(name = "Camera", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping Camera");
         sleep_random();
 
         if let Some(camera) = self.output_image.write_uninit() {
-            let image = self.get_image();
+            let image = self.generator.next_image();
             debug!("Sending image: {image:?}");
             let camera = camera.write_payload(image);
             camera.send();
         }
     }
 
-    #[instrument(name = "Camera shutdown")]
+    #[instrument(name = "Camera shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -101,6 +119,7 @@ pub struct Radar {
     output_scan: ActivityOutput<RadarScan>,
 
     // Local state for pseudo-random output generation
+    rng: Rng,
     distance_obstacle: f64,
 }
 
@@ -108,7 +127,8 @@ impl Radar {
     pub fn build(activity_id: ActivityId, radar_topic: &str) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            output_scan: ActivityOutput::get(radar_topic),
+            output_scan: ActivityOutput::get_or_panic(radar_topic),
+            rng: Rng::for_activity(activity_id),
             distance_obstacle: 40.0,
         })
     }
@@ -116,10 +136,15 @@ impl Radar {
     fn get_scan(&mut self) -> RadarScan {
         const DISTANCE_CHANGE_PROP: f64 = 1.0;
 
-        let sample = random_walk_float(self.distance_obstacle, DISTANCE_CHANGE_PROP, 6.0);
+        let sample = random_walk_float(
+            &mut self.rng,
+            self.distance_obstacle,
+            DISTANCE_CHANGE_PROP,
+            6.0,
+        );
         self.distance_obstacle = sample.clamp(16.0, 60.0);
 
-        let error_margin = gen_random_in_range(-10..10) as f64 / 10.0;
+        let error_margin = self.rng.gen_range(-10..10) as f64 / 10.0;
 
         RadarScan {
             distance_obstacle: self.distance_obstacle,
@@ -133,10 +158,10 @@ impl Activity for Radar {
         self.activity_id
     }
 
-    #[instrument(name = "Radar startup")]
+    #[instrument(name = "Radar startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "Radar")]
+    #[instrument(name = "Radar", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping Radar");
         sleep_random();
@@ -149,7 +174,7 @@ impl Activity for Radar {
         }
     }
 
-    #[instrument(name = "Radar shutdown")]
+    #[instrument(name = "Radar shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -168,6 +193,8 @@ pub struct NeuralNet {
     input_scan: ActivityInput<RadarScan>,
     /// Scene output
     output_scene: ActivityOutput<Scene>,
+    /// Generator of the pseudo-inferred lane distances
+    rng: Rng,
 }
 
 impl NeuralNet {
@@ -179,13 +206,14 @@ impl NeuralNet {
     ) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            input_image: ActivityInput::get(image_topic),
-            input_scan: ActivityInput::get(scan_topic),
-            output_scene: ActivityOutput::get(scene_topic),
+            input_image: ActivityInput::get_or_panic(image_topic),
+            input_scan: ActivityInput::get_or_panic(scan_topic),
+            output_scene: ActivityOutput::get_or_panic(scene_topic),
+            rng: Rng::for_activity(activity_id),
         })
     }
 
-    fn infer(image: &CameraImage, radar: &RadarScan, scene: &mut MaybeUninit<Scene>) {
+    fn infer(rng: &mut Rng, image: &CameraImage, radar: &RadarScan) -> Scene {
         let CameraImage {
             num_people,
             num_cars,
@@ -193,20 +221,15 @@ impl NeuralNet {
         } = *image;
 
         let distance_obstacle = distance_obstacle.min(radar.distance_obstacle);
-        let distance_left_lane = gen_random_in_range(5..10) as f64 / 10.0;
-        let distance_right_lane = gen_random_in_range(5..10) as f64 / 10.0;
-
-        // Get raw pointer to payload within `MaybeUninit`.
-        let scene_ptr = scene.as_mut_ptr();
-
-        // Safety: `scene_ptr` was create from a `MaybeUninit` of the right type and size.
-        // The underlying type `Scene` has `repr(C)` and can be populated field by field.
-        unsafe {
-            (*scene_ptr).num_people = num_people;
-            (*scene_ptr).num_cars = num_cars;
-            (*scene_ptr).distance_obstacle = distance_obstacle;
-            (*scene_ptr).distance_left_lane = distance_left_lane;
-            (*scene_ptr).distance_right_lane = distance_right_lane;
+        let distance_left_lane = rng.gen_range(5..10) as f64 / 10.0;
+        let distance_right_lane = rng.gen_range(5..10) as f64 / 10.0;
+
+        Scene {
+            num_people,
+            num_cars,
+            distance_obstacle,
+            distance_left_lane,
+            distance_right_lane,
         }
     }
 }
@@ -216,10 +239,10 @@ impl Activity for NeuralNet {
         self.activity_id
     }
 
-    #[instrument(name = "NeuralNet startup")]
+    #[instrument(name = "NeuralNet startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "NeuralNet")]
+    #[instrument(name = "NeuralNet", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping NeuralNet");
         sleep_random();
@@ -228,17 +251,15 @@ impl Activity for NeuralNet {
         let radar = self.input_scan.read();
         let scene = self.output_scene.write_uninit();
 
-        if let (Some(camera), Some(radar), Some(mut scene)) = (camera, radar, scene) {
+        if let (Some(camera), Some(radar), Some(scene)) = (camera, radar, scene) {
             debug!("Inferring scene with neural network");
 
-            Self::infer(camera.get(), radar.get(), scene.payload_mut());
-            // Safety: `Scene` has `repr(C)` and was fully initialized by `Self::infer` above.
-            let scene = unsafe { scene.assume_init() };
-            scene.send();
+            let inferred = Self::infer(&mut self.rng, camera.get(), radar.get());
+            scene.write_struct(inferred).send();
         }
     }
 
-    #[instrument(name = "NeuralNet shutdown")]
+    #[instrument(name = "NeuralNet shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -266,8 +287,8 @@ impl EmergencyBraking {
     ) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            input_scene: ActivityInput::get(scene_topic),
-            output_brake_instruction: ActivityOutput::get(brake_instruction_topic),
+            input_scene: ActivityInput::get_or_panic(scene_topic),
+            output_brake_instruction: ActivityOutput::get_or_panic(brake_instruction_topic),
         })
     }
 }
@@ -277,10 +298,10 @@ impl Activity for EmergencyBraking {
         self.activity_id
     }
 
-    #[instrument(name = "EmergencyBraking startup")]
+    #[instrument(name = "EmergencyBraking startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "EmergencyBraking")]
+    #[instrument(name = "EmergencyBraking", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping EmergencyBraking");
         sleep_random();
@@ -315,7 +336,7 @@ impl Activity for EmergencyBraking {
         }
     }
 
-    #[instrument(name = "EmergencyBraking shutdown")]
+    #[instrument(name = "EmergencyBraking shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -337,7 +358,7 @@ impl BrakeController {
     pub fn build(activity_id: ActivityId, brake_instruction_topic: &str) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            input_brake_instruction: ActivityInput::get(brake_instruction_topic),
+            input_brake_instruction: ActivityInput::get_or_panic(brake_instruction_topic),
         })
     }
 }
@@ -347,10 +368,10 @@ impl Activity for BrakeController {
         self.activity_id
     }
 
-    #[instrument(name = "BrakeController startup")]
+    #[instrument(name = "BrakeController startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "BrakeController")]
+    #[instrument(name = "BrakeController", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping BrakeController");
         sleep_random();
@@ -365,7 +386,7 @@ impl Activity for BrakeController {
         }
     }
 
-    #[instrument(name = "BrakeController shutdown")]
+    #[instrument(name = "BrakeController shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -386,7 +407,7 @@ impl EnvironmentRenderer {
     pub fn build(activity_id: ActivityId, scene_topic: &str) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            input_scene: ActivityInput::get(scene_topic),
+            input_scene: ActivityInput::get_or_panic(scene_topic),
         })
     }
 }
@@ -396,10 +417,10 @@ impl Activity for EnvironmentRenderer {
         self.activity_id
     }
 
-    #[instrument(name = "EnvironmentRenderer startup")]
+    #[instrument(name = "EnvironmentRenderer startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "EnvironmentRenderer")]
+    #[instrument(name = "EnvironmentRenderer", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping EnvironmentRenderer");
         sleep_random();
@@ -409,7 +430,7 @@ impl Activity for EnvironmentRenderer {
         }
     }
 
-    #[instrument(name = "EnvironmentRenderer shutdown")]
+    #[instrument(name = "EnvironmentRenderer shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
@@ -442,8 +463,8 @@ impl LaneAssist {
 
         Box::new(Self {
             activity_id,
-            input_scene: ActivityInput::get(scene_topic),
-            output_steering: ActivityOutput::get(steering_topic),
+            input_scene: ActivityInput::get_or_panic(scene_topic),
+            output_steering: ActivityOutput::get_or_panic(steering_topic),
             cpp_activity,
         })
     }
@@ -461,12 +482,12 @@ impl Activity for LaneAssist {
         self.activity_id
     }
 
-    #[instrument(name = "LaneAssist startup")]
+    #[instrument(name = "LaneAssist startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {
         unsafe { startup_lane_assist(self.cpp_activity) };
     }
 
-    #[instrument(name = "LaneAssist")]
+    #[instrument(name = "LaneAssist", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping LaneAssist");
         sleep_random();
@@ -487,7 +508,7 @@ impl Activity for LaneAssist {
         }
     }
 
-    #[instrument(name = "LaneAssist shutdown")]
+    #[instrument(name = "LaneAssist shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {
         unsafe { shutdown_lane_assist(self.cpp_activity) };
     }
@@ -511,7 +532,7 @@ impl SteeringController {
     pub fn build(activity_id: ActivityId, steering_topic: &str) -> Box<dyn Activity> {
         Box::new(Self {
             activity_id,
-            input_steering: ActivityInput::get(steering_topic),
+            input_steering: ActivityInput::get_or_panic(steering_topic),
         })
     }
 }
@@ -521,10 +542,10 @@ impl Activity for SteeringController {
         self.activity_id
     }
 
-    #[instrument(name = "SteeringController startup")]
+    #[instrument(name = "SteeringController startup", fields(activity_id = %self.activity_id))]
     fn startup(&mut self) {}
 
-    #[instrument(name = "SteeringController")]
+    #[instrument(name = "SteeringController", fields(activity_id = %self.activity_id))]
     fn step(&mut self) {
         debug!("Stepping SteeringController");
         sleep_random();
@@ -537,11 +558,11 @@ impl Activity for SteeringController {
         }
     }
 
-    #[instrument(name = "SteeringController shutdown")]
+    #[instrument(name = "SteeringController shutdown", fields(activity_id = %self.activity_id))]
     fn shutdown(&mut self) {}
 }
 
-/// Generate a pseudo-random number in the specified range.
+/// Generate a pseudo-random number in the specified range, not reproducible
 fn gen_random_in_range(range: Range<i64>) -> i64 {
     let rand = RandomState::new().build_hasher().finish();
     let rand = (rand % (i64::MAX as u64)) as i64;
@@ -549,13 +570,13 @@ fn gen_random_in_range(range: Range<i64>) -> i64 {
 }
 
 /// Random walk from `previous` with a probability of `change_prop` in a range of +/-`max_delta`
-fn random_walk_float(previous: f64, change_prop: f64, max_delta: f64) -> f64 {
-    if gen_random_in_range(0..100) as f64 / 100.0 < change_prop {
+fn random_walk_float(rng: &mut Rng, previous: f64, change_prop: f64, max_delta: f64) -> f64 {
+    if rng.gen_range(0..100) as f64 / 100.0 < change_prop {
         const SCALE_FACTOR: f64 = 1000.0;
 
         // Scale delta to work in integers
         let scaled_max_delta = (max_delta * SCALE_FACTOR) as i64;
-        let scaled_delta = gen_random_in_range(-scaled_max_delta..scaled_max_delta) as f64;
+        let scaled_delta = rng.gen_range(-scaled_max_delta..scaled_max_delta) as f64;
 
         return previous + (scaled_delta / SCALE_FACTOR);
     }
@@ -564,11 +585,16 @@ fn random_walk_float(previous: f64, change_prop: f64, max_delta: f64) -> f64 {
 }
 
 /// Random walk from `previous` with a probability of `change_prop` in a range of +/-`max_delta`
-fn random_walk_integer(previous: usize, change_prop: f64, max_delta: usize) -> usize {
+fn random_walk_integer(
+    rng: &mut Rng,
+    previous: usize,
+    change_prop: f64,
+    max_delta: usize,
+) -> usize {
     let max_delta = max_delta as i64;
 
-    if gen_random_in_range(0..100) as f64 / 100.0 < change_prop {
-        let delta = gen_random_in_range(-max_delta..max_delta);
+    if rng.gen_range(0..100) as f64 / 100.0 < change_prop {
+        let delta = rng.gen_range(-max_delta..max_delta);
 
         return i64::max(0, previous as i64 + delta) as usize;
     }
@@ -576,9 +602,34 @@ fn random_walk_integer(previous: usize, change_prop: f64, max_delta: usize) -> u
     previous
 }
 
-/// Sleep for a random amount of time
+/// Sleep for a random amount of time, unless disabled by [`rng::SLEEP_VAR`]
+///
+/// The sleep time does not affect the outputs, so it is not drawn from the seeded generators.
 fn sleep_random() {
+    if !rng::sleep_enabled() {
+        return;
+    }
     thread::sleep(Duration::from_millis(
         gen_random_in_range(SLEEP_RANGE) as u64
     ));
 }
+
+#[cfg(test)]
+mod test {
+    use super::ImageGenerator;
+    use crate::activities::rng::Rng;
+
+    /// Generate a sequence of images, formatted for comparison
+    fn images(seed: u64) -> Vec<String> {
+        let mut generator = ImageGenerator::new(Rng::new(seed));
+        (0..100)
+            .map(|_| format!("{:?}", generator.next_image()))
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_images() {
+        assert_eq!(images(42), images(42));
+        assert_ne!(images(42), images(43));
+    }
+}
@@ -6,8 +6,9 @@ use configuration::primary_agent::Builder;
 use feo::configuration::worker_pool;
 use feo::prelude::*;
 use feo::signalling::{channel, Signal};
-use feo_log::{info, LevelFilter};
+use feo_log::{error, info, LevelFilter};
 use feo_time::Duration;
+use mini_adas_recording::activities::rng;
 use mini_adas_recording::config;
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -19,18 +20,26 @@ const DEFAULT_FEO_CYCLE_TIME: Duration = Duration::from_secs(5);
 fn main() {
     feo_logger::init(LevelFilter::Debug, true, true);
     feo_tracing::init(feo_tracing::LevelFilter::TRACE);
+    rng::init_from_env();
 
     let params = Params::from_args();
 
     info!("Starting primary agent {AGENT_ID}. Waiting for connections");
 
     // Initialize topics. Do not drop.
-    let _topic_guards: Vec<_> = config::initialize_topics();
+    let _topic_guards = match config::initialize_topics() {
+        Ok(guards) => guards,
+        Err(e) => {
+            error!("Failed to initialize topics: {e}");
+            std::process::exit(1);
+        }
+    };
 
     // Create local worker pool
     let (worker_pool, agent_map, ready_channel) = {
         let pool_configuration = config::pool_configuration();
         let mut worker_pool_builder = worker_pool::Builder::default();
+        worker_pool_builder.agent_id(AGENT_ID);
         let mut agent_map: HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>> = HashMap::new();
 
         // Recreate the HashMap without the builder on the lowest level.
@@ -74,6 +83,7 @@ fn main() {
         .agent_map(agent_map)
         .worker_pool(worker_pool)
         .activity_dependencies(activity_dependencies)
+        .topic_wiring(config::topic_wiring())
         .intra_proc_ready_channel(ready_channel.0, ready_channel.1)
         .recorders(recorders)
         .build();
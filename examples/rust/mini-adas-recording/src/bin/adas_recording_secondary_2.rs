@@ -6,6 +6,7 @@ use configuration::secondary_agent::Builder;
 use feo::configuration::worker_pool;
 use feo::prelude::*;
 use feo_log::{info, LevelFilter};
+use mini_adas_recording::activities::rng;
 use mini_adas_recording::config;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
@@ -17,11 +18,13 @@ const PRIMARY_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST)
 fn main() {
     feo_logger::init(LevelFilter::Debug, true, true);
     feo_tracing::init(feo_tracing::LevelFilter::TRACE);
+    rng::init_from_env();
 
     info!("Starting agent {AGENT_ID}");
 
     // Create worker pool builder activity builder for local worker pool
     let mut worker_pool_builder = worker_pool::Builder::default();
+    worker_pool_builder.agent_id(AGENT_ID);
 
     let mut worker_pool_configuration = config::pool_configuration();
     let assignments = worker_pool_configuration
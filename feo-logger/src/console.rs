@@ -7,10 +7,17 @@ use crate::record::Record;
 use std::io::{self};
 
 #[derive(Debug, Default)]
-pub struct Console;
+pub struct Console {
+    /// Write to `stderr` instead of `stdout`
+    pub stderr: bool,
+}
 
 impl Console {
     pub fn write(&self, record: &Record) -> io::Result<()> {
-        fmt::format(record, io::stdout())
+        if self.stderr {
+            fmt::format(record, io::stderr())
+        } else {
+            fmt::format(record, io::stdout())
+        }
     }
 }
@@ -2,38 +2,73 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+//! Formatting of records for the console
+//!
+//! Records are written directly to the given writer, including the color codes and padding,
+//! without allocating on the heap. This allows real-time threads to log to the console.
+
 use crate::record::{OwnedRecord, Record};
-use console::{style, Color, StyledObject};
+use console::Color;
 use core::str;
 use feo_log::Level;
 use feo_time::SystemTime;
-use std::sync::atomic::{self, AtomicUsize, Ordering};
-use time::format_description::FormatItem;
-use time::macros::format_description;
+use std::fmt;
+use std::sync::atomic::{self, Ordering};
 
 // TODO: Add monochrome support.
 
-const TIMESTAMP_FORMAT: &[FormatItem<'static>] =
-    format_description!("[hour]:[minute]:[second].[subsecond digits:3]");
-
 static TARGET_SIZE: atomic::AtomicUsize = atomic::AtomicUsize::new(16);
 static TGID_SIZE: atomic::AtomicUsize = atomic::AtomicUsize::new(4);
 static TID_SIZE: atomic::AtomicUsize = atomic::AtomicUsize::new(4);
 
-pub fn format<W: std::io::Write>(record: &Record, mut writer: W) -> Result<(), std::io::Error> {
-    let timestamp = {
-        let timestamp = record.timestamp;
-        let timestamp = time::OffsetDateTime::from_unix_timestamp_nanos(
-            timestamp
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos() as i128,
-        )
-        .unwrap();
-        timestamp
-            .format(TIMESTAMP_FORMAT)
-            .expect("failed to format timestamp")
+/// Widths of the aligned columns of formatted records
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Widths {
+    /// Minimum width of the target
+    pub target: usize,
+    /// Minimum width of the hex formatted process id
+    pub tgid: usize,
+    /// Minimum width of the hex formatted thread id
+    pub tid: usize,
+}
+
+impl Widths {
+    /// Grow the widths to fit a record with the given target, process and thread id
+    pub fn fit(&mut self, target: &str, tgid: u32, tid: u32) {
+        self.target = self.target.max(target.len());
+        self.tgid = self.tgid.max(num_hex_digits(tgid));
+        self.tid = self.tid.max(num_hex_digits(tid));
+    }
+}
+
+/// Format a record aligned to the widest record formatted so far in this process
+pub fn format<W: std::io::Write>(record: &Record, writer: W) -> Result<(), std::io::Error> {
+    TARGET_SIZE.fetch_max(record.target.len(), Ordering::Relaxed);
+    TGID_SIZE.fetch_max(num_hex_digits(record.tgid), Ordering::Relaxed);
+    TID_SIZE.fetch_max(num_hex_digits(record.tid), Ordering::Relaxed);
+    let widths = Widths {
+        target: TARGET_SIZE.load(Ordering::Relaxed),
+        tgid: TGID_SIZE.load(Ordering::Relaxed),
+        tid: TID_SIZE.load(Ordering::Relaxed),
     };
+    format_with(record, &widths, writer)
+}
+
+/// Format a record with the given column widths
+pub fn format_with<W: std::io::Write>(
+    record: &Record,
+    widths: &Widths,
+    mut writer: W,
+) -> Result<(), std::io::Error> {
+    let timestamp = time::OffsetDateTime::from_unix_timestamp_nanos(
+        record
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as i128,
+    )
+    .unwrap();
+    let (hour, minute, second, millis) = timestamp.to_hms_milli();
 
     let level = {
         let level_color = match record.level {
@@ -43,35 +78,37 @@ pub fn format<W: std::io::Write>(record: &Record, mut writer: W) -> Result<(), s
             Level::Debug => Color::Color256(243),
             Level::Trace => Color::White,
         };
-        style(record.level).bold().fg(level_color)
+        Styled::new(record.level, level_color).bold()
     };
 
-    let tgid = format_id(record.tgid, &TGID_SIZE, true);
-    let tid = format_id(record.tid, &TID_SIZE, false);
+    let tgid = Styled::new(Hex(record.tgid), record.tgid.color());
+    let tid = Styled::new(Hex(record.tid), record.tid.color());
 
     let message = unsafe { str::from_utf8_unchecked(record.args) };
 
-    let target = {
-        let target = record.target;
-        TARGET_SIZE.fetch_max(target.len(), Ordering::Relaxed);
-        let target_size = TARGET_SIZE.load(Ordering::Relaxed);
-        let target_color = target.color();
-        style(format!("{target:<s$}", s = target_size)).fg(target_color)
-    };
+    let target = Styled::new(record.target, record.target.color());
 
     // Log location on trace level - otherwise just the message.
     if record.level == Level::Trace {
         let file = record.file.unwrap_or("file unknown");
-        let file = style(file).fg(file.color());
+        let file = Styled::new(file, file.color());
         let line = record.line.unwrap_or(0);
         writeln!(
             writer,
-            "{timestamp} {target} ({tgid} {tid}): {level:<5}: {file}:{line}: {message}",
+            "{hour:02}:{minute:02}:{second:02}.{millis:03} {target:<t$} ({tgid:<p$} {tid:>i$}): \
+             {level:<5}: {file}:{line}: {message}",
+            t = widths.target,
+            p = widths.tgid,
+            i = widths.tid,
         )
     } else {
         writeln!(
             writer,
-            "{timestamp} {target} ({tgid} {tid}): {level:<5}: {message}"
+            "{hour:02}:{minute:02}:{second:02}.{millis:03} {target:<t$} ({tgid:<p$} {tid:>i$}): \
+             {level:<5}: {message}",
+            t = widths.target,
+            p = widths.tgid,
+            i = widths.tid,
         )
     }
 }
@@ -80,6 +117,20 @@ pub fn format_owned<W: std::io::Write>(
     record: OwnedRecord,
     writer: W,
 ) -> Result<(), std::io::Error> {
+    with_record(record, |record| format(record, writer))
+}
+
+/// Format an owned record with the given column widths
+pub fn format_owned_with<W: std::io::Write>(
+    record: OwnedRecord,
+    widths: &Widths,
+    writer: W,
+) -> Result<(), std::io::Error> {
+    with_record(record, |record| format_with(record, widths, writer))
+}
+
+/// Call `f` with a record borrowing from `record`
+fn with_record<T>(record: OwnedRecord, f: impl FnOnce(&Record) -> T) -> T {
     let record = Record {
         timestamp: record.timestamp,
         level: record.level,
@@ -90,7 +141,7 @@ pub fn format_owned<W: std::io::Write>(
         tid: record.tid,
         args: record.args.as_bytes(),
     };
-    format(&record, writer)
+    f(&record)
 }
 
 /// Generate a color of `self`.
@@ -128,17 +179,71 @@ impl HashColor for u64 {
     }
 }
 
-/// Format `id` with a color based on the hash of `id`. Update `g` with the
-/// maximum length of the formatted `id`.
-fn format_id(id: u32, g: &AtomicUsize, align_left: bool) -> StyledObject<String> {
-    let tgid_len = num_hex_digits(id);
-    let color = id.color();
-    g.fetch_max(tgid_len, Ordering::Relaxed);
-    let len = g.load(Ordering::Relaxed);
-    if align_left {
-        style(format!("{:<l$x}", id, l = len)).fg(color)
-    } else {
-        style(format!("{:>l$x}", id, l = len)).fg(color)
+/// Value displayed in a color, if colors are enabled on stdout
+///
+/// In contrast to `console::StyledObject`, creating and displaying it does not allocate.
+/// Width and alignment apply to the value, excluding the color codes.
+struct Styled<D> {
+    value: D,
+    color: Color,
+    bold: bool,
+}
+
+impl<D> Styled<D> {
+    fn new(value: D, color: Color) -> Self {
+        Self {
+            value,
+            color,
+            bold: false,
+        }
+    }
+
+    fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+}
+
+impl<D: fmt::Display> fmt::Display for Styled<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colors = console::colors_enabled();
+        if colors {
+            match self.color {
+                Color::Color256(color) => write!(f, "\x1b[38;5;{color}m")?,
+                color => write!(f, "\x1b[{}m", ansi_num(color) + 30)?,
+            }
+            if self.bold {
+                f.write_str("\x1b[1m")?;
+            }
+        }
+        self.value.fmt(f)?;
+        if colors {
+            f.write_str("\x1b[0m")?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of a basic color in the ANSI escape codes
+fn ansi_num(color: Color) -> u8 {
+    match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::Color256(color) => color,
+    }
+}
+
+/// Id displayed in lowercase hex
+struct Hex(u32);
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
     }
 }
 
@@ -149,7 +254,73 @@ fn num_hex_digits(n: u32) -> usize {
 
 #[cfg(test)]
 mod test {
-    use super::num_hex_digits;
+    use super::{format, num_hex_digits};
+    use crate::record::Record;
+    use feo_log::Level;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::io;
+
+    /// Allocator counting the allocations of each thread
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn format_without_allocation() {
+        let record = |level| {
+            Record::new(
+                feo_time::SystemTime::now(),
+                level,
+                "target",
+                Some("file.rs"),
+                Some(42),
+                0x1234,
+                0x5678,
+                b"message",
+            )
+        };
+        let mut buffer = [0u8; 256];
+
+        // Initialize the lazily determined color support outside of the measurement
+        console::set_colors_enabled(true);
+        format(&record(Level::Info), io::Cursor::new(&mut buffer[..])).unwrap();
+
+        for level in [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ] {
+            let record = record(level);
+            let mut writer = io::Cursor::new(&mut buffer[..]);
+            let before = ALLOCATIONS.with(Cell::get);
+            format(&record, &mut writer).unwrap();
+            assert_eq!(ALLOCATIONS.with(Cell::get), before, "{level}");
+
+            let len = writer.position() as usize;
+            let line = std::str::from_utf8(&buffer[..len]).unwrap();
+            assert!(line.contains("\x1b[38;5;"), "{line}");
+            assert!(line.ends_with("message\n"), "{line}");
+        }
+    }
 
     #[test]
     fn hex_digits() {
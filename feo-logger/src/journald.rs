@@ -0,0 +1,159 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sink forwarding records to the systemd journal via its native protocol
+
+use crate::record::Record;
+use crate::MAX_RECORD_SIZE;
+use feo_log::Level;
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Socket of the native protocol of journald
+pub const JOURNAL_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// Connector to journald sending each record as one datagram of journal fields
+#[derive(Debug)]
+pub struct Journald {
+    path: PathBuf,
+    socket: Mutex<Option<UnixDatagram>>,
+}
+
+impl Default for Journald {
+    fn default() -> Self {
+        Self::new(JOURNAL_SOCKET_PATH)
+    }
+}
+
+impl Journald {
+    /// Create a connector sending to the journal socket at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            socket: Mutex::new(None),
+        }
+    }
+
+    pub fn write(&self, record: &Record) -> io::Result<()> {
+        let mut writer = io::Cursor::new([0u8; MAX_RECORD_SIZE]);
+        Self::encode(record, &mut writer).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "record too large to encode")
+        })?;
+        let len = writer.position() as usize;
+        let buffer = &writer.get_ref()[..len];
+
+        let mut guard = self.socket.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(UnixDatagram::unbound()?);
+        }
+        let socket = guard.as_ref().unwrap();
+        socket.send_to(buffer, &self.path).map(|_| ())
+    }
+
+    /// Encode the given record as journal fields
+    ///
+    /// The level is mapped to the syslog priority, the location and origin of the record to
+    /// the corresponding well-known fields. The target is not a journal field, it is sent as
+    /// `FEO_TARGET`.
+    fn encode<W: Write>(record: &Record, mut w: W) -> io::Result<()> {
+        number(&mut w, "PRIORITY", priority(record.level))?;
+        field(&mut w, "MESSAGE", record.args)?;
+        field(&mut w, "FEO_TARGET", record.target.as_bytes())?;
+        if let Some(file) = record.file {
+            field(&mut w, "CODE_FILE", file.as_bytes())?;
+        }
+        if let Some(line) = record.line {
+            number(&mut w, "CODE_LINE", line)?;
+        }
+        number(&mut w, "SYSLOG_PID", record.tgid)?;
+        number(&mut w, "TID", record.tid)
+    }
+}
+
+/// Syslog priority of the given level
+fn priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Write a journal field. Values containing a newline are written length-prefixed.
+fn field<W: Write>(mut w: W, name: &str, value: &[u8]) -> io::Result<()> {
+    w.write_all(name.as_bytes())?;
+    if value.contains(&b'\n') {
+        w.write_all(b"\n")?;
+        w.write_all(&(value.len() as u64).to_le_bytes())?;
+    } else {
+        w.write_all(b"=")?;
+    }
+    w.write_all(value)?;
+    w.write_all(b"\n")
+}
+
+/// Write a journal field with a decimal number as value, without heap allocation
+fn number<W: Write>(w: W, name: &str, value: impl Into<u64>) -> io::Result<()> {
+    let mut buffer = io::Cursor::new([0u8; 20]);
+    write!(buffer, "{}", value.into())?;
+    let len = buffer.position() as usize;
+    field(w, name, &buffer.get_ref()[..len])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{priority, Journald};
+    use crate::record::Record;
+    use feo_log::Level;
+    use std::os::unix::net::UnixDatagram;
+    use std::{env, fs, process};
+
+    #[test]
+    fn priorities() {
+        assert_eq!(priority(Level::Error), 3);
+        assert_eq!(priority(Level::Warn), 4);
+        assert_eq!(priority(Level::Info), 6);
+        assert_eq!(priority(Level::Debug), 7);
+        assert_eq!(priority(Level::Trace), 7);
+    }
+
+    #[test]
+    fn write() {
+        // Receive on a socket standing in for the journal
+        let path = env::temp_dir().join(format!("feo-logger-journal-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let journal = UnixDatagram::bind(&path).unwrap();
+        let journald = Journald::new(&path);
+
+        let record = Record::new(
+            feo_time::SystemTime::now(),
+            Level::Warn,
+            "target",
+            Some("file.rs"),
+            Some(42),
+            1,
+            2,
+            b"first\nsecond",
+        );
+        journald.write(&record).unwrap();
+        let mut buf = [0u8; 1024];
+        let len = journal.recv(&mut buf).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let mut expected = b"PRIORITY=4\nMESSAGE\n".to_vec();
+        expected.extend_from_slice(&12u64.to_le_bytes());
+        expected.extend_from_slice(
+            b"first\nsecond\n\
+              FEO_TARGET=target\n\
+              CODE_FILE=file.rs\n\
+              CODE_LINE=42\n\
+              SYSLOG_PID=1\n\
+              TID=2\n",
+        );
+        assert_eq!(&buf[..len], expected);
+    }
+}
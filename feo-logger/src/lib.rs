@@ -17,10 +17,15 @@ use std::{io, process};
 mod console;
 // TODO: hide fmt and its deps behind a feature flag: `console` and `time`.
 pub mod fmt;
+#[cfg(feature = "journald")]
+mod journald;
 mod logd;
+mod panic;
 pub mod record;
 mod thread;
 
+pub use panic::install_panic_hook;
+
 const ENV_RUST_LOG: &str = "RUST_LOG";
 const MAX_ARGS_SIZE: usize = 8 * 1024;
 pub const MAX_RECORD_SIZE: usize = 8 * 1024;
@@ -31,19 +36,41 @@ pub const MAX_RECORD_SIZE: usize = 8 * 1024;
 /// Enable output to `stdout` via `console`.
 /// Enable output forwarding to `logd` via `logd=true`.
 pub fn init(level: LevelFilter, console: bool, logd: bool) {
-    let logger = Logger::new(console, logd);
+    init_logger(Logger::new(console, logd), level);
+}
+
+/// Initialize the logger like [`init`] and additionally forward records to the systemd journal.
+///
+/// See [`Logger::journald`].
+#[cfg(feature = "journald")]
+pub fn init_with_journald(level: LevelFilter, console: bool, logd: bool) {
+    init_logger(Logger::new(console, logd).journald(), level);
+}
 
+/// Install the given logger with the given level, unless overridden by `RUST_LOG`.
+fn init_logger(logger: Logger, level: LevelFilter) {
     // Set the maximum log level the log subsystem will forward to this logger impl.
     feo_log::set_max_level(level_from_env().unwrap_or(level));
     // Set the logger in the global subsystem.
     feo_log::set_boxed_logger(Box::new(logger)).expect("failed to set logger")
 }
 
+/// Initialize the logger like [`init`] and log panics as error records.
+///
+/// Each panic message is logged with its location before the previously installed panic hook
+/// is called. See [`install_panic_hook`].
+pub fn init_with_panic_hook(level: LevelFilter, console: bool, logd: bool) {
+    init(level, console, logd);
+    install_panic_hook();
+}
+
 /// The FEO logger.
 #[derive(Debug)]
 pub struct Logger {
     console: Option<console::Console>,
     logd: Option<logd::Logd>,
+    #[cfg(feature = "journald")]
+    journald: Option<journald::Journald>,
 }
 
 impl Logger {
@@ -51,7 +78,30 @@ impl Logger {
     pub fn new(console: bool, logd: bool) -> Self {
         let console = console.then(console::Console::default);
         let logd = logd.then(logd::Logd::default);
-        Self { console, logd }
+        Self {
+            console,
+            logd,
+            #[cfg(feature = "journald")]
+            journald: None,
+        }
+    }
+
+    /// Forward records to the systemd journal via its native protocol.
+    ///
+    /// The level is mapped to the syslog priority, the target, location, process and thread
+    /// of each record are sent as journal fields.
+    #[cfg(feature = "journald")]
+    pub fn journald(mut self) -> Self {
+        self.journald = Some(journald::Journald::default());
+        self
+    }
+
+    /// Write console output to `stderr` instead of `stdout`, e.g. if `stdout` carries data
+    pub fn console_stderr(mut self) -> Self {
+        if let Some(console) = &mut self.console {
+            console.stderr = true;
+        }
+        self
     }
 }
 
@@ -88,6 +138,11 @@ impl Log for Logger {
         if let Some(logd) = &self.logd {
             let _ = logd.write(&record);
         }
+
+        #[cfg(feature = "journald")]
+        if let Some(journald) = &self.journald {
+            let _ = journald.write(&record);
+        }
     }
 
     fn flush(&self) {}
@@ -0,0 +1,106 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Forwarding of panic messages to the logger
+
+use feo_log::{Level, Log, Record};
+use std::panic::{self, PanicHookInfo};
+use std::thread;
+
+/// Target of the records created for panics
+const TARGET: &str = "panic";
+
+/// Install a panic hook logging each panic as error record before calling the previous hook
+pub fn install_panic_hook() {
+    install_panic_hook_with(feo_log::logger);
+}
+
+/// Install a panic hook logging to the logger returned by `logger` at the time of the panic
+fn install_panic_hook_with(logger: fn() -> &'static dyn Log) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log_panic(logger(), info);
+        previous(info);
+    }));
+}
+
+/// Log the payload and location of a panic as error record
+fn log_panic(logger: &dyn Log, info: &PanicHookInfo) {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    let current = thread::current();
+    let thread = current.name().unwrap_or("<unnamed>");
+    let location = info.location();
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .target(TARGET)
+            .file(location.map(|l| l.file()))
+            .line(location.map(|l| l.line()))
+            .args(format_args!("thread '{thread}' panicked: {message}"))
+            .build(),
+    );
+    logger.flush();
+}
+
+#[cfg(test)]
+mod test {
+    use super::{install_panic_hook_with, TARGET};
+    use feo_log::{Level, Log, Metadata, Record};
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Level, target, line and message of a record
+    type Captured = (Level, String, Option<u32>, String);
+
+    /// Logger capturing each record
+    struct Capture(Mutex<Vec<Captured>>);
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push((
+                record.level(),
+                record.target().to_string(),
+                record.line(),
+                record.args().to_string(),
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    #[test]
+    fn panic_in_thread_is_logged() {
+        install_panic_hook_with(|| &CAPTURE);
+
+        let line = line!() + 4;
+        let result = thread::Builder::new()
+            .name("worker".into())
+            .spawn(|| {
+                panic!("boom {}", 42);
+            })
+            .unwrap()
+            .join();
+        assert!(result.is_err());
+
+        let records = CAPTURE.0.lock().unwrap();
+        assert!(records.contains(&(
+            Level::Error,
+            TARGET.to_string(),
+            Some(line),
+            "thread 'worker' panicked: boom 42".to_string()
+        )));
+    }
+}
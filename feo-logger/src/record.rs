@@ -62,6 +62,13 @@ impl Record<'_> {
         len
     }
 
+    /// Encode the record prefixed with its length as a big endian u32, as expected on stream
+    /// transports where message boundaries are not preserved.
+    pub fn encode_framed<W: io::Write>(&self, mut w: W) -> io::Result<usize> {
+        w.write_all(&(self.encoded_len() as u32).to_be_bytes())?;
+        Ok(size_of::<u32>() + self.encode(w)?)
+    }
+
     pub fn encode<W: io::Write>(&self, mut w: W) -> io::Result<usize> {
         let mut len = 0;
         // Timestamp
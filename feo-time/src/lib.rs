@@ -35,17 +35,50 @@
 //! let elapsed_time = now.elapsed();
 //! println!("Running slow_function() took {} seconds.", elapsed_time.as_secs());
 //! ```
+//!
+//! # Features
+//!
+//! - `std` (default): [`Instant`], [`SystemTime`], [`Stopwatch`] and setting the speed factor.
+//!   Without it, the crate is `no_std` and provides the [`scale`] math and [`Scaled`] trait
+//!   only.
+//! - `serde`: `Serialize` and `Deserialize` for [`Instant`] and [`SystemTime`]. A [`SystemTime`]
+//!   is represented as nanoseconds since [`UNIX_EPOCH`]. An [`Instant`] is represented as
+//!   nanoseconds since the start of the process' time scaling and is only meaningful within the
+//!   same process run.
+//!
+//! # Manual clock
+//!
+//! For deterministic simulations and tests, [`manual`] detaches [`Instant::now`] and
+//! [`SystemTime::now`] from the OS clock. Time then only passes by calls to [`advance`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
 mod ffi;
-#[cfg(test)]
+pub mod scale;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+mod stopwatch;
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+pub use core::time::Duration;
+#[cfg(feature = "std")]
+use scale::FACTOR;
+pub use scale::{get_speed, Scaled};
+#[cfg(feature = "std")]
 use std::error::Error;
+#[cfg(feature = "std")]
 use std::ops::{Add, AddAssign, Sub, SubAssign};
-use std::sync::atomic::{AtomicI32, Ordering};
+#[cfg(feature = "std")]
 use std::sync::{LazyLock, Once};
-pub use std::time::Duration;
+#[cfg(feature = "std")]
 use std::{fmt, time};
+#[cfg(feature = "std")]
+pub use stopwatch::Stopwatch;
 
 /// An anchor in time which can be used to create new `SystemTime` instances or
 /// learn about where in time a `SystemTime` lies.
@@ -74,35 +107,93 @@ use std::{fmt, time};
 ///     Err(_) => panic!("SystemTime before UNIX EPOCH!"),
 /// }
 /// ```
+#[cfg(feature = "std")]
 pub const UNIX_EPOCH: SystemTime = SystemTime(time::UNIX_EPOCH);
 
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub struct SystemTimeError(Duration);
 
+#[cfg(feature = "std")]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Instant(time::Instant);
 
+#[cfg(feature = "std")]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SystemTime(time::SystemTime);
 
 /// Initialization synchronization. Ensures that `speed` can be set only once.
+#[cfg(feature = "std")]
 static INIT: Once = Once::new();
 /// Time scaling start timestamps
-static START: LazyLock<(SystemTime, Instant)> =
-    LazyLock::new(|| (SystemTime::now(), Instant::now()));
-/// Factor on systemtime and instant if set via `speed`
-static FACTOR: AtomicI32 = AtomicI32::new(0);
-
-/// A trait for scaling durations based on the factor set by `speed`.
-pub trait Scaled {
-    /// Scale the duration based on the factor set by `speed` for using in sleep functions.
-    /// Background: std::thread::sleep and friends need a time base on the unscaled system time.
-    /// If the factor is set to a positive value the duration must be shortened (shorter sleep).
-    /// If the factor is set to a negative value the duration must be lengthened (longer sleep).
-    fn scaled(&self) -> Self;
+#[cfg(feature = "std")]
+static START: LazyLock<(SystemTime, Instant)> = LazyLock::new(|| {
+    (
+        SystemTime(time::SystemTime::now()),
+        Instant(time::Instant::now()),
+    )
+});
+
+/// Whether the clock is driven by [`advance`] instead of the OS clock, see [`manual`]
+#[cfg(feature = "std")]
+static MANUAL: AtomicBool = AtomicBool::new(false);
+/// Nanoseconds passed on the manual clock since the start timestamps
+#[cfg(feature = "std")]
+static ADVANCED: AtomicU64 = AtomicU64::new(0);
+
+/// Drive the clock by [`advance`] instead of the OS clock.
+///
+/// [`Instant::now`] and [`SystemTime::now`] return the time of this call until the clock is
+/// advanced. Can only be called once and not together with [`speed`]. Sleeps and timeouts of
+/// `std` are not affected and still run on the OS clock: waits for a deadline on the manual clock
+/// have to check [`is_manual`] and wake up periodically to see whether it has been advanced.
+#[cfg(feature = "std")]
+pub fn manual() {
+    // Share the guard with `speed`, only one mode of time scaling can be set
+    assert!(!INIT.is_completed(), "clock mode can be set only once");
+    INIT.call_once(|| ());
+
+    // Initialize the start timestamps
+    let _ = &*START;
+
+    MANUAL.store(true, Ordering::Relaxed);
+}
+
+/// Advance the manual clock by `duration`.
+///
+/// # Panics
+///
+/// Panics if the clock is not in manual mode, see [`manual`].
+#[cfg(feature = "std")]
+pub fn advance(duration: Duration) {
+    assert!(
+        MANUAL.load(Ordering::Relaxed),
+        "clock is not in manual mode"
+    );
+    let nanos = u64::try_from(duration.as_nanos()).expect("overflow when advancing clock");
+    ADVANCED
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |advanced| {
+            advanced.checked_add(nanos)
+        })
+        .expect("overflow when advancing clock");
+}
+
+/// Check whether the clock is driven by [`advance`], see [`manual`]
+#[cfg(feature = "std")]
+pub fn is_manual() -> bool {
+    MANUAL.load(Ordering::Relaxed)
+}
+
+/// Time passed on the manual clock, if in manual mode
+#[cfg(feature = "std")]
+fn manual_elapsed() -> Option<Duration> {
+    MANUAL
+        .load(Ordering::Relaxed)
+        .then(|| Duration::from_nanos(ADVANCED.load(Ordering::Relaxed)))
 }
 
 /// Set a speedup or down factor on the system time.
+#[cfg(feature = "std")]
 pub fn speed(factor: i32) {
     // Ensure that speed can be set only once
     assert!(!INIT.is_completed(), "speed can be set only once");
@@ -115,12 +206,7 @@ pub fn speed(factor: i32) {
     FACTOR.store(factor, Ordering::Relaxed);
 }
 
-/// Get the current speed factor if set. Otherwise return None.
-pub fn get_speed() -> Option<i32> {
-    let factor = FACTOR.load(Ordering::Relaxed);
-    (factor != 0).then_some(factor)
-}
-
+#[cfg(feature = "std")]
 impl Instant {
     /// Returns an instant corresponding to "now".
     ///
@@ -133,30 +219,25 @@ impl Instant {
     /// ```
     #[must_use]
     pub fn now() -> Instant {
+        // Initialize the start timestamps. Instants are serialized relative to them.
+        let start = START.1;
+
+        if let Some(elapsed) = manual_elapsed() {
+            return start.checked_add(elapsed).expect("clock error");
+        }
+
         // Get current system time unscaled from the os
         let now = Instant(time::Instant::now());
 
         // Load the factor set by `SystemTime::speed`
         let factor = FACTOR.load(Ordering::Relaxed);
         if factor != 0 {
-            // Load start timestamp
-            let start = START.1;
-
             // Calculate elapsed time since start timestamp
             let duration_since_start = now.duration_since(start);
 
-            // Calculate new "feo" time
-            if factor.is_positive() {
-                // Factor is greater than 0, so we speed up time by multiplying
-                // the elapsed time by factor add add to the start time
-                let elapsed = duration_since_start * factor.unsigned_abs();
-                start.checked_add(elapsed).expect("clock error")
-            } else {
-                // Factor is less than 0, so we slow down time by dividing
-                // the elapsed time by factor add add to the start time
-                let elapsed = duration_since_start / factor.unsigned_abs();
-                start.checked_add(elapsed).expect("clock error")
-            }
+            // Calculate new "feo" time by scaling the elapsed time and adding it to the start time
+            let elapsed = scale::scale(duration_since_start, factor);
+            start.checked_add(elapsed).expect("clock error")
         } else {
             now
         }
@@ -274,8 +355,23 @@ impl Instant {
     pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
         self.0.checked_sub(duration).map(Instant)
     }
+
+    /// Returns `Some(t)` where `t` is the time `self + duration.scaled()`, i.e. the real clock
+    /// equivalent of `duration` under the speed factor set by [`speed`], if `t` can be
+    /// represented as `Instant`, `None` otherwise.
+    ///
+    /// Use this to compute deadlines to wait for with sleep or timeout functions of `std`.
+    pub fn checked_add_scaled(&self, duration: Duration) -> Option<Instant> {
+        self.checked_add_scaled_with(duration, FACTOR.load(Ordering::Relaxed))
+    }
+
+    /// [`Instant::checked_add_scaled`] with the given speed factor
+    fn checked_add_scaled_with(&self, duration: Duration, factor: i32) -> Option<Instant> {
+        scale::checked_unscale(duration, factor).and_then(|real| self.checked_add(real))
+    }
 }
 
+#[cfg(feature = "std")]
 impl Add<Duration> for Instant {
     type Output = Instant;
 
@@ -289,12 +385,14 @@ impl Add<Duration> for Instant {
     }
 }
 
+#[cfg(feature = "std")]
 impl AddAssign<Duration> for Instant {
     fn add_assign(&mut self, other: Duration) {
         *self = *self + other;
     }
 }
 
+#[cfg(feature = "std")]
 impl Sub<Duration> for Instant {
     type Output = Instant;
 
@@ -304,12 +402,14 @@ impl Sub<Duration> for Instant {
     }
 }
 
+#[cfg(feature = "std")]
 impl SubAssign<Duration> for Instant {
     fn sub_assign(&mut self, other: Duration) {
         *self = *self - other;
     }
 }
 
+#[cfg(feature = "std")]
 impl Sub<Instant> for Instant {
     type Output = Duration;
 
@@ -328,18 +428,24 @@ impl Sub<Instant> for Instant {
     }
 }
 
+#[cfg(feature = "std")]
 impl fmt::Debug for Instant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
     }
 }
 
+#[cfg(feature = "std")]
 impl SystemTime {
     /// An anchor in time which can be used to create new `SystemTime` instances or
     /// learn about where in time a `SystemTime` lies.
     pub const UNIX_EPOCH: SystemTime = UNIX_EPOCH;
 
     pub fn now() -> SystemTime {
+        if let Some(elapsed) = manual_elapsed() {
+            return START.0.checked_add(elapsed).expect("clock error");
+        }
+
         // Get current system time unscaled from the os
         let now = SystemTime(time::SystemTime::now());
 
@@ -353,18 +459,9 @@ impl SystemTime {
             // Calculate elapsed "real" time since start timestamp
             let duration_since_start = now.duration_since(start).unwrap();
 
-            // Calculate new "feo" time
-            if factor.is_positive() {
-                // Factor is greater than 0, so we speed up time by multiplying
-                // the elapsed time by factor add add to the start time
-                let elapsed = duration_since_start * factor.unsigned_abs();
-                start.checked_add(elapsed).expect("clock error")
-            } else {
-                // Factor is less than 0, so we slow down time by dividing
-                // the elapsed time by factor add add to the start time
-                let elapsed = duration_since_start / factor.unsigned_abs();
-                start.checked_add(elapsed).expect("clock error")
-            }
+            // Calculate new "feo" time by scaling the elapsed time and adding it to the start time
+            let elapsed = scale::scale(duration_since_start, factor);
+            start.checked_add(elapsed).expect("clock error")
         } else {
             now
         }
@@ -442,6 +539,7 @@ impl SystemTime {
     }
 }
 
+#[cfg(feature = "std")]
 impl Add<Duration> for SystemTime {
     type Output = SystemTime;
 
@@ -454,12 +552,14 @@ impl Add<Duration> for SystemTime {
     }
 }
 
+#[cfg(feature = "std")]
 impl AddAssign<Duration> for SystemTime {
     fn add_assign(&mut self, other: Duration) {
         *self = *self + other;
     }
 }
 
+#[cfg(feature = "std")]
 impl Sub<Duration> for SystemTime {
     type Output = SystemTime;
 
@@ -468,18 +568,21 @@ impl Sub<Duration> for SystemTime {
     }
 }
 
+#[cfg(feature = "std")]
 impl SubAssign<Duration> for SystemTime {
     fn sub_assign(&mut self, other: Duration) {
         *self = *self - other;
     }
 }
 
+#[cfg(feature = "std")]
 impl fmt::Debug for SystemTime {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
     }
 }
 
+#[cfg(feature = "std")]
 impl SystemTimeError {
     /// Returns the positive duration which represents how far forward the
     /// second system time was from the first.
@@ -489,12 +592,14 @@ impl SystemTimeError {
     }
 }
 
+#[cfg(feature = "std")]
 impl fmt::Display for SystemTimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "second time provided was later than self")
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for SystemTimeError {
     #[allow(deprecated)]
     fn description(&self) -> &str {
@@ -502,27 +607,9 @@ impl Error for SystemTimeError {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<time::SystemTimeError> for SystemTimeError {
     fn from(e: time::SystemTimeError) -> Self {
         SystemTimeError(e.duration())
     }
 }
-
-impl Scaled for Duration {
-    fn scaled(&self) -> Self {
-        let factor = FACTOR.load(Ordering::Relaxed);
-        if factor != 0 {
-            if factor.is_positive() {
-                // Factor is greater than 0, so we speed up time by dividing
-                // the duration by factor
-                *self / factor.unsigned_abs()
-            } else {
-                // Factor is less than 0, so we slow down time by multiplying
-                // the duration by factor
-                *self * factor.unsigned_abs()
-            }
-        } else {
-            *self
-        }
-    }
-}
@@ -0,0 +1,106 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Time scaling math
+//!
+//! Pure arithmetic on [`core::time::Duration`] which is available without `std`, e.g. for
+//! `no_std` activity libraries. The speed factor is process global: it is set by
+//! [`speed`](crate::speed) which requires the `std` feature.
+
+use core::sync::atomic::{AtomicI32, Ordering};
+use core::time::Duration;
+
+/// Factor on systemtime and instant if set via `speed`
+pub(crate) static FACTOR: AtomicI32 = AtomicI32::new(0);
+
+/// A trait for scaling durations based on the factor set by `speed`.
+pub trait Scaled {
+    /// Scale the duration based on the factor set by `speed` for using in sleep functions.
+    /// Background: std::thread::sleep and friends need a time base on the unscaled system time.
+    /// If the factor is set to a positive value the duration must be shortened (shorter sleep).
+    /// If the factor is set to a negative value the duration must be lengthened (longer sleep).
+    fn scaled(&self) -> Self;
+}
+
+impl Scaled for Duration {
+    fn scaled(&self) -> Self {
+        unscale(*self, FACTOR.load(Ordering::Relaxed))
+    }
+}
+
+/// Get the current speed factor if set. Otherwise return None.
+pub fn get_speed() -> Option<i32> {
+    let factor = FACTOR.load(Ordering::Relaxed);
+    (factor != 0).then_some(factor)
+}
+
+/// Scale a duration elapsed in real time to the scaled time base with the given factor
+///
+/// A positive factor speeds up time, a negative factor slows it down and zero leaves the
+/// duration unchanged.
+pub fn scale(elapsed: Duration, factor: i32) -> Duration {
+    if factor.is_positive() {
+        // Factor is greater than 0, so we speed up time by multiplying
+        // the elapsed time by factor
+        elapsed * factor.unsigned_abs()
+    } else if factor.is_negative() {
+        // Factor is less than 0, so we slow down time by dividing
+        // the elapsed time by factor
+        elapsed / factor.unsigned_abs()
+    } else {
+        elapsed
+    }
+}
+
+/// Scale a duration in the scaled time base back to real time with the given factor,
+/// i.e. the inverse of [`scale`]
+pub fn unscale(duration: Duration, factor: i32) -> Duration {
+    if factor.is_positive() {
+        // Factor is greater than 0, so we speed up time by dividing
+        // the duration by factor
+        duration / factor.unsigned_abs()
+    } else if factor.is_negative() {
+        // Factor is less than 0, so we slow down time by multiplying
+        // the duration by factor
+        duration * factor.unsigned_abs()
+    } else {
+        duration
+    }
+}
+
+/// Checked [`unscale`], returning `None` if the real time duration overflows
+pub fn checked_unscale(duration: Duration, factor: i32) -> Option<Duration> {
+    if factor.is_negative() {
+        duration.checked_mul(factor.unsigned_abs())
+    } else {
+        Some(unscale(duration, factor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checked_unscale, scale, unscale};
+    use core::time::Duration;
+
+    #[test]
+    fn test_scale_unscale() {
+        let second = Duration::from_secs(1);
+        assert_eq!(scale(second, 0), second);
+        assert_eq!(scale(second, 4), Duration::from_secs(4));
+        assert_eq!(scale(second, -4), Duration::from_millis(250));
+
+        assert_eq!(unscale(second, 0), second);
+        assert_eq!(unscale(second, 4), Duration::from_millis(250));
+        assert_eq!(unscale(second, -4), Duration::from_secs(4));
+
+        for factor in [-4, -1, 0, 1, 4] {
+            assert_eq!(unscale(scale(second, factor), factor), second);
+            assert_eq!(
+                checked_unscale(second, factor),
+                Some(unscale(second, factor))
+            );
+        }
+        assert_eq!(checked_unscale(Duration::MAX, -2), None);
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Serde support for [`Instant`] and [`SystemTime`]
+
+use crate::{Duration, Instant, SystemTime, START, UNIX_EPOCH};
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Nanoseconds of `duration` as u64
+fn as_nanos<E: serde::ser::Error>(duration: Duration) -> Result<u64, E> {
+    u64::try_from(duration.as_nanos()).map_err(|_| E::custom("duration exceeds u64 nanoseconds"))
+}
+
+/// Serialized as nanoseconds since [`UNIX_EPOCH`]
+impl Serialize for SystemTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let since_epoch = self
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| S::Error::custom("system time before unix epoch"))?;
+        serializer.serialize_u64(as_nanos(since_epoch)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for SystemTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let nanos = u64::deserialize(deserializer)?;
+        UNIX_EPOCH
+            .checked_add(Duration::from_nanos(nanos))
+            .ok_or_else(|| D::Error::custom("system time out of range"))
+    }
+}
+
+/// Serialized as nanoseconds since the start of the time scaling of this process
+///
+/// The representation is only meaningful within the same process run.
+impl Serialize for Instant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let since_start = self
+            .checked_duration_since(START.1)
+            .ok_or_else(|| S::Error::custom("instant before process start"))?;
+        serializer.serialize_u64(as_nanos(since_start)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Instant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let nanos = u64::deserialize(deserializer)?;
+        START
+            .1
+            .checked_add(Duration::from_nanos(nanos))
+            .ok_or_else(|| D::Error::custom("instant out of range"))
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stopwatch accumulating elapsed time over several runs
+
+use crate::{Duration, Instant};
+
+/// Stopwatch accumulating the time elapsed while it runs
+///
+/// Built on [`Instant`], so the measured time is scaled by the speed factor set by
+/// [`speed`](crate::speed) and follows the [`manual`](crate::manual) clock. Start and stop it
+/// around the section to measure, e.g. the step of an activity, to get its total time over
+/// all steps. Laps split the total into the parts between two calls of [`Stopwatch::lap`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stopwatch {
+    /// Start of the current run, if running
+    started: Option<Instant>,
+    /// Time accumulated by the previous runs
+    accumulated: Duration,
+    /// Total elapsed time at the previous lap
+    lap_start: Duration,
+}
+
+impl Stopwatch {
+    /// Create a stopped stopwatch without elapsed time
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a running stopwatch without elapsed time
+    pub fn start_new() -> Self {
+        let mut stopwatch = Self::new();
+        stopwatch.start();
+        stopwatch
+    }
+
+    /// Start the stopwatch, keeping the time accumulated so far. Does nothing if running.
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    /// Stop the stopwatch and return the total elapsed time. Does nothing if stopped.
+    pub fn stop(&mut self) -> Duration {
+        if let Some(started) = self.started.take() {
+            self.accumulated += started.elapsed();
+        }
+        self.accumulated
+    }
+
+    /// Whether the stopwatch is running
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Total time elapsed while running, including the current run
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        let running = self.started.map(|started| started.elapsed());
+        self.accumulated + running.unwrap_or_default()
+    }
+
+    /// Complete a lap and return the time elapsed while running since the previous lap, or
+    /// since the stopwatch has been created or reset
+    pub fn lap(&mut self) -> Duration {
+        let elapsed = self.elapsed();
+        let lap = elapsed - self.lap_start;
+        self.lap_start = elapsed;
+        lap
+    }
+
+    /// Stop the stopwatch and discard the elapsed time and laps
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Discard the elapsed time and laps and start the stopwatch again
+    pub fn restart(&mut self) {
+        *self = Self::start_new();
+    }
+}
@@ -92,6 +92,30 @@ fn instant_math() {
     assert_eq!(a + year, a.checked_add(year).unwrap());
 }
 
+#[test]
+fn instant_checked_add_scaled() {
+    let now = Instant::now();
+    let second = Duration::from_secs(1);
+
+    // Twice as fast: the real deadline is half a second ahead
+    assert_eq!(
+        now.checked_add_scaled_with(second, 2),
+        Some(now + Duration::from_millis(500))
+    );
+    // Twice as slow: the real deadline is two seconds ahead
+    assert_eq!(
+        now.checked_add_scaled_with(second, -2),
+        Some(now + Duration::from_secs(2))
+    );
+    // Unscaled
+    assert_eq!(now.checked_add_scaled_with(second, 0), Some(now + second));
+
+    // Overflow of the scaled duration is reported like for `checked_add`
+    let max_duration = Duration::from_secs(u64::MAX / 2);
+    let far = now.checked_add_scaled_with(max_duration, -2);
+    assert_eq!(far, None);
+}
+
 #[test]
 fn instant_math_is_associative() {
     let now = Instant::now();
@@ -275,3 +299,41 @@ fn set_speed_twice() {
     assert_eq!(crate::get_speed(), Some(2));
     crate::speed(3);
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn system_time_serde_round_trip() {
+    let time = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+    let serialized = serde_json::to_string(&time).unwrap();
+    assert_eq!(serialized, "1700000000123456789");
+    assert_eq!(
+        serde_json::from_str::<SystemTime>(&serialized).unwrap(),
+        time
+    );
+
+    let now = SystemTime::now();
+    let serialized = serde_json::to_string(&now).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SystemTime>(&serialized).unwrap(),
+        now
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn instant_serde_round_trip() {
+    let now = Instant::now();
+    let later = now + Duration::from_millis(1500);
+    for instant in [now, later] {
+        let serialized = serde_json::to_string(&instant).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Instant>(&serialized).unwrap(),
+            instant
+        );
+    }
+
+    // Instants are represented relative to each other within the process
+    let now_nanos: u64 = serde_json::from_str(&serde_json::to_string(&now).unwrap()).unwrap();
+    let later_nanos: u64 = serde_json::from_str(&serde_json::to_string(&later).unwrap()).unwrap();
+    assert_eq!(later_nanos - now_nanos, 1_500_000_000);
+}
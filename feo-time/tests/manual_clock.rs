@@ -0,0 +1,33 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tests of the manual clock, in their own process as the clock mode is process global
+
+use feo_time::{Duration, Instant, SystemTime};
+use std::thread;
+
+#[test]
+fn manual_clock() {
+    assert!(!feo_time::is_manual());
+    feo_time::manual();
+    assert!(feo_time::is_manual());
+    let instant = Instant::now();
+    let system_time = SystemTime::now();
+
+    // Time does not pass on its own
+    thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(Instant::now(), instant);
+    assert_eq!(SystemTime::now(), system_time);
+
+    feo_time::advance(Duration::from_millis(250));
+    feo_time::advance(Duration::from_nanos(7));
+    let advanced = Duration::from_millis(250) + Duration::from_nanos(7);
+    assert_eq!(Instant::now() - instant, advanced);
+    assert_eq!(
+        SystemTime::now().duration_since(system_time).unwrap(),
+        advanced
+    );
+    assert_eq!(instant.elapsed(), advanced);
+    assert_eq!(feo_time::get_speed(), None);
+}
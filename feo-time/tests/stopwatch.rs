@@ -0,0 +1,65 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tests of the stopwatch on the manual clock, in their own process as the clock mode is
+//! process global
+
+use feo_time::{Duration, Stopwatch};
+
+#[test]
+fn stopwatch() {
+    feo_time::manual();
+    let ms = Duration::from_millis;
+
+    // Time passes only while running
+    let mut stopwatch = Stopwatch::new();
+    assert!(!stopwatch.is_running());
+    feo_time::advance(ms(5));
+    assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+
+    stopwatch.start();
+    feo_time::advance(ms(10));
+    assert_eq!(stopwatch.stop(), ms(10));
+    feo_time::advance(ms(100));
+    assert_eq!(stopwatch.elapsed(), ms(10));
+
+    // Laps accumulate to the total, counting only the time while running
+    assert_eq!(stopwatch.lap(), ms(10));
+    stopwatch.start();
+    feo_time::advance(ms(20));
+    assert_eq!(stopwatch.elapsed(), ms(30));
+    assert_eq!(stopwatch.lap(), ms(20));
+    feo_time::advance(ms(3));
+    stopwatch.stop();
+    feo_time::advance(ms(50));
+    stopwatch.start();
+    feo_time::advance(ms(4));
+    assert_eq!(stopwatch.lap(), ms(7));
+    assert_eq!(stopwatch.lap(), Duration::ZERO);
+    assert_eq!(stopwatch.stop(), ms(37));
+
+    // Starting a running or stopping a stopped stopwatch does nothing
+    stopwatch.start();
+    feo_time::advance(ms(1));
+    stopwatch.start();
+    feo_time::advance(ms(1));
+    assert_eq!(stopwatch.stop(), ms(39));
+    assert_eq!(stopwatch.stop(), ms(39));
+
+    // Reset discards the elapsed time and laps
+    stopwatch.reset();
+    assert!(!stopwatch.is_running());
+    assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    feo_time::advance(ms(5));
+    assert_eq!(stopwatch.lap(), Duration::ZERO);
+
+    stopwatch.restart();
+    assert!(stopwatch.is_running());
+    feo_time::advance(ms(6));
+    assert_eq!(stopwatch.lap(), ms(6));
+
+    let started = Stopwatch::start_new();
+    feo_time::advance(ms(2));
+    assert_eq!(started.elapsed(), ms(2));
+}
@@ -0,0 +1,152 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Chrome trace event JSON output format
+//!
+//! Writes the trace as a JSON array of trace events which can be loaded in `chrome://tracing`.
+
+use crate::writer::{metadata_name, Event, EventKind, Format};
+use anyhow::Error;
+use feo_log::{info, warn};
+use serde_json::{json, Value};
+use std::io;
+
+/// Chrome trace event JSON output format
+pub struct ChromeJson<W: io::Write> {
+    writer: (W, u64),
+    /// Whether any trace event has been written
+    started: bool,
+}
+
+impl<W: io::Write> Drop for ChromeJson<W> {
+    fn drop(&mut self) {
+        // Close the array of trace events
+        let end: &[u8] = if self.started { b"\n]\n" } else { b"[]\n" };
+        if let Err(e) = self
+            .writer
+            .0
+            .write_all(end)
+            .and_then(|_| self.writer.0.flush())
+        {
+            warn!("Failed to finish chrome json output: {e:?}");
+        }
+        self.writer.1 += end.len() as u64;
+
+        info!(
+            "Dropping chrome json writer. Wrote {} bytes",
+            human_bytes::human_bytes(self.writer.1 as f64)
+        );
+    }
+}
+
+impl<W: io::Write> ChromeJson<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: (writer, 0),
+            started: false,
+        }
+    }
+}
+
+impl<W: io::Write> Format for ChromeJson<W> {
+    type Packet = Value;
+
+    fn descriptors(
+        &self,
+        pid: u32,
+        process_name: Option<&str>,
+        tid: u32,
+        thread_name: Option<&str>,
+    ) -> Vec<Value> {
+        let process = process_name.map(|name| {
+            json!({ "ph": "M", "name": "process_name", "pid": pid, "args": { "name": name } })
+        });
+        let thread = thread_name.map(|name| {
+            json!({
+                "ph": "M",
+                "name": "thread_name",
+                "pid": pid,
+                "tid": tid,
+                "args": { "name": name },
+            })
+        });
+        process.into_iter().chain(thread).collect()
+    }
+
+    fn event(&self, event: &Event<'_>) -> Value {
+        let ph = match event.kind {
+            EventKind::SliceBegin => "B",
+            EventKind::SliceEnd => "E",
+            EventKind::Instant => "i",
+        };
+        let mut value = json!({
+            "name": metadata_name(event.attributes).unwrap_or_default(),
+            "ph": ph,
+            "pid": event.pid,
+            "tid": event.tid,
+            // Microseconds
+            "ts": event.timestamp as f64 / 1000.0,
+        });
+        if event.kind == EventKind::Instant {
+            // Thread scoped instant event
+            value["s"] = json!("t");
+        }
+        if let Some(fields) = event.attributes.get("fields") {
+            value["args"] = fields.clone();
+        }
+        value
+    }
+
+    fn write(&mut self, packets: Vec<Value>) -> Result<(), Error> {
+        for packet in packets {
+            let separator: &[u8] = if self.started { b",\n" } else { b"[\n" };
+            self.started = true;
+            let buf = serde_json::to_vec(&packet)?;
+            self.writer.0.write_all(separator)?;
+            self.writer.0.write_all(&buf)?;
+            self.writer.1 += (separator.len() + buf.len()) as u64;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer.0.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChromeJson;
+    use crate::data::TraceData;
+    use crate::writer::test::{new_span, packet, PID, TID};
+    use crate::writer::TraceWriter;
+    use serde_json::Value;
+
+    #[test]
+    fn test_begin_end_records() {
+        let mut buf = Vec::new();
+        let mut writer = TraceWriter::new(ChromeJson::new(&mut buf));
+
+        writer.on_packet(new_span(1000, 1)).unwrap();
+        writer
+            .on_packet(packet(2000, TraceData::EnterSpan { id: 1 }))
+            .unwrap();
+        writer
+            .on_packet(packet(3000, TraceData::ExitSpan { id: 1 }))
+            .unwrap();
+        drop(writer);
+
+        let events: Vec<Value> = serde_json::from_slice(&buf).expect("invalid json");
+        let slices: Vec<&Value> = events.iter().filter(|e| e["ph"] != "M").collect();
+        assert_eq!(slices.len(), 2);
+        for (slice, (ph, ts)) in slices.iter().zip([("B", 2.0), ("E", 3.0)]) {
+            assert_eq!(slice["ph"], ph);
+            assert_eq!(slice["name"], "span");
+            assert_eq!(slice["pid"], PID);
+            assert_eq!(slice["tid"], TID);
+            assert_eq!(slice["ts"], ts);
+        }
+    }
+}
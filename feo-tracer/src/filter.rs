@@ -0,0 +1,247 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Filter trace packets by process, thread and span name
+//!
+//! Spans are filtered as a whole when they are created: the enter, exit and records of a
+//! span follow the decision on its `NewSpan`, so the slices written stay balanced. Events
+//! within a span follow the decision on the span.
+//!
+//! Excluded spans are forgotten on their `ExitSpan`, like open spans in the writer. Beyond
+//! [`MAX_OPEN_SPANS`] excluded spans, the oldest one is forgotten and its remaining packets pass.
+
+use crate::data::{ThreadId, TraceData, TracePacket, Value};
+use crate::writer::{metadata_name, MAX_OPEN_SPANS};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+/// Key of a span: process id and span id
+type SpanKey = (u32, u64);
+
+/// A single filter rule, parsed from `process=<glob>`, `thread=<tid>` or `span=<substring>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Process name matching a glob pattern with `*` and `?` wildcards
+    Process(String),
+    /// Thread id
+    Thread(ThreadId),
+    /// Name of spans and events containing a substring
+    Name(String),
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("invalid filter {s}, expected process=<glob>, thread=<tid> or span=<name>");
+        let (kind, value) = s.split_once('=').ok_or_else(invalid)?;
+        match kind {
+            "process" => Ok(Rule::Process(value.to_string())),
+            "thread" => value.parse().map(Rule::Thread).map_err(|_| invalid()),
+            "span" => Ok(Rule::Name(value.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Process(glob) => write!(f, "process={glob}"),
+            Rule::Thread(tid) => write!(f, "thread={tid}"),
+            Rule::Name(name) => write!(f, "span={name}"),
+        }
+    }
+}
+
+impl Rule {
+    /// Check the rule against a span or an event outside of spans
+    /// Remember an excluded span, forgetting the oldest one beyond [`MAX_OPEN_SPANS`]
+    fn exclude(&mut self, key: SpanKey) {
+        if self.order.len() >= MAX_OPEN_SPANS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.excluded.remove(&oldest);
+            }
+        }
+        self.excluded.insert(key);
+        self.order.push_back(key);
+    }
+
+    fn matches(&self, packet: &TracePacket, attributes: &Value) -> bool {
+        match self {
+            Rule::Process(glob) => packet
+                .process
+                .name
+                .as_deref()
+                .is_some_and(|name| glob_match(glob, name)),
+            Rule::Thread(tid) => packet.thread.as_ref().is_some_and(|t| t.id == *tid),
+            Rule::Name(name) => metadata_name(attributes).is_some_and(|n| n.contains(name)),
+        }
+    }
+}
+
+/// Filter passing the spans and events matching all of its rules
+#[derive(Debug, Default)]
+pub struct Filter {
+    rules: Vec<Rule>,
+    /// Spans that did not pass the filter
+    excluded: HashSet<SpanKey>,
+    /// Excluded spans in order of their creation
+    order: VecDeque<SpanKey>,
+}
+
+impl Filter {
+    /// Create a filter from the given rules. Without rules, all packets pass.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// Check whether a packet passes the filter
+    ///
+    /// Process lifecycle packets always pass. Packets of excluded spans are dropped.
+    pub fn pass(&mut self, packet: &TracePacket) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+
+        let pid = packet.process.id;
+        match &packet.data {
+            TraceData::Exec => true,
+            TraceData::Exit => {
+                self.excluded.retain(|key| key.0 != pid);
+                self.order.retain(|key| key.0 != pid);
+                true
+            }
+            TraceData::NewSpan { id, attributes } => {
+                let pass = self.matches(packet, attributes);
+                if !pass {
+                    self.exclude((pid, *id));
+                }
+                pass
+            }
+            TraceData::ExitSpan { id } => {
+                let key = (pid, *id);
+                let excluded = self.excluded.remove(&key);
+                if excluded {
+                    self.order.retain(|k| *k != key);
+                }
+                !excluded
+            }
+            TraceData::EnterSpan { id }
+            | TraceData::Record { id, .. }
+            | TraceData::Event {
+                parent_span: Some(id),
+                ..
+            } => !self.excluded.contains(&(pid, *id)),
+            TraceData::Event {
+                parent_span: None,
+                event,
+            } => self.matches(packet, event),
+        }
+    }
+
+    /// Check whether the given span was excluded by the filter
+    pub fn is_excluded(&self, pid: u32, id: u64) -> bool {
+        self.excluded.contains(&(pid, id))
+    }
+
+    fn matches(&self, packet: &TracePacket, attributes: &Value) -> bool {
+        self.rules
+            .iter()
+            .all(|rule| rule.matches(packet, attributes))
+    }
+}
+
+/// Match a text against a glob pattern with `*` (any sequence) and `?` (any character)
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Position after the last `*` in the pattern and the text position it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` consume one more character
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob_match, Filter, Rule};
+    use crate::data::TraceData;
+    use crate::writer::test::{new_span, packet, PID, TID};
+    use crate::writer::MAX_OPEN_SPANS;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("adas_*", "adas_primary"));
+        assert!(glob_match("*primary", "adas_primary"));
+        assert!(glob_match("a?as*y", "adas_primary"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("adas_?", "adas_primary"));
+        assert!(!glob_match("*secondary*", "adas_primary"));
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        for s in ["process=adas_*", "thread=42", "span=Step"] {
+            assert_eq!(s.parse::<Rule>().unwrap().to_string(), s);
+        }
+        assert!("thread=x".parse::<Rule>().is_err());
+        assert!("pid=1".parse::<Rule>().is_err());
+        assert!("span".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_excluded_span() {
+        let mut filter = Filter::new(vec![Rule::Thread(TID + 1)]);
+        assert!(!filter.pass(&new_span(1, 1)));
+        assert!(!filter.pass(&packet(2, TraceData::EnterSpan { id: 1 })));
+        assert!(filter.is_excluded(PID, 1));
+        assert!(filter.pass(&packet(3, TraceData::Exit)));
+        assert!(!filter.is_excluded(PID, 1));
+    }
+
+    #[test]
+    fn test_excluded_spans_bounded() {
+        let mut filter = Filter::new(vec![Rule::Thread(TID + 1)]);
+        assert!(!filter.pass(&new_span(1, 1)));
+        assert!(!filter.pass(&packet(2, TraceData::ExitSpan { id: 1 })));
+        assert!(!filter.is_excluded(PID, 1));
+
+        let spans = MAX_OPEN_SPANS as u64 + 100;
+        for id in 0..spans {
+            assert!(!filter.pass(&new_span(id, id)));
+        }
+        assert_eq!(filter.excluded.len(), MAX_OPEN_SPANS);
+        assert_eq!(filter.order.len(), MAX_OPEN_SPANS);
+        // The oldest spans have been forgotten, the latest one is still excluded
+        assert!(!filter.is_excluded(PID, 0));
+        assert!(filter.is_excluded(PID, spans - 1));
+    }
+}
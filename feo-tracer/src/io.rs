@@ -4,20 +4,31 @@
 
 //! Collect trace data - placeholder
 
-use crate::data;
-use anyhow::{Context, Error};
+use crate::data::{self, TraceData};
+use crate::writer::MAX_OPEN_SPANS;
+use anyhow::{bail, Context, Error};
 use feo_log::{debug, info, warn};
 use feo_tracing::protocol;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::SystemTime;
-use tokio::sync::mpsc;
+use tokio::sync::mpsc::{self, error::TrySendError};
 use tokio::task;
 use tokio_seqpacket::{UnixSeqpacket, UnixSeqpacketListener};
 
 pub const UNIX_PACKET_PATH: &str = "/tmp/feo-tracer.sock";
 
+/// Number of trace packets dropped because the sink was full
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Number of trace packets of all connections dropped so far because the consumers of the
+/// sink did not keep up
+pub fn dropped_packets() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
 pub async fn listen(path: &Path, sink: mpsc::Sender<data::TracePacket>) -> Result<(), Error> {
     // Bind
     info!("Binding to {path:?}");
@@ -36,6 +47,34 @@ pub async fn listen(path: &Path, sink: mpsc::Sender<data::TracePacket>) -> Resul
     }
 }
 
+/// Answer the hello of a new connection, returning the negotiated maximum packet size
+///
+/// A peer speaking another protocol version is answered with the version of the tracer, for
+/// it to report the mismatch as well, and rejected. A legacy peer sending trace packets right
+/// away is reported with [`LEGACY_PROTOCOL_VERSION`](protocol::LEGACY_PROTOCOL_VERSION).
+async fn hello(socket: &UnixSeqpacket) -> Result<usize, Error> {
+    let mut buffer = [0u8; protocol::HELLO_SIZE];
+    let len = socket
+        .recv(&mut buffer)
+        .await
+        .context("failed to receive hello")?;
+    let request = protocol::Hello::decode(&buffer[..len]).context("failed to decode hello")?;
+
+    let answer = request.answer(protocol::MAX_NEGOTIABLE_PACKET_SIZE);
+    let message = answer
+        .encode(&mut buffer)
+        .context("failed to encode hello")?;
+    socket.send(message).await.context("failed to send hello")?;
+    if request.version != protocol::PROTOCOL_VERSION {
+        bail!(
+            "peer protocol v{}, tracer expects v{}",
+            request.version,
+            protocol::PROTOCOL_VERSION
+        );
+    }
+    Ok(answer.max_packet_size as usize)
+}
+
 async fn connection(socket: UnixSeqpacket, sink: mpsc::Sender<data::TracePacket>) {
     // Retrieve the PID of the peer
     let pid = socket.peer_cred().unwrap().pid().unwrap() as u32;
@@ -48,16 +87,25 @@ async fn connection(socket: UnixSeqpacket, sink: mpsc::Sender<data::TracePacket>
     // Create a cache for the thread names in order to avoid frequent reads of procfs entries
     let mut thread_cache = ThreadCache::new(pid);
 
+    // Negotiate the maximum packet size
+    let max_packet_size = match hello(&socket).await {
+        Ok(max_packet_size) => max_packet_size,
+        Err(e) => {
+            warn!("Handshake with {pid} failed: {e:#}. Skipping connection");
+            return;
+        }
+    };
+
     // Buffer for incoming packets
-    let mut buffer = [0u8; protocol::MAX_PACKET_SIZE];
+    let mut buffer = vec![0u8; max_packet_size];
 
     info!(
-        "Processing messages from {pid:x} ({})",
+        "Processing messages from {pid:x} ({}) with packets of up to {max_packet_size} bytes",
         process_name.as_deref().unwrap_or("")
     );
 
     // Send a process exec event
-    sink.send(data::TracePacket {
+    let exec = sink.send(data::TracePacket {
         timestamp: SystemTime::now(),
         process: data::Process {
             id: pid,
@@ -66,9 +114,15 @@ async fn connection(socket: UnixSeqpacket, sink: mpsc::Sender<data::TracePacket>
         thread: None,
         data: data::TraceData::Exec,
         metadata: data::Metadata::default(),
-    })
-    .await
-    .expect("channel error");
+    });
+    if exec.await.is_err() {
+        info!("Sink closed. Closing connection from {pid}");
+        return;
+    }
+
+    // Number of packets of this connection dropped because the sink was full
+    let mut dropped = 0u64;
+    let mut dropped_spans = DroppedSpans::default();
 
     // Loop on messages received via the socket. Each message contains a full valid trace packet
     loop {
@@ -101,12 +155,42 @@ async fn connection(socket: UnixSeqpacket, sink: mpsc::Sender<data::TracePacket>
             thread.name = thread_cache.get(thread.id).map(|s| s.to_string());
         }
 
-        // Forward packet to consumers connected to the sink
-        sink.send(packet).await.expect("channel error");
+        // Forward packet to consumers connected to the sink. Drop it if they do not keep up
+        // instead of stalling the connection and, with it, the client. Only the exit of a span
+        // waits for room, otherwise its slice would never end.
+        let sent = if dropped_spans.drops(&packet.data) {
+            Err(TrySendError::Full(packet))
+        } else if matches!(packet.data, TraceData::ExitSpan { .. }) {
+            sink.send(packet)
+                .await
+                .map_err(|e| TrySendError::Closed(e.0))
+        } else {
+            sink.try_send(packet)
+        };
+        match sent {
+            Ok(()) => (),
+            Err(TrySendError::Full(packet)) => {
+                if dropped == 0 {
+                    warn!("Sink full. Dropping packets from {pid}");
+                }
+                dropped_spans.insert(&packet.data);
+                dropped += 1;
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Closed(_)) => {
+                info!("Sink closed. Closing connection from {pid}");
+                return;
+            }
+        }
     }
 
-    // Send a process exit event
-    sink.send(data::TracePacket {
+    if dropped > 0 {
+        warn!("Dropped {dropped} packets from {pid}");
+    }
+
+    // Send a process exit event. Wait for the sink to take it in order to close the spans
+    // of the process.
+    let exit = sink.send(data::TracePacket {
         timestamp: SystemTime::now(),
         process: data::Process {
             id: pid,
@@ -115,9 +199,52 @@ async fn connection(socket: UnixSeqpacket, sink: mpsc::Sender<data::TracePacket>
         thread: None,
         data: data::TraceData::Exit,
         metadata: data::Metadata::default(),
-    })
-    .await
-    .expect("channel error");
+    });
+    if exit.await.is_err() {
+        info!("Sink closed before exit of {pid}");
+    }
+}
+
+/// Spans of a connection for which a `NewSpan` or `EnterSpan` packet has been dropped
+///
+/// The remaining packets of such a span are dropped as well, so the trace holds no slice end
+/// without its begin. A span is forgotten on its exit, which is final for the writer.
+#[derive(Debug, Default)]
+struct DroppedSpans(HashSet<data::Id>);
+
+impl DroppedSpans {
+    /// Record the span of a dropped packet, if it is the creation or the enter of a span
+    fn insert(&mut self, data: &TraceData) {
+        match *data {
+            TraceData::NewSpan { id, .. } | TraceData::EnterSpan { id } => {
+                // Beyond that, the writer evicts the open spans anyway
+                if self.0.len() < MAX_OPEN_SPANS {
+                    self.0.insert(id);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Check whether to drop the packet of a span with dropped packets. Forgets the span on
+    /// its exit.
+    fn drops(&mut self, data: &TraceData) -> bool {
+        match *data {
+            TraceData::ExitSpan { id } => self.0.remove(&id),
+            // Span ids are reused once closed
+            TraceData::NewSpan { id, .. } => {
+                self.0.remove(&id);
+                false
+            }
+            TraceData::EnterSpan { id }
+            | TraceData::Record { id, .. }
+            | TraceData::Event {
+                parent_span: Some(id),
+                ..
+            } => self.0.contains(&id),
+            TraceData::Exec | TraceData::Exit | TraceData::Event { .. } => false,
+        }
+    }
 }
 
 /// Cache for thread names in order to avoid frequent reads of procfs entries.
@@ -151,3 +278,158 @@ impl<'a> ThreadCache {
             .as_deref()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{connection, dropped_packets, hello, DroppedSpans};
+    use crate::data::TraceData;
+    use feo_tracing::protocol::{self, Hello, Process, TracePacket};
+    use tokio::sync::mpsc;
+    use tokio::task;
+    use tokio_seqpacket::UnixSeqpacket;
+
+    #[tokio::test]
+    async fn test_sink_full() {
+        const PACKETS: usize = 100;
+        const CHANNEL_SIZE: usize = 10;
+
+        let (client, server) = UnixSeqpacket::pair().unwrap();
+        let (sink, mut receiver) = mpsc::channel(CHANNEL_SIZE);
+        let handle = task::spawn(connection(server, sink));
+
+        let mut buffer = vec![0u8; protocol::MAX_PACKET_SIZE];
+        let hello = Hello {
+            version: protocol::PROTOCOL_VERSION,
+            max_packet_size: protocol::MAX_PACKET_SIZE as u32,
+        };
+        client
+            .send(hello.encode(&mut buffer).unwrap())
+            .await
+            .unwrap();
+        client.recv(&mut buffer).await.unwrap();
+
+        // Send more packets than the sink takes without consuming any
+        for span in 0..PACKETS as u64 {
+            let data = protocol::TraceData::Enter { span };
+            let packet = TracePacket::new(span, Process { pid: 1, tid: 1 }, data);
+            client
+                .send(postcard::to_slice(&packet, &mut buffer).unwrap())
+                .await
+                .unwrap();
+        }
+        drop(client);
+
+        // Wait until the connection has handled all packets
+        let expected_drops = (PACKETS - (CHANNEL_SIZE - 1)) as u64;
+        while dropped_packets() < expected_drops {
+            task::yield_now().await;
+        }
+
+        // The connection degrades gracefully: it takes what fits, drops the rest and
+        // signals the exit of the client once the sink has room
+        let mut received = Vec::new();
+        while let Some(packet) = receiver.recv().await {
+            let exit = matches!(packet.data, TraceData::Exit);
+            received.push(packet);
+            if exit {
+                break;
+            }
+        }
+        handle.await.unwrap();
+
+        assert!(matches!(received[0].data, TraceData::Exec));
+        let entered = received
+            .iter()
+            .filter(|p| matches!(p.data, TraceData::EnterSpan { .. }))
+            .count();
+        assert_eq!(entered, CHANNEL_SIZE - 1);
+        assert_eq!(dropped_packets(), expected_drops);
+
+        // A closed sink closes the connection without panicking
+        let (client, server) = UnixSeqpacket::pair().unwrap();
+        let (sink, receiver) = mpsc::channel(CHANNEL_SIZE);
+        drop(receiver);
+        client
+            .send(hello.encode(&mut buffer).unwrap())
+            .await
+            .unwrap();
+        connection(server, sink).await;
+    }
+
+    #[test]
+    fn test_dropped_spans() {
+        let mut spans = DroppedSpans::default();
+        spans.insert(&TraceData::EnterSpan { id: 1 });
+        spans.insert(&TraceData::Exit);
+
+        // The remaining packets of the span are dropped until its exit
+        assert!(spans.drops(&TraceData::EnterSpan { id: 1 }));
+        assert!(!spans.drops(&TraceData::EnterSpan { id: 2 }));
+        assert!(spans.drops(&TraceData::ExitSpan { id: 1 }));
+        assert!(!spans.drops(&TraceData::EnterSpan { id: 1 }));
+        assert!(!spans.drops(&TraceData::ExitSpan { id: 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_version_mismatch() {
+        let request = Hello {
+            version: protocol::PROTOCOL_VERSION + 1,
+            max_packet_size: protocol::MAX_PACKET_SIZE as u32,
+        };
+        let mut buffer = [0u8; protocol::HELLO_SIZE];
+
+        // The handshake fails naming both versions
+        let (client, server) = UnixSeqpacket::pair().unwrap();
+        client
+            .send(request.encode(&mut buffer).unwrap())
+            .await
+            .unwrap();
+        let error = hello(&server).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "peer protocol v{}, tracer expects v{}",
+                protocol::PROTOCOL_VERSION + 1,
+                protocol::PROTOCOL_VERSION
+            )
+        );
+
+        // The peer learns the version of the tracer
+        let len = client.recv(&mut buffer).await.unwrap();
+        let answer = Hello::decode(&buffer[..len]).unwrap();
+        assert_eq!(answer.version, protocol::PROTOCOL_VERSION);
+
+        // The connection is skipped without forwarding anything to the sink
+        let (client, server) = UnixSeqpacket::pair().unwrap();
+        let (sink, mut receiver) = mpsc::channel(1);
+        client
+            .send(request.encode(&mut buffer).unwrap())
+            .await
+            .unwrap();
+        connection(server, sink).await;
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_peer() {
+        let mut buffer = vec![0u8; protocol::MAX_PACKET_SIZE];
+        let data = protocol::TraceData::Enter { span: 1 };
+        let packet = TracePacket::now_with_data(data);
+
+        // A peer sending trace packets without a hello is reported as legacy peer
+        let (client, server) = UnixSeqpacket::pair().unwrap();
+        client
+            .send(postcard::to_slice(&packet, &mut buffer).unwrap())
+            .await
+            .unwrap();
+        let error = hello(&server).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "peer protocol v{}, tracer expects v{}",
+                protocol::LEGACY_PROTOCOL_VERSION,
+                protocol::PROTOCOL_VERSION
+            )
+        );
+    }
+}
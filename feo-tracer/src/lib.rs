@@ -4,6 +4,9 @@
 
 //! Central trace collector
 
+pub mod chrome;
 pub mod data;
+pub mod filter;
 pub mod io;
 pub mod perfetto;
+pub mod writer;
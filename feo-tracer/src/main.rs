@@ -6,13 +6,18 @@
 
 use anyhow::{bail, Context, Error};
 use argh::FromArgs;
-use feo_log::{debug, info, LevelFilter};
-use feo_tracer::io::listen;
-use feo_tracer::perfetto;
+use feo_log::{debug, info, warn, LevelFilter};
+use feo_tracer::chrome::ChromeJson;
+use feo_tracer::data::TracePacket;
+use feo_tracer::filter::{Filter, Rule};
+use feo_tracer::io::{dropped_packets, listen};
+use feo_tracer::perfetto::Perfetto;
+use feo_tracer::writer::TraceWriter;
 use futures::FutureExt;
 use indicatif_log_bridge::LogWrapper;
 use std::future::pending;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::{fs, io};
 use tokio::sync::mpsc;
 use tokio::{runtime, select, signal, task, time};
@@ -20,11 +25,36 @@ use tokio::{runtime, select, signal, task, time};
 /// Progress bar wrapper
 mod progress;
 
-/// Path to the seqpacket socket
+/// Default path to the seqpacket socket
 const UNIX_PACKET_PATH: &str = "/tmp/feo-tracer.sock";
+/// Output path selecting stdout
+const STDOUT: &str = "-";
 /// Size of the message channel
 const MESSAGE_CHANNEL_SIZE: usize = 100;
 
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Perfetto protobuf
+    Perfetto,
+    /// Chrome trace event JSON
+    ChromeJson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perfetto" => Ok(Self::Perfetto),
+            "chrome-json" => Ok(Self::ChromeJson),
+            _ => Err(format!(
+                "invalid format {s}, expected perfetto or chrome-json"
+            )),
+        }
+    }
+}
+
 #[derive(FromArgs)]
 #[argh(help_triggers("-h", "--help", "help"))]
 /// Tracer arguments
@@ -33,13 +63,27 @@ struct Args {
     #[argh(option, short = 'd')]
     duration: Option<u64>,
 
-    #[argh(description = "output path")]
+    #[argh(description = "output path or - for stdout")]
     #[argh(option, short = 'o')]
     out: PathBuf,
 
+    #[argh(description = "path of the seqpacket socket to listen on")]
+    #[argh(option, short = 's', default = "PathBuf::from(UNIX_PACKET_PATH)")]
+    socket: PathBuf,
+
+    #[argh(description = "output format: perfetto (default) or chrome-json")]
+    #[argh(option, short = 'f', default = "OutputFormat::Perfetto")]
+    format: OutputFormat,
+
     #[argh(description = "log level")]
     #[argh(option, short = 'l')]
     log_level: Option<LevelFilter>,
+
+    #[argh(
+        description = "only write spans and events matching all filters: process=<glob>, thread=<tid> or span=<name substring>"
+    )]
+    #[argh(option)]
+    filter: Vec<Rule>,
 }
 
 /// Tracer main entry point
@@ -47,17 +91,27 @@ fn main() -> Result<(), Error> {
     let Args {
         duration,
         out,
+        socket,
+        format,
         log_level,
+        filter,
     } = argh::from_env();
 
-    // Initialize logging
-    let logger = feo_logger::Logger::new(true, false);
+    // Initialize logging. Logs go to stderr, stdout may carry the trace.
+    let logger = feo_logger::Logger::new(true, false).console_stderr();
 
     // Initialize progress bar
     let mut progress = progress::Progress::new()?;
 
-    // Wrap the loger in the progress bar to avoid interleaving
-    LogWrapper::new(progress.bar(), logger).try_init()?;
+    let to_stdout = out.as_os_str() == STDOUT;
+    if to_stdout {
+        // The output is streamed into other tooling, no progress to show
+        progress.hide();
+        feo_log::set_boxed_logger(Box::new(logger))?;
+    } else {
+        // Wrap the loger in the progress bar to avoid interleaving
+        LogWrapper::new(progress.bar(), logger).try_init()?;
+    }
     feo_log::set_max_level(log_level.unwrap_or(LevelFilter::Warn));
 
     info!("Starting feo-tracer");
@@ -71,7 +125,7 @@ fn main() -> Result<(), Error> {
     let fan_in_seqpacket = {
         let message_sender = message_sender.clone();
         async move {
-            let path = Path::new(UNIX_PACKET_PATH);
+            let path = socket.as_path();
             // Check if socket is present and remove if necessary
             if path.exists() {
                 debug!("Removing stale socket at {path:?}");
@@ -84,23 +138,46 @@ fn main() -> Result<(), Error> {
     // Handle incoming messages on the message channel. The channel yields
     // messages from all connected processes.
     let process_messages = {
-        // Open the output file and create a progress bar for the writes
-        let writer = io::BufWriter::new(
-            fs::File::create(&out)
-                .with_context(|| format!("failed to create {}", out.display()))?,
-        );
+        // Open the output file or stdout and create a progress bar for the writes
+        let output: Box<dyn io::Write + Send> = if to_stdout {
+            Box::new(io::stdout())
+        } else {
+            Box::new(
+                fs::File::create(&out)
+                    .with_context(|| format!("failed to create {}", out.display()))?,
+            )
+        };
+        let writer = io::BufWriter::new(output);
 
         // Wrap writer in a progress bar
-        let writer = progress.add_writer(&format!("perfetto output ({})", out.display()), writer);
+        let name = match format {
+            OutputFormat::Perfetto => "perfetto",
+            OutputFormat::ChromeJson => "chrome json",
+        };
+        let writer = progress.add_writer(&format!("{name} output ({})", out.display()), writer);
 
-        // Create a perfetto writer
-        let mut perfetto = perfetto::Perfetto::new(writer);
+        // Create a trace writer for the selected format
+        if !filter.is_empty() {
+            let filter: Vec<String> = filter.iter().map(ToString::to_string).collect();
+            info!("Filtering by {}", filter.join(", "));
+        }
+        let filter = Filter::new(filter);
+        let mut on_packet: Box<dyn FnMut(TracePacket) -> Result<(), Error> + Send> = match format {
+            OutputFormat::Perfetto => {
+                let mut writer = TraceWriter::new(Perfetto::new(writer)).with_filter(filter);
+                Box::new(move |packet| writer.on_packet(packet))
+            }
+            OutputFormat::ChromeJson => {
+                let mut writer = TraceWriter::new(ChromeJson::new(writer)).with_filter(filter);
+                Box::new(move |packet| writer.on_packet(packet))
+            }
+        };
 
         // Process messages as they arrive
         let process_packets = async move {
             while let Some(message) = message_receiver.recv().await {
                 progress.on_packet(&message);
-                perfetto.on_packet(message)?;
+                on_packet(message)?;
             }
             Ok(())
         };
@@ -122,7 +199,13 @@ fn main() -> Result<(), Error> {
                 _ =  signal::ctrl_c() => Ok(()),
             }
         };
-        run.inspect(|_| info!("Tracing complete"))
+        run.inspect(|_| {
+            info!("Tracing complete");
+            let dropped = dropped_packets();
+            if dropped > 0 {
+                warn!("Dropped {dropped} packets because the output did not keep up");
+            }
+        })
     };
 
     // Wait for all tasks to finish or error
@@ -4,7 +4,7 @@
 
 use anyhow::Error;
 use feo_tracer::data;
-use indicatif::{MultiProgress, ProgressStyle};
+use indicatif::{MultiProgress, ProgressDrawTarget, ProgressStyle};
 use std::collections::HashMap;
 use std::io::Write;
 use tokio::{task, time};
@@ -46,6 +46,11 @@ impl Progress {
         })
     }
 
+    /// Hide the progress bars, e.g. if the terminal is not attended
+    pub fn hide(&self) {
+        self.bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
     /// Return a copy of the multi progress bar
     pub fn bar(&self) -> MultiProgress {
         self.bar.clone()
@@ -0,0 +1,630 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Trace writer independent of the output format
+//!
+//! The [`TraceWriter`] turns the stream of trace packets of all connected processes into
+//! slices and instant events. It keeps track of the open spans and hands the events to a
+//! [`Format`] for encoding and writing.
+
+use crate::data::{TraceData, TracePacket, Value};
+use crate::filter::Filter;
+use anyhow::{anyhow, bail, Error};
+use feo_log::warn;
+use std::collections::{HashMap, VecDeque};
+use std::time::UNIX_EPOCH;
+
+/// Key of a span: process id and span id
+type SpanKey = (u32, u64);
+
+/// Maximum number of open spans. The least recently active span is evicted beyond that.
+pub(crate) const MAX_OPEN_SPANS: usize = 4096;
+/// Maximum number of unknown spans for which packets are held back
+const MAX_PENDING_SPANS: usize = 1024;
+/// Maximum number of packets held back per unknown span
+const MAX_PENDING_PACKETS: usize = 64;
+
+/// Kind of a trace event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Begin of a slice, i.e. a span has been entered
+    SliceBegin,
+    /// End of a slice, i.e. a span has been exited
+    SliceEnd,
+    /// Instant event
+    Instant,
+}
+
+/// A trace event to be encoded by a [`Format`]
+#[derive(Debug)]
+pub struct Event<'a> {
+    /// Kind of the event
+    pub kind: EventKind,
+    /// Process id
+    pub pid: u32,
+    /// Thread id
+    pub tid: u32,
+    /// Timestamp in nanoseconds since epoch
+    pub timestamp: u64,
+    /// Attributes of the span or event
+    pub attributes: &'a Value,
+}
+
+/// Output format of a [`TraceWriter`]
+pub trait Format {
+    /// Encoded packet
+    type Packet;
+
+    /// Packets describing a process and thread. Written before the events of the thread.
+    fn descriptors(
+        &self,
+        pid: u32,
+        process_name: Option<&str>,
+        tid: u32,
+        thread_name: Option<&str>,
+    ) -> Vec<Self::Packet>;
+
+    /// Encode an event
+    fn event(&self, event: &Event<'_>) -> Self::Packet;
+
+    /// Write encoded packets
+    fn write(&mut self, packets: Vec<Self::Packet>) -> Result<(), Error>;
+
+    /// Flush the underlying writer
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+/// Span
+#[derive(Debug)]
+struct Span<P> {
+    /// Thread group name in which the span was created.
+    pid: u32,
+    /// Packets of the span.
+    packets: Vec<P>,
+    /// Attributes of the span.
+    attributes: Value,
+    /// Timestamp of the last packet of the span in nanoseconds since epoch.
+    last_active: u64,
+    /// Thread on which the span has been entered, if entered.
+    entered: Option<u32>,
+}
+
+impl<P> Span<P> {
+    /// Create a new span.
+    fn new(pid: u32, packets: Vec<P>, attributes: Value, timestamp: u64) -> Self {
+        Self {
+            pid,
+            packets,
+            attributes,
+            last_active: timestamp,
+            entered: None,
+        }
+    }
+}
+
+/// Packets referring to spans that have not been created (yet)
+///
+/// Packets may arrive before the `NewSpan` of the span they refer to. They are held back
+/// until the span is created. The number of spans and packets held back is bounded; the
+/// oldest span is evicted first.
+#[derive(Debug, Default)]
+struct Pending {
+    /// Held back packets by span
+    packets: HashMap<SpanKey, Vec<TracePacket>>,
+    /// Spans in order of their first held back packet
+    order: VecDeque<SpanKey>,
+}
+
+impl Pending {
+    /// Hold back a packet for the given span. Returns the packets of an evicted span, if any.
+    fn push(&mut self, key: SpanKey, packet: TracePacket) -> Option<(SpanKey, Vec<TracePacket>)> {
+        if let Some(packets) = self.packets.get_mut(&key) {
+            if packets.len() < MAX_PENDING_PACKETS {
+                packets.push(packet);
+            } else {
+                warn!("Too many packets pending for span {key:?}. Dropping packet");
+            }
+            return None;
+        }
+
+        let evicted = if self.order.len() >= MAX_PENDING_SPANS {
+            self.order
+                .pop_front()
+                .and_then(|key| self.packets.remove(&key).map(|packets| (key, packets)))
+        } else {
+            None
+        };
+
+        self.order.push_back(key);
+        self.packets.insert(key, vec![packet]);
+        evicted
+    }
+
+    /// Take all packets held back for the given span
+    fn take(&mut self, key: &SpanKey) -> Option<Vec<TracePacket>> {
+        let packets = self.packets.remove(key)?;
+        self.order.retain(|k| k != key);
+        Some(packets)
+    }
+
+    /// Take all packets held back for spans of the given process
+    fn take_process(&mut self, pid: u32) -> Vec<(SpanKey, Vec<TracePacket>)> {
+        let keys: Vec<SpanKey> = self.order.iter().filter(|k| k.0 == pid).copied().collect();
+        keys.into_iter()
+            .filter_map(|key| self.take(&key).map(|packets| (key, packets)))
+            .collect()
+    }
+}
+
+/// Trace writer
+pub struct TraceWriter<F: Format> {
+    format: F,
+    spans: HashMap<SpanKey, Span<F::Packet>>,
+    pending: Pending,
+    /// Filter applied to all packets
+    filter: Filter,
+    /// Timestamp of the latest packet in nanoseconds since epoch.
+    last_timestamp: u64,
+}
+
+impl<F: Format> Drop for TraceWriter<F> {
+    fn drop(&mut self) {
+        // Close all open spans at the end of the capture, otherwise the trace ends mid-span
+        let spans: Vec<_> = self.spans.drain().map(|(_, span)| span).collect();
+        for span in spans {
+            if let Err(e) = self.close_span(span, self.last_timestamp) {
+                warn!("Failed to flush open span: {e:?}");
+            }
+        }
+        if let Err(e) = self.format.flush() {
+            warn!("Failed to flush trace writer: {e:?}");
+        }
+    }
+}
+
+impl<F: Format> TraceWriter<F> {
+    pub fn new(format: F) -> Self {
+        Self {
+            format,
+            spans: HashMap::new(),
+            pending: Pending::default(),
+            filter: Filter::default(),
+            last_timestamp: 0,
+        }
+    }
+
+    /// Write only the spans and events passing the given filter
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn on_packet(&mut self, message: TracePacket) -> Result<(), Error> {
+        if !self.filter.pass(&message) {
+            // Drop the packets held back for a span excluded on creation
+            if let TraceData::NewSpan { id, .. } = message.data {
+                self.pending.take(&(message.process.id, id));
+            }
+            return Ok(());
+        }
+
+        // Hold back packets referring to spans not created yet
+        if let Some(key) = self.unknown_span(&message) {
+            if let Some((key, packets)) = self.pending.push(key, message) {
+                warn!("Span {key:?} never created. Evicting its pending packets");
+                self.discard_pending(packets)?;
+            }
+            return Ok(());
+        }
+
+        let pid = message.process.id;
+        let process = message.process;
+        let thread = message.thread;
+        let timestamp_nanos = message.timestamp.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+        self.last_timestamp = self.last_timestamp.max(timestamp_nanos);
+
+        // Map record to event. This is unfortunate not possible directly in the match bel
+        // below because the types of the fields differ.
+        let data = match message.data {
+            TraceData::Record { id, event } => TraceData::Event {
+                parent_span: Some(id),
+                event,
+            },
+            data => data,
+        };
+
+        match data {
+            TraceData::Exec => (),
+            TraceData::Exit => {
+                // Remove all spans that belong to the process
+                self.spans.retain(|_, span| span.pid != pid);
+                for (key, packets) in self.pending.take_process(pid) {
+                    warn!("Span {key:?} never created before process exit");
+                    self.discard_pending(packets)?;
+                }
+            }
+            TraceData::NewSpan { id, attributes } => {
+                let key = (pid, id);
+                assert!(!self.spans.contains_key(&key));
+
+                // There's the process, thread, and the span itself
+                let thread = thread.expect("missing thread info in new span");
+                let packets = self.format.descriptors(
+                    pid,
+                    process.name.as_deref(),
+                    thread.id,
+                    thread.name.as_deref(),
+                );
+
+                if self.spans.len() >= MAX_OPEN_SPANS {
+                    self.evict_span()?;
+                }
+                let span = Span::new(pid, packets, attributes, timestamp_nanos);
+                self.spans.insert(key, span);
+
+                // Replay the packets that arrived before the span was created
+                if let Some(packets) = self.pending.take(&key) {
+                    for packet in packets {
+                        self.on_packet(packet)?;
+                    }
+                }
+            }
+            TraceData::EnterSpan { id } => {
+                let Some(span) = self.spans.get_mut(&(pid, id)) else {
+                    return Ok(());
+                };
+
+                let Some(tid) = thread.map(|t| t.id) else {
+                    bail!("missing thread info in enter span");
+                };
+                let packet = self.format.event(&Event {
+                    kind: EventKind::SliceBegin,
+                    pid,
+                    tid,
+                    timestamp: timestamp_nanos,
+                    attributes: &span.attributes,
+                });
+
+                span.packets.push(packet);
+                span.last_active = timestamp_nanos;
+                span.entered = Some(tid);
+            }
+            TraceData::ExitSpan { id } => {
+                let key = (pid, id);
+                let Some(mut span) = self.spans.remove(&key) else {
+                    return Ok(());
+                };
+                let Some(tid) = thread.map(|t| t.id) else {
+                    return Err(anyhow!("missing thread info in exit span"));
+                };
+
+                let packet = self.slice_end(&span, tid, timestamp_nanos);
+                span.packets.push(packet);
+
+                // Flush
+                self.format.write(span.packets)?;
+            }
+
+            TraceData::Record { .. } => unreachable!(),
+            TraceData::Event { parent_span, event } => {
+                let Some(tid) = thread.as_ref().map(|t| t.id) else {
+                    bail!("missing thread info in exit span");
+                };
+                let packet = self.format.event(&Event {
+                    kind: EventKind::Instant,
+                    pid,
+                    tid,
+                    timestamp: timestamp_nanos,
+                    attributes: &event,
+                });
+
+                // If the event is associated with a span, append to the span.
+                if let Some(span) = parent_span.and_then(|id| self.spans.get_mut(&(pid, id))) {
+                    span.packets.push(packet);
+                    span.last_active = timestamp_nanos;
+                    // No need to flush - will happen when the span exits
+                } else {
+                    // Not in a span.
+                    // Process and thread track *must* be present *before* the event
+                    let process_name = process.name.as_deref();
+                    let thread_name = thread.and_then(|t| t.name);
+                    let mut packets =
+                        self.format
+                            .descriptors(pid, process_name, tid, thread_name.as_deref());
+                    packets.push(packet);
+                    self.format.write(packets)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evict the least recently active open span and flush its partial trace
+    fn evict_span(&mut self) -> Result<(), Error> {
+        let Some(key) = self
+            .spans
+            .iter()
+            .min_by_key(|(_, span)| span.last_active)
+            .map(|(key, _)| *key)
+        else {
+            return Ok(());
+        };
+        let span = self.spans.remove(&key).expect("span vanished");
+        warn!("Too many open spans. Evicting span {key:?}");
+        let timestamp = span.last_active;
+        self.close_span(span, timestamp)
+    }
+
+    /// Flush the trace of a span which has not been exited. An entered span is closed with a
+    /// synthetic slice end at the given timestamp.
+    fn close_span(&mut self, mut span: Span<F::Packet>, timestamp: u64) -> Result<(), Error> {
+        if let Some(tid) = span.entered {
+            let packet = self.slice_end(&span, tid, timestamp);
+            span.packets.push(packet);
+        }
+        self.format.write(span.packets)
+    }
+
+    /// Create the slice end packet of a span exited on the given thread
+    fn slice_end(&self, span: &Span<F::Packet>, tid: u32, timestamp: u64) -> F::Packet {
+        self.format.event(&Event {
+            kind: EventKind::SliceEnd,
+            pid: span.pid,
+            tid,
+            timestamp,
+            attributes: &span.attributes,
+        })
+    }
+
+    /// Get the span a packet refers to if that span has not been created
+    fn unknown_span(&self, message: &TracePacket) -> Option<SpanKey> {
+        let id = match message.data {
+            TraceData::EnterSpan { id }
+            | TraceData::ExitSpan { id }
+            | TraceData::Record { id, .. }
+            | TraceData::Event {
+                parent_span: Some(id),
+                ..
+            } => id,
+            _ => return None,
+        };
+        let key = (message.process.id, id);
+        (!self.spans.contains_key(&key)).then_some(key)
+    }
+
+    /// Handle packets held back for a span that is never created. Events are written
+    /// without their span, span enter and exit packets are dropped.
+    fn discard_pending(&mut self, packets: Vec<TracePacket>) -> Result<(), Error> {
+        for mut packet in packets {
+            packet.data = match packet.data {
+                TraceData::Record { event, .. } | TraceData::Event { event, .. } => {
+                    TraceData::Event {
+                        parent_span: None,
+                        event,
+                    }
+                }
+                _ => continue,
+            };
+            self.on_packet(packet)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the name from the metadata of span attributes or an event
+pub(crate) fn metadata_name(value: &Value) -> Option<&str> {
+    value
+        .as_object()
+        .and_then(|o| o.get("metadata"))
+        .and_then(|o| o.get("name"))
+        .and_then(|s| s.as_str())
+}
+
+/// Get the source location from the metadata of span attributes or an event
+pub(crate) fn metadata_location(value: &Value) -> Option<(&str, u32)> {
+    let file = value
+        .as_object()
+        .and_then(|o| o.get("metadata"))
+        .and_then(|o| o.get("file"))
+        .and_then(|s| s.as_str());
+    let line = value
+        .as_object()
+        .and_then(|o| o.get("metadata"))
+        .and_then(|o| o.get("line"))
+        .and_then(|s| s.as_u64());
+    match (file, line) {
+        (Some(file), Some(line)) => Some((file, line as u32)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{Event, EventKind, Format, TraceWriter, MAX_OPEN_SPANS};
+    use crate::data::{Metadata, Process, Thread, TraceData, TracePacket};
+    use crate::filter::{Filter, Rule};
+    use anyhow::Error;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    pub(crate) const PID: u32 = 1;
+    pub(crate) const TID: u32 = 2;
+
+    pub(crate) fn packet(nanos: u64, data: TraceData) -> TracePacket {
+        TracePacket::new(
+            UNIX_EPOCH + Duration::from_nanos(nanos),
+            Process {
+                id: PID,
+                name: Some("test".into()),
+            },
+            Some(Thread {
+                id: TID,
+                name: Some("test".into()),
+            }),
+            data,
+            Metadata::default(),
+        )
+    }
+
+    pub(crate) fn new_span(nanos: u64, id: u64) -> TracePacket {
+        new_named_span(nanos, id, "span")
+    }
+
+    pub(crate) fn new_named_span(nanos: u64, id: u64, name: &str) -> TracePacket {
+        let attributes = serde_json::json!({ "metadata": { "name": name } });
+        packet(nanos, TraceData::NewSpan { id, attributes })
+    }
+
+    /// Format recording the kinds of the written events
+    #[derive(Default)]
+    struct Kinds(Rc<RefCell<Vec<EventKind>>>);
+
+    impl Format for Kinds {
+        type Packet = EventKind;
+
+        fn descriptors(&self, _: u32, _: Option<&str>, _: u32, _: Option<&str>) -> Vec<EventKind> {
+            Vec::new()
+        }
+
+        fn event(&self, event: &Event<'_>) -> EventKind {
+            event.kind
+        }
+
+        fn write(&mut self, packets: Vec<EventKind>) -> Result<(), Error> {
+            self.0.borrow_mut().extend(packets);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_enter_before_new_span() {
+        let kinds = Kinds::default();
+        let written = kinds.0.clone();
+        let mut writer = TraceWriter::new(kinds);
+
+        writer
+            .on_packet(packet(2, TraceData::EnterSpan { id: 1 }))
+            .unwrap();
+        writer.on_packet(new_span(1, 1)).unwrap();
+        writer
+            .on_packet(packet(3, TraceData::ExitSpan { id: 1 }))
+            .unwrap();
+        drop(writer);
+
+        assert_eq!(
+            *written.borrow(),
+            [EventKind::SliceBegin, EventKind::SliceEnd]
+        );
+    }
+
+    #[test]
+    fn test_open_spans_bounded() {
+        let kinds = Kinds::default();
+        let written = kinds.0.clone();
+        let mut writer = TraceWriter::new(kinds);
+
+        let spans = MAX_OPEN_SPANS as u64 + 100;
+        for id in 0..spans {
+            writer.on_packet(new_span(id, id)).unwrap();
+            writer
+                .on_packet(packet(id, TraceData::EnterSpan { id }))
+                .unwrap();
+        }
+        assert_eq!(writer.spans.len(), MAX_OPEN_SPANS);
+        // The oldest spans have been evicted, the latest one is still open
+        assert!(!writer.spans.contains_key(&(PID, 0)));
+        assert!(writer.spans.contains_key(&(PID, spans - 1)));
+        assert_eq!(written.borrow().len(), 2 * 100);
+        drop(writer);
+
+        // The traces of the evicted spans have been flushed
+        assert_eq!(written.borrow().len(), 2 * spans as usize);
+    }
+
+    #[test]
+    fn test_filter_span_name() {
+        let kinds = Kinds::default();
+        let written = kinds.0.clone();
+        let mut writer =
+            TraceWriter::new(kinds).with_filter(Filter::new(vec![Rule::Name("Step".into())]));
+        let event = || serde_json::json!({ "metadata": { "name": "event" } });
+
+        // Entered before created, held back and dropped with the excluded span
+        writer
+            .on_packet(packet(1, TraceData::EnterSpan { id: 1 }))
+            .unwrap();
+        writer.on_packet(new_named_span(2, 1, "Startup")).unwrap();
+        writer.on_packet(new_named_span(3, 2, "Step")).unwrap();
+        writer
+            .on_packet(packet(4, TraceData::EnterSpan { id: 2 }))
+            .unwrap();
+        for id in [1, 2] {
+            writer
+                .on_packet(packet(
+                    5,
+                    TraceData::Event {
+                        parent_span: Some(id),
+                        event: event(),
+                    },
+                ))
+                .unwrap();
+        }
+        for id in [1, 2] {
+            writer
+                .on_packet(packet(6, TraceData::ExitSpan { id }))
+                .unwrap();
+        }
+        // Events outside of spans are filtered by their own name
+        writer
+            .on_packet(packet(
+                7,
+                TraceData::Event {
+                    parent_span: None,
+                    event: event(),
+                },
+            ))
+            .unwrap();
+        drop(writer);
+
+        assert_eq!(
+            *written.borrow(),
+            [
+                EventKind::SliceBegin,
+                EventKind::Instant,
+                EventKind::SliceEnd
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_closes_open_spans() {
+        let kinds = Kinds::default();
+        let written = kinds.0.clone();
+        let mut writer = TraceWriter::new(kinds);
+
+        writer.on_packet(new_span(1, 1)).unwrap();
+        writer
+            .on_packet(packet(2, TraceData::EnterSpan { id: 1 }))
+            .unwrap();
+        writer.on_packet(new_span(3, 2)).unwrap();
+        writer
+            .on_packet(packet(4, TraceData::EnterSpan { id: 2 }))
+            .unwrap();
+        writer
+            .on_packet(packet(5, TraceData::ExitSpan { id: 2 }))
+            .unwrap();
+        drop(writer);
+
+        let written = written.borrow();
+        let count = |kind| written.iter().filter(|&&k| k == kind).count();
+        assert_eq!(count(EventKind::SliceBegin), 2);
+        assert_eq!(count(EventKind::SliceEnd), 2);
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stream the trace of a client to stdout
+
+use feo_tracing::protocol::{self, Hello, Process, TraceData, TracePacket};
+use perfetto_model as idl;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, process, thread};
+use tokio::runtime;
+use tokio_seqpacket::UnixSeqpacket;
+use tracing_serde_structured::{
+    CowString, SerializeEvent, SerializeFieldSet, SerializeLevel, SerializeMetadata,
+    SerializeRecordFields, SerializeValue,
+};
+
+/// Create an event packet outside of spans
+fn event_packet() -> TracePacket<'static> {
+    let fields = BTreeMap::from([(
+        CowString::Borrowed("message"),
+        SerializeValue::Str(CowString::Borrowed("hello")),
+    )]);
+    let event = SerializeEvent {
+        fields: SerializeRecordFields::De(fields),
+        metadata: SerializeMetadata {
+            name: CowString::Borrowed("event"),
+            target: CowString::Borrowed("test"),
+            level: SerializeLevel::Info,
+            module_path: None,
+            file: None,
+            line: None,
+            fields: SerializeFieldSet::De(vec![CowString::Borrowed("message")]),
+            is_span: false,
+            is_event: true,
+        },
+        parent: None,
+    };
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    TracePacket::new(
+        timestamp.as_nanos() as u64,
+        Process {
+            pid: process::id(),
+            tid: 1,
+        },
+        TraceData::Event {
+            parent_span: None,
+            event,
+        },
+    )
+}
+
+/// Connect to the tracer, negotiate the packet size and send a single event
+async fn send_event(path: &std::path::Path) {
+    let socket = UnixSeqpacket::connect(path).await.unwrap();
+
+    let mut buffer = vec![0u8; protocol::MAX_PACKET_SIZE];
+    let hello = Hello {
+        version: protocol::PROTOCOL_VERSION,
+        max_packet_size: protocol::MAX_PACKET_SIZE as u32,
+    };
+    socket
+        .send(hello.encode(&mut buffer).unwrap())
+        .await
+        .unwrap();
+    let len = socket.recv(&mut buffer).await.unwrap();
+    let answer = Hello::decode(&buffer[..len]).unwrap();
+    assert_eq!(answer, hello);
+
+    let packet = postcard::to_slice(&event_packet(), &mut buffer).unwrap();
+    socket.send(packet).await.unwrap();
+}
+
+#[test]
+fn test_perfetto_to_stdout() {
+    let path = env::temp_dir().join(format!("feo-tracer-test-{}.sock", process::id()));
+    let tracer = Command::new(env!("CARGO_BIN_EXE_feo-tracer"))
+        .args(["-o", "-", "-d", "2", "-s"])
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn feo-tracer");
+
+    // Wait for the tracer to listen
+    let start = Instant::now();
+    while !path.exists() {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "tracer not listening"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap()
+        .block_on(send_event(&path));
+
+    let output = tracer.wait_with_output().unwrap();
+    let _ = fs::remove_file(&path);
+    assert!(output.status.success());
+
+    // The stream of trace messages decodes as a single trace
+    let trace = idl::Trace::decode(output.stdout.as_slice()).expect("invalid protobuf");
+    let instants = trace
+        .packet
+        .iter()
+        .filter(|p| {
+            matches!(&p.data, Some(idl::trace_packet::Data::TrackEvent(e))
+                if e.r#type() == idl::track_event::Type::Instant)
+        })
+        .count();
+    assert_eq!(instants, 1);
+}
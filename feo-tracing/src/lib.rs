@@ -11,6 +11,8 @@ mod feo_subscriber;
 pub mod protocol;
 
 /// Initialize tracing
-pub use feo_subscriber::init;
+pub use feo_subscriber::{init, init_with_packet_size};
+/// Statistics of the tracing subscriber
+pub use feo_subscriber::{stats, Stats};
 /// Re-export of the `tracing` crate.
 pub use tracing::{self, event, instrument, level_filters::LevelFilter, span, Level};
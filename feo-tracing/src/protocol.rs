@@ -9,8 +9,80 @@ use tracing_serde_structured::{SerializeAttributes, SerializeEvent, SerializeRec
 
 pub type Id = u64;
 
+/// Default maximum size of a trace packet
 pub const MAX_PACKET_SIZE: usize = 16 * 1024;
 
+/// Largest maximum packet size the tracer accepts
+///
+/// A seqpacket message must fit into the send buffer of the socket.
+pub const MAX_NEGOTIABLE_PACKET_SIZE: usize = 128 * 1024;
+
+/// Maximum size of an encoded [`Hello`], see [`Hello::encode`]
+pub const HELLO_SIZE: usize = 16;
+
+/// Prefix of an encoded [`Hello`]
+///
+/// Peers of protocol v0 send trace packets right away without a hello. The postcard varint of
+/// the timestamp starting a [`TracePacket`] has the continuation bit set in its first byte for
+/// any timestamp after 1970, so such a packet never starts with the magic.
+pub const HELLO_MAGIC: [u8; 4] = *b"FEOT";
+
+/// Version of the protocol between subscriber and tracer
+///
+/// Must be incremented on each incompatible change of the [`Hello`] or the [`TracePacket`].
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Version reported for legacy peers not starting with a [`Hello`] prefixed by [`HELLO_MAGIC`]
+pub const LEGACY_PROTOCOL_VERSION: u8 = 0;
+
+/// First message in each direction of a connection to the tracer
+///
+/// The subscriber requests its maximum packet size, the tracer answers with the maximum
+/// packet size used on the connection, see [`Hello::answer`]. Both send their protocol
+/// version, a peer with another version is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u8,
+    pub max_packet_size: u32,
+}
+
+impl Hello {
+    /// Answer of a tracer accepting packets of at most `limit` bytes to this request
+    pub fn answer(&self, limit: usize) -> Hello {
+        let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+        Hello {
+            version: PROTOCOL_VERSION,
+            max_packet_size: self.max_packet_size.min(limit),
+        }
+    }
+
+    /// Encode the hello into the given buffer of at least [`HELLO_SIZE`] bytes, prefixed with
+    /// [`HELLO_MAGIC`]
+    pub fn encode<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b mut [u8], postcard::Error> {
+        let prefix = HELLO_MAGIC.len();
+        if buffer.len() < prefix {
+            return Err(postcard::Error::SerializeBufferFull);
+        }
+        buffer[..prefix].copy_from_slice(&HELLO_MAGIC);
+        let len = postcard::to_slice(self, &mut buffer[prefix..])?.len();
+        Ok(&mut buffer[..prefix + len])
+    }
+
+    /// Decode a hello encoded by [`encode`](Self::encode)
+    ///
+    /// A message without [`HELLO_MAGIC`] is the first trace packet of a legacy peer, decoded
+    /// as a hello of [`LEGACY_PROTOCOL_VERSION`] for the version check to reject it.
+    pub fn decode(message: &[u8]) -> Result<Hello, postcard::Error> {
+        match message.strip_prefix(&HELLO_MAGIC) {
+            Some(hello) => postcard::from_bytes(hello),
+            None => Ok(Hello {
+                version: LEGACY_PROTOCOL_VERSION,
+                max_packet_size: 0,
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Process {
     pub pid: u32,
@@ -2,24 +2,75 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::protocol::{TraceData, TracePacket, MAX_PACKET_SIZE};
-use feo_log::{trace, warn};
+use crate::protocol::{
+    Hello, TraceData, TracePacket, HELLO_SIZE, MAX_NEGOTIABLE_PACKET_SIZE, MAX_PACKET_SIZE,
+    PROTOCOL_VERSION,
+};
+use feo_log::{debug, trace, warn};
 use libc::{sockaddr_un, AF_UNIX};
-use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::collections::BTreeMap;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 use std::sync::{atomic, Mutex};
+use std::time::{Duration, Instant};
 use std::{io, mem};
 use tracing::level_filters::LevelFilter;
 use tracing::span;
 use tracing::subscriber::set_global_default;
-use tracing_serde_structured::AsSerde;
+use tracing_serde_structured::{
+    AsSerde, CowString, SerializeRecord, SerializeRecordFields, SerializeValue,
+};
 
 pub const UNIX_PACKET_PATH: &str = "/tmp/feo-tracer.sock";
 
+/// Delay before the first reconnection attempt after a failure
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Maximum delay between reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+/// Time to wait for the tracer to answer the hello of a new connection, packets are dropped
+/// meanwhile
+const HELLO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of trace packets dropped because the tracer was not reachable or the packet
+/// could not be serialized
+static DROPPED: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+
+/// Number of trace packets whose fields were dropped because they exceeded the packet size
+static TRUNCATED: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+
+/// Statistics of the tracing subscriber
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Number of trace packets dropped because the tracer was not reachable or the packet
+    /// could not be serialized
+    pub dropped: u64,
+    /// Number of trace packets whose fields were dropped because they exceeded the packet size
+    pub truncated: u64,
+}
+
+/// Get the statistics of the tracing subscriber
+pub fn stats() -> Stats {
+    Stats {
+        dropped: DROPPED.load(atomic::Ordering::Relaxed),
+        truncated: TRUNCATED.load(atomic::Ordering::Relaxed),
+    }
+}
+
 /// Initialize the tracing subscriber with the given level
 pub fn init(level: LevelFilter) {
+    init_with_packet_size(level, MAX_PACKET_SIZE);
+}
+
+/// Initialize the tracing subscriber with the given level and maximum packet size
+///
+/// Packets with large structured fields are sent up to the given size, limited to
+/// [`MAX_NEGOTIABLE_PACKET_SIZE`]. The size used is negotiated with the tracer on connection,
+/// see [`Hello`]. Fields of larger packets are truncated.
+pub fn init_with_packet_size(level: LevelFilter, max_packet_size: usize) {
     let subscriber = Subscriber {
         max_level: level,
-        tracer: Mutex::new(None),
+        max_packet_size: max_packet_size.min(MAX_NEGOTIABLE_PACKET_SIZE),
+        tracer: Mutex::new(Connection::default()),
     };
     set_global_default(subscriber).expect("setting tracing default failed");
 }
@@ -28,7 +79,128 @@ pub fn init(level: LevelFilter) {
 /// See the `TraceData` and `TracePacket` types for the data format.
 struct Subscriber {
     max_level: LevelFilter,
-    tracer: Mutex<Option<OwnedFd>>,
+    max_packet_size: usize,
+    tracer: Mutex<Connection>,
+}
+
+/// Connection to the tracer
+///
+/// Reconnection attempts are delayed with an exponential backoff in order to avoid a
+/// `connect` per trace packet while the tracer is not running.
+#[derive(Debug, Default)]
+struct Connection {
+    state: State,
+    /// Buffer of the maximum packet size negotiated with the tracer
+    buffer: Vec<u8>,
+    backoff: Backoff,
+}
+
+/// State of the connection to the tracer
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Disconnected,
+    /// Hello sent, waiting for the answer of the tracer until the deadline
+    Handshake { socket: OwnedFd, deadline: Instant },
+    Connected(OwnedFd),
+}
+
+impl Connection {
+    /// Get the socket once connected to the tracer
+    ///
+    /// Connects with `connect` if disconnected and no backoff is pending. `connect` returns
+    /// the socket after sending the hello requesting `max_packet_size`. The answer of the
+    /// tracer is read without blocking on later calls, until then no socket is returned.
+    fn socket(
+        &mut self,
+        now: Instant,
+        max_packet_size: usize,
+        connect: impl FnOnce() -> io::Result<OwnedFd>,
+    ) -> Option<&OwnedFd> {
+        self.state = match mem::take(&mut self.state) {
+            State::Disconnected if self.backoff.ready(now) => match connect() {
+                Ok(socket) => State::Handshake {
+                    socket,
+                    deadline: now + HELLO_TIMEOUT,
+                },
+                Err(e) => {
+                    trace!("Failed to connect to feo-tracer: {e:?}");
+                    self.backoff.failed(now);
+                    State::Disconnected
+                }
+            },
+            State::Handshake { socket, deadline } => {
+                match answer(socket.as_raw_fd(), max_packet_size) {
+                    Ok(Some(max_packet_size)) => {
+                        debug!(
+                            "Connected to feo-tracer with packets of up to {max_packet_size} bytes. {} packets dropped so far",
+                            DROPPED.load(atomic::Ordering::Relaxed)
+                        );
+                        self.buffer = vec![0; max_packet_size];
+                        self.backoff.reset();
+                        State::Connected(socket)
+                    }
+                    Ok(None) if now < deadline => State::Handshake { socket, deadline },
+                    Ok(None) => {
+                        trace!("feo-tracer did not answer hello within {HELLO_TIMEOUT:?}");
+                        self.backoff.failed(now);
+                        State::Disconnected
+                    }
+                    Err(e) => {
+                        trace!("Failed to negotiate with feo-tracer: {e:?}");
+                        self.backoff.failed(now);
+                        State::Disconnected
+                    }
+                }
+            }
+            state => state,
+        };
+        match &self.state {
+            State::Connected(socket) => Some(socket),
+            _ => None,
+        }
+    }
+
+    /// Close the connection after an error. The next attempt to reconnect is immediate.
+    fn disconnect(&mut self) {
+        self.state = State::Disconnected;
+    }
+}
+
+/// Exponential backoff for reconnection attempts
+#[derive(Debug)]
+struct Backoff {
+    /// Delay applied after the next failure
+    delay: Duration,
+    /// Earliest point in time of the next attempt
+    next_attempt: Option<Instant>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            delay: INITIAL_BACKOFF,
+            next_attempt: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Check if an attempt is allowed at `now`
+    fn ready(&self, now: Instant) -> bool {
+        self.next_attempt.is_none_or(|next| now >= next)
+    }
+
+    /// Record a failed attempt at `now`
+    fn failed(&mut self, now: Instant) {
+        self.next_attempt = Some(now + self.delay);
+        self.delay = (self.delay * 2).min(MAX_BACKOFF);
+    }
+
+    /// Record a successful attempt
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
 impl Subscriber {
@@ -43,35 +215,28 @@ impl Subscriber {
         span::Id::from_u64(id)
     }
 
-    // Send a value to the tracer
+    // Send a value to the tracer, dropping it while not connected
     fn send(&self, packet: TracePacket<'_>) {
         let mut guard = self.tracer.lock().unwrap();
 
-        if guard.is_none() {
-            // Connect
-            match connect() {
-                Ok(connection) => *guard = Some(connection),
-                Err(e) => {
-                    trace!("Failed to connect to feo-tracer: {:?}. Discarding value", e);
-                    return;
-                }
-            };
-        }
-
-        let socket = guard.as_mut().unwrap();
+        let max_packet_size = self.max_packet_size;
+        let Some(socket) = guard.socket(Instant::now(), max_packet_size, || {
+            connect(max_packet_size)
+        }) else {
+            DROPPED.fetch_add(1, atomic::Ordering::Relaxed);
+            return;
+        };
+        let fd = socket.as_raw_fd();
 
-        let message = postcard::to_vec::<_, MAX_PACKET_SIZE>(&packet).expect("failed to serialize"); // TODO throw?
+        let Some(len) = serialize(packet, &mut guard.buffer) else {
+            DROPPED.fetch_add(1, atomic::Ordering::Relaxed);
+            return;
+        };
 
-        // Note: Seqpacket writes write all data or fail. No need to loop around and check for partial writes.
-        let fd = socket.as_raw_fd();
-        let buf = message.as_ptr() as *const libc::c_void;
-        let len = message.len();
-        // Safety: buf is a valid pointer to a buffer of the correct length
-        let ret = unsafe { libc::send(fd, buf, len, 0) };
-        if ret < 0 {
-            let error = io::Error::last_os_error();
+        if let Err(error) = send_message(fd, &guard.buffer[..len]) {
             warn!("Failed to send to feo-tracer: {error:?}");
-            guard.take();
+            DROPPED.fetch_add(1, atomic::Ordering::Relaxed);
+            guard.disconnect();
         }
     }
 }
@@ -135,7 +300,115 @@ impl tracing::Subscriber for Subscriber {
     fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
 }
 
-fn connect() -> io::Result<OwnedFd> {
+/// Serialize a packet into `buffer` and return the length of the message
+///
+/// The fields of packets exceeding the size of `buffer` are replaced by a single `truncated`
+/// field. Returns `None` if the packet cannot be serialized even then.
+fn serialize(packet: TracePacket<'_>, buffer: &mut [u8]) -> Option<usize> {
+    match postcard::to_slice(&packet, buffer) {
+        Ok(message) => return Some(message.len()),
+        Err(postcard::Error::SerializeBufferFull) => (),
+        Err(e) => {
+            warn!("Failed to serialize trace packet: {e:?}");
+            return None;
+        }
+    }
+
+    let truncated =
+        || BTreeMap::from([(CowString::Borrowed("truncated"), SerializeValue::Bool(true))]);
+    let data = match packet.data {
+        TraceData::Record { span, .. } => TraceData::Record {
+            span,
+            values: SerializeRecord::De(truncated()),
+        },
+        TraceData::Event {
+            parent_span,
+            mut event,
+        } => {
+            event.fields = SerializeRecordFields::De(truncated());
+            TraceData::Event { parent_span, event }
+        }
+        _ => {
+            warn!("Trace packet exceeds {} bytes. Dropping", buffer.len());
+            return None;
+        }
+    };
+    TRUNCATED.fetch_add(1, atomic::Ordering::Relaxed);
+    let packet = TracePacket { data, ..packet };
+
+    match postcard::to_slice(&packet, buffer) {
+        Ok(message) => Some(message.len()),
+        Err(e) => {
+            warn!("Failed to serialize truncated trace packet: {e:?}");
+            None
+        }
+    }
+}
+
+/// Send a message on the given seqpacket socket
+fn send_message(fd: RawFd, message: &[u8]) -> io::Result<()> {
+    // Note: Seqpacket writes write all data or fail. No need to loop around and check for partial writes.
+    let buf = message.as_ptr() as *const libc::c_void;
+    // Safety: buf is a valid pointer to a buffer of the correct length
+    let ret = unsafe { libc::send(fd, buf, message.len(), 0) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Request `max_packet_size` from the tracer on the given new connection, see [`answer`]
+fn hello(fd: RawFd, max_packet_size: usize) -> io::Result<()> {
+    let request = Hello {
+        version: PROTOCOL_VERSION,
+        max_packet_size: u32::try_from(max_packet_size).unwrap_or(u32::MAX),
+    };
+    let mut buffer = [0u8; HELLO_SIZE];
+    let message = request.encode(&mut buffer).map_err(io::Error::other)?;
+    send_message(fd, message)
+}
+
+/// Read the answer of the tracer to the [`hello`] requesting `max_packet_size` without blocking
+///
+/// Returns the size answered by the tracer, at most the requested one, or `None` while the
+/// answer is pending. Fails if the tracer speaks another protocol version.
+fn answer(fd: RawFd, max_packet_size: usize) -> io::Result<Option<usize>> {
+    let mut buffer = [0u8; HELLO_SIZE];
+    // Safety: buffer is a valid pointer to a buffer of the correct length
+    let len = unsafe {
+        libc::recv(
+            fd,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            libc::MSG_DONTWAIT,
+        )
+    };
+    if len < 0 {
+        let error = io::Error::last_os_error();
+        if error.kind() == io::ErrorKind::WouldBlock {
+            return Ok(None);
+        }
+        return Err(error);
+    }
+    if len == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let answer = Hello::decode(&buffer[..len as usize])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if answer.version != PROTOCOL_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "tracer protocol v{}, subscriber expects v{PROTOCOL_VERSION}",
+                answer.version
+            ),
+        ));
+    }
+    Ok(Some((answer.max_packet_size as usize).min(max_packet_size)))
+}
+
+/// Connect to the tracer and send the hello requesting `max_packet_size`, see [`answer`]
+fn connect(max_packet_size: usize) -> io::Result<OwnedFd> {
     // Create a seqpacket socket
     let socket = unsafe { libc::socket(AF_UNIX, libc::SOCK_SEQPACKET, 0) };
     assert!(socket >= 0, "socket failed");
@@ -166,5 +439,265 @@ fn connect() -> io::Result<OwnedFd> {
         return Err(io::Error::last_os_error());
     }
 
+    hello(fd.as_raw_fd(), max_packet_size)?;
     Ok(fd)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        hello, send_message, serialize, stats, Connection, HELLO_SIZE, HELLO_TIMEOUT,
+        INITIAL_BACKOFF, MAX_BACKOFF,
+    };
+    use crate::protocol::{
+        Hello, Process, TraceData, TracePacket, LEGACY_PROTOCOL_VERSION,
+        MAX_NEGOTIABLE_PACKET_SIZE, MAX_PACKET_SIZE, PROTOCOL_VERSION,
+    };
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::{Duration, Instant};
+    use tracing_serde_structured::{
+        CowString, SerializeEvent, SerializeFieldSet, SerializeLevel, SerializeMetadata,
+        SerializeRecordFields, SerializeValue,
+    };
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let start = Instant::now();
+        let mut connection = Connection::default();
+        let mut attempts = Vec::new();
+
+        // Try to connect every millisecond for a minute while the tracer is down
+        for ms in 0..60_000 {
+            let now = start + Duration::from_millis(ms);
+            let socket = connection.socket(now, MAX_PACKET_SIZE, || {
+                attempts.push(now - start);
+                Err(io::ErrorKind::NotFound.into())
+            });
+            assert!(socket.is_none());
+        }
+
+        assert_eq!(attempts[0], Duration::ZERO);
+        let spacing: Vec<Duration> = attempts.windows(2).map(|w| w[1] - w[0]).collect();
+        assert_eq!(spacing[0], INITIAL_BACKOFF);
+        for w in spacing.windows(2) {
+            assert_eq!(w[1], (w[0] * 2).min(MAX_BACKOFF));
+        }
+        assert_eq!(*spacing.last().unwrap(), MAX_BACKOFF);
+    }
+
+    /// Create a connected pair of packet sockets
+    fn socket_pair() -> (OwnedFd, OwnedFd) {
+        let mut fds = [0; 2];
+        // Safety: fds is a valid array of two file descriptors
+        let ret =
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0, fds.as_mut_ptr()) };
+        assert_eq!(ret, 0, "socketpair failed");
+        // Safety: socketpair returned two valid file descriptors owned by nobody else
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+    }
+
+    #[test]
+    fn test_handshake_without_blocking() {
+        let start = Instant::now();
+        let mut connection = Connection::default();
+        let (socket, tracer) = socket_pair();
+        let mut socket = Some(socket);
+        let mut connect = |now| {
+            connection
+                .socket(now, MAX_PACKET_SIZE, || {
+                    let socket = socket.take().expect("connected twice");
+                    hello(socket.as_raw_fd(), MAX_PACKET_SIZE)?;
+                    Ok(socket)
+                })
+                .is_some()
+        };
+
+        // Not connected until the tracer answers the hello
+        assert!(!connect(start));
+        assert!(!connect(start + HELLO_TIMEOUT / 2));
+
+        let mut buffer = [0u8; HELLO_SIZE];
+        // Safety: buffer is a valid pointer to a buffer of the correct length
+        let len = unsafe {
+            libc::recv(
+                tracer.as_raw_fd(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                0,
+            )
+        };
+        assert!(len > 0, "no hello received");
+        let request = Hello::decode(&buffer[..len as usize]).unwrap();
+        let answer = request.answer(1024).encode(&mut buffer).unwrap();
+        send_message(tracer.as_raw_fd(), answer).unwrap();
+
+        assert!(connect(start + HELLO_TIMEOUT / 2));
+        assert_eq!(connection.buffer.len(), 1024);
+    }
+
+    #[test]
+    fn test_handshake_timeout() {
+        let start = Instant::now();
+        let mut connection = Connection::default();
+        let (socket, _tracer) = socket_pair();
+        let mut socket = Some(socket);
+
+        for now in [start, start + HELLO_TIMEOUT] {
+            let socket = connection.socket(now, MAX_PACKET_SIZE, || {
+                Ok(socket.take().expect("connected twice"))
+            });
+            assert!(socket.is_none());
+        }
+
+        // The tracer did not answer in time, so the next attempt is delayed by the backoff
+        let socket = connection.socket(start + HELLO_TIMEOUT, MAX_PACKET_SIZE, || {
+            panic!("reconnected without backoff")
+        });
+        assert!(socket.is_none());
+    }
+
+    /// Create an event packet with the given message
+    fn event_packet(message: &str) -> TracePacket<'_> {
+        let fields = BTreeMap::from([(
+            CowString::Borrowed("message"),
+            SerializeValue::Str(CowString::Borrowed(message)),
+        )]);
+        let event = SerializeEvent {
+            fields: SerializeRecordFields::De(fields),
+            metadata: SerializeMetadata {
+                name: CowString::Borrowed("event"),
+                target: CowString::Borrowed("test"),
+                level: SerializeLevel::Info,
+                module_path: None,
+                file: None,
+                line: None,
+                fields: SerializeFieldSet::De(vec![CowString::Borrowed("message")]),
+                is_span: false,
+                is_event: true,
+            },
+            parent: None,
+        };
+        TracePacket::new(
+            0,
+            Process { pid: 1, tid: 2 },
+            TraceData::Event {
+                parent_span: None,
+                event,
+            },
+        )
+    }
+
+    #[test]
+    fn test_hello_answer() {
+        let request = Hello {
+            version: PROTOCOL_VERSION,
+            max_packet_size: 1024 * 1024,
+        };
+        assert_eq!(
+            request.answer(MAX_NEGOTIABLE_PACKET_SIZE).max_packet_size as usize,
+            MAX_NEGOTIABLE_PACKET_SIZE
+        );
+        let request = Hello {
+            version: PROTOCOL_VERSION,
+            max_packet_size: 1024,
+        };
+        assert_eq!(
+            request.answer(MAX_NEGOTIABLE_PACKET_SIZE).max_packet_size,
+            1024
+        );
+
+        // The tracer answers with its own version, whatever the version of the request
+        let request = Hello {
+            version: PROTOCOL_VERSION + 1,
+            max_packet_size: 1024,
+        };
+        assert_eq!(
+            request.answer(MAX_NEGOTIABLE_PACKET_SIZE).version,
+            PROTOCOL_VERSION
+        );
+    }
+
+    #[test]
+    fn test_hello_encoding() {
+        let hello = Hello {
+            version: u8::MAX,
+            max_packet_size: u32::MAX,
+        };
+        let mut buffer = [0u8; HELLO_SIZE];
+        let message = hello.encode(&mut buffer).unwrap();
+        assert_eq!(Hello::decode(message).unwrap(), hello);
+
+        // The first packet of a legacy peer not sending a hello is detected as such
+        let packet = TracePacket::now_with_data(TraceData::Enter { span: 1 });
+        let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+        let message = postcard::to_slice(&packet, &mut buffer).unwrap();
+        let hello = Hello::decode(message).unwrap();
+        assert_eq!(hello.version, LEGACY_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_send_event_near_negotiated_size() {
+        const NEGOTIATED: usize = 64 * 1024;
+
+        let (sender, receiver) = socket_pair();
+
+        // An event far exceeding the default packet size but fitting the negotiated one
+        let message = "x".repeat(NEGOTIATED - 256);
+        let mut buffer = vec![0u8; NEGOTIATED];
+        let len = serialize(event_packet(&message), &mut buffer).expect("packet dropped");
+        assert!(len > MAX_PACKET_SIZE);
+        send_message(sender.as_raw_fd(), &buffer[..len]).expect("send failed");
+
+        let mut received = vec![0u8; NEGOTIATED];
+        // Safety: received is a valid pointer to a buffer of the correct length
+        let received_len = unsafe {
+            libc::recv(
+                receiver.as_raw_fd(),
+                received.as_mut_ptr().cast(),
+                received.len(),
+                0,
+            )
+        };
+        assert_eq!(received_len, len as isize);
+
+        let packet: TracePacket = postcard::from_bytes(&received[..len]).expect("invalid packet");
+        let TraceData::Event { event, .. } = packet.data else {
+            panic!("unexpected trace data");
+        };
+        let SerializeRecordFields::De(fields) = event.fields else {
+            panic!("unexpected fields");
+        };
+        assert!(!fields.contains_key(&CowString::Borrowed("truncated")));
+        let Some(SerializeValue::Str(received_message)) =
+            fields.get(&CowString::Borrowed("message"))
+        else {
+            panic!("missing message");
+        };
+        assert_eq!(received_message.as_str(), message);
+    }
+
+    #[test]
+    fn test_truncate_oversized_event() {
+        let message = "x".repeat(2 * MAX_PACKET_SIZE);
+        let packet = event_packet(&message);
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let len = serialize(packet, &mut buffer).expect("packet dropped");
+        assert!(stats().truncated >= 1);
+
+        let packet: TracePacket = postcard::from_bytes(&buffer[..len]).expect("invalid packet");
+        let TraceData::Event { event, .. } = packet.data else {
+            panic!("unexpected trace data");
+        };
+        let SerializeRecordFields::De(fields) = event.fields else {
+            panic!("unexpected fields");
+        };
+        assert!(matches!(
+            fields.get(&CowString::Borrowed("truncated")),
+            Some(SerializeValue::Bool(true))
+        ));
+        assert!(!fields.contains_key(&CowString::Borrowed("message")));
+    }
+}
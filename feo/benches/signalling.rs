@@ -0,0 +1,112 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Benchmarks of the signalling PDU encoding and decoding
+//!
+//! One PDU is sent per trigger and per ready signal of each activity in each cycle, so these
+//! are on the hot path of the inter-process signalling. Run with `cargo bench -p feo`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use feo::activity::ActivityId;
+use feo::configuration::topology::TopologyHash;
+use feo::signalling::{
+    AgentId, CycleId, MioSocketReceiver, MioSocketSender, Receiver, Sender, Signal, SignalPdu,
+};
+use mio::net::TcpStream;
+use mio::{Events, Poll};
+use std::net::TcpListener;
+
+/// One signal of each variant, named for the benchmark ids
+fn signals() -> Vec<(&'static str, Signal)> {
+    let activity = ActivityId::from(42);
+    let agent = AgentId::new(3);
+    let cycle = CycleId::from(1_000);
+    vec![
+        (
+            "hello_trigger",
+            Signal::HelloTrigger((agent, Some(TopologyHash(0x0123_4567_89ab_cdef)))),
+        ),
+        ("hello_ready", Signal::HelloReady((agent, None))),
+        ("startup_sync", Signal::StartupSync(1_000_000u64.into())),
+        ("task_chain_start", Signal::TaskChainStart(2_000u64.into())),
+        ("task_chain_end", Signal::TaskChainEnd(3_000u64.into())),
+        ("startup", Signal::Startup((activity, 4_000u64.into()))),
+        ("shutdown", Signal::Shutdown((activity, 5_000u64.into()))),
+        ("step", Signal::Step((activity, 6_000u64.into(), cycle))),
+        ("ready", Signal::Ready((activity, 7_000u64.into(), cycle))),
+        (
+            "recorder_ready",
+            Signal::RecorderReady((agent, 8_000u64.into())),
+        ),
+        (
+            "sync_request",
+            Signal::SyncRequest((agent, 9_000u64.into())),
+        ),
+        (
+            "sync_response",
+            Signal::SyncResponse((10_000u64.into(), 11_000u64.into())),
+        ),
+        ("system_shutdown", Signal::SystemShutdown(12_000u64.into())),
+        (
+            "activity_stalled",
+            Signal::ActivityStalled((activity, 13_000u64.into())),
+        ),
+        ("system_ready", Signal::SystemReady(14_000u64.into())),
+    ]
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signal_to_pdu");
+    for (name, signal) in signals() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &signal, |b, signal| {
+            b.iter(|| SignalPdu::from(black_box(signal)))
+        });
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pdu_to_signal");
+    for (name, signal) in signals() {
+        let pdu = SignalPdu::from(signal);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &pdu, |b, pdu| {
+            b.iter(|| Signal::try_from(black_box(pdu)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Send a signal and receive it back over a loopback connection
+///
+/// The senders and receivers work on mio streams, so a loopback connection stands in for a
+/// stream in memory.
+fn send_recv(c: &mut Criterion) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let writer = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    writer.set_nodelay(true).unwrap();
+    writer.set_nonblocking(true).unwrap();
+    let mut sender = MioSocketSender::new(TcpStream::from_std(writer));
+    let (reader, _) = listener.accept().unwrap();
+    reader.set_nonblocking(true).unwrap();
+    let mut reader = TcpStream::from_std(reader);
+
+    let mut poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(8);
+    let mut receiver = MioSocketReceiver::new(&mut reader, &mut poll, &mut events);
+    receiver.register(0).unwrap();
+
+    let mut group = c.benchmark_group("signal_send_recv");
+    for (name, signal) in signals() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &signal, |b, signal| {
+            b.iter(|| {
+                sender.send(*black_box(signal)).unwrap();
+                receiver.recv().unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode, send_recv);
+criterion_main!(benches);
@@ -0,0 +1,202 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Activity and related structs and traits
+
+#[cfg(any(feature = "ipc_inprocess", test))]
+pub mod testing;
+
+#[cfg(feature = "recording")]
+use postcard::experimental::max_size::MaxSize;
+#[cfg(feature = "recording")]
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Identifies an Activity / Task
+///
+/// Activity, agent and worker ids share the numeric space of `usize` but are distinct types.
+/// Converting between them takes an explicit detour via `usize`:
+///
+/// ```
+/// use feo::prelude::{ActivityId, AgentId};
+///
+/// let agent_id = AgentId::new(100);
+/// let activity_id = ActivityId::from(usize::from(agent_id));
+/// assert_eq!(usize::from(activity_id), 100);
+/// ```
+///
+/// Passing an agent or worker id where an activity id is expected does not compile:
+///
+/// ```compile_fail,E0308
+/// use feo::prelude::{ActivityId, AgentId};
+///
+/// fn step(_: ActivityId) {}
+/// step(AgentId::new(100));
+/// ```
+///
+/// ```compile_fail,E0308
+/// use feo::prelude::{ActivityId, WorkerId};
+///
+/// let activity_id: ActivityId = WorkerId::from(40);
+/// ```
+#[cfg_attr(feature = "recording", derive(Serialize, Deserialize, MaxSize))]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ActivityId(usize);
+
+impl From<usize> for ActivityId {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&ActivityId> for usize {
+    fn from(value: &ActivityId) -> Self {
+        value.0
+    }
+}
+
+impl From<ActivityId> for usize {
+    fn from(value: ActivityId) -> Self {
+        value.0
+    }
+}
+
+impl Display for ActivityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A{}", self.0)
+    }
+}
+
+impl FromStr for ActivityId {
+    type Err = ParseIntError;
+
+    /// Parse from either the bare number (e.g. `3`) or the [`Display`] form (e.g. `A3`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('A').unwrap_or(s).parse().map(Self)
+    }
+}
+
+/// Framework-level error affecting an activity, see [`Activity::on_error`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActivityError {
+    /// A required input had no payload available on the given topic
+    MissingInput(String),
+    /// No payload could be loaned for sending on the given topic
+    LoanFailed(String),
+}
+
+impl Display for ActivityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivityError::MissingInput(topic) => write!(f, "missing input on topic {topic}"),
+            ActivityError::LoanFailed(topic) => {
+                write!(f, "failed to loan payload on topic {topic}")
+            }
+        }
+    }
+}
+
+/// Lifecycle state of an activity, maintained by the worker executing it
+///
+/// See [`ActivityStates`](crate::worker_pool::ActivityStates) for querying the states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActivityState {
+    /// Not started up yet
+    Created,
+    /// `startup` has returned
+    StartedUp,
+    /// `step` has returned at least once
+    Running,
+    /// `shutdown` has returned
+    ShutDown,
+    /// `startup`, `step` or `shutdown` panicked, the activity is not executed anymore
+    Failed,
+}
+
+thread_local! {
+    /// Errors reported while an activity is executed on the current thread
+    static ERRORS: RefCell<Vec<ActivityError>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Report an error to the activity currently executed on this thread
+///
+/// The worker hands the error to [`Activity::on_error`] once the current call to `startup`,
+/// `step` or `shutdown` returned.
+pub fn report_error(err: ActivityError) {
+    ERRORS.with_borrow_mut(|errors| errors.push(err));
+}
+
+/// Take the errors reported on this thread since the last call
+pub(crate) fn take_errors() -> Vec<ActivityError> {
+    ERRORS.with_borrow_mut(std::mem::take)
+}
+
+/// Activity trait, to be implemented by any activity intended to run in a WorkerPool
+pub trait Activity {
+    /// Get the ID of the activity
+    fn id(&self) -> ActivityId;
+
+    /// Called upon startup
+    fn startup(&mut self);
+
+    /// Called upon each step
+    fn step(&mut self);
+
+    /// Called upon shutdown
+    fn shutdown(&mut self);
+
+    /// Called for each framework-level error reported during `startup`, `step` or `shutdown`,
+    /// e.g. a missing input or a failed loan, giving the activity a chance to enter a safe state
+    fn on_error(&mut self, _err: ActivityError) {}
+
+    /// Names of the topics the activity reads from, if declared
+    ///
+    /// Declared topics are cross-checked against the topic configuration by
+    /// [`validate_wiring`](crate::configuration::topics::validate_wiring), e.g. when passed to
+    /// [`primary_agent::Builder::declared_topics`](crate::configuration::primary_agent::Builder::declared_topics).
+    fn declared_inputs(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Names of the topics the activity writes to, if declared
+    ///
+    /// Declared topics are cross-checked against the topic configuration by
+    /// [`validate_wiring`](crate::configuration::topics::validate_wiring), e.g. when passed to
+    /// [`primary_agent::Builder::declared_topics`](crate::configuration::primary_agent::Builder::declared_topics).
+    fn declared_outputs(&self) -> Vec<&str> {
+        Vec::new()
+    }
+}
+
+/// Activity Builder trait.
+///
+/// To instantiate a worker pool with activities, an ActivityBuilder
+/// shall be passed for each activity. At startup of the worker threads are started the
+/// activities will be built within their respective thread.
+/// In this way, activities can avoid implementing the Send trait, which may not
+/// always be possible.
+pub trait ActivityBuilder: FnOnce(ActivityId) -> Box<dyn Activity> + Send {}
+
+impl<T: FnOnce(ActivityId) -> Box<dyn Activity> + Send> ActivityBuilder for T {}
+
+/// [ActivityId] coupled with an [ActivityBuilder].
+pub type ActivityIdAndBuilder = (ActivityId, Box<dyn ActivityBuilder>);
+
+#[cfg(test)]
+mod test {
+    use super::ActivityId;
+
+    #[test]
+    fn test_parse_activity_id() {
+        let id = ActivityId::from(42);
+        assert_eq!(id, id.to_string().parse().unwrap());
+        assert_eq!(id, "42".parse().unwrap());
+        assert!("A".parse::<ActivityId>().is_err());
+        assert!("x42".parse::<ActivityId>().is_err());
+    }
+}
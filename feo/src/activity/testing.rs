@@ -0,0 +1,277 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Test harness for stepping a single [`Activity`] without worker pools, agents or IPC
+//!
+//! The harness creates in-process topics (see [`backend_inprocess`]) for the inputs and
+//! outputs of an activity, feeds provided input payloads, steps the activity once and
+//! captures whatever the activity published.
+//!
+//! The activity under test must read and write its topics through the in-process backend,
+//! i.e. through [`ActivityInput`](crate::com::ActivityInput) and
+//! [`ActivityOutput`](crate::com::ActivityOutput) with the `ipc_inprocess` feature enabled.
+//!
+//! ```ignore
+//! let harness = Harness::new()
+//!     .input::<Scene>("test/scene")
+//!     .output::<BrakeInstruction>("test/brake");
+//! let mut activity = EmergencyBraking::build(0.into(), "test/scene", "test/brake");
+//! let outputs = harness.step_once(&mut *activity, Inputs::new().with("test/scene", scene));
+//! assert!(outputs.get::<BrakeInstruction>("test/brake").unwrap().active);
+//! ```
+
+use crate::activity::Activity;
+use crate::com::backend_inprocess::{self, InProcPublisher, InProcSubscriber};
+use crate::com::{Input, Output, TopicHandle};
+use crate::configuration::topics::Topic;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Type-erased function publishing a boxed payload on a topic
+type PublishFn = Box<dyn Fn(Box<dyn Any>)>;
+
+/// Type-erased function reading a payload from a topic, if available
+type ReadFn = Box<dyn Fn() -> Option<Box<dyn Any>>>;
+
+/// Topics connected to an activity under test
+pub struct Harness {
+    /// Handles keeping the topics alive
+    _topics: Vec<TopicHandle>,
+    /// Publishers feeding the inputs of the activity, by topic
+    publishers: HashMap<Topic, PublishFn>,
+    /// Subscribers capturing the outputs of the activity, by topic
+    subscribers: HashMap<Topic, ReadFn>,
+}
+
+impl Harness {
+    /// Create a harness without any topics
+    pub fn new() -> Self {
+        Self {
+            _topics: Vec::new(),
+            publishers: HashMap::new(),
+            subscribers: HashMap::new(),
+        }
+    }
+
+    /// Add a topic the activity under test reads from
+    ///
+    /// # Panics
+    ///
+    /// Panics if the topic already exists
+    pub fn input<T: Send + Sync + 'static>(mut self, topic: Topic) -> Self {
+        self._topics
+            .push(backend_inprocess::init_topic_or_panic::<T>(topic, 1, 1));
+        let output = Output::<T, InProcPublisher<T>>::get_or_panic(topic);
+        let publish = move |payload: Box<dyn Any>| {
+            let payload = payload
+                .downcast::<T>()
+                .unwrap_or_else(|_| panic!("invalid payload type for topic {topic}"));
+            output
+                .write_uninit()
+                .expect("failed to loan payload")
+                .write_payload(*payload)
+                .send();
+        };
+        self.publishers.insert(topic, Box::new(publish));
+        self
+    }
+
+    /// Add a topic the activity under test writes to
+    ///
+    /// # Panics
+    ///
+    /// Panics if the topic already exists
+    pub fn output<T: Send + Sync + 'static>(mut self, topic: Topic) -> Self {
+        self._topics
+            .push(backend_inprocess::init_topic_or_panic::<T>(topic, 1, 1));
+        let input = Input::<T, InProcSubscriber<T>>::get_or_panic(topic);
+        let read = move || {
+            input
+                .read()
+                .map(|guard| Box::new(Arc::clone(&guard.inner.payload)) as Box<dyn Any>)
+        };
+        self.subscribers.insert(topic, Box::new(read));
+        self
+    }
+
+    /// Feed the given inputs, step the activity once and return the captured outputs
+    ///
+    /// # Panics
+    ///
+    /// Panics if an input refers to a topic not added with [`Harness::input`]
+    pub fn step_once(&self, activity: &mut dyn Activity, inputs: Inputs) -> Outputs {
+        for (topic, payload) in inputs.payloads {
+            let publish = self
+                .publishers
+                .get(topic)
+                .unwrap_or_else(|| panic!("unknown input topic {topic}"));
+            publish(payload);
+        }
+
+        activity.step();
+
+        let payloads = self
+            .subscribers
+            .iter()
+            .filter_map(|(topic, read)| read().map(|payload| (*topic, payload)))
+            .collect();
+        Outputs { payloads }
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Input payloads to feed to an activity under test
+#[derive(Default)]
+pub struct Inputs {
+    payloads: Vec<(Topic, Box<dyn Any>)>,
+}
+
+impl Inputs {
+    /// Create an empty set of inputs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a payload to be published on the given topic
+    pub fn with<T: 'static>(mut self, topic: Topic, payload: T) -> Self {
+        self.payloads.push((topic, Box::new(payload)));
+        self
+    }
+}
+
+/// Output payloads published by an activity under test
+pub struct Outputs {
+    payloads: HashMap<Topic, Box<dyn Any>>,
+}
+
+impl Outputs {
+    /// Get the payload published on the given topic, if any
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is not of type `T`
+    pub fn get<T: 'static>(&self, topic: &str) -> Option<&T> {
+        self.payloads.get(topic).map(|payload| {
+            payload
+                .downcast_ref::<Arc<T>>()
+                .unwrap_or_else(|| panic!("invalid payload type for topic {topic}"))
+                .as_ref()
+        })
+    }
+
+    /// Check whether anything has been published on the given topic
+    pub fn contains(&self, topic: &str) -> bool {
+        self.payloads.contains_key(topic)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Harness, Inputs};
+    use crate::activity::{Activity, ActivityId};
+    use crate::com::backend_inprocess::{InProcPublisher, InProcSubscriber};
+    use crate::com::{Input, Output};
+
+    #[derive(Debug)]
+    struct Scene {
+        distance_obstacle: f64,
+    }
+
+    #[derive(Debug)]
+    struct BrakeInstruction {
+        active: bool,
+        level: f64,
+    }
+
+    /// Emergency braking activity as found in the mini-adas example
+    struct EmergencyBraking {
+        activity_id: ActivityId,
+        input_scene: Input<Scene, InProcSubscriber<Scene>>,
+        output_brake_instruction: Output<BrakeInstruction, InProcPublisher<BrakeInstruction>>,
+    }
+
+    impl EmergencyBraking {
+        fn build(
+            activity_id: ActivityId,
+            scene_topic: &str,
+            brake_instruction_topic: &str,
+        ) -> Box<dyn Activity> {
+            Box::new(Self {
+                activity_id,
+                input_scene: Input::get_or_panic(scene_topic),
+                output_brake_instruction: Output::get_or_panic(brake_instruction_topic),
+            })
+        }
+    }
+
+    impl Activity for EmergencyBraking {
+        fn id(&self) -> ActivityId {
+            self.activity_id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            let scene = self.input_scene.read();
+            let brake_instruction = self.output_brake_instruction.write_uninit();
+
+            if let (Some(scene), Some(brake_instruction)) = (scene, brake_instruction) {
+                const ENGAGE_DISTANCE: f64 = 30.0;
+                const MAX_BRAKE_DISTANCE: f64 = 15.0;
+
+                let distance = scene.get().distance_obstacle;
+                let instruction = if distance < ENGAGE_DISTANCE {
+                    let level = f64::min(
+                        1.0,
+                        (ENGAGE_DISTANCE - distance) / (ENGAGE_DISTANCE - MAX_BRAKE_DISTANCE),
+                    );
+                    BrakeInstruction {
+                        active: true,
+                        level,
+                    }
+                } else {
+                    BrakeInstruction {
+                        active: false,
+                        level: 0.0,
+                    }
+                };
+                brake_instruction.write_payload(instruction).send();
+            }
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_emergency_braking_engages() {
+        let harness = Harness::new()
+            .input::<Scene>("test/harness/scene")
+            .output::<BrakeInstruction>("test/harness/brake");
+        let mut activity =
+            EmergencyBraking::build(0.into(), "test/harness/scene", "test/harness/brake");
+
+        let inputs = Inputs::new().with(
+            "test/harness/scene",
+            Scene {
+                distance_obstacle: 20.0,
+            },
+        );
+        let outputs = harness.step_once(&mut *activity, inputs);
+        let brake = outputs
+            .get::<BrakeInstruction>("test/harness/brake")
+            .expect("no brake instruction");
+        assert!(brake.active);
+        assert!(brake.level > 0.0);
+
+        // Without input, nothing is published
+        let outputs = harness.step_once(&mut *activity, Inputs::new());
+        assert!(!outputs.contains("test/harness/brake"));
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Histogram of task chain durations
+
+use feo_time::Duration;
+use std::fmt;
+use std::ops::Range;
+
+/// Number of buckets of a [`LatencyHistogram`]
+pub const BUCKETS: usize = 32;
+
+/// Histogram of durations with logarithmic buckets
+///
+/// Bucket 0 counts durations below 1 µs, bucket `i` durations in `[2^(i-1), 2^i)` µs.
+/// The last bucket also counts all longer durations. Recording never allocates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; BUCKETS],
+}
+
+impl LatencyHistogram {
+    /// Count the given duration in its bucket
+    pub fn record(&mut self, duration: Duration) {
+        self.counts[Self::bucket(duration)] += 1;
+    }
+
+    /// Index of the bucket counting the given duration
+    pub fn bucket(duration: Duration) -> usize {
+        let micros = duration.as_micros();
+        if micros == 0 {
+            0
+        } else {
+            ((u128::BITS - micros.leading_zeros()) as usize).min(BUCKETS - 1)
+        }
+    }
+
+    /// Range of durations counted in the given bucket. The range of the last bucket is
+    /// open-ended, its end is [`Duration::MAX`].
+    pub fn bucket_range(index: usize) -> Range<Duration> {
+        let bound = |i: usize| match i {
+            0 => Duration::ZERO,
+            BUCKETS => Duration::MAX,
+            i => Duration::from_micros(1 << (i - 1)),
+        };
+        bound(index)..bound(index + 1)
+    }
+
+    /// Counts of all buckets
+    pub fn counts(&self) -> &[u64; BUCKETS] {
+        &self.counts
+    }
+
+    /// Total number of recorded durations
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Iterate over the non-empty buckets with their ranges and counts
+    pub fn iter(&self) -> impl Iterator<Item = (Range<Duration>, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| (Self::bucket_range(index), *count))
+    }
+}
+
+impl fmt::Display for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, (range, count)) in self.iter().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "[{:?}, {:?}): {count}", range.start, range.end)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LatencyHistogram, BUCKETS};
+    use feo_time::Duration;
+
+    #[test]
+    fn test_bucket_placement() {
+        let mut histogram = LatencyHistogram::default();
+        for micros in [0, 1, 3, 4, 7, 1000, 1023, 1024] {
+            histogram.record(Duration::from_micros(micros));
+        }
+        histogram.record(Duration::from_nanos(999));
+        histogram.record(Duration::from_secs(3600));
+
+        let counts = histogram.counts();
+        // Below 1 µs
+        assert_eq!(counts[0], 2);
+        // [1, 2) µs
+        assert_eq!(counts[1], 1);
+        // [2, 4) µs
+        assert_eq!(counts[2], 1);
+        // [4, 8) µs
+        assert_eq!(counts[3], 2);
+        // [512, 1024) µs
+        assert_eq!(counts[10], 2);
+        // [1024, 2048) µs
+        assert_eq!(counts[11], 1);
+        // One hour is beyond the last bound
+        assert_eq!(counts[BUCKETS - 1], 1);
+        assert_eq!(histogram.total(), 10);
+
+        // Each duration lies in the range of its bucket
+        for micros in [0, 1, 5, 1000, 1 << 20] {
+            let duration = Duration::from_micros(micros);
+            let range = LatencyHistogram::bucket_range(LatencyHistogram::bucket(duration));
+            assert!(range.contains(&duration), "{duration:?} not in {range:?}");
+        }
+        assert_eq!(
+            LatencyHistogram::bucket_range(11),
+            Duration::from_micros(1024)..Duration::from_micros(2048)
+        );
+        assert_eq!(
+            LatencyHistogram::bucket_range(BUCKETS - 1).end,
+            Duration::MAX
+        );
+    }
+}
@@ -8,5 +8,53 @@
 //! agents. The primary agent is responsible for triggering the execution of all activities distributed
 //! across all agents.
 
+pub mod histogram;
 pub mod primary;
 pub mod secondary;
+
+use crate::error::Error;
+use crate::signalling::Receiver;
+use feo_time::{Duration, Instant, Scaled};
+
+/// Convert a timeout budget in feo-time to the real time timeout to pass to [`mio::Poll::poll`]
+///
+/// Timeouts of `mio` and `std` run on the OS clock. If feo-time is scaled with
+/// [`feo_time::speed`], a budget of e.g. 100ms feo-time passes in 50ms real time at factor 2.
+/// Converting timeouts of watchdogs and heartbeats with this function lets them fire after
+/// the same feo-time regardless of the factor. No budget, i.e. waiting forever, stays `None`.
+pub fn poll_timeout(budget: Option<Duration>) -> Option<std::time::Duration> {
+    budget.map(|budget| budget.scaled())
+}
+
+/// Real time to wait for a deadline on the manual clock before checking it again
+const MANUAL_CLOCK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+
+/// Real time timeout until the given deadline in feo-time, see [`poll_timeout`]
+///
+/// Zero if the deadline has passed. On the manual clock, time doesn't pass while waiting:
+/// the timeout is a short interval after which the deadline is to be checked again, see
+/// [`recv_until`].
+pub(crate) fn timeout_until(deadline: Instant) -> std::time::Duration {
+    let budget = deadline.saturating_duration_since(Instant::now());
+    if feo_time::is_manual() && !budget.is_zero() {
+        return MANUAL_CLOCK_INTERVAL;
+    }
+    poll_timeout(Some(budget)).unwrap_or_default()
+}
+
+/// Receive from `receiver`, waiting at most until the given deadline in feo-time
+///
+/// Fails with [`Error::Timeout`] once the deadline has passed, on the OS clock scaled by the
+/// speed factor or on the manual clock as soon as it has been advanced past the deadline.
+pub(crate) fn recv_until<T>(
+    receiver: &mut impl Receiver<T>,
+    deadline: Instant,
+) -> Result<T, Error> {
+    loop {
+        match receiver.recv_timeout(timeout_until(deadline)) {
+            // Rounding of the scaled timeout or the manual clock may wake up before the deadline
+            Err(Error::Timeout(_)) if Instant::now() < deadline => continue,
+            result => return result,
+        }
+    }
+}
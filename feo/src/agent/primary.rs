@@ -2,32 +2,94 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::activity::ActivityId;
+use crate::activity::{ActivityBuilder, ActivityId};
+use crate::agent;
+use crate::agent::histogram::LatencyHistogram;
+use crate::configuration::describe::describe;
+use crate::configuration::topics::TopicWiring;
+use crate::configuration::topology::{Topology, TopologyHash};
 use crate::error::Error;
+use crate::signalling::inter_proc_socket::PartialPdu;
+#[cfg(feature = "signalling_auth")]
+use crate::signalling::CHALLENGE_SIZE;
 use crate::signalling::{
-    AgentId, IntraProcReceiver, IntraProcSender, MioMultiSocketReceiver, MioMultiSocketSender,
-    MioSocketReceiver, Receiver, Sender, Signal,
+    AgentId, CycleId, IntraProcReceiver, IntraProcSender, MioMultiSocketReceiver,
+    MioMultiSocketSender, MioSocketReceiver, MioSocketSender, Receiver, Sender, Signal, SignalPdu,
+    SignallingConfig,
 };
-use crate::timestamp::{self, timestamp};
-use crate::worker_pool::{WorkerId, WorkerPool};
+use crate::timestamp::{self, timestamp, Timestamp};
+use crate::worker_pool::{ActivityStates, WorkerId, WorkerPool};
 use feo_log::{debug, error, info, trace, warn};
 use feo_time::{Duration, Instant};
+use feo_tracing::tracing::Span;
+use feo_tracing::{span, Level};
 use mio::net::{TcpListener, TcpStream};
-use mio::{Events, Interest, Poll, Token};
+use mio::{Events, Interest, Poll, Token, Waker};
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::io;
 use std::net::SocketAddr;
+use std::sync::{mpsc, Arc};
 use std::thread;
 
+/// Default watchdog timeout of a task chain cycle as a multiple of the cycle time
+const DEFAULT_WATCHDOG_CYCLES: u32 = 10;
+
+/// Configuration of a task chain scheduled by the primary agent
+pub struct TaskChainConfig {
+    /// The name of the task chain, used in log messages
+    pub name: String,
+
+    /// The target duration of a cycle of the task chain
+    pub cycle_time: Duration,
+
+    /// Time after which a stalled cycle is reported (defaults to ten cycle times)
+    pub watchdog_timeout: Option<Duration>,
+
+    /// Time to wait for the recorders to signal ready after the end of a cycle, after which
+    /// those which have not are disconnected (defaults to the watchdog timeout)
+    pub flush_timeout: Option<Duration>,
+
+    /// For each activity of the task chain the list of activities it depends on
+    pub activity_depends: HashMap<ActivityId, Vec<ActivityId>>,
+
+    /// For some activities of the task chain a list of activities they optionally depend on.
+    /// An activity is stepped without waiting for its optional dependencies and sees the
+    /// latest output of those which have already signalled ready. A dependency listed here is
+    /// not waited for even if it is also listed in [`Self::activity_depends`].
+    pub optional_depends: HashMap<ActivityId, Vec<ActivityId>>,
+
+    /// Activities stepped only every n-th cycle, with their rate divisor n.
+    /// All other activities are stepped in every cycle.
+    pub rate_divisors: HashMap<ActivityId, usize>,
+
+    /// Deadlines of some activities relative to the start of a cycle. A ready signal
+    /// arriving after the deadline of its activity is reported, independently of whether
+    /// the cycle as a whole overruns its cycle time.
+    pub activity_deadlines: HashMap<ActivityId, Duration>,
+}
+
 pub struct PrimaryAgentConfig {
     /// The id of the agent
     pub agent_id: AgentId,
 
-    /// The socket address on which to listen for connections from secondary agents
-    pub bind_addr: SocketAddr,
+    /// The socket addresses on which to listen for connections from secondary agents
+    pub bind_addrs: Vec<SocketAddr>,
 
-    /// The target duration of a fixed execution order task chain cycle
-    pub cycle_time: Duration,
+    /// The task chains to be scheduled independently, each with a disjoint set of activities
+    pub task_chains: Vec<TaskChainConfig>,
+
+    /// Whether to start up the activities one after the other in the order of their ids
+    /// instead of concurrently
+    pub serial_startup: bool,
+
+    /// Whether to accept secondary agents reconnecting while running instead of failing once
+    /// one of them has gone away
+    pub accept_reconnects: bool,
+
+    /// Whether to shut down right after the startup instead of running any cycles
+    pub dry_run: bool,
 
     /// Overall map of task assignment to agents and workers
     pub agent_map: HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>,
@@ -35,8 +97,9 @@ pub struct PrimaryAgentConfig {
     /// List of agent IDs of attached recorders
     pub recorders: Option<HashSet<AgentId>>,
 
-    /// For each activity the list of activities it depends on
-    pub activity_depends: HashMap<ActivityId, Vec<ActivityId>>,
+    /// Recorders running in the process of the primary agent, each with the sender of the
+    /// signals to record. They signal ready through the intra-process ready channel.
+    pub local_recorders: HashMap<AgentId, IntraProcSender<Signal>>,
 
     /// The optional worker pool run by the primary agent
     pub local_worker_pool: Option<WorkerPool>,
@@ -46,54 +109,197 @@ pub struct PrimaryAgentConfig {
 
     /// Intra-process receiver of (ready) signals from all activities
     pub intra_ready_receiver: IntraProcReceiver<Signal>,
+
+    /// Tunables of the signalling with secondary agents and recorders
+    pub signalling: SignallingConfig,
+
+    /// Wiring of the topics, only used to describe the configuration
+    pub topics: Vec<TopicWiring>,
 }
 
 /// Implementation of the primary FEO agent
 pub struct PrimaryAgent {
     scheduler: Scheduler,
+    /// Lifecycle states of the activities of the local worker pool, if any
+    activity_states: Option<ActivityStates>,
+    /// Whether to shut down right after the startup, see [`Self::run_result`]
+    dry_run: bool,
 }
 
 impl PrimaryAgent {
     /// Create a new primary agent from the given configuration
     pub fn new(config: PrimaryAgentConfig) -> Self {
+        info!("Configuration:\n{}", describe(&config));
         let PrimaryAgentConfig {
             agent_id,
-            bind_addr,
-            cycle_time,
+            bind_addrs,
+            task_chains,
+            serial_startup,
+            accept_reconnects,
+            dry_run,
             agent_map,
             recorders,
-            activity_depends,
+            local_recorders,
             local_worker_pool,
             intra_ready_sender,
             intra_ready_receiver,
+            signalling,
+            topics,
         } = config;
 
+        let dependencies = task_chains.iter().map(|chain| &chain.activity_depends);
+        let topology = Topology::new(&agent_map, dependencies, &topics).hash();
+        info!("Topology hash: {topology}");
+
+        let activity_states = local_worker_pool.as_ref().map(WorkerPool::activity_states);
         let activity_connector = ActivityConnector::new(
             &agent_map,
             recorders.unwrap_or(HashSet::default()),
+            local_recorders,
             agent_id,
-            bind_addr,
+            bind_addrs,
             intra_ready_sender,
             intra_ready_receiver,
             local_worker_pool,
+            signalling,
+            topology,
+            accept_reconnects,
         );
 
-        let scheduler = Scheduler::new(cycle_time, activity_depends, activity_connector);
-        Self { scheduler }
+        let chains = task_chains.into_iter().map(TaskChain::new).collect();
+        let scheduler = Scheduler::new(chains, serial_startup, activity_connector);
+        Self {
+            scheduler,
+            activity_states,
+            dry_run,
+        }
     }
 
+    /// Run the FEO execution loop, panicking on errors
+    ///
+    /// See [`run_result`](Self::run_result) for handling errors instead.
     pub fn run(&mut self) {
+        self.run_result().expect("failed to run primary agent");
+    }
+
+    /// Run the FEO execution loop until an error occurs
+    ///
+    /// Fails on errors of the signalling with activities, secondary agents and recorders,
+    /// e.g. with [`Error::Disconnected`] if a remote agent has gone away, leaving it to the
+    /// caller whether to restart or exit. Secondary agents going away are waited for instead
+    /// if the agent accepts reconnects, see
+    /// [`Builder::accept_reconnects`](crate::configuration::primary_agent::Builder::accept_reconnects).
+    ///
+    /// In a dry run, all activities are started up and shut down again without running any
+    /// cycle, see [`Builder::dry_run`](crate::configuration::primary_agent::Builder::dry_run).
+    pub fn run_result(&mut self) -> Result<(), Error> {
+        self.connect()?;
+        if self.dry_run {
+            return self.scheduler.dry_run();
+        }
+
+        // Run the FEO execution loop
+        self.scheduler.run()
+    }
+
+    /// Run exactly `n` cycles of each task chain, then shut down all activities and return
+    ///
+    /// No cycle is run in a dry run, see [`Self::run_result`].
+    pub fn run_cycles(&mut self, n: usize) -> Result<(), Error> {
+        self.connect()?;
+        if self.dry_run {
+            return self.scheduler.dry_run();
+        }
+
+        // Run the FEO execution loop for the given number of cycles
+        self.scheduler.run_cycles(n)
+    }
+
+    /// Histogram of the cycle durations of the given task chain, if it exists
+    ///
+    /// The duration of a cycle is measured from its start until all activities and recorders
+    /// have signalled ready.
+    pub fn latency_histogram(&self, task_chain: &str) -> Option<&LatencyHistogram> {
+        self.scheduler.latency_histogram(task_chain)
+    }
+
+    /// Number of cycles in which the given activity signalled ready after its deadline
+    ///
+    /// See [`TaskChainConfig::activity_deadlines`].
+    pub fn deadline_misses(&self, activity_id: ActivityId) -> usize {
+        self.scheduler.deadline_misses(activity_id)
+    }
+
+    /// Handle to add activities to the local worker pool while the agent runs
+    pub fn activity_registrar(&self) -> ActivityRegistrar {
+        self.scheduler.activity_registrar()
+    }
+
+    /// Lifecycle states of the activities run by the local worker pool, if any
+    ///
+    /// The states of activities of secondary agents are maintained in their processes, see
+    /// [`SecondaryAgent::activity_states`](super::secondary::SecondaryAgent::activity_states).
+    pub fn activity_states(&self) -> Option<ActivityStates> {
+        self.activity_states.clone()
+    }
+
+    /// Initialize local time and connect to and synchronize remote agents
+    fn connect(&mut self) -> Result<(), Error> {
         // Initialize local time
         timestamp::initialize();
 
         // Connect to remote agents
-        self.scheduler.connect_remotes();
+        self.scheduler.connect_remotes()?;
 
         // synchronize timestamps by distribute system startup time
-        self.scheduler.sync_remotes();
+        self.scheduler.sync_remotes()
+    }
+}
 
-        // Run the FEO execution loop
-        self.scheduler.run();
+/// Activity to be added to a running primary agent, see [`ActivityRegistrar::add`]
+struct NewActivity {
+    task_chain: String,
+    worker_id: WorkerId,
+    activity_id: ActivityId,
+    builder: Box<dyn ActivityBuilder>,
+    depends: Vec<ActivityId>,
+}
+
+/// Handle to add activities to the local worker pool of a running primary agent, e.g. a
+/// diagnostic activity
+///
+/// Obtained from [`PrimaryAgent::activity_registrar`], can be sent to other threads.
+#[derive(Clone)]
+pub struct ActivityRegistrar(mpsc::Sender<NewActivity>);
+
+impl ActivityRegistrar {
+    /// Add an activity to the given task chain, depending on the given activities of the chain
+    ///
+    /// Only leaf activities can be added, i.e. no other activity depends on the new one. The
+    /// activity runs on a new worker with the given id in the local worker pool. Once the task
+    /// chain is between two cycles, the scheduler starts up the activity, waiting for it to
+    /// signal ready, and steps it from the next cycle on. An addition of an existing activity
+    /// or worker, or with dependencies outside the task chain, is logged and dropped.
+    ///
+    /// Fails if the agent has been dropped.
+    pub fn add(
+        &self,
+        task_chain: &str,
+        worker_id: WorkerId,
+        activity_id: ActivityId,
+        builder: Box<dyn ActivityBuilder>,
+        depends: Vec<ActivityId>,
+    ) -> Result<(), Error> {
+        let activity = NewActivity {
+            task_chain: task_chain.into(),
+            worker_id,
+            activity_id,
+            builder,
+            depends,
+        };
+        self.0
+            .send(activity)
+            .map_err(|_| Error::Channel("primary agent has been dropped"))
     }
 }
 
@@ -104,31 +310,152 @@ struct ActivityState {
 
     /// Whether the activity has finished its previously triggered operation
     ready: bool,
+
+    /// Time after the start of the current cycle at which the activity has been stepped
+    stepped: Option<Duration>,
 }
 
-/// Global activity scheduler
-///
-/// The scheduler (aka 'FEO Executor') executes the FEO activities according to the defined order
-struct Scheduler {
-    /// Target duration of a task chain cycle
+/// Activities a task chain cycle is still waiting for
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PendingActivities {
+    /// Activities triggered but not yet signalled 'ready'
+    triggered: Vec<ActivityId>,
+
+    /// Activities not yet triggered, i.e. waiting for their dependencies
+    untriggered: Vec<ActivityId>,
+}
+
+impl Display for PendingActivities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |ids: &[ActivityId]| {
+            ids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "triggered but not ready: [{}], not triggered: [{}]",
+            join(&self.triggered),
+            join(&self.untriggered)
+        )
+    }
+}
+
+/// Phase of a task chain cycle
+#[derive(Debug)]
+enum Phase {
+    /// Waiting for the start of the next cycle
+    Idle,
+
+    /// Stepping the activities of the current cycle
+    Running,
+
+    /// Waiting for the given recorders to signal ready after the end of the cycle
+    Flushing(HashSet<AgentId>),
+}
+
+/// Task chain with its own cycle time and activity dependencies
+struct TaskChain {
+    /// Name of the task chain
+    name: String,
+
+    /// Target duration of a cycle
     cycle_time: Duration,
 
+    /// Time after which a stalled cycle is reported
+    watchdog_timeout: Duration,
+
+    /// Time after which recorders not having signalled ready after the end of a cycle are
+    /// disconnected
+    flush_timeout: Duration,
+
     /// For each activity: list of activities it depends on
     activity_depends: HashMap<ActivityId, Vec<ActivityId>>,
 
-    /// Helper object connecting to activities in all connected agents
-    activity_connector: ActivityConnector,
+    /// For some activities: list of activities they optionally depend on
+    optional_depends: HashMap<ActivityId, Vec<ActivityId>>,
+
+    /// For each sub-rate activity: number of cycles per step
+    rate_divisors: HashMap<ActivityId, usize>,
+
+    /// For some activities: deadline relative to the start of a cycle
+    activity_deadlines: HashMap<ActivityId, Duration>,
+
+    /// For each activity with a deadline: number of cycles in which it was missed
+    deadline_misses: HashMap<ActivityId, usize>,
 
     /// Map keeping track of activity states
     activity_states: HashMap<ActivityId, ActivityState>,
+
+    /// Phase of the current cycle
+    phase: Phase,
+
+    /// Id of the current cycle, the default id if idle
+    cycle_id: CycleId,
+
+    /// Span of the current cycle from its start to its end
+    span: Span,
+
+    /// Start of the current cycle or, if idle, of the next cycle
+    cycle_start: Instant,
+
+    /// Point in time at which a stall of the current cycle is reported or, while flushing,
+    /// at which the pending recorders are disconnected
+    watchdog: Instant,
+
+    /// Number of completed cycles
+    cycles: usize,
+
+    /// Durations of the completed cycles
+    latency_histogram: LatencyHistogram,
 }
 
-impl Scheduler {
-    fn new(
-        feo_cycle_time: Duration,
-        activity_depends: HashMap<ActivityId, Vec<ActivityId>>,
-        activity_connector: ActivityConnector,
-    ) -> Self {
+impl TaskChain {
+    fn new(config: TaskChainConfig) -> Self {
+        let TaskChainConfig {
+            name,
+            cycle_time,
+            watchdog_timeout,
+            flush_timeout,
+            activity_depends,
+            optional_depends,
+            rate_divisors,
+            activity_deadlines,
+        } = config;
+
+        for (act_id, dependencies) in optional_depends.iter() {
+            assert!(
+                activity_depends.contains_key(act_id),
+                "Optional dependencies given for activity {act_id} not in task chain {name}"
+            );
+            for dependency in dependencies {
+                assert!(
+                    activity_depends.contains_key(dependency),
+                    "Optional dependency {dependency} of activity {act_id} not in task chain {name}"
+                );
+            }
+        }
+
+        for (act_id, divisor) in rate_divisors.iter() {
+            assert!(
+                activity_depends.contains_key(act_id),
+                "Rate divisor given for activity {act_id} not in task chain {name}"
+            );
+            assert!(
+                *divisor > 0,
+                "Rate divisor of activity {act_id} must not be zero"
+            );
+        }
+
+        for act_id in activity_deadlines.keys() {
+            assert!(
+                activity_depends.contains_key(act_id),
+                "Deadline given for activity {act_id} not in task chain {name}"
+            );
+        }
+        let deadline_misses = activity_deadlines.keys().map(|id| (*id, 0)).collect();
+
         // Pre-allocate state map
         let activity_states: HashMap<ActivityId, ActivityState> = activity_depends
             .keys()
@@ -138,142 +465,721 @@ impl Scheduler {
                     ActivityState {
                         triggered: false,
                         ready: false,
+                        stepped: None,
                     },
                 )
             })
             .collect();
 
+        let watchdog_timeout = watchdog_timeout.unwrap_or(cycle_time * DEFAULT_WATCHDOG_CYCLES);
+        let now = Instant::now();
         Self {
-            cycle_time: feo_cycle_time,
+            name,
+            cycle_time,
+            watchdog_timeout,
+            flush_timeout: flush_timeout.unwrap_or(watchdog_timeout),
             activity_depends,
-            activity_connector,
+            optional_depends,
+            rate_divisors,
+            activity_deadlines,
+            deadline_misses,
             activity_states,
+            phase: Phase::Idle,
+            cycle_id: CycleId::default(),
+            span: Span::none(),
+            cycle_start: now,
+            watchdog: now,
+            cycles: 0,
+            latency_histogram: LatencyHistogram::default(),
+        }
+    }
+
+    /// Check if all activities have signalled 'ready'
+    fn is_all_ready(&self) -> bool {
+        self.activity_states.values().all(|v| v.ready)
+    }
+
+    /// Get the activities the current cycle is waiting for, sorted by id
+    fn pending_activities(&self) -> PendingActivities {
+        let mut pending = PendingActivities::default();
+        for (act_id, state) in self.activity_states.iter() {
+            if !state.triggered {
+                pending.untriggered.push(*act_id);
+            } else if !state.ready {
+                pending.triggered.push(*act_id);
+            }
+        }
+        pending.triggered.sort();
+        pending.untriggered.sort();
+        pending
+    }
+
+    /// Report the given activity having signalled ready in the current cycle if that is after
+    /// its deadline
+    fn check_deadline(&mut self, act_id: &ActivityId) {
+        let Some(deadline) = self.activity_deadlines.get(act_id) else {
+            return;
+        };
+        let ready = self.cycle_start.elapsed();
+        if ready <= *deadline {
+            return;
+        }
+        *self.deadline_misses.get_mut(act_id).unwrap() += 1;
+        let stepped = self.activity_states[act_id].stepped.unwrap_or_default();
+        error!(
+            "Activity {act_id} of task chain {} ready after {ready:?}, stepped after {stepped:?}. Expected to be ready within {deadline:?}",
+            self.name
+        );
+    }
+
+    /// Point in time at which the scheduler has to act on this chain without a signal
+    fn next_deadline(&self) -> Option<Instant> {
+        match self.phase {
+            Phase::Idle => Some(self.cycle_start),
+            Phase::Running | Phase::Flushing(_) => Some(self.watchdog),
+        }
+    }
+}
+
+/// Global activity scheduler
+///
+/// The scheduler (aka 'FEO Executor') executes the FEO activities according to the defined order.
+/// Each task chain is cycled independently at its own cycle time.
+struct Scheduler {
+    /// Task chains to be scheduled
+    chains: Vec<TaskChain>,
+
+    /// For each activity: index of the task chain it belongs to
+    activity_chains: HashMap<ActivityId, usize>,
+
+    /// Indices of the flushing task chains in the order their end has been recorded
+    flush_order: VecDeque<usize>,
+
+    /// Id of the most recently started cycle of any task chain
+    last_cycle_id: CycleId,
+
+    /// Whether to start up the activities one after the other
+    serial_startup: bool,
+
+    /// Helper object connecting to activities in all connected agents
+    activity_connector: ActivityConnector,
+
+    /// Sender handed out to add activities, see [`ActivityRegistrar`]
+    registrar: mpsc::Sender<NewActivity>,
+
+    /// Receiver of the activities to add
+    new_activities: mpsc::Receiver<NewActivity>,
+
+    /// Activities to add once their task chain is between two cycles
+    queued_activities: Vec<NewActivity>,
+
+    /// Activities of reconnected secondary agents being started up again, with their agent
+    restarting: HashMap<ActivityId, AgentId>,
+}
+
+impl Scheduler {
+    fn new(
+        chains: Vec<TaskChain>,
+        serial_startup: bool,
+        activity_connector: ActivityConnector,
+    ) -> Self {
+        // Map each activity to its task chain
+        let mut activity_chains: HashMap<ActivityId, usize> = Default::default();
+        for (index, chain) in chains.iter().enumerate() {
+            for act_id in chain.activity_depends.keys() {
+                let previous = activity_chains.insert(*act_id, index);
+                assert!(
+                    previous.is_none(),
+                    "Activity {act_id} assigned to more than one task chain"
+                );
+            }
+        }
+
+        let (registrar, new_activities) = mpsc::channel();
+        Self {
+            chains,
+            activity_chains,
+            flush_order: Default::default(),
+            last_cycle_id: CycleId::default(),
+            serial_startup,
+            activity_connector,
+            registrar,
+            new_activities,
+            queued_activities: Vec::new(),
+            restarting: HashMap::new(),
         }
     }
 
+    /// Handle to add activities while running
+    fn activity_registrar(&self) -> ActivityRegistrar {
+        ActivityRegistrar(self.registrar.clone())
+    }
+
     /// Connect to all expected secondary agents and recorders (i.e. all remote processes)
-    pub fn connect_remotes(&mut self) {
+    pub fn connect_remotes(&mut self) -> Result<(), Error> {
         self.activity_connector.connect_remotes()
     }
 
     /// Synchronize all remote agents and recorders
-    pub fn sync_remotes(&mut self) {
-        self.activity_connector.sync_time();
+    pub fn sync_remotes(&mut self) -> Result<(), Error> {
+        self.activity_connector.sync_time()?;
         info!("Time synchronization of remote agents done");
+        Ok(())
     }
 
     /// Run the task lifecycle, i.e. startup, stepping, shutdown
     ///
     /// Shutdown is not implemented, as it is not yet defined in the architecture
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), Error> {
+        self.startup()?;
+        self.run_until(usize::MAX, None)
+    }
+
+    /// Run the task lifecycle with exactly `n` cycles of each task chain, i.e. startup,
+    /// `n` cycles, shutdown
+    pub fn run_cycles(&mut self, n: usize) -> Result<(), Error> {
+        self.startup()?;
+        self.run_until(n, None)?;
+        self.shutdown()
+    }
+
+    /// Run the task lifecycle without any cycle, i.e. startup and shutdown
+    pub fn dry_run(&mut self) -> Result<(), Error> {
+        self.startup()?;
+        info!("Dry run: system ready, shutting down");
+        self.shutdown()
+    }
+
+    /// Call startup on all activities and wait until all of them have signalled ready
+    fn startup(&mut self) -> Result<(), Error> {
         // Sort activity ids
-        let mut activity_ids: Vec<_> = self.activity_states.keys().collect();
+        let mut activity_ids: Vec<_> = self.activity_chains.keys().copied().collect();
+        activity_ids.sort();
+
+        if self.serial_startup {
+            // Call startup on each activity sorted according to their ids and wait
+            // for its ready signal before starting up the next one
+            for activity_id in activity_ids.iter() {
+                self.activity_connector.startup_activity(activity_id)?;
+                while !self.is_ready(activity_id) {
+                    self.wait_next_signal(None)?;
+                }
+            }
+        } else {
+            // Call startup on all activities sorted according to their ids
+            // Note: Actual startup may occur in different order, depending on the assignment
+            // of activities to worker threads. (A worker with greater id value may start up in
+            // one thread before an activity with smaller id value in another thread.)
+            for activity_id in activity_ids.iter() {
+                self.activity_connector.startup_activity(activity_id)?;
+            }
+
+            // Wait until all activities have returned their ready signal
+            self.wait_all_ready()?;
+        }
+
+        // All agents and recorders are connected since `connect_remotes`
+        self.activity_connector.broadcast_system_ready();
+
+        // Start the first cycle of all task chains now
+        let now = Instant::now();
+        self.chains
+            .iter_mut()
+            .for_each(|chain| chain.cycle_start = now);
+        Ok(())
+    }
+
+    /// Call shutdown on all activities and wait until all of them have signalled ready,
+    /// then stop all secondary agents and recorders
+    fn shutdown(&mut self) -> Result<(), Error> {
+        let mut activity_ids: Vec<_> = self.activity_chains.keys().copied().collect();
         activity_ids.sort();
 
-        // Call startup on all activities sorted according to their ids
-        // Note: Actual startup may occur in different order, depending on the assignment
-        // of activities to worker threads. (A worker with greater id value may start up in
-        // one thread before an activity with smaller id value in another thread.)
-        for activity_id in activity_ids {
-            self.activity_connector.startup_activity(activity_id)
+        // Clear ready signals
+        for chain in self.chains.iter_mut() {
+            chain
+                .activity_states
+                .values_mut()
+                .for_each(|v| v.ready = false);
+        }
+
+        // Call shutdown on all activities sorted according to their ids. Activities of
+        // disconnected agents are not waited for.
+        for activity_id in activity_ids.iter() {
+            self.activity_connector.shutdown_activity(activity_id)?;
+            if self.activity_connector.is_disconnected(activity_id) {
+                let index = self.activity_chains[activity_id];
+                let state = self.chains[index].activity_states.get_mut(activity_id);
+                state.unwrap().ready = true;
+            }
         }
 
         // Wait until all activities have returned their ready signal
-        while !self.is_all_ready() {
-            self.wait_next_ready()
-                .expect("failed while waiting for ready signal");
+        self.wait_all_ready()?;
+
+        // Recorders close their recordings like at the end of a cycle, so they get the longest
+        // flush timeout of all task chains
+        let timeout = self
+            .chains
+            .iter()
+            .map(|chain| chain.flush_timeout)
+            .max()
+            .unwrap_or_default();
+        let result = self.activity_connector.shutdown_remotes(timeout);
+        #[cfg(any(feature = "ipc_iceoryx2", feature = "ipc_inprocess"))]
+        crate::com::shutdown();
+        result
+    }
+
+    /// Histogram of the cycle durations of the given task chain, if it exists
+    fn latency_histogram(&self, task_chain: &str) -> Option<&LatencyHistogram> {
+        self.chains
+            .iter()
+            .find(|chain| chain.name == task_chain)
+            .map(|chain| &chain.latency_histogram)
+    }
+
+    /// Number of cycles in which the given activity signalled ready after its deadline
+    fn deadline_misses(&self, activity_id: ActivityId) -> usize {
+        self.activity_chains
+            .get(&activity_id)
+            .and_then(|index| self.chains[*index].deadline_misses.get(&activity_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Check if the given activity has signalled ready
+    fn is_ready(&self, activity_id: &ActivityId) -> bool {
+        let index = self.activity_chains[activity_id];
+        self.chains[index].activity_states[activity_id].ready
+    }
+
+    /// Wait until all activities of all task chains have signalled ready
+    fn wait_all_ready(&mut self) -> Result<(), Error> {
+        while !self.chains.iter().all(TaskChain::is_all_ready) {
+            self.wait_next_signal(None)?;
         }
+        Ok(())
+    }
+
+    /// Loop the task chains. A task chain starts no new cycle once it completed `max_cycles`
+    /// cycles or at or after the given point in time. The method returns once all started
+    /// cycles have finished.
+    fn run_until(&mut self, max_cycles: usize, until: Option<Instant>) -> Result<(), Error> {
+        // Whether the given chain may start another cycle
+        let may_start = |chain: &TaskChain| {
+            chain.cycles < max_cycles && until.is_none_or(|until| chain.cycle_start < until)
+        };
 
-        // Loop the FEO task chain
         loop {
-            let task_chain_start = Instant::now();
+            self.add_activities()?;
+            self.restart_reconnected()?;
+
+            let now = Instant::now();
+            for index in 0..self.chains.len() {
+                let chain = &self.chains[index];
+                let start_due = matches!(chain.phase, Phase::Idle)
+                    && chain.cycle_start <= now
+                    && may_start(chain);
+                if start_due {
+                    self.start_cycle(index);
+                }
+                if matches!(self.chains[index].phase, Phase::Running) {
+                    // Step all activities that have their dependencies met
+                    self.step_foreach_ready(index)?;
+                    if self.chains[index].is_all_ready() {
+                        self.end_cycle(index);
+                    }
+                }
+            }
 
-            // Record start of task chain on registered recorders
-            self.activity_connector.record_task_chain_start();
+            // Determine when to act next without a signal
+            let deadline = self
+                .chains
+                .iter()
+                .filter(|chain| !matches!(chain.phase, Phase::Idle) || may_start(chain))
+                .filter_map(TaskChain::next_deadline)
+                .min();
+            let active = self
+                .chains
+                .iter()
+                .any(|chain| !matches!(chain.phase, Phase::Idle));
+            if deadline.is_none() && !active {
+                return Ok(());
+            }
 
-            // Clear ready and triggered signals
-            self.activity_states.values_mut().for_each(|v| {
-                v.ready = false;
-                v.triggered = false;
-            });
+            // Wait until a new signal has been received,
+            // report the pending activities of chains whose watchdog elapsed
+            // and disconnect the recorders not having flushed in time
+            match self.wait_next_signal(deadline) {
+                Ok(()) => (),
+                Err(Error::Timeout(_)) => {
+                    self.check_watchdogs();
+                    self.check_flush_timeouts();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-            debug!("Starting task chain");
+    /// Add the registered activities whose task chain is between two cycles, queueing the
+    /// others
+    fn add_activities(&mut self) -> Result<(), Error> {
+        self.queued_activities
+            .extend(self.new_activities.try_iter());
+        for activity in std::mem::take(&mut self.queued_activities) {
+            let task_chain = &activity.task_chain;
+            match self
+                .chains
+                .iter()
+                .position(|chain| chain.name == *task_chain)
+            {
+                None => error!(
+                    "Cannot add activity {}: no task chain {task_chain}",
+                    activity.activity_id
+                ),
+                Some(index) if matches!(self.chains[index].phase, Phase::Idle) => {
+                    self.add_activity(index, activity)?
+                }
+                Some(_) => self.queued_activities.push(activity),
+            }
+        }
+        Ok(())
+    }
 
-            while !self.is_all_ready() {
-                // Step all activities that have their dependencies met
-                self.step_foreach_ready();
-                // Wait until a new ready signal has been received
-                self.wait_next_ready()
-                    .expect("failed while waiting for ready signal");
+    /// Start up the activities of the secondary agents reconnected since the last call again
+    ///
+    /// Activities triggered but not ready in the running cycle of their task chain are
+    /// stepped again after their startup.
+    fn restart_reconnected(&mut self) -> Result<(), Error> {
+        for agent_id in self.activity_connector.take_reconnected() {
+            let activities = self.activity_connector.activities_of(agent_id);
+            if activities.is_empty() {
+                self.activity_connector.send_system_ready(agent_id);
+                continue;
             }
+            for activity_id in activities {
+                self.restarting.insert(activity_id, agent_id);
+                self.activity_connector.startup_activity(&activity_id)?;
+
+                let chain = &self.chains[self.activity_chains[&activity_id]];
+                let state = &chain.activity_states[&activity_id];
+                if matches!(chain.phase, Phase::Running) && state.triggered && !state.ready {
+                    self.activity_connector
+                        .step_activity(&activity_id, chain.cycle_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
 
-            // Record end of task chain on registered recorders => recorders will flush
-            // => wait until all recorders have signalled to be ready
-            trace!("Flushing recorders");
-            let start_flush = Instant::now();
-            self.activity_connector.record_task_chain_end();
-            self.activity_connector.wait_recorders_ready();
-            let flush_duration = start_flush.elapsed();
-            trace!("Flushing recorders took {flush_duration:?}");
+    /// Add the given activity to the given idle task chain and start it up
+    fn add_activity(&mut self, index: usize, activity: NewActivity) -> Result<(), Error> {
+        let NewActivity {
+            task_chain,
+            worker_id,
+            activity_id,
+            builder,
+            depends,
+        } = activity;
+        if self.activity_chains.contains_key(&activity_id) {
+            error!("Cannot add activity {activity_id}: already scheduled");
+            return Ok(());
+        }
+        let chain = &self.chains[index];
+        let missing = depends
+            .iter()
+            .find(|id| !chain.activity_depends.contains_key(id));
+        if let Some(dependency) = missing {
+            error!("Cannot add activity {activity_id}: dependency {dependency} not in task chain {task_chain}");
+            return Ok(());
+        }
+        if !self
+            .activity_connector
+            .add_local_activity(worker_id, activity_id, builder)
+        {
+            return Ok(());
+        }
 
-            let task_chain_duration = task_chain_start.elapsed();
-            let time_left = self.cycle_time.saturating_sub(task_chain_duration);
-            if time_left.is_zero() {
-                error!(
-                    "Finished task chain after {task_chain_duration:?}. Expected to be less than {:?}",
-                    self.cycle_time
-                );
-            } else {
-                debug!(
-                    "Finished task chain after {task_chain_duration:?}. Sleeping for {time_left:?}"
-                );
-                thread::sleep(time_left);
+        let chain = &mut self.chains[index];
+        chain.activity_depends.insert(activity_id, depends);
+        let state = ActivityState {
+            triggered: false,
+            ready: false,
+            stepped: None,
+        };
+        chain.activity_states.insert(activity_id, state);
+        self.activity_chains.insert(activity_id, index);
+
+        // The idle task chain accepts the ready signal of the startup, an activity not started
+        // up within the watchdog timeout of the task chain is removed again
+        self.activity_connector.startup_activity(&activity_id)?;
+        let deadline = Instant::now() + self.chains[index].watchdog_timeout;
+        while !self.is_ready(&activity_id) {
+            match self.wait_next_signal(Some(deadline)) {
+                Ok(()) => {}
+                Err(Error::Timeout(_)) => {
+                    error!("Activity {activity_id} did not start up in time, removing it from task chain {task_chain}");
+                    self.remove_activity(index, activity_id);
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
             }
         }
+        info!("Added activity {activity_id} on worker {worker_id} to task chain {task_chain}");
+        Ok(())
+    }
+
+    /// Remove the given activity added by [`Self::add_activity`] from the given task chain and
+    /// the local worker pool
+    fn remove_activity(&mut self, index: usize, activity_id: ActivityId) {
+        let chain = &mut self.chains[index];
+        chain.activity_depends.remove(&activity_id);
+        chain.activity_states.remove(&activity_id);
+        self.activity_chains.remove(&activity_id);
+        self.activity_connector.remove_local_activity(activity_id);
+    }
+
+    /// Start a new cycle of the given task chain
+    fn start_cycle(&mut self, index: usize) {
+        // Record start of task chain on registered recorders
+        self.activity_connector.record_task_chain_start();
+
+        let chain = &mut self.chains[index];
+        debug!("Starting task chain {}", chain.name);
+
+        // Clear ready and triggered signals. Sub-rate activities skipping this cycle count
+        // as ready right away, their dependents see their previous output.
+        let cycle = chain.cycles;
+        for (act_id, state) in chain.activity_states.iter_mut() {
+            let skip = chain
+                .rate_divisors
+                .get(act_id)
+                .is_some_and(|divisor| cycle % *divisor != 0);
+            state.ready = skip;
+            state.triggered = skip;
+            state.stepped = None;
+        }
+        self.last_cycle_id.0 += 1;
+        chain.cycle_id = self.last_cycle_id;
+        chain.cycle_start = Instant::now();
+        chain.watchdog = chain.cycle_start + chain.watchdog_timeout;
+        chain.phase = Phase::Running;
+
+        // Cycles of multiple task chains overlap, so the span is entered and exited explicitly
+        // instead of by a guard
+        chain.span = span!(
+            Level::INFO,
+            "TaskChain",
+            name = chain.name.as_str(),
+            cycle_id = %chain.cycle_id
+        );
+        chain
+            .span
+            .with_subscriber(|(id, dispatch)| dispatch.enter(id));
+    }
+
+    /// End the current cycle of the given task chain once all its activities are ready
+    fn end_cycle(&mut self, index: usize) {
+        // Record end of task chain on registered recorders => recorders will flush
+        // => wait until all recorders have signalled to be ready
+        trace!("Flushing recorders");
+        self.activity_connector.record_task_chain_end();
+        let span = std::mem::replace(&mut self.chains[index].span, Span::none());
+        span.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+        let recorders = self.activity_connector.all_recorders();
+        if recorders.is_empty() {
+            self.finish_cycle(index);
+        } else {
+            let recorders = recorders.clone();
+            let chain = &mut self.chains[index];
+            chain.phase = Phase::Flushing(recorders);
+            chain.watchdog = Instant::now() + chain.flush_timeout;
+            self.flush_order.push_back(index);
+        }
     }
 
-    /// Step each activity whose dependencies have signalled 'ready'
-    fn step_foreach_ready(&mut self) {
-        // Get data from activity_depends in self so that we can iterate over it
-        // and at the same time modify another member of self
-        for (act_id, dependencies) in self.activity_depends.iter() {
+    /// Finish the current cycle of the given task chain and schedule the next one
+    fn finish_cycle(&mut self, index: usize) {
+        let chain = &mut self.chains[index];
+        let task_chain_duration = chain.cycle_start.elapsed();
+        chain.latency_histogram.record(task_chain_duration);
+        let time_left = chain.cycle_time.saturating_sub(task_chain_duration);
+        if time_left.is_zero() {
+            error!(
+                "Finished task chain {} after {task_chain_duration:?}. Expected to be less than {:?}",
+                chain.name, chain.cycle_time
+            );
+        } else {
+            debug!(
+                "Finished task chain {} after {task_chain_duration:?}. Next cycle in {time_left:?}",
+                chain.name
+            );
+        }
+        chain.cycle_start += task_chain_duration + time_left;
+        chain.cycles += 1;
+        chain.cycle_id = CycleId::default();
+        chain.phase = Phase::Idle;
+    }
+
+    /// Step each activity of the given task chain whose dependencies have signalled 'ready'
+    ///
+    /// Optional dependencies are not waited for, see [`TaskChainConfig::optional_depends`].
+    fn step_foreach_ready(&mut self, index: usize) -> Result<(), Error> {
+        let chain = &mut self.chains[index];
+        // Get data from activity_depends in the chain so that we can iterate over it
+        // and at the same time modify another member of the chain
+        for (act_id, dependencies) in chain.activity_depends.iter() {
             // skip activity if already triggered
-            if self.activity_states[act_id].triggered {
+            if chain.activity_states[act_id].triggered {
                 continue;
             }
 
-            // If dependencies are fulfilled
-            let is_ready = self
+            // If dependencies are fulfilled, not waiting for optional ones
+            let optional = chain.optional_depends.get(act_id);
+            let is_ready = chain
                 .activity_states
                 .iter()
                 .filter(|(id, _)| dependencies.contains(id))
+                .filter(|(id, _)| !optional.is_some_and(|optional| optional.contains(id)))
                 .all(|(_, state)| state.ready);
             if is_ready {
-                self.activity_connector.step_activity(act_id);
-                self.activity_states.get_mut(act_id).unwrap().triggered = true;
+                self.activity_connector
+                    .step_activity(act_id, chain.cycle_id)?;
+                let state = chain.activity_states.get_mut(act_id).unwrap();
+                state.triggered = true;
+                state.stepped = Some(chain.cycle_start.elapsed());
             }
         }
+        Ok(())
     }
 
-    /// Wait for the next incoming ready signal
-    fn wait_next_ready(&mut self) -> Result<(), Error> {
-        // Wait for next intra-process ready signal from one of the workers
-        let act_id = self.activity_connector.wait_next_ready()?;
-
-        // Set corresponding ready flag
-        self.activity_states.get_mut(&act_id).unwrap().ready = true;
+    /// Wait for the next incoming ready signal of an activity or recorder,
+    /// at most until the given deadline
+    fn wait_next_signal(&mut self, deadline: Option<Instant>) -> Result<(), Error> {
+        // Wait for next intra-process signal from one of the workers or recorders
+        match self.activity_connector.wait_next_signal(deadline)? {
+            Signal::Ready((act_id, _, _)) if self.restarting.contains_key(&act_id) => {
+                // The startup of an activity of a reconnected agent completed, the agent
+                // learns that the system is ready once all of its activities are started
+                let agent_id = self.restarting.remove(&act_id).unwrap();
+                if !self.restarting.values().any(|id| *id == agent_id) {
+                    self.activity_connector.send_system_ready(agent_id);
+                }
+            }
+            Signal::Ready((act_id, _, cycle_id)) => {
+                // Set corresponding ready flag, unless the signal is a late one
+                // belonging to another cycle
+                let Some(&index) = self.activity_chains.get(&act_id) else {
+                    warn!("Ignoring ready signal of unscheduled activity {act_id}");
+                    return Ok(());
+                };
+                let chain = &mut self.chains[index];
+                if cycle_id != chain.cycle_id {
+                    warn!(
+                        "Ignoring ready signal of activity {act_id} from cycle {cycle_id} in cycle {} of task chain {}",
+                        chain.cycle_id, chain.name
+                    );
+                    return Ok(());
+                }
+                let state = chain.activity_states.get_mut(&act_id).unwrap();
+                state.ready = true;
+                chain.check_deadline(&act_id);
+            }
+            Signal::RecorderReady((agent_id, _)) => self.recorder_ready(agent_id),
+            signal => error!("Ignoring unexpected signal {signal}"),
+        }
         Ok(())
     }
 
-    /// Check if all activities have signalled 'ready'
-    fn is_all_ready(&self) -> bool {
-        self.activity_states.values().all(|v| v.ready)
-    }
+    /// Handle the ready signal of a recorder. It belongs to the earliest flushing task chain
+    /// the recorder has not yet signalled ready for.
+    fn recorder_ready(&mut self, agent_id: AgentId) {
+        let Some(position) = self.flush_order.iter().position(|index| {
+            matches!(&self.chains[*index].phase, Phase::Flushing(recorders) if recorders.contains(&agent_id))
+        }) else {
+            error!("Received unexpected recorder ready signal from {agent_id}");
+            return;
+        };
+
+        let index = self.flush_order[position];
+        let Phase::Flushing(recorders) = &mut self.chains[index].phase else {
+            unreachable!()
+        };
+        recorders.remove(&agent_id);
+        if recorders.is_empty() {
+            self.flush_order.remove(position);
+            self.finish_cycle(index);
+        }
+    }
+
+    /// Report the pending activities of all task chains whose watchdog has elapsed
+    fn check_watchdogs(&mut self) {
+        let now = Instant::now();
+        for chain in self.chains.iter_mut() {
+            if matches!(chain.phase, Phase::Running) && chain.watchdog <= now {
+                error!(
+                    "Task chain {} stalled after {:?}. Pending activities: {}",
+                    chain.name,
+                    chain.cycle_start.elapsed(),
+                    chain.pending_activities()
+                );
+                chain.watchdog += chain.watchdog_timeout;
+            }
+        }
+    }
+
+    /// Disconnect the recorders which have not signalled ready within the flush timeout of a
+    /// task chain after the end of its cycle, instead of stalling the chain
+    ///
+    /// The cycles of all flushing task chains stop waiting for these recorders.
+    fn check_flush_timeouts(&mut self) {
+        let now = Instant::now();
+        let mut dropped: HashSet<AgentId> = HashSet::new();
+        for index in self.flush_order.iter() {
+            let chain = &self.chains[*index];
+            let Phase::Flushing(recorders) = &chain.phase else {
+                continue;
+            };
+            if chain.watchdog > now {
+                continue;
+            }
+            let mut pending: Vec<_> = recorders.iter().copied().collect();
+            pending.sort_by_key(|id| usize::from(*id));
+            let ids: Vec<_> = pending.iter().map(ToString::to_string).collect();
+            error!(
+                "Recorders [{}] did not signal ready within {:?} after the end of task chain {}. Disconnecting them",
+                ids.join(", "),
+                chain.flush_timeout,
+                chain.name
+            );
+            dropped.extend(pending);
+        }
+        if dropped.is_empty() {
+            return;
+        }
+
+        for agent_id in dropped.iter() {
+            self.activity_connector.disconnect_recorder(*agent_id);
+        }
+        for index in std::mem::take(&mut self.flush_order) {
+            let Phase::Flushing(recorders) = &mut self.chains[index].phase else {
+                unreachable!()
+            };
+            recorders.retain(|id| !dropped.contains(id));
+            if recorders.is_empty() {
+                self.finish_cycle(index);
+            } else {
+                self.flush_order.push_back(index);
+            }
+        }
+    }
 }
 
 struct IpcSignalReceiver {
     streams_ready: Option<HashMap<AgentId, TcpStream>>,
     intra_ready_sender: Option<IntraProcSender<Signal>>,
+    signalling: SignallingConfig,
     _thread: Option<thread::JoinHandle<()>>,
 }
 
@@ -281,10 +1187,12 @@ impl IpcSignalReceiver {
     fn new(
         streams_ready: HashMap<AgentId, TcpStream>,
         intra_sender: IntraProcSender<Signal>,
+        signalling: SignallingConfig,
     ) -> Self {
         IpcSignalReceiver {
             streams_ready: Some(streams_ready),
             intra_ready_sender: Some(intra_sender),
+            signalling,
             _thread: None,
         }
     }
@@ -292,15 +1200,27 @@ impl IpcSignalReceiver {
     fn thread_main(
         streams_ready: HashMap<AgentId, TcpStream>,
         mut intra_ready_send: impl Sender<Signal>,
+        signalling: SignallingConfig,
     ) {
         let mut poll = Poll::new().unwrap();
-        let mut events = Events::with_capacity(1024);
+        let mut events = signalling.events();
         let mut ipc_ready_receiver =
             MioMultiSocketReceiver::new(streams_ready, &mut poll, &mut events);
         ipc_ready_receiver.register().unwrap();
 
         loop {
-            let (_, pdu) = ipc_ready_receiver.recv().unwrap();
+            // A failing stream, e.g. closed by its agent, is dropped by the receiver
+            let pdu = match ipc_ready_receiver.recv() {
+                Ok((_, pdu)) => pdu,
+                Err(e) => {
+                    error!("Failed to receive ready signal: {e}");
+                    // Exit once all streams are gone, reconnecting agents get a new thread
+                    if ipc_ready_receiver.is_empty() {
+                        return;
+                    }
+                    continue;
+                }
+            };
             let signal = Signal::try_from(&pdu).unwrap();
             intra_ready_send.send(signal).unwrap();
         }
@@ -317,8 +1237,9 @@ impl IpcSignalReceiver {
             .expect("missing intra-process ready sender");
 
         // Start ready signal receiver thread
+        let signalling = self.signalling;
         self._thread = Some(thread::spawn(move || {
-            IpcSignalReceiver::thread_main(streams_ready, intra_ready_sender)
+            IpcSignalReceiver::thread_main(streams_ready, intra_ready_sender, signalling)
         }));
     }
 }
@@ -328,17 +1249,20 @@ struct ActivityConnector {
     /// ID of the primary agent
     local_agent_id: AgentId,
 
-    /// Socket address on which to wait for connecting remote processing
-    local_addr: SocketAddr,
+    /// Socket addresses on which to wait for connecting remote processes
+    local_addrs: Vec<SocketAddr>,
 
     /// Map providing the IDs of agent and worker executing a given activity
     activity_map: HashMap<ActivityId, (AgentId, WorkerId)>,
 
-    /// Set of connected recorders (possibly empty)
+    /// Set of remote recorders (possibly empty)
     recorders: HashSet<AgentId>,
 
-    /// Map of recorders' ready states
-    recorders_ready: HashMap<AgentId, bool>,
+    /// Senders to the recorders running in the local process (possibly empty)
+    local_recorders: HashMap<AgentId, IntraProcSender<Signal>>,
+
+    /// Ids of all recorders, remote and local, updated when a recorder is disconnected
+    all_recorders: HashSet<AgentId>,
 
     /// List of all expected secondary agents
     secondary_agents: Vec<AgentId>,
@@ -357,17 +1281,40 @@ struct ActivityConnector {
 
     /// Helper for handling signals from the secondary agents
     ipc_receiver: Option<IpcSignalReceiver>,
+
+    /// Tunables of the signalling with secondary agents and recorders
+    signalling: SignallingConfig,
+
+    /// Hash of the configured topology, secondary agents announcing a different one are rejected
+    topology: TopologyHash,
+
+    /// Whether to accept secondary agents reconnecting while running
+    accept_reconnects: bool,
+
+    /// Receiver of the trigger streams of reconnected and synchronized secondary agents
+    reconnected: Option<mpsc::Receiver<(AgentId, TcpStream)>>,
+
+    /// Secondary agents which could not be sent to anymore and have not yet reconnected
+    disconnected: HashSet<AgentId>,
+
+    /// Waker stopping the thread accepting reconnects and its handle, joined on drop
+    reconnect_thread: Option<(Arc<Waker>, thread::JoinHandle<()>)>,
 }
 
 impl ActivityConnector {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         agent_map: &HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>,
         recorders: HashSet<AgentId>,
+        local_recorders: HashMap<AgentId, IntraProcSender<Signal>>,
         local_agent_id: AgentId,
-        local_socket_addr: SocketAddr,
+        local_socket_addrs: Vec<SocketAddr>,
         intra_ready_sender: IntraProcSender<Signal>,
         intra_ready_receiver: IntraProcReceiver<Signal>,
         local_workpool: Option<WorkerPool>,
+        signalling: SignallingConfig,
+        topology: TopologyHash,
+        accept_reconnects: bool,
     ) -> Self {
         // Create map from ActivityId to corresponding AgentId and WorkerId
         let mut activity_map: HashMap<ActivityId, (AgentId, WorkerId)> = Default::default();
@@ -390,41 +1337,48 @@ impl ActivityConnector {
             .filter(|x| *x != local_agent_id)
             .collect();
 
-        // Pre-allocate recorder ready state map
-        let recorders_ready: HashMap<AgentId, bool> =
-            recorders.iter().map(|id| (*id, false)).collect();
+        let all_recorders = recorders
+            .iter()
+            .chain(local_recorders.keys())
+            .copied()
+            .collect();
 
         Self {
             local_agent_id,
-            local_addr: local_socket_addr,
+            local_addrs: local_socket_addrs,
             activity_map,
             recorders,
-            recorders_ready,
+            local_recorders,
+            all_recorders,
             secondary_agents,
             intra_ready_sender,
             intra_ready_receiver,
             local_workpool,
             ipc_sender: None,
             ipc_receiver: None,
+            signalling,
+            topology,
+            accept_reconnects,
+            reconnected: None,
+            disconnected: HashSet::new(),
+            reconnect_thread: None,
         }
     }
 
     /// Wait for connection from expected secondary agents and recorders
-    pub fn connect_remotes(&mut self) {
-        let mut listener = mio::net::TcpListener::bind(self.local_addr)
-            .unwrap_or_else(|e| panic!("failed to bind local socket: {e:?}"));
-        let mut listen_events = Events::with_capacity(1024);
-        let mut listen_poll =
-            Poll::new().unwrap_or_else(|e| panic!("failed to create poll instance: {e:?}"));
-
-        let mut connection_events = Events::with_capacity(1024);
-        let mut connection_poll =
-            Poll::new().unwrap_or_else(|e| panic!("failed to create poll instance: {e:?}"));
-
-        listen_poll
-            .registry()
-            .register(&mut listener, Token(0), Interest::READABLE)
-            .unwrap_or_else(|e| panic!("failed to register listener for polling: {e:?}"));
+    ///
+    /// Listens on all local addresses, each agent may connect to any of them. If reconnects are
+    /// accepted, the listeners are handed to a thread accepting reconnecting secondary agents
+    /// afterwards, see [`accept_reconnects`].
+    pub fn connect_remotes(&mut self) -> Result<(), Error> {
+        let expected = self
+            .secondary_agents
+            .iter()
+            .chain(self.recorders.iter())
+            .copied()
+            .collect();
+        let mut acceptor =
+            HelloAcceptor::new(&self.local_addrs, expected, self.signalling, self.topology)?;
 
         let mut streams_trigger: HashMap<AgentId, TcpStream> = Default::default();
         let mut streams_ready: HashMap<AgentId, TcpStream> = Default::default();
@@ -453,116 +1407,44 @@ impl ActivityConnector {
 
             // Wait for the next incoming connection with hello message and handle it,
             // i.e. determine the type of message and put the stream into the corresponding collection
-            self.wait_and_handle_hello(
-                &mut listen_poll,
-                &mut listen_events,
-                &listener,
-                &mut connection_poll,
-                &mut connection_events,
-                &mut streams_trigger,
-                &mut streams_ready,
-            )
+            acceptor.wait_and_handle_hello(&mut streams_trigger, &mut streams_ready)?;
         }
 
         // Start ready signal handler
         self.ipc_receiver = Some(IpcSignalReceiver::new(
             streams_ready,
             self.intra_ready_sender.clone(),
+            self.signalling,
         ));
         self.ipc_receiver.as_mut().unwrap().run();
 
         // Create sender to remote agents (secondaries and recorders)
         let streams_send: HashMap<AgentId, TcpStream> = streams_trigger.into_iter().collect();
         self.ipc_sender = Some(MioMultiSocketSender::new(streams_send));
-    }
 
-    /// Helper method: Wait for the next hello message from another agent
-    #[allow(clippy::too_many_arguments)]
-    fn wait_and_handle_hello(
-        &mut self,
-        listen_poll: &mut Poll,
-        listen_events: &mut Events,
-        listener: &TcpListener,
-        connection_poll: &mut Poll,
-        connection_events: &mut Events,
-        streams_trigger: &mut HashMap<AgentId, TcpStream>,
-        streams_ready: &mut HashMap<AgentId, TcpStream>,
-    ) {
-        listen_poll
-            .poll(listen_events, None)
-            .unwrap_or_else(|e| panic!("polling failed: {e:?}"));
-
-        for event in listen_events.iter() {
-            if event.token() == Token(0) {
-                debug!("Received listener event");
-                let (mut stream, addr) = listener
-                    .accept()
-                    .unwrap_or_else(|e| panic!("listener accept failed: {e:?}"));
-                stream
-                    .set_nodelay(true)
-                    .unwrap_or_else(|e| panic!("setting nodelay for stream failed: {e:?}"));
-
-                info!("Incoming connection from {addr}");
-                let mut conn =
-                    MioSocketReceiver::new(&mut stream, connection_poll, connection_events);
-                conn.register(0)
-                    .unwrap_or_else(|e| panic!("registering connection failed {e:?}"));
-                let pdu = conn
-                    .recv()
-                    .unwrap_or_else(|e| panic!("reception of pdu failed {e:?}"));
-                drop(conn);
-
-                let signal = Signal::try_from(&pdu);
-
-                // If a valid signal has been received, check if and which hello message it is,
-                // then move the stream into the corresponding collection or drop it
-                if let Ok(signal) = signal {
-                    self.handle_hello(signal, stream, streams_trigger, streams_ready)
-                } else {
-                    warn!("Dropping stream with invalid signal");
-                }
-            }
-        }
-    }
-
-    /// Handle the given signal received on the given stream as a hello message from an agent  
-    fn handle_hello(
-        &mut self,
-        signal: Signal,
-        stream: TcpStream,
-        streams_trigger: &mut HashMap<AgentId, TcpStream>,
-        streams_ready: &mut HashMap<AgentId, TcpStream>,
-    ) {
-        if let Signal::HelloTrigger(id) = signal {
-            debug!("Received 'hello_trigger' from {id}");
-            if self.secondary_agents.contains(&id) || self.recorders.contains(&id) {
-                if let Entry::Vacant(e) = streams_trigger.entry(id) {
-                    e.insert(stream);
-                    info!("Received 'hello_trigger' from expected id {id}");
-                } else {
-                    warn!("Ignoring new 'hello_trigger' from already encountered id {id}")
-                }
-            } else {
-                warn!("Ignoring 'hello_trigger' from unexpected id {id}")
-            }
-        } else if let Signal::HelloReady(id) = signal {
-            debug!("Received 'hello_ready' from {id}");
-            if self.secondary_agents.contains(&id) || self.recorders.contains(&id) {
-                if let Entry::Vacant(e) = streams_ready.entry(id) {
-                    e.insert(stream);
-                    info!("Received 'hello_ready' from expected id {id}");
-                } else {
-                    warn!("Ignoring new 'hello_ready' from already encountered id {id}")
-                }
-            } else {
-                warn!("Ignoring 'hello_ready' from unexpected id {id}")
-            }
-        } else {
-            warn!("Dropping stream with signal {signal}");
+        // Keep accepting secondary agents which do not host a recorder, the recording of a
+        // restarted recorder cannot be resumed
+        if self.accept_reconnects {
+            acceptor.expected = self
+                .secondary_agents
+                .iter()
+                .filter(|id| !self.recorders.contains(id))
+                .copied()
+                .collect();
+            acceptor.replace = true;
+            let waker = acceptor.waker.clone();
+            let intra_ready_sender = self.intra_ready_sender.clone();
+            let (reconnected_sender, reconnected) = mpsc::channel();
+            let thread = thread::spawn(move || {
+                accept_reconnects(acceptor, intra_ready_sender, reconnected_sender)
+            });
+            self.reconnected = Some(reconnected);
+            self.reconnect_thread = Some((waker, thread));
         }
+        Ok(())
     }
 
-    pub fn sync_time(&mut self) {
+    pub fn sync_time(&mut self) -> Result<(), Error> {
         let ipc_sender = self
             .ipc_sender
             .as_mut()
@@ -571,95 +1453,170 @@ impl ActivityConnector {
         // Send startup time to all secondary agents
         let signal = Signal::StartupSync(timestamp::sync_info());
         for agent_id in self.secondary_agents.iter() {
-            ipc_sender.send((*agent_id, signal)).unwrap_or_else(|e| {
-                panic!("failed to send signal {signal} to agent {agent_id}: {e:?}")
-            });
+            Self::send_to_agent(signal, *agent_id, ipc_sender)?;
         }
 
-        // Send startup time to all recoders
+        // Send startup time to all recoders, except those hosted by a secondary agent
         let signal = Signal::StartupSync(timestamp::sync_info());
         for agent_id in self.recorders.iter() {
-            ipc_sender.send((*agent_id, signal)).unwrap_or_else(|e| {
-                panic!("failed to send signal {signal} to agent {agent_id}: {e:?}")
-            });
+            if self.secondary_agents.contains(agent_id) {
+                continue;
+            }
+            Self::send_to_agent(signal, *agent_id, ipc_sender)?;
         }
-    }
 
-    /// Wait until the next Ready signal has been received and return the wrapped activity id
-    pub fn wait_next_ready(&mut self) -> Result<ActivityId, Error> {
-        // get the sender for distributing signals to the recorders
-        let ipc_sender = self
-            .ipc_sender
-            .as_mut()
-            .expect("activity connector not connected");
+        // Tell local recorders that the startup time is set, they share it with this agent
+        for (agent_id, sender) in self.local_recorders.iter_mut() {
+            sender.send(signal).inspect_err(|e| {
+                error!("Failed to send signal {signal} to local recorder {agent_id}: {e}");
+            })?;
+        }
 
-        // Wait for next intra-process ready signal from one of the workers
-        // and return the corresponding activity ID
-        loop {
-            let signal: Signal = self.intra_ready_receiver.recv()?;
-            if let Signal::Ready((id, _)) = signal {
-                // Forward the signal to the recorders
-                Self::record_signal(signal, &self.recorders, ipc_sender);
-                return Ok(id);
+        // Answer the synchronization requests of all secondary agents
+        let mut rounds_left: HashMap<AgentId, usize> = self
+            .secondary_agents
+            .iter()
+            .map(|id| (*id, timestamp::SYNC_ROUNDS))
+            .collect();
+        while rounds_left.values().any(|n| *n > 0) {
+            let signal = self.intra_ready_receiver.recv()?;
+            let Signal::SyncRequest((agent_id, _)) = signal else {
+                error!("Received unexpected signal {signal} while waiting for sync request");
+                continue;
+            };
+            let t2 = timestamp::elapsed_since_startup();
+            match rounds_left.get_mut(&agent_id) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => {
+                    error!("Received unexpected sync request from agent {agent_id}");
+                    continue;
+                }
             }
-            error!("Received unexpected signal {signal:?} while waiting for ready signal");
+            let t3 = timestamp::elapsed_since_startup();
+            let response = Signal::SyncResponse((Timestamp(t2), Timestamp(t3)));
+            Self::send_to_agent(response, agent_id, ipc_sender)?;
         }
+        Ok(())
     }
 
-    /// Wait until all the connected recorders have signalled ready
-    pub fn wait_recorders_ready(&mut self) {
-        // If there are no connected recorders, return immediately
-        if self.recorders.is_empty() {
-            return;
-        }
-
-        // Clear all ready flags
-        self.recorders_ready.values_mut().for_each(|v| {
-            *v = false;
-        });
-
-        // Loop until all recorders have signalled RecorderReady
+    /// Wait until the next Ready signal of an activity or RecorderReady signal of a recorder
+    /// has been received and return it
+    ///
+    /// Fails with [`Error::Timeout`] if no such signal has been received until the given deadline.
+    pub fn wait_next_signal(&mut self, deadline: Option<Instant>) -> Result<Signal, Error> {
         loop {
-            // Wait for the next signal (from any agent, but only recorders are expected to send)
-            let signal = match self.intra_ready_receiver.recv() {
-                Ok(signal) => signal,
-                Err(_) => {
-                    error!("failed to receive signal, skipping recorder flush");
-                    return;
-                }
+            let signal: Signal = match deadline {
+                Some(deadline) => agent::recv_until(&mut self.intra_ready_receiver, deadline)?,
+                None => self.intra_ready_receiver.recv()?,
             };
-            if let Signal::RecorderReady((id, _)) = signal {
-                if self.recorders.contains(&id) {
-                    // signal received, set ready entry of the corresponding recorder
-                    let entry = self.recorders_ready.get_mut(&id).unwrap();
-                    *entry = true;
-
-                    // return, if all flags are set
-                    if self.recorders_ready.values().all(|v| *v) {
-                        return;
-                    }
-                } else {
+            match signal {
+                Signal::Ready(_) => {
+                    // Forward the signal to the recorders
+                    let ipc_sender = self
+                        .ipc_sender
+                        .as_mut()
+                        .expect("activity connector not connected");
+                    Self::record_signal(
+                        signal,
+                        &self.recorders,
+                        &mut self.local_recorders,
+                        ipc_sender,
+                    );
+                    return Ok(signal);
+                }
+                Signal::RecorderReady((id, _))
+                    if self.recorders.contains(&id) || self.local_recorders.contains_key(&id) =>
+                {
+                    return Ok(signal)
+                }
+                Signal::RecorderReady((id, _)) => {
                     error!("Received unexpected id {id} in recorder ready signal");
                 }
-            } else {
-                error!(
-                    "Received unexpected signal {signal} while waiting for recorder ready signal"
-                );
+                Signal::ActivityStalled((id, _)) => {
+                    error!("Activity {id} reported stalled by the watchdog of its agent");
+                    let ipc_sender = self
+                        .ipc_sender
+                        .as_mut()
+                        .expect("activity connector not connected");
+                    Self::record_signal(
+                        signal,
+                        &self.recorders,
+                        &mut self.local_recorders,
+                        ipc_sender,
+                    );
+                }
+                _ => error!("Received unexpected signal {signal:?} while waiting for ready signal"),
             }
         }
     }
 
+    /// Add the given activity on a new worker with the given id to the local worker pool
+    ///
+    /// Returns whether the activity has been added, failures are logged.
+    pub fn add_local_activity(
+        &mut self,
+        worker_id: WorkerId,
+        activity_id: ActivityId,
+        builder: Box<dyn ActivityBuilder>,
+    ) -> bool {
+        let Some(worker_pool) = self.local_workpool.as_mut() else {
+            error!("Cannot add activity {activity_id}: no local worker pool");
+            return false;
+        };
+        if worker_pool.has_worker(worker_id) {
+            error!("Cannot add activity {activity_id}: worker {worker_id} already exists");
+            return false;
+        }
+        worker_pool.add_activity(worker_id, activity_id, builder);
+        self.activity_map
+            .insert(activity_id, (self.local_agent_id, worker_id));
+        true
+    }
+
+    /// Remove the given activity from the local worker pool
+    ///
+    /// The activity cannot be triggered anymore, its worker exits once its current operation
+    /// returns.
+    pub fn remove_local_activity(&mut self, activity_id: ActivityId) {
+        if let Some(worker_pool) = self.local_workpool.as_mut() {
+            worker_pool.remove_activity(activity_id);
+        }
+        self.activity_map.remove(&activity_id);
+    }
+
+    /// Get the set of all recorders, remote and local
+    pub fn all_recorders(&self) -> &HashSet<AgentId> {
+        &self.all_recorders
+    }
+
+    /// Stop recording on the given recorder, e.g. because it did not signal ready in time
+    ///
+    /// The connection to a remote recorder is closed, unless a secondary agent hosting the
+    /// recorder shares it. The channel to a local recorder is closed.
+    pub fn disconnect_recorder(&mut self, agent_id: AgentId) {
+        self.all_recorders.remove(&agent_id);
+        if self.local_recorders.remove(&agent_id).is_some() {
+            return;
+        }
+        if !self.recorders.remove(&agent_id) || self.secondary_agents.contains(&agent_id) {
+            return;
+        }
+        if let Some(ipc_sender) = self.ipc_sender.as_mut() {
+            ipc_sender.remove(agent_id);
+        }
+    }
+
     /// Send the given signal to the corresponding activity.
     ///
     /// The activity may be on a remote process or in the local worker pool
-    fn trigger_activity(&mut self, signal: Signal) {
+    fn trigger_activity(&mut self, signal: Signal) -> Result<(), Error> {
         let activity_id = signal.activity_id().unwrap_or_else(|| {
             panic!("an activity cannot be triggered by the given signal {signal}")
         });
-        let (agent_id, worker_id) = self
-            .activity_map
-            .get(&activity_id)
-            .unwrap_or_else(|| panic!("missing agent entry for target activity {activity_id}"));
+        let Some((agent_id, worker_id)) = self.activity_map.get(&activity_id) else {
+            error!("Missing agent entry for target activity {activity_id}");
+            return Err(Error::Trigger(activity_id));
+        };
 
         trace!("Sending {signal} to worker {worker_id} at agent {agent_id}");
 
@@ -676,35 +1633,179 @@ impl ActivityConnector {
                 .local_workpool
                 .as_mut()
                 .expect("local worker pool is missing");
-            worker_pool.trigger(signal);
-        } else {
-            ipc_sender.send((*agent_id, signal)).unwrap_or_else(|e| {
-                panic!("failed to send signal {signal} to agent {agent_id}: {e:?}")
-            });
+            // A mismatch between the activity map and the pool must not abort the cycle,
+            // the activity never becomes ready and is reported pending by the watchdog
+            if let Err(e) = worker_pool.try_trigger(signal) {
+                error!("Failed to trigger activity {activity_id} in local worker pool: {e}");
+                return Ok(());
+            }
+        } else if self.disconnected.contains(agent_id) {
+            debug!("Not sending {signal} to disconnected agent {agent_id}");
+            return Ok(());
+        } else if let Err(e) = Self::send_to_agent(signal, *agent_id, ipc_sender) {
+            if !self.accept_reconnects {
+                return Err(e);
+            }
+            warn!("Waiting for disconnected agent {agent_id} to reconnect");
+            self.disconnected.insert(*agent_id);
+            return Ok(());
         }
 
-        // Send signal to the recorders
-        Self::record_signal(signal, &self.recorders, ipc_sender);
+        // Send signal to the recorders. A recorder hosted by the target agent shares its
+        // connection and receives the signal sent above.
+        Self::record_signal(
+            signal,
+            self.recorders.iter().filter(|id| *id != agent_id),
+            &mut self.local_recorders,
+            ipc_sender,
+        );
+        Ok(())
     }
 
-    /// Send step signal to the given activity
-    pub fn step_activity(&mut self, id: &ActivityId) {
-        debug!("Triggering step for activity {}", id);
-        self.trigger_activity(Signal::Step((*id, timestamp())));
+    /// Send step signal for the given cycle to the given activity
+    pub fn step_activity(&mut self, id: &ActivityId, cycle_id: CycleId) -> Result<(), Error> {
+        debug!("Triggering step for activity {} in cycle {}", id, cycle_id);
+        self.trigger_activity(Signal::Step((*id, timestamp(), cycle_id)))
     }
 
     /// Send startup signal to the given activity
-    pub fn startup_activity(&mut self, id: &ActivityId) {
+    pub fn startup_activity(&mut self, id: &ActivityId) -> Result<(), Error> {
         debug!("Triggering Startup for activity {}", id);
-        self.trigger_activity(Signal::Startup((*id, timestamp())));
+        self.trigger_activity(Signal::Startup((*id, timestamp())))
     }
 
     /// Send shutdown signal to the given activity
-    #[allow(dead_code)]
-    pub fn shutdown_activity(&mut self, id: &ActivityId) {
-        // TODO: System Shutdown not yet specified => this method never gets called
+    pub fn shutdown_activity(&mut self, id: &ActivityId) -> Result<(), Error> {
         debug!("Triggering Shutdown for activity {}", id);
-        self.trigger_activity(Signal::Shutdown((*id, timestamp())));
+        self.trigger_activity(Signal::Shutdown((*id, timestamp())))
+    }
+
+    /// Hand the streams of the secondary agents reconnected since the last call to the sender
+    /// and return the ids of these agents
+    pub fn take_reconnected(&mut self) -> Vec<AgentId> {
+        let Some(reconnected) = self.reconnected.as_ref() else {
+            return Vec::new();
+        };
+        let ipc_sender = self
+            .ipc_sender
+            .as_mut()
+            .expect("activity connector not connected");
+        reconnected
+            .try_iter()
+            .map(|(agent_id, stream)| {
+                ipc_sender.replace(agent_id, stream);
+                self.disconnected.remove(&agent_id);
+                info!("Agent {agent_id} reconnected");
+                agent_id
+            })
+            .collect()
+    }
+
+    /// Get the sorted ids of the activities of the given agent
+    pub fn activities_of(&self, agent_id: AgentId) -> Vec<ActivityId> {
+        let mut activities: Vec<ActivityId> = self
+            .activity_map
+            .iter()
+            .filter(|(_, (id, _))| *id == agent_id)
+            .map(|(activity_id, _)| *activity_id)
+            .collect();
+        activities.sort();
+        activities
+    }
+
+    /// Check if the agent of the given activity has gone away and not yet reconnected
+    pub fn is_disconnected(&self, activity_id: &ActivityId) -> bool {
+        self.activity_map
+            .get(activity_id)
+            .is_some_and(|(agent_id, _)| self.disconnected.contains(agent_id))
+    }
+
+    /// Tell the given reconnected secondary agent that the system is ready, once its
+    /// activities have completed their startup again
+    pub fn send_system_ready(&mut self, agent_id: AgentId) {
+        let ipc_sender = self
+            .ipc_sender
+            .as_mut()
+            .expect("activity connector not connected");
+        let signal = Signal::SystemReady(timestamp());
+        ipc_sender.send((agent_id, signal)).unwrap_or_else(|e| {
+            error!("Failed to send signal {signal} to agent {agent_id}: {e:?}")
+        });
+    }
+
+    /// Broadcast that the system is ready to all secondary agents and recorders, remote and
+    /// local, once all activities have completed their startup
+    pub fn broadcast_system_ready(&mut self) {
+        self.broadcast(Signal::SystemReady(timestamp()));
+        info!("System ready");
+    }
+
+    /// Broadcast a system shutdown to all secondary agents and recorders, remote and local,
+    /// and wait until each recorder has signalled ready after closing its recording
+    ///
+    /// Recorders not signalling ready within the given timeout are logged and not waited for.
+    pub fn shutdown_remotes(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.broadcast(Signal::SystemShutdown(timestamp()));
+
+        let deadline = Instant::now() + timeout;
+        let mut pending = self.all_recorders.clone();
+        while !pending.is_empty() {
+            match agent::recv_until(&mut self.intra_ready_receiver, deadline) {
+                Ok(Signal::RecorderReady((id, _))) if pending.remove(&id) => {
+                    debug!("Recorder {id} shut down");
+                }
+                Ok(signal) => {
+                    error!("Received unexpected signal {signal} while shutting down recorders")
+                }
+                Err(Error::Timeout(_)) => {
+                    let mut pending: Vec<usize> = pending.iter().map(|id| id.0).collect();
+                    pending.sort();
+                    error!("Recorders {pending:?} did not shut down within {timeout:?}");
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        info!("Shut down all secondary agents and recorders");
+        Ok(())
+    }
+
+    /// Get the ids of all remote processes
+    ///
+    /// A secondary agent hosting a recorder shares a single connection for both roles and is
+    /// listed once.
+    fn remotes(&self) -> Vec<AgentId> {
+        let recorders = self
+            .recorders
+            .iter()
+            .filter(|id| !self.secondary_agents.contains(id));
+        self.secondary_agents
+            .iter()
+            .chain(recorders)
+            .copied()
+            .collect()
+    }
+
+    /// Send the given signal to all secondary agents and recorders, remote and local,
+    /// logging failures
+    fn broadcast(&mut self, signal: Signal) {
+        let remotes = self.remotes();
+        let ipc_sender = self
+            .ipc_sender
+            .as_mut()
+            .expect("activity connector not connected");
+        for agent_id in remotes {
+            debug!("Sending {signal} to agent {agent_id}");
+            ipc_sender.send((agent_id, signal)).unwrap_or_else(|e| {
+                error!("Failed to send signal {signal} to agent {agent_id}: {e:?}")
+            });
+        }
+        for (agent_id, sender) in self.local_recorders.iter_mut() {
+            debug!("Sending {signal} to local recorder {agent_id}");
+            sender.send(signal).unwrap_or_else(|e| {
+                error!("Failed to send signal {signal} to local recorder {agent_id}: {e:?}")
+            });
+        }
     }
 
     pub fn record_task_chain_start(&mut self) {
@@ -715,7 +1816,12 @@ impl ActivityConnector {
             .as_mut()
             .expect("activity connector not connected");
         let signal = Signal::TaskChainStart(timestamp());
-        Self::record_signal(signal, &self.recorders, ipc_sender);
+        Self::record_signal(
+            signal,
+            &self.recorders,
+            &mut self.local_recorders,
+            ipc_sender,
+        );
     }
 
     pub fn record_task_chain_end(&mut self) {
@@ -726,12 +1832,32 @@ impl ActivityConnector {
             .as_mut()
             .expect("activity connector not connected");
         let signal = Signal::TaskChainEnd(timestamp());
-        Self::record_signal(signal, &self.recorders, ipc_sender);
+        Self::record_signal(
+            signal,
+            &self.recorders,
+            &mut self.local_recorders,
+            ipc_sender,
+        );
+    }
+
+    /// Send the given signal to the given remote agent, logging failures
+    fn send_to_agent(
+        signal: Signal,
+        agent_id: AgentId,
+        sender: &mut MioMultiSocketSender,
+    ) -> Result<(), Error> {
+        sender.send((agent_id, signal)).inspect_err(|e| {
+            error!("Failed to send signal {signal} to agent {agent_id}: {e}");
+        })
     }
 
-    /// Transmit the given signal for recording to the given recorders
-    fn record_signal<'s, R>(signal: Signal, recorders: R, sender: &mut MioMultiSocketSender)
-    where
+    /// Transmit the given signal for recording to the given remote and local recorders
+    fn record_signal<'s, R>(
+        signal: Signal,
+        recorders: R,
+        local_recorders: &mut HashMap<AgentId, IntraProcSender<Signal>>,
+        sender: &mut MioMultiSocketSender,
+    ) where
         R: IntoIterator<Item = &'s AgentId>,
     {
         for agent_id in recorders.into_iter() {
@@ -741,9 +1867,1590 @@ impl ActivityConnector {
                 error!("Failed to send signal {signal} to recorder {agent_id}: {e:?}. Disconnecting recorder")
             });
         }
+        for (agent_id, local_sender) in local_recorders.iter_mut() {
+            trace!("Sending {signal} to local recorder {agent_id}");
+            local_sender.send(signal).unwrap_or_else(|e| {
+                error!("Failed to send signal {signal} to local recorder {agent_id}: {e:?}")
+            });
+        }
+    }
+}
+
+impl Drop for ActivityConnector {
+    fn drop(&mut self) {
+        // Stop the thread accepting reconnects, it must not outlive the connector
+        if let Some((waker, thread)) = self.reconnect_thread.take() {
+            if let Err(e) = waker.wake() {
+                error!("Failed to stop accepting reconnects: {e}");
+                return;
+            }
+            if thread.join().is_err() {
+                error!("Thread accepting reconnects panicked");
+            }
+        }
+    }
+}
+
+/// Token of the waker of a [`HelloAcceptor`], the listeners are registered by their index,
+/// the accepted connections after them
+const WAKE_TOKEN: Token = Token(usize::MAX);
+
+/// Maximum time for an accepted connection to send its hello and, with a pre-shared key,
+/// answer the challenge
+const HELLO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connection accepted by a [`HelloAcceptor`] which has not completed its hello yet
+struct Handshake {
+    stream: TcpStream,
+    addr: SocketAddr,
+    /// PDU received so far
+    pdu: PartialPdu,
+    /// Hello received and challenge sent in return, waiting for the answer
+    #[cfg(feature = "signalling_auth")]
+    challenged: Option<(SignalPdu, [u8; CHALLENGE_SIZE])>,
+    deadline: Instant,
+}
+
+impl Handshake {
+    /// Read what the peer has sent so far, returning its hello once complete
+    ///
+    /// With a pre-shared key, the hello is complete once the peer has answered the challenge
+    /// sent in return.
+    fn advance(&mut self, signalling: &SignallingConfig) -> Result<Option<SignalPdu>, Error> {
+        #[cfg(not(feature = "signalling_auth"))]
+        let _ = signalling;
+        let Some(pdu) = self.pdu.read(&mut self.stream)? else {
+            return Ok(None);
+        };
+        #[cfg(feature = "signalling_auth")]
+        if let Some(key) = &signalling.psk {
+            // The answer arrives on a new readiness event, after the peer got the challenge
+            let Some((hello, challenge)) = self.challenged.take() else {
+                let challenge = SignalPdu::send_challenge(&mut self.stream)?;
+                self.challenged = Some((pdu, challenge));
+                return Ok(None);
+            };
+            pdu.verify_answer(key, &challenge, &hello)?;
+            return Ok(Some(hello));
+        }
+        Ok(Some(pdu))
+    }
+}
+
+/// Listeners accepting the connections of remote agents and recorders and reading their
+/// hello messages
+///
+/// The hellos of all accepted connections are read on a single poll without blocking, so a
+/// slow or malicious peer delays no other one.
+struct HelloAcceptor {
+    listeners: Vec<TcpListener>,
+    poll: Poll,
+    events: Events,
+
+    /// Accepted connections by token
+    handshakes: HashMap<Token, Handshake>,
+
+    /// Number of connections accepted so far, numbering their tokens
+    accepted: usize,
+
+    /// Waker interrupting the wait for connections, after which the acceptor is stopped
+    waker: Arc<Waker>,
+
+    /// Whether the acceptor has been stopped by its waker
+    stopped: bool,
+
+    /// Ids of the agents and recorders allowed to connect
+    expected: Vec<AgentId>,
+
+    /// Whether a new hello message replaces the stream of an earlier one from the same id,
+    /// instead of being ignored
+    replace: bool,
+
+    /// Tunables of the signalling with secondary agents and recorders
+    signalling: SignallingConfig,
+
+    /// Hash of the configured topology, agents announcing a different one are rejected
+    topology: TopologyHash,
+}
+
+impl HelloAcceptor {
+    /// Listen on all given addresses
+    fn new(
+        addrs: &[SocketAddr],
+        expected: Vec<AgentId>,
+        signalling: SignallingConfig,
+        topology: TopologyHash,
+    ) -> Result<Self, Error> {
+        let mut listeners: Vec<TcpListener> = addrs
+            .iter()
+            .map(|addr| {
+                TcpListener::bind(*addr).map_err(|e| {
+                    error!("Failed to bind local socket {addr}: {e:?}");
+                    Error::Io((e, "failed to bind local socket"))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let poll = Poll::new().map_err(|e| Error::Io((e, "failed to create poll instance")))?;
+
+        for (index, listener) in listeners.iter_mut().enumerate() {
+            poll.registry()
+                .register(listener, Token(index), Interest::READABLE)
+                .map_err(|e| Error::Io((e, "failed to register listener for polling")))?;
+        }
+        let waker = Waker::new(poll.registry(), WAKE_TOKEN)
+            .map_err(|e| Error::Io((e, "failed to create waker")))?;
+
+        Ok(Self {
+            listeners,
+            poll,
+            events: signalling.events(),
+            handshakes: HashMap::new(),
+            accepted: 0,
+            waker: Arc::new(waker),
+            stopped: false,
+            expected,
+            replace: false,
+            signalling,
+            topology,
+        })
+    }
+
+    /// Wait for the next hello messages from other agents
+    ///
+    /// Returns without handling any connection once the acceptor has been stopped by its waker.
+    fn wait_and_handle_hello(
+        &mut self,
+        streams_trigger: &mut HashMap<AgentId, TcpStream>,
+        streams_ready: &mut HashMap<AgentId, TcpStream>,
+    ) -> Result<(), Error> {
+        let timeout = self
+            .handshakes
+            .values()
+            .map(|handshake| handshake.deadline)
+            .min()
+            .map(agent::timeout_until);
+        self.poll
+            .poll(&mut self.events, timeout)
+            .map_err(|e| Error::Io((e, "polling failed")))?;
+
+        let tokens: Vec<Token> = self.events.iter().map(|event| event.token()).collect();
+        for token in tokens {
+            if token == WAKE_TOKEN {
+                self.stopped = true;
+                return Ok(());
+            }
+            if token.0 < self.listeners.len() {
+                debug!("Received listener event");
+                self.accept(token.0)?;
+            } else {
+                self.advance(token, streams_trigger, streams_ready);
+            }
+        }
+
+        let now = Instant::now();
+        self.handshakes.retain(|_, handshake| {
+            let pending = handshake.deadline > now;
+            if !pending {
+                warn!(
+                    "Dropping stream from {} failing to send hello within {HELLO_TIMEOUT:?}",
+                    handshake.addr
+                );
+            }
+            pending
+        });
+        Ok(())
+    }
+
+    /// Accept all pending connections on the listener with the given index
+    fn accept(&mut self, index: usize) -> Result<(), Error> {
+        // The event is not repeated for connections already pending
+        loop {
+            let (mut stream, addr) = match self.listeners[index].accept() {
+                Ok(connection) => connection,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(Error::Io((e, "listener accept failed"))),
+            };
+            stream
+                .set_nodelay(true)
+                .map_err(|e| Error::Io((e, "setting nodelay for stream failed")))?;
+
+            info!("Incoming connection from {addr}");
+            let token = Token(self.listeners.len() + self.accepted);
+            self.accepted += 1;
+            self.poll
+                .registry()
+                .register(&mut stream, token, Interest::READABLE)
+                .map_err(|e| Error::Io((e, "registering connection failed")))?;
+            let handshake = Handshake {
+                stream,
+                addr,
+                pdu: PartialPdu::default(),
+                #[cfg(feature = "signalling_auth")]
+                challenged: None,
+                deadline: Instant::now() + HELLO_TIMEOUT,
+            };
+            self.handshakes.insert(token, handshake);
+        }
+    }
+
+    /// Advance the handshake of the connection with the given token, handling its hello once
+    /// complete
+    fn advance(
+        &mut self,
+        token: Token,
+        streams_trigger: &mut HashMap<AgentId, TcpStream>,
+        streams_ready: &mut HashMap<AgentId, TcpStream>,
+    ) {
+        let Some(handshake) = self.handshakes.get_mut(&token) else {
+            return;
+        };
+        let result = handshake.advance(&self.signalling);
+        if let Ok(None) = result {
+            return;
+        }
+
+        let mut handshake = self.handshakes.remove(&token).unwrap();
+        _ = self.poll.registry().deregister(&mut handshake.stream); // errors ignored
+        let pdu = match result {
+            Ok(pdu) => pdu.unwrap(),
+            Err(e) => {
+                warn!("Dropping stream from {} failing hello: {e}", handshake.addr);
+                return;
+            }
+        };
+
+        // If a valid signal has been received, check if and which hello message it is,
+        // then move the stream into the corresponding collection or drop it
+        if let Ok(signal) = Signal::try_from(&pdu) {
+            Self::handle_hello(
+                &self.expected,
+                self.replace,
+                self.topology,
+                signal,
+                handshake.stream,
+                streams_trigger,
+                streams_ready,
+            )
+        } else {
+            warn!("Dropping stream with invalid signal");
+        }
+    }
+
+    /// Handle the given signal received on the given stream as a hello message from an agent  
+    fn handle_hello(
+        expected: &[AgentId],
+        replace: bool,
+        own_topology: TopologyHash,
+        signal: Signal,
+        mut stream: TcpStream,
+        streams_trigger: &mut HashMap<AgentId, TcpStream>,
+        streams_ready: &mut HashMap<AgentId, TcpStream>,
+    ) {
+        let rejected = match signal {
+            Signal::HelloTrigger((id, Some(topology)))
+            | Signal::HelloReady((id, Some(topology)))
+                if topology != own_topology =>
+            {
+                Some(id)
+            }
+            _ => None,
+        };
+        if let Some(id) = rejected {
+            warn!(
+                "Rejecting stream with signal {signal}, topology differs from own topology {own_topology}"
+            );
+            Self::reply(&mut stream, id, Signal::HelloRejected(own_topology));
+            return;
+        }
+
+        if let Signal::HelloTrigger((id, _)) = signal {
+            debug!("Received 'hello_trigger' from {id}");
+            if expected.contains(&id) {
+                match streams_trigger.entry(id) {
+                    Entry::Vacant(e) => {
+                        if Self::reply(&mut stream, id, Signal::HelloAccepted(id)) {
+                            e.insert(stream);
+                            info!("Received 'hello_trigger' from expected id {id}");
+                        }
+                    }
+                    Entry::Occupied(mut e) if replace => {
+                        if !Self::reply(&mut stream, id, Signal::HelloAccepted(id)) {
+                            return;
+                        }
+                        e.insert(stream);
+                        info!("Received new 'hello_trigger' from id {id}, replacing its stream");
+                    }
+                    Entry::Occupied(_) => {
+                        warn!("Ignoring new 'hello_trigger' from already encountered id {id}")
+                    }
+                }
+            } else {
+                warn!("Ignoring 'hello_trigger' from unexpected id {id}")
+            }
+        } else if let Signal::HelloReady((id, _)) = signal {
+            debug!("Received 'hello_ready' from {id}");
+            if expected.contains(&id) {
+                match streams_ready.entry(id) {
+                    Entry::Vacant(e) => {
+                        if Self::reply(&mut stream, id, Signal::HelloAccepted(id)) {
+                            e.insert(stream);
+                            info!("Received 'hello_ready' from expected id {id}");
+                        }
+                    }
+                    Entry::Occupied(mut e) if replace => {
+                        if !Self::reply(&mut stream, id, Signal::HelloAccepted(id)) {
+                            return;
+                        }
+                        e.insert(stream);
+                        info!("Received new 'hello_ready' from id {id}, replacing its stream");
+                    }
+                    Entry::Occupied(_) => {
+                        warn!("Ignoring new 'hello_ready' from already encountered id {id}")
+                    }
+                }
+            } else {
+                warn!("Ignoring 'hello_ready' from unexpected id {id}")
+            }
+        } else {
+            warn!("Dropping stream with signal {signal}");
+        }
+    }
+
+    /// Reply to the hello message received on the given stream, logging failures
+    ///
+    /// Returns whether the reply has been sent completely. Otherwise, the stream is to be
+    /// dropped: a partially sent reply would corrupt the next PDU sent on it. The remote agent
+    /// sees the connection closed and connects again.
+    fn reply(stream: &mut TcpStream, agent_id: AgentId, signal: Signal) -> bool {
+        MioSocketSender::to_agent(stream, agent_id)
+            .send(signal)
+            .inspect_err(|e| warn!("Failed to send {signal} in reply to hello: {e}"))
+            .is_ok()
+    }
+}
+
+/// Maximum time for a reconnecting agent to complete its time synchronization
+const RECONNECT_SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Initial and maximum real time to wait after a failure to accept a reconnecting agent
+const ACCEPT_BACKOFF: (std::time::Duration, std::time::Duration) = (
+    std::time::Duration::from_millis(10),
+    std::time::Duration::from_secs(1),
+);
+
+/// Accept secondary agents reconnecting while the primary agent runs
+///
+/// Each agent having sent both hello messages again is synchronized like at startup. Its
+/// ready stream is then forwarded by a receiver thread of its own, its trigger stream is
+/// handed to the activity connector via the given sender. Returns once the connector has
+/// been dropped and stopped the acceptor. Repeated failures to accept are backed off
+/// exponentially.
+fn accept_reconnects(
+    mut acceptor: HelloAcceptor,
+    intra_ready_sender: IntraProcSender<Signal>,
+    reconnected: mpsc::Sender<(AgentId, TcpStream)>,
+) {
+    let signalling = acceptor.signalling;
+    let mut streams_trigger: HashMap<AgentId, TcpStream> = Default::default();
+    let mut streams_ready: HashMap<AgentId, TcpStream> = Default::default();
+    let mut backoff = ACCEPT_BACKOFF.0;
+    loop {
+        let result = acceptor.wait_and_handle_hello(&mut streams_trigger, &mut streams_ready);
+        if acceptor.stopped {
+            return;
+        }
+        if let Err(e) = result {
+            error!("Failed to accept reconnecting agent, retrying in {backoff:?}: {e}");
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(ACCEPT_BACKOFF.1);
+            continue;
+        }
+        backoff = ACCEPT_BACKOFF.0;
+
+        let complete: Vec<AgentId> = streams_trigger
+            .keys()
+            .filter(|id| streams_ready.contains_key(id))
+            .copied()
+            .collect();
+        for agent_id in complete {
+            let mut stream_trigger = streams_trigger.remove(&agent_id).unwrap();
+            let mut stream_ready = streams_ready.remove(&agent_id).unwrap();
+            let synced =
+                sync_reconnected(agent_id, &mut stream_trigger, &mut stream_ready, signalling);
+            if let Err(e) = synced {
+                warn!("Dropping reconnecting agent {agent_id} failing time synchronization: {e}");
+                continue;
+            }
+            info!("Time synchronization of reconnecting agent {agent_id} done");
+
+            let sender = intra_ready_sender.clone();
+            let streams_ready = HashMap::from([(agent_id, stream_ready)]);
+            thread::spawn(move || {
+                IpcSignalReceiver::thread_main(streams_ready, sender, signalling)
+            });
+            if reconnected.send((agent_id, stream_trigger)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Synchronize a reconnecting secondary agent on its streams, see
+/// [`ActivityConnector::sync_time`]
+///
+/// All signals are sent by one sender, completing partially sent signals before sending the
+/// next one. On success, nothing is left pending on the trigger stream. On failure, the streams
+/// are to be dropped.
+fn sync_reconnected(
+    agent_id: AgentId,
+    stream_trigger: &mut TcpStream,
+    stream_ready: &mut TcpStream,
+    signalling: SignallingConfig,
+) -> Result<(), Error> {
+    let mut sender = MioSocketSender::to_agent(stream_trigger, agent_id);
+    sender.send(Signal::StartupSync(timestamp::sync_info()))?;
+
+    let mut poll = Poll::new().map_err(|e| Error::Io((e, "failed to create poll instance")))?;
+    let mut events = signalling.events();
+    let mut receiver = MioSocketReceiver::new(stream_ready, &mut poll, &mut events);
+    receiver
+        .register(0)
+        .map_err(|e| Error::Io((e, "registering connection failed")))?;
+    let deadline = Instant::now() + RECONNECT_SYNC_TIMEOUT;
+    for _ in 0..timestamp::SYNC_ROUNDS {
+        let pdu = agent::recv_until(&mut receiver, deadline)?;
+        let signal = Signal::try_from(&pdu)?;
+        if !matches!(signal, Signal::SyncRequest(_)) {
+            return Err(Error::Protocol(format!(
+                "unexpected signal {signal} while waiting for sync request"
+            )));
+        }
+        let t2 = timestamp::elapsed_since_startup();
+        let t3 = timestamp::elapsed_since_startup();
+        sender.send(Signal::SyncResponse((Timestamp(t2), Timestamp(t3))))?;
     }
+    Ok(())
 }
 
 pub fn run(mut agent: PrimaryAgent) {
     agent.run();
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ActivityConnector, PendingActivities, Phase, Scheduler, TaskChain, TaskChainConfig,
+    };
+    use crate::activity::{Activity, ActivityId, ActivityIdAndBuilder};
+    use crate::agent::secondary::{connect_to_primary, ConnectRetry};
+    use crate::configuration::topology::TopologyHash;
+    use crate::error::Error;
+    use crate::signalling::{
+        channel, AgentId, CycleId, IntraProcReceiver, IntraProcSender, MioSocketReceiver,
+        MioSocketSender, Receiver, Sender, Signal, SignallingConfig,
+    };
+    use crate::span_capture::{SpanCapture, SpanEvent};
+    use crate::timestamp::{self, timestamp};
+    use crate::worker_pool::{WorkerId, WorkerPool};
+    use feo_time::{Duration, Instant};
+    use mio::{Events, Poll};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Activity counting its steps
+    struct Counter(ActivityId, Arc<AtomicUsize>);
+
+    impl Activity for Counter {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    /// Create a task chain configuration
+    fn chain(
+        name: &str,
+        cycle_time: Duration,
+        activity_depends: HashMap<ActivityId, Vec<ActivityId>>,
+    ) -> TaskChainConfig {
+        TaskChainConfig {
+            name: name.into(),
+            cycle_time,
+            watchdog_timeout: None,
+            flush_timeout: None,
+            activity_depends,
+            optional_depends: HashMap::new(),
+            rate_divisors: HashMap::new(),
+            activity_deadlines: HashMap::new(),
+        }
+    }
+
+    /// Create a scheduler without remote agents running the given activities on local workers
+    fn scheduler(
+        chains: Vec<TaskChainConfig>,
+        activities: HashMap<WorkerId, Vec<ActivityIdAndBuilder>>,
+        ready_sender: IntraProcSender<Signal>,
+        ready_receiver: IntraProcReceiver<Signal>,
+    ) -> Scheduler {
+        let agent_id = AgentId::from(0);
+        let workers = activities
+            .iter()
+            .map(|(worker_id, builders)| (*worker_id, builders.iter().map(|(id, _)| *id).collect()))
+            .collect();
+        let agent_map = HashMap::from([(agent_id, workers)]);
+        let pool = (!activities.is_empty())
+            .then(|| WorkerPool::new(activities, &ready_sender, None, Some(agent_id), None));
+        let connector = ActivityConnector::new(
+            &agent_map,
+            Default::default(),
+            Default::default(),
+            agent_id,
+            vec!["127.0.0.1:0".parse().unwrap()],
+            ready_sender,
+            ready_receiver,
+            pool,
+            SignallingConfig::default(),
+            TopologyHash(0),
+            false,
+        );
+        let chains = chains.into_iter().map(TaskChain::new).collect();
+        Scheduler::new(chains, false, connector)
+    }
+
+    /// Add a recorder running in the local process, receiving its signals through `sender`
+    fn add_local_recorder(
+        scheduler: &mut Scheduler,
+        agent_id: AgentId,
+        sender: IntraProcSender<Signal>,
+    ) {
+        let connector = &mut scheduler.activity_connector;
+        connector.local_recorders.insert(agent_id, sender);
+        connector.all_recorders.insert(agent_id);
+    }
+
+    #[test]
+    fn test_pending_activities() {
+        let ids: Vec<ActivityId> = (0..3).map(ActivityId::from).collect();
+        // Chain of three activities, each depending on its predecessor
+        let activity_depends = HashMap::from([
+            (ids[0], vec![]),
+            (ids[1], vec![ids[0]]),
+            (ids[2], vec![ids[1]]),
+        ]);
+        let (sender, receiver) = channel();
+        let mut config = chain("test", Duration::from_millis(10), activity_depends);
+        config.watchdog_timeout = Some(Duration::from_millis(20));
+        let mut scheduler = scheduler(vec![config], HashMap::new(), sender, receiver);
+
+        // The first activity completed, the second one has been triggered but never completes
+        let chain = &mut scheduler.chains[0];
+        for (id, (triggered, ready)) in ids.iter().zip([(true, true), (true, false)]) {
+            let state = chain.activity_states.get_mut(id).unwrap();
+            state.triggered = triggered;
+            state.ready = ready;
+        }
+
+        let deadline = Instant::now() + chain.watchdog_timeout;
+        let result = scheduler.wait_next_signal(Some(deadline));
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(Instant::now() >= deadline);
+
+        let chain = &scheduler.chains[0];
+        assert_eq!(
+            chain.pending_activities(),
+            PendingActivities {
+                triggered: vec![ids[1]],
+                untriggered: vec![ids[2]],
+            }
+        );
+        assert_eq!(
+            chain.pending_activities().to_string(),
+            "triggered but not ready: [A1], not triggered: [A2]"
+        );
+    }
+
+    #[test]
+    fn test_multiple_bind_addrs() {
+        // Bind and drop listeners to get local ports nobody listens on
+        let addrs: Vec<SocketAddr> = ["127.0.0.1:0", "127.0.0.2:0"]
+            .into_iter()
+            .map(|addr| {
+                std::net::TcpListener::bind(addr)
+                    .unwrap()
+                    .local_addr()
+                    .unwrap()
+            })
+            .collect();
+        let local_id = AgentId::from(0);
+        let remote_ids = [AgentId::from(1), AgentId::from(2)];
+        let agent_map = [local_id, remote_ids[0], remote_ids[1]]
+            .into_iter()
+            .map(|id| (id, HashMap::new()))
+            .collect();
+        let (ready_sender, ready_receiver) = channel();
+        let mut connector = ActivityConnector::new(
+            &agent_map,
+            Default::default(),
+            Default::default(),
+            local_id,
+            addrs.clone(),
+            ready_sender,
+            ready_receiver,
+            None,
+            SignallingConfig::default(),
+            TopologyHash(0),
+            false,
+        );
+
+        // Connect one secondary to each address
+        let secondaries: Vec<_> = remote_ids
+            .into_iter()
+            .zip(addrs)
+            .map(|(id, addr)| {
+                thread::spawn(move || {
+                    connect_to_primary(
+                        id,
+                        addr,
+                        &ConnectRetry::default(),
+                        &SignallingConfig::default(),
+                        None,
+                    )
+                    .unwrap()
+                })
+            })
+            .collect();
+        // Returns only once both secondaries said hello
+        connector.connect_remotes().unwrap();
+        let _streams: Vec<_> = secondaries
+            .into_iter()
+            .map(|secondary| secondary.join().unwrap())
+            .collect();
+        assert!(connector.ipc_sender.is_some());
+    }
+
+    #[test]
+    fn test_topology_mismatch_rejected() {
+        let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let local_id = AgentId::from(0);
+        let remote_id = AgentId::from(1);
+        let agent_map = [local_id, remote_id]
+            .into_iter()
+            .map(|id| (id, HashMap::new()))
+            .collect();
+        let topology = TopologyHash(1);
+        let (ready_sender, ready_receiver) = channel();
+        let mut connector = ActivityConnector::new(
+            &agent_map,
+            Default::default(),
+            Default::default(),
+            local_id,
+            vec![addr],
+            ready_sender,
+            ready_receiver,
+            None,
+            SignallingConfig::default(),
+            topology,
+            false,
+        );
+
+        // The secondary first connects with another topology, then with the matching one
+        let secondary = thread::spawn(move || {
+            let connect = |topology| {
+                connect_to_primary(
+                    remote_id,
+                    addr,
+                    &ConnectRetry::default(),
+                    &SignallingConfig::default(),
+                    Some(topology),
+                )
+            };
+            (connect(TopologyHash(2)), connect(topology))
+        });
+        // Returns only once the secondary with the matching topology said hello
+        connector.connect_remotes().unwrap();
+        let (rejected, accepted) = secondary.join().unwrap();
+        assert!(connector.ipc_sender.is_some());
+
+        // The secondary learns that the primary rejected the other topology
+        assert!(matches!(rejected, Err(Error::Config(_))), "{rejected:?}");
+        assert!(accepted.is_ok());
+    }
+
+    #[test]
+    fn test_silent_peer_not_blocking() {
+        let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let local_id = AgentId::from(0);
+        let remote_id = AgentId::from(1);
+        let agent_map = [local_id, remote_id]
+            .into_iter()
+            .map(|id| (id, HashMap::new()))
+            .collect();
+        let (ready_sender, ready_receiver) = channel();
+        let mut connector = ActivityConnector::new(
+            &agent_map,
+            Default::default(),
+            Default::default(),
+            local_id,
+            vec![addr],
+            ready_sender,
+            ready_receiver,
+            None,
+            SignallingConfig::default(),
+            TopologyHash(1),
+            false,
+        );
+
+        // A peer connects first and never says hello, then the secondary connects
+        let secondary = thread::spawn(move || {
+            let silent = loop {
+                match std::net::TcpStream::connect(addr) {
+                    Ok(stream) => break stream,
+                    Err(_) => thread::sleep(std::time::Duration::from_millis(10)),
+                }
+            };
+            let connected = connect_to_primary(
+                remote_id,
+                addr,
+                &ConnectRetry::default(),
+                &SignallingConfig::default(),
+                None,
+            );
+            (silent, connected)
+        });
+        connector.connect_remotes().unwrap();
+        let (_silent, connected) = secondary.join().unwrap();
+        assert!(connected.is_ok());
+    }
+
+    /// Secondary agent with the given id connecting to the primary agent at the given address,
+    /// answering the time synchronization and signalling ready for each trigger
+    ///
+    /// Returns the number of steps received once the system shuts down or, dropping its
+    /// streams like a crashing agent, once it received `max_steps` steps.
+    fn fake_secondary(id: AgentId, addr: SocketAddr, max_steps: usize) -> usize {
+        let (mut stream_trigger, mut stream_ready) = connect_to_primary(
+            id,
+            addr,
+            &ConnectRetry::default(),
+            &SignallingConfig::default(),
+            None,
+        )
+        .unwrap();
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(8);
+        let mut receiver = MioSocketReceiver::new(&mut stream_trigger, &mut poll, &mut events);
+        receiver.register(0).unwrap();
+        let mut sender = MioSocketSender::new(&mut stream_ready);
+        let mut recv = || Signal::try_from(&receiver.recv().unwrap()).unwrap();
+
+        assert!(matches!(recv(), Signal::StartupSync(_)));
+        for _ in 0..timestamp::SYNC_ROUNDS {
+            sender.send(Signal::SyncRequest((id, timestamp()))).unwrap();
+            assert!(matches!(recv(), Signal::SyncResponse(_)));
+        }
+
+        let mut steps = 0;
+        loop {
+            match recv() {
+                Signal::Startup((act_id, _)) | Signal::Shutdown((act_id, _)) => sender
+                    .send(Signal::Ready((act_id, timestamp(), CycleId::default())))
+                    .unwrap(),
+                Signal::Step((act_id, _, cycle_id)) => {
+                    steps += 1;
+                    sender
+                        .send(Signal::Ready((act_id, timestamp(), cycle_id)))
+                        .unwrap();
+                    if steps == max_steps {
+                        return steps;
+                    }
+                }
+                Signal::SystemReady(_) => (),
+                Signal::SystemShutdown(_) => return steps,
+                signal => panic!("unexpected signal {signal}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_secondary_reconnect() {
+        timestamp::initialize_for_test();
+        let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let local_id = AgentId::from(0);
+        let remote_id = AgentId::from(1);
+        let act_id = ActivityId::from(0);
+        let agent_map = HashMap::from([
+            (local_id, HashMap::new()),
+            (
+                remote_id,
+                HashMap::from([(WorkerId::from(1), vec![act_id])]),
+            ),
+        ]);
+        let (ready_sender, ready_receiver) = channel();
+        let connector = ActivityConnector::new(
+            &agent_map,
+            Default::default(),
+            Default::default(),
+            local_id,
+            vec![addr],
+            ready_sender,
+            ready_receiver,
+            None,
+            SignallingConfig::default(),
+            TopologyHash(0),
+            true,
+        );
+        let chains = vec![TaskChain::new(chain(
+            "test",
+            Duration::from_millis(5),
+            HashMap::from([(act_id, vec![])]),
+        ))];
+        let mut scheduler = Scheduler::new(chains, false, connector);
+
+        // The secondary crashes after three steps and is restarted
+        let secondary = thread::spawn(move || {
+            let crashed = fake_secondary(remote_id, addr, 3);
+            let restarted = fake_secondary(remote_id, addr, usize::MAX);
+            (crashed, restarted)
+        });
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.run_cycles(6).unwrap();
+
+        // The restarted secondary is triggered in the remaining cycles, including the one
+        // its predecessor did not complete
+        assert_eq!(secondary.join().unwrap(), (3, 3));
+
+        // Dropping the connector stops accepting reconnects and closes the listener
+        drop(scheduler);
+        mio::net::TcpListener::bind(addr).unwrap();
+    }
+
+    #[test]
+    fn test_stale_ready_ignored() {
+        timestamp::initialize_for_test();
+        let act_id = ActivityId::from(0);
+        let (mut sender, receiver) = channel();
+        let chains = vec![chain(
+            "test",
+            Duration::from_millis(10),
+            HashMap::from([(act_id, vec![])]),
+        )];
+        let mut scheduler = scheduler(chains, HashMap::new(), sender.clone(), receiver);
+        scheduler.connect_remotes().unwrap();
+
+        // The activity has been triggered in the second cycle
+        let chain = &mut scheduler.chains[0];
+        chain.phase = Phase::Running;
+        chain.cycle_id = CycleId(2);
+        chain.activity_states.get_mut(&act_id).unwrap().triggered = true;
+
+        // A late ready signal from the first cycle is ignored
+        sender
+            .send(Signal::Ready((act_id, timestamp(), CycleId(1))))
+            .unwrap();
+        scheduler.wait_next_signal(None).unwrap();
+        assert!(!scheduler.is_ready(&act_id));
+
+        // The ready signal of the current cycle is accepted
+        sender
+            .send(Signal::Ready((act_id, timestamp(), CycleId(2))))
+            .unwrap();
+        scheduler.wait_next_signal(None).unwrap();
+        assert!(scheduler.is_ready(&act_id));
+    }
+
+    #[test]
+    fn test_independent_task_chains() {
+        timestamp::initialize_for_test();
+        let fast = ActivityId::from(0);
+        let slow = ActivityId::from(1);
+        let steps: Vec<Arc<AtomicUsize>> = (0..2).map(|_| Default::default()).collect();
+        // Both activities share a single worker
+        let activities: Vec<ActivityIdAndBuilder> = [fast, slow]
+            .into_iter()
+            .zip(steps.iter().cloned())
+            .map(|(id, count)| {
+                let builder: ActivityIdAndBuilder = (
+                    id,
+                    Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+                );
+                builder
+            })
+            .collect();
+
+        let chains = vec![
+            chain(
+                "fast",
+                Duration::from_millis(10),
+                HashMap::from([(fast, vec![])]),
+            ),
+            chain(
+                "slow",
+                Duration::from_millis(50),
+                HashMap::from([(slow, vec![])]),
+            ),
+        ];
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(chains, activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.startup().unwrap();
+        scheduler
+            .run_until(
+                usize::MAX,
+                Some(Instant::now() + Duration::from_millis(500)),
+            )
+            .unwrap();
+
+        // Each chain keeps its own cadence, ideally 50 and 10 cycles within 500ms. Only lower
+        // bounds are checked, a loaded machine may run fewer cycles.
+        let cycles: Vec<usize> = scheduler.chains.iter().map(|c| c.cycles).collect();
+        assert!(cycles[1] >= 2, "slow chain ran {} cycles", cycles[1]);
+        assert!(
+            cycles[0] >= 2 * cycles[1],
+            "fast chain ran {} cycles, slow chain {}",
+            cycles[0],
+            cycles[1]
+        );
+        for (count, cycles) in steps.iter().zip(cycles) {
+            assert_eq!(count.load(Ordering::Relaxed), cycles);
+        }
+    }
+
+    #[test]
+    fn test_run_receive_error() {
+        timestamp::initialize_for_test();
+        let id = ActivityId::from(0);
+        let count = Arc::new(AtomicUsize::new(0));
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            id,
+            Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+        )];
+        let chains = vec![chain(
+            "test",
+            Duration::from_millis(1),
+            HashMap::from([(id, vec![])]),
+        )];
+
+        // The scheduler receives from a channel without senders, failing on the first receive
+        let (ready_sender, _ready_receiver) = channel();
+        let (_, receiver) = channel::<Signal>();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(chains, activities, ready_sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+
+        assert!(matches!(scheduler.run(), Err(Error::Channel(_))));
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let steps: Vec<Arc<AtomicUsize>> = ids.iter().map(|_| Default::default()).collect();
+        let activities: Vec<ActivityIdAndBuilder> = ids
+            .iter()
+            .zip(steps.iter().cloned())
+            .map(|(id, count)| {
+                let builder: ActivityIdAndBuilder = (
+                    *id,
+                    Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+                );
+                builder
+            })
+            .collect();
+
+        let activity_depends = HashMap::from([(ids[0], vec![]), (ids[1], vec![ids[0]])]);
+        let chains = vec![chain("test", Duration::from_millis(1), activity_depends)];
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(chains, activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.run_cycles(3).unwrap();
+
+        assert_eq!(scheduler.chains[0].cycles, 3);
+        assert_eq!(scheduler.latency_histogram("test").unwrap().total(), 3);
+        assert!(scheduler.latency_histogram("other").is_none());
+        for count in steps.iter() {
+            assert_eq!(count.load(Ordering::Relaxed), 3);
+        }
+    }
+
+    /// Activity logging the completion of its startup after a delay
+    struct SlowStartup(ActivityId, Duration, Arc<Mutex<Vec<ActivityId>>>);
+
+    impl Activity for SlowStartup {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {
+            thread::sleep(self.1);
+            self.2.lock().unwrap().push(self.0);
+        }
+
+        fn step(&mut self) {}
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_serial_startup() {
+        timestamp::initialize_for_test();
+        let started: Arc<Mutex<Vec<ActivityId>>> = Default::default();
+        // Each activity on its own worker, lower ids take longer to start up
+        let activities: HashMap<WorkerId, Vec<ActivityIdAndBuilder>> = (0..3)
+            .map(|n| {
+                let started = started.clone();
+                let delay = Duration::from_millis(30 * (3 - n as u64));
+                let builder: ActivityIdAndBuilder = (
+                    ActivityId::from(n),
+                    Box::new(move |id| {
+                        Box::new(SlowStartup(id, delay, started)) as Box<dyn Activity>
+                    }),
+                );
+                (WorkerId::from(n), vec![builder])
+            })
+            .collect();
+
+        let activity_depends = (0..3).map(|n| (ActivityId::from(n), vec![])).collect();
+        let chains = vec![chain("test", Duration::from_millis(10), activity_depends)];
+        let (sender, receiver) = channel();
+        let mut scheduler = scheduler(chains, activities, sender, receiver);
+        scheduler.serial_startup = true;
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.startup().unwrap();
+
+        let expected: Vec<ActivityId> = (0..3).map(ActivityId::from).collect();
+        assert_eq!(*started.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_system_ready() {
+        use crate::signalling::Receiver;
+
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let activities: HashMap<WorkerId, Vec<ActivityIdAndBuilder>> = ids
+            .iter()
+            .map(|id| {
+                let delay = Duration::from_millis(20 * usize::from(id) as u64);
+                let builder: ActivityIdAndBuilder = (
+                    *id,
+                    Box::new(move |id| {
+                        Box::new(SlowStartup(id, delay, Default::default())) as Box<dyn Activity>
+                    }),
+                );
+                (WorkerId::from(usize::from(id)), vec![builder])
+            })
+            .collect();
+        let activity_depends = ids.iter().map(|id| (*id, vec![])).collect();
+        let chains = vec![chain("test", Duration::from_millis(10), activity_depends)];
+        let (sender, receiver) = channel();
+        let mut scheduler = scheduler(chains, activities, sender, receiver);
+
+        // Observe the signals sent to recorders through a local recorder
+        let (record_sender, mut record_receiver) = channel();
+        add_local_recorder(&mut scheduler, AgentId::from(1), record_sender);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.startup().unwrap();
+
+        let mut signals = Vec::new();
+        while let Ok(signal) = record_receiver.recv_timeout(Duration::from_millis(50)) {
+            signals.push(signal);
+        }
+        let ready = signals
+            .iter()
+            .filter(|s| matches!(s, Signal::SystemReady(_)))
+            .count();
+        assert_eq!(ready, 1, "{signals:?}");
+        assert!(
+            matches!(signals.last(), Some(Signal::SystemReady(_))),
+            "{signals:?}"
+        );
+        let startup_ready = signals
+            .iter()
+            .filter(|s| matches!(s, Signal::Ready(_)))
+            .count();
+        assert_eq!(startup_ready, ids.len());
+    }
+
+    #[test]
+    fn test_rate_divisor() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let steps: Vec<Arc<AtomicUsize>> = ids.iter().map(|_| Default::default()).collect();
+        let activities: Vec<ActivityIdAndBuilder> = ids
+            .iter()
+            .zip(steps.iter().cloned())
+            .map(|(id, count)| {
+                let builder: ActivityIdAndBuilder = (
+                    *id,
+                    Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+                );
+                builder
+            })
+            .collect();
+
+        // The second activity depends on the first one, which only steps every other cycle
+        let activity_depends = HashMap::from([(ids[0], vec![]), (ids[1], vec![ids[0]])]);
+        let mut config = chain("test", Duration::from_millis(1), activity_depends);
+        config.rate_divisors.insert(ids[0], 2);
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.run_cycles(5).unwrap();
+
+        let steps: Vec<usize> = steps.iter().map(|c| c.load(Ordering::Relaxed)).collect();
+        assert_eq!(steps, [3, 5]);
+    }
+
+    #[test]
+    fn test_add_activity() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let steps: Vec<Arc<AtomicUsize>> = ids.iter().map(|_| Default::default()).collect();
+        let count = steps[0].clone();
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            ids[0],
+            Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+        )];
+        let activity_depends = HashMap::from([(ids[0], vec![])]);
+        let config = chain("test", Duration::from_millis(1), activity_depends);
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.startup().unwrap();
+        scheduler.run_until(2, None).unwrap();
+
+        // Add a second activity depending on the first one on a new worker between two cycles
+        let count = steps[1].clone();
+        let registrar = scheduler.activity_registrar();
+        registrar
+            .add(
+                "test",
+                WorkerId::from(1),
+                ids[1],
+                Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+                vec![ids[0]],
+            )
+            .unwrap();
+        // Adding an existing activity is dropped
+        registrar
+            .add(
+                "test",
+                WorkerId::from(2),
+                ids[0],
+                Box::new(|id| Box::new(Counter(id, Default::default())) as Box<dyn Activity>),
+                vec![],
+            )
+            .unwrap();
+        scheduler.run_until(5, None).unwrap();
+        scheduler.shutdown().unwrap();
+
+        let steps: Vec<usize> = steps.iter().map(|c| c.load(Ordering::Relaxed)).collect();
+        assert_eq!(steps, [5, 3]);
+        assert_eq!(scheduler.chains[0].activity_depends[&ids[1]], [ids[0]]);
+    }
+
+    #[test]
+    fn test_add_activity_timeout() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            ids[0],
+            Box::new(|id| Box::new(Counter(id, Default::default())) as Box<dyn Activity>),
+        )];
+        let activity_depends = HashMap::from([(ids[0], vec![])]);
+        let mut config = chain("test", Duration::from_millis(1), activity_depends);
+        config.watchdog_timeout = Some(Duration::from_millis(20));
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.startup().unwrap();
+        scheduler.run_until(1, None).unwrap();
+
+        // Add an activity not starting up within the watchdog timeout
+        scheduler
+            .activity_registrar()
+            .add(
+                "test",
+                WorkerId::from(1),
+                ids[1],
+                Box::new(|id| {
+                    let delay = Duration::from_millis(200);
+                    Box::new(SlowStartup(id, delay, Default::default())) as Box<dyn Activity>
+                }),
+                vec![],
+            )
+            .unwrap();
+        scheduler.run_until(3, None).unwrap();
+        assert!(!scheduler.activity_chains.contains_key(&ids[1]));
+
+        // The late ready signal of the removed activity is ignored
+        thread::sleep(Duration::from_millis(250));
+        scheduler.run_until(5, None).unwrap();
+        scheduler.shutdown().unwrap();
+
+        assert!(!scheduler.activity_chains.contains_key(&ids[1]));
+        assert!(!scheduler.chains[0].activity_states.contains_key(&ids[1]));
+        assert_eq!(
+            scheduler.activity_connector.activities_of(AgentId::from(0)),
+            [ids[0]]
+        );
+    }
+
+    /// Activity taking the given time for each step
+    struct Slow(ActivityId, Duration);
+
+    impl Activity for Slow {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            thread::sleep(self.1);
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_activity_deadline() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        // The first activity takes longer than its deadline, the second one does not
+        let activities: Vec<ActivityIdAndBuilder> = vec![
+            (
+                ids[0],
+                Box::new(|id| Box::new(Slow(id, Duration::from_millis(20))) as Box<dyn Activity>),
+            ),
+            (
+                ids[1],
+                Box::new(|id| Box::new(Counter(id, Default::default())) as Box<dyn Activity>),
+            ),
+        ];
+
+        // Both activities finish well within the cycle time
+        let activity_depends = HashMap::from([(ids[0], vec![]), (ids[1], vec![ids[0]])]);
+        let mut config = chain("test", Duration::from_millis(200), activity_depends);
+        config
+            .activity_deadlines
+            .insert(ids[0], Duration::from_millis(5));
+        config
+            .activity_deadlines
+            .insert(ids[1], Duration::from_millis(150));
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.run_cycles(2).unwrap();
+
+        let histogram = scheduler.latency_histogram("test").unwrap();
+        let overruns: u64 = histogram
+            .iter()
+            .filter(|(range, _)| range.end > Duration::from_millis(200))
+            .map(|(_, count)| count)
+            .sum();
+        assert_eq!(overruns, 0);
+        assert_eq!(scheduler.deadline_misses(ids[0]), 2);
+        assert_eq!(scheduler.deadline_misses(ids[1]), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline given for activity A2 not in task chain test")]
+    fn test_activity_deadline_outside_chain() {
+        let activity_depends = HashMap::from([(ActivityId::from(0), vec![])]);
+        let mut config = chain("test", Duration::from_millis(10), activity_depends);
+        config
+            .activity_deadlines
+            .insert(ActivityId::from(2), Duration::from_millis(5));
+        TaskChain::new(config);
+    }
+
+    #[test]
+    fn test_optional_dependency() {
+        timestamp::initialize_for_test();
+        let ids: Vec<ActivityId> = (0..2).map(ActivityId::from).collect();
+        let count = Arc::new(AtomicUsize::new(0));
+        let steps = count.clone();
+        // Only the second activity runs on a worker
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            ids[1],
+            Box::new(move |id| Box::new(Counter(id, steps)) as Box<dyn Activity>),
+        )];
+
+        // The second activity optionally depends on the first one
+        let activity_depends = HashMap::from([(ids[0], vec![]), (ids[1], vec![])]);
+        let mut config = chain("test", Duration::from_millis(10), activity_depends);
+        config.optional_depends.insert(ids[1], vec![ids[0]]);
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+
+        // The first activity has been triggered but never becomes ready
+        let chain = &mut scheduler.chains[0];
+        chain.phase = Phase::Running;
+        chain.cycle_id = CycleId(1);
+        chain.activity_states.get_mut(&ids[0]).unwrap().triggered = true;
+
+        // The second activity is stepped nevertheless
+        scheduler.step_foreach_ready(0).unwrap();
+        assert!(scheduler.chains[0].activity_states[&ids[1]].triggered);
+        scheduler
+            .wait_next_signal(Some(Instant::now() + Duration::from_secs(1)))
+            .unwrap();
+        assert!(scheduler.is_ready(&ids[1]));
+        assert!(!scheduler.is_ready(&ids[0]));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Optional dependency A2 of activity A1 not in task chain test")]
+    fn test_optional_dependency_outside_chain() {
+        let ids: Vec<ActivityId> = (0..3).map(ActivityId::from).collect();
+        let activity_depends = HashMap::from([(ids[0], vec![]), (ids[1], vec![])]);
+        let mut config = chain("test", Duration::from_millis(10), activity_depends);
+        config.optional_depends.insert(ids[1], vec![ids[2]]);
+        TaskChain::new(config);
+    }
+
+    #[test]
+    fn test_task_chain_span() {
+        timestamp::initialize_for_test();
+        let act_id = ActivityId::from(4711);
+        let count = Arc::new(AtomicUsize::new(0));
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            act_id,
+            Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+        )];
+        let chains = vec![chain(
+            "span test",
+            Duration::from_millis(1),
+            HashMap::from([(act_id, vec![])]),
+        )];
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+
+        // The worker created in the scope of the subscriber captures its spans as well
+        let capture = SpanCapture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let mut scheduler = scheduler(chains, activities, sender, receiver);
+            scheduler.connect_remotes().unwrap();
+            scheduler.sync_remotes().unwrap();
+            scheduler.run_cycles(2).unwrap();
+        });
+
+        // Positions of the enter and exit of each span in the log, with the cycle id of
+        // task chain spans
+        let events = capture.events();
+        let ranges = |name: &str, field: &str, value: &str| {
+            let mut ranges = Vec::new();
+            for (id, event) in events.iter() {
+                let SpanEvent::New(data) = event else {
+                    continue;
+                };
+                if data.name != name || data.field(field) != Some(value) {
+                    continue;
+                }
+                let position = |kind: fn(&SpanEvent) -> bool| {
+                    events
+                        .iter()
+                        .position(|(i, e)| i == id && kind(e))
+                        .expect("span not entered and exited")
+                };
+                let enter = position(|e| matches!(e, SpanEvent::Enter));
+                let exit = position(|e| matches!(e, SpanEvent::Exit));
+                ranges.push((data.field("cycle_id").map(String::from), enter..exit));
+            }
+            ranges
+        };
+        let cycles = ranges("TaskChain", "name", "\"span test\"");
+        let steps = ranges("Step", "activity_id", "A4711");
+
+        // One span per cycle, each containing the step of the activity in that cycle
+        let cycle_ids: Vec<_> = cycles.iter().map(|(id, _)| id.as_deref()).collect();
+        assert_eq!(cycle_ids, [Some("C1"), Some("C2")]);
+        assert_eq!(steps.len(), 2);
+        for ((_, cycle), (_, step)) in cycles.iter().zip(&steps) {
+            assert!(cycle.start < step.start && step.end < cycle.end);
+        }
+    }
+
+    #[test]
+    fn test_flush_timeout() {
+        timestamp::initialize_for_test();
+        let act_id = ActivityId::from(0);
+        let count = Arc::new(AtomicUsize::new(0));
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            act_id,
+            Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+        )];
+        let mut config = chain(
+            "test",
+            Duration::from_millis(1),
+            HashMap::from([(act_id, vec![])]),
+        );
+        config.flush_timeout = Some(Duration::from_millis(30));
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(vec![config], activities, sender, receiver);
+
+        // A local recorder never signalling ready
+        let recorder_id = AgentId::from(1);
+        let (record_sender, mut record_receiver) = channel();
+        add_local_recorder(&mut scheduler, recorder_id, record_sender);
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        let start = Instant::now();
+        scheduler.run_cycles(3).unwrap();
+
+        // The first cycle waited for the recorder until the timeout, the others went on
+        // without it
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(scheduler.latency_histogram("test").unwrap().total(), 3);
+        assert!(scheduler.activity_connector.all_recorders().is_empty());
+
+        // The recorder has been disconnected after the end of the first cycle
+        let mut signals = Vec::new();
+        while let Ok(signal) = record_receiver.recv_timeout(Duration::from_millis(50)) {
+            signals.push(signal);
+        }
+        assert!(
+            matches!(signals.last(), Some(Signal::TaskChainEnd(_))),
+            "{signals:?}"
+        );
+        let ends = signals
+            .iter()
+            .filter(|s| matches!(s, Signal::TaskChainEnd(_)))
+            .count();
+        assert_eq!(ends, 1);
+    }
+
+    #[test]
+    fn test_shutdown_recorder_timeout() {
+        timestamp::initialize_for_test();
+        let (sender, receiver) = channel();
+        let mut scheduler = scheduler(vec![], HashMap::new(), sender, receiver);
+
+        // A local recorder never signalling ready after shutdown
+        let (record_sender, mut record_receiver) = channel();
+        add_local_recorder(&mut scheduler, AgentId::from(1), record_sender);
+        scheduler.connect_remotes().unwrap();
+        let start = Instant::now();
+        scheduler
+            .activity_connector
+            .shutdown_remotes(Duration::from_millis(30))
+            .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert!(matches!(
+            record_receiver.recv_timeout(Duration::from_millis(50)),
+            Ok(Signal::SystemShutdown(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "recording")]
+    fn test_local_recorder() {
+        use crate::recording::reader::RecordingReader;
+        use crate::recording::recorder::{Record, Recorder, RecordingRules};
+        use crate::recording::registry::TypeRegistry;
+        use std::{env, fs, process};
+
+        timestamp::initialize_for_test();
+        let act_id = ActivityId::from(0);
+        let count = Arc::new(AtomicUsize::new(0));
+        let activities: Vec<ActivityIdAndBuilder> = vec![(
+            act_id,
+            Box::new(move |id| Box::new(Counter(id, count)) as Box<dyn Activity>),
+        )];
+        let chains = vec![chain(
+            "test",
+            Duration::from_millis(1),
+            HashMap::from([(act_id, vec![])]),
+        )];
+        let (sender, receiver) = channel();
+        let activities = HashMap::from([(WorkerId::from(0), activities)]);
+        let mut scheduler = scheduler(chains, activities, sender.clone(), receiver);
+
+        // The recorder runs on a thread of this process, connected by intra-process channels
+        let recorder_id = AgentId::from(1);
+        let (record_sender, record_receiver) = channel();
+        add_local_recorder(&mut scheduler, recorder_id, record_sender);
+        let path = env::temp_dir().join(format!("feo-test-local-recorder-{}.bin", process::id()));
+        let record_file: &'static str = path.to_str().unwrap().to_owned().leak();
+        let recorder = thread::spawn(move || {
+            let registry = TypeRegistry::default();
+            let rules = RecordingRules::new();
+            let mut recorder = Recorder::new_local(
+                recorder_id,
+                record_receiver,
+                sender,
+                record_file,
+                rules,
+                &registry,
+            )
+            .unwrap();
+            recorder.run();
+        });
+        scheduler.connect_remotes().unwrap();
+        scheduler.sync_remotes().unwrap();
+        scheduler.run_cycles(3).unwrap();
+        recorder.join().unwrap();
+
+        // Each cycle has been recorded, closed cleanly on system shutdown
+        let recording = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let signals: Vec<String> = RecordingReader::new(&recording)
+            .map(|record| match record.unwrap().0 {
+                Record::Signal(record) => record.signal.to_string(),
+                Record::EndOfRecording { .. } => "EndOfRecording".to_owned(),
+                record => panic!("unexpected record {record:?}"),
+            })
+            .map(|signal| signal.split('(').next().unwrap().to_owned())
+            .collect();
+        let cycle = ["TaskChainStart", "Step", "Ready", "TaskChainEnd"];
+        let expected: Vec<&str> = ["Startup", "Ready", "SystemReady"]
+            .into_iter()
+            .chain(cycle.into_iter().cycle().take(3 * cycle.len()))
+            .chain(["Shutdown", "Ready", "SystemShutdown", "EndOfRecording"])
+            .collect();
+        assert_eq!(signals, expected);
+        assert_eq!(scheduler.latency_histogram("test").unwrap().total(), 3);
+    }
+}
@@ -3,85 +3,293 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::activity::ActivityId;
+use crate::agent;
+use crate::configuration::topology::TopologyHash;
 use crate::error::Error;
 use crate::signalling::inter_proc_socket::FdExt;
+#[cfg(feature = "signalling_auth")]
+use crate::signalling::inter_proc_socket::SignalPdu;
 use crate::signalling::{
-    AgentId, IntraProcReceiver, MioSocketReceiver, MioSocketSender, Receiver, Sender, Signal,
+    AgentId, CycleId, IntraProcReceiver, IntraProcSender, MioSocketReceiver, MioSocketSender,
+    Receiver, Sender, Signal, SignallingConfig,
+};
+use crate::timestamp::{self, timestamp, SyncInfo, SyncSample, Timestamp};
+use crate::worker_pool::{
+    ActivityStates, TriggerError, Worker, WorkerPool, WorkerPoolListener, WorkerPoolTrigger,
 };
-use crate::timestamp::{self, timestamp, SyncInfo};
-use crate::worker_pool::{WorkerPool, WorkerPoolListener, WorkerPoolTrigger};
 use feo_log::{debug, error, info};
 use mio::net::TcpStream;
-use mio::{Events, Poll};
+use mio::Poll;
 use std::net::SocketAddr;
-use std::thread;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{io, thread};
+
+/// Default watchdog timeout as a multiple of the cycle time
+const DEFAULT_WATCHDOG_CYCLES: u32 = 10;
 
 pub struct SecondaryAgent {
     wp_listener: WorkerPoolListener,
+    workers: Vec<Worker>,
     primary_connector: PrimaryConnector,
+    watchdog: Option<Watchdog>,
+    activity_states: ActivityStates,
+    /// Number of ready signals still expected from a recorder hosted in this process
+    pending_recorder_ready: Arc<AtomicUsize>,
 }
 
 impl SecondaryAgent {
+    /// Create a secondary agent
+    ///
+    /// If `recorder` is given, the agent hosts a recorder receiving the signals to record
+    /// through it, see [`Builder::recorder`](crate::configuration::secondary_agent::Builder::recorder).
+    /// If `topology` is given, the primary agent rejects the agent unless its own topology
+    /// has the same hash. In a `dry_run`, the agent fails if it is shut down before the system
+    /// is ready or an activity is stepped.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         agent_id: AgentId,
         remote_socket_addr: SocketAddr,
         worker_pool: WorkerPool,
         intra_ready_receiver: IntraProcReceiver<Signal>,
+        connect_retry: ConnectRetry,
+        signalling: SignallingConfig,
+        watchdog: Option<Watchdog>,
+        recorder: Option<IntraProcSender<Signal>>,
+        topology: Option<TopologyHash>,
+        dry_run: bool,
     ) -> Self {
         let wp_listener = worker_pool.listener(intra_ready_receiver);
-        let (_, wp_trigger) = worker_pool.split();
+        let activity_states = worker_pool.activity_states();
+        let (workers, wp_trigger) = worker_pool.split();
+        let pending_recorder_ready = Arc::new(AtomicUsize::new(0));
+        let recorder = recorder.map(|sender| LocalRecorder {
+            sender,
+            pending_ready: pending_recorder_ready.clone(),
+        });
 
         // create connector to primary agent
-        let primary_connector = PrimaryConnector::new(agent_id, remote_socket_addr, wp_trigger);
+        let primary_connector = PrimaryConnector::new(
+            agent_id,
+            remote_socket_addr,
+            connect_retry,
+            signalling,
+            wp_trigger,
+            recorder,
+            topology,
+            dry_run,
+        );
 
         Self {
             wp_listener,
+            workers,
             primary_connector,
+            watchdog,
+            activity_states,
+            pending_recorder_ready,
         }
     }
 
-    fn run(&mut self) {
-        self.connect_primary();
+    /// Lifecycle states of the activities of this agent
+    ///
+    /// The returned handle can be queried from other threads while the agent runs.
+    pub fn activity_states(&self) -> ActivityStates {
+        self.activity_states.clone()
+    }
+
+    /// Run until the primary agent broadcasts a system shutdown
+    fn run(mut self) -> Result<(), Error> {
+        self.connect_primary()?;
 
         loop {
             self.wp_listener.clear_ready();
-            self.wp_listener.wait_next_ready();
+            if let Some(stalled) = wait_next_ready(&mut self.wp_listener, self.watchdog.as_ref()) {
+                self.report_stalled(&stalled);
+                continue;
+            }
+            self.forward_recorder_ready();
+            if self.wp_listener.is_shutdown() {
+                // Wait until a recorder in this process has closed its recording
+                if self.pending_recorder_ready.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                continue;
+            }
 
             let ready_ids = self
                 .wp_listener
                 .ready_iter()
                 .filter_map(|(id, ready)| ready.then_some(id));
             for id in ready_ids {
-                if let Err(e) = self.primary_connector.send_ready(id) {
+                let cycle_id = self.wp_listener.cycle_id(id);
+                if let Err(e) = self.primary_connector.send_ready(id, cycle_id) {
                     error!("Failed to transmit ready signal for activity ID {id}: {e}");
                 }
             }
         }
+
+        // Wait for the signal forwarding thread and all workers to exit
+        let result = self.primary_connector.join();
+        self.workers.into_iter().for_each(Worker::join);
+        #[cfg(any(feature = "ipc_iceoryx2", feature = "ipc_inprocess"))]
+        crate::com::shutdown();
+        info!("Secondary agent shut down");
+        result
     }
 
-    fn connect_primary(&mut self) {
+    fn connect_primary(&mut self) -> Result<(), Error> {
         self.primary_connector.connect_primary()
     }
+
+    /// Forward the ready signals of a recorder in this process to the primary agent
+    fn forward_recorder_ready(&mut self) {
+        for signal in self.wp_listener.take_recorder_ready() {
+            self.pending_recorder_ready.fetch_sub(1, Ordering::SeqCst);
+            if let Err(e) = self.primary_connector.send_signal(signal) {
+                error!("Failed to transmit recorder ready signal: {e}");
+            }
+        }
+    }
+
+    /// Report activities found stalled by the watchdog
+    fn report_stalled(&mut self, stalled: &[ActivityId]) {
+        let Some(watchdog) = self.watchdog else {
+            return;
+        };
+        let ids: Vec<String> = stalled.iter().map(ToString::to_string).collect();
+        error!(
+            "No activity signalled ready within {:?}. Pending activities: {}",
+            watchdog.timeout(),
+            ids.join(", ")
+        );
+        if watchdog.notify_primary {
+            for id in stalled {
+                if let Err(e) = self.primary_connector.send_stalled(id) {
+                    error!("Failed to transmit stalled signal for activity ID {id}: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Watchdog of the activities of a secondary agent
+///
+/// Fires if activities are pending but none of them signalled ready within the timeout.
+#[derive(Debug, Clone, Copy)]
+pub struct Watchdog {
+    /// Expected cycle time of the task chains
+    pub cycle_time: Duration,
+
+    /// Number of cycle times without a ready signal after which the watchdog fires
+    pub cycles: u32,
+
+    /// Send a [`Signal::ActivityStalled`] per pending activity to the primary agent when firing
+    pub notify_primary: bool,
+}
+
+impl Watchdog {
+    /// Create a watchdog for the given cycle time, firing after the default number of cycles
+    pub fn new(cycle_time: Duration) -> Self {
+        Self {
+            cycle_time,
+            cycles: DEFAULT_WATCHDOG_CYCLES,
+            notify_primary: false,
+        }
+    }
+
+    /// Time without a ready signal after which the watchdog fires
+    pub fn timeout(&self) -> Duration {
+        self.cycle_time * self.cycles
+    }
+}
+
+/// Wait for the next ready signal or shutdown of the worker pool
+///
+/// Returns the pending activities if the watchdog fired before. Without pending activities,
+/// the watchdog does not fire.
+fn wait_next_ready(
+    listener: &mut WorkerPoolListener,
+    watchdog: Option<&Watchdog>,
+) -> Option<Vec<ActivityId>> {
+    let Some(watchdog) = watchdog else {
+        listener.wait_next_ready();
+        return None;
+    };
+    // The watchdog timeout is in feo-time, the listener waits in real time
+    let timeout = agent::poll_timeout(Some(watchdog.timeout())).unwrap_or_default();
+    loop {
+        match listener.wait_next_ready_timeout(timeout) {
+            Ok(()) => return None,
+            Err(Error::Timeout(_)) => {
+                let pending = listener.pending();
+                if !pending.is_empty() {
+                    return Some(pending);
+                }
+            }
+            Err(e) => panic!("failed to get signal from worker: {e}"),
+        }
+    }
+}
+
+/// Recorder hosted in the process of a secondary agent
+///
+/// The recorder is registered with the primary agent under the id of the secondary agent and
+/// shares its connection. It receives the signals to record through `sender` and signals ready
+/// through the ready channel of the worker pool, see `Recorder::new_local`. The secondary agent
+/// forwards its ready signals to the primary agent.
+struct LocalRecorder {
+    sender: IntraProcSender<Signal>,
+    /// Number of ready signals still expected from the recorder, shared with the agent
+    pending_ready: Arc<AtomicUsize>,
+}
+
+impl LocalRecorder {
+    /// Send the given signal to the recorder, logging failures
+    fn record(&mut self, signal: Signal) {
+        // The recorder signals ready after each task chain end and when closing the recording
+        let answered = matches!(signal, Signal::TaskChainEnd(_) | Signal::SystemShutdown(_));
+        if answered {
+            self.pending_ready.fetch_add(1, Ordering::SeqCst);
+        }
+        if let Err(e) = self.sender.send(signal) {
+            error!("Failed to send signal {signal} to local recorder: {e:?}");
+            if answered {
+                self.pending_ready.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
 }
 
 struct IpcSignalReceiver {
     trigger_stream: Option<TcpStream>,
     workpool_trigger: Option<WorkerPoolTrigger>,
-    _thread: Option<thread::JoinHandle<()>>,
+    recorder: Option<LocalRecorder>,
+    signalling: SignallingConfig,
+    dry_run: bool,
+    thread: Option<thread::JoinHandle<Result<(), Error>>>,
 }
 
 impl IpcSignalReceiver {
-    fn new(trigger_stream: TcpStream, wp_trigger: WorkerPoolTrigger) -> Self {
+    fn new(
+        trigger_stream: TcpStream,
+        wp_trigger: WorkerPoolTrigger,
+        recorder: Option<LocalRecorder>,
+        signalling: SignallingConfig,
+        dry_run: bool,
+    ) -> Self {
         IpcSignalReceiver {
             trigger_stream: Some(trigger_stream),
             workpool_trigger: Some(wp_trigger),
-            _thread: None,
+            recorder,
+            signalling,
+            dry_run,
+            thread: None,
         }
     }
 
-    /// Wait for and receive synchronization event from primary agent
-    fn receive_sync(&mut self) -> SyncInfo {
+    /// Wait for and receive the next signal from the primary agent
+    fn receive_signal(&mut self) -> Signal {
         // Get trigger stream
         let trigger_stream = self
             .trigger_stream
@@ -90,12 +298,11 @@ impl IpcSignalReceiver {
 
         // Register stream with Poll
         let mut poll = Poll::new().unwrap();
-        let mut events = Events::with_capacity(1024);
+        let mut events = self.signalling.events();
         let mut receiver = MioSocketReceiver::new(trigger_stream, &mut poll, &mut events);
         receiver.register(0).unwrap();
 
         // Wait until signal received
-        debug!("Waiting for startup synchronization pdu");
         let signal: Signal = receiver
             .recv()
             .expect("failed to receive")
@@ -103,25 +310,49 @@ impl IpcSignalReceiver {
             .expect("failed to decode signal pdu");
         debug!("Received signal {signal}");
 
-        // Extract synchronization info or panic, if signal is incorrect
-        let sync_info = match signal {
-            Signal::StartupSync(info) => info,
-            _ => panic!("received unexpected signal {signal}"),
-        };
-
         // Deregister receiver from Poll
         receiver
             .deregister()
             .expect("failed to deregister receiver");
 
-        // Return result
-        sync_info
+        signal
+    }
+
+    /// Wait for and receive synchronization event from primary agent
+    fn receive_sync(&mut self) -> SyncInfo {
+        debug!("Waiting for startup synchronization pdu");
+        // Extract synchronization info or panic, if signal is incorrect
+        match self.receive_signal() {
+            Signal::StartupSync(info) => info,
+            signal => panic!("received unexpected signal {signal}"),
+        }
+    }
+
+    /// Wait for and receive the response to a synchronization request from primary agent
+    ///
+    /// Returns the primary's receive and send times of the request
+    fn receive_sync_response(&mut self) -> (Duration, Duration) {
+        match self.receive_signal() {
+            Signal::SyncResponse((t2, t3)) => (t2.0, t3.0),
+            signal => panic!("received unexpected signal {signal}"),
+        }
     }
 
     /// Thread main function waiting for and forwarding trigger signals from the primary process
-    fn thread_main(trigger_stream: &mut TcpStream, workpool_trigger: &mut WorkerPoolTrigger) {
+    ///
+    /// All signals are also forwarded to a recorder in this process, if any. Returns after
+    /// shutting down the worker pool upon a system shutdown. In a dry run, fails if the
+    /// system is shut down before it is ready or an activity is stepped.
+    fn thread_main(
+        trigger_stream: &mut TcpStream,
+        mut workpool_trigger: WorkerPoolTrigger,
+        mut recorder: Option<LocalRecorder>,
+        signalling: SignallingConfig,
+        dry_run: bool,
+    ) -> Result<(), Error> {
+        let mut system_ready = false;
         let mut poll = Poll::new().unwrap();
-        let mut events = Events::with_capacity(1024);
+        let mut events = signalling.events();
         let mut ipc_trigger_receiver =
             MioSocketReceiver::new(trigger_stream, &mut poll, &mut events);
         ipc_trigger_receiver.register(0).unwrap();
@@ -133,21 +364,79 @@ impl IpcSignalReceiver {
                 .try_into()
                 .expect("failed to decode signal pdu");
             debug!("Received signal {signal}");
-            workpool_trigger.trigger(signal); // Forward the received signal to the worker pool
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(signal);
+            }
+            if let Signal::SystemShutdown(_) = signal {
+                info!("Received system shutdown, stopping workers");
+                workpool_trigger.shutdown();
+                if dry_run && !system_ready {
+                    return Err(Error::Protocol("system shut down before ready".into()));
+                }
+                return Ok(());
+            }
+            if let Signal::SystemReady(_) = signal {
+                info!("Received system ready");
+                system_ready = true;
+                continue;
+            }
+            if let (true, Signal::Step((activity_id, _, _))) = (dry_run, signal) {
+                error!("Received step of activity {activity_id} in dry run, stopping workers");
+                workpool_trigger.shutdown();
+                return Err(Error::Protocol(format!(
+                    "step of activity {activity_id} triggered in dry run"
+                )));
+            }
+            // With a recorder in this process, the primary agent also sends signals to record
+            // only, like ready signals and triggers of activities of other agents
+            let is_trigger = matches!(
+                signal,
+                Signal::Startup(_) | Signal::Step(_) | Signal::Shutdown(_)
+            );
+            if recorder.is_some() && !is_trigger {
+                continue;
+            }
+
+            // Forward the received signal to the worker pool
+            match workpool_trigger.try_trigger(signal) {
+                Ok(()) => {}
+                Err(TriggerError::UnknownActivity(_)) if recorder.is_some() => {}
+                Err(e) => error!("Failed to forward signal {signal} to worker pool: {e}"),
+            }
         }
     }
 
     /// Start the signal forwarding thread
     fn run(&mut self) {
-        assert!(self._thread.is_none(), "thread is already running");
+        assert!(self.thread.is_none(), "thread is already running");
 
         // start ready signal receiver thread
         let mut trigger_stream = self.trigger_stream.take().unwrap();
-        let mut workpool_trigger = self.workpool_trigger.take().unwrap();
-        self._thread = Some(thread::spawn(move || {
-            IpcSignalReceiver::thread_main(&mut trigger_stream, &mut workpool_trigger)
+        let workpool_trigger = self.workpool_trigger.take().unwrap();
+        let recorder = self.recorder.take();
+        let signalling = self.signalling;
+        let dry_run = self.dry_run;
+        self.thread = Some(thread::spawn(move || {
+            IpcSignalReceiver::thread_main(
+                &mut trigger_stream,
+                workpool_trigger,
+                recorder,
+                signalling,
+                dry_run,
+            )
         }));
     }
+
+    /// Wait for the signal forwarding thread to exit, returning its outcome
+    fn join(&mut self) -> Result<(), Error> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        thread.join().unwrap_or_else(|_| {
+            error!("Signal forwarding thread panicked");
+            Err(Error::Channel("signal forwarding thread panicked"))
+        })
+    }
 }
 
 /// Handle signalling from and to the primary agent
@@ -158,9 +447,24 @@ struct PrimaryConnector {
     // Socket address of the primary process
     remote_addr: SocketAddr,
 
+    // Retry policy for connecting to the primary process
+    connect_retry: ConnectRetry,
+
+    // Tunables of the signalling with the primary process
+    signalling: SignallingConfig,
+
     // Trigger interface to the local worker pool
     workpool_trigger: Option<WorkerPoolTrigger>,
 
+    // Recorder in this process sharing the connection, if any
+    recorder: Option<LocalRecorder>,
+
+    // Hash of the configured topology sent to the primary agent, if any
+    topology: Option<TopologyHash>,
+
+    // Whether to fail on steps and on a shutdown before the system is ready
+    dry_run: bool,
+
     // Helper for handling signals from the primary agent
     ipc_receiver: Option<IpcSignalReceiver>,
 
@@ -169,87 +473,182 @@ struct PrimaryConnector {
 }
 
 impl PrimaryConnector {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         local_agent_id: AgentId,
         remote_socket_addr: SocketAddr,
+        connect_retry: ConnectRetry,
+        signalling: SignallingConfig,
         wp_trigger: WorkerPoolTrigger,
+        recorder: Option<LocalRecorder>,
+        topology: Option<TopologyHash>,
+        dry_run: bool,
     ) -> Self {
         Self {
             local_agent_id,
             remote_addr: remote_socket_addr,
+            connect_retry,
+            signalling,
             workpool_trigger: Some(wp_trigger),
+            recorder,
+            topology,
+            dry_run,
             ipc_receiver: None,
             ipc_sender: None,
         }
     }
 
-    pub fn connect_primary(&mut self) {
+    pub fn connect_primary(&mut self) -> Result<(), Error> {
+        // Connect to primary process
+        let (trigger_stream, ready_stream) = connect_to_primary(
+            self.local_agent_id,
+            self.remote_addr,
+            &self.connect_retry,
+            &self.signalling,
+            self.topology,
+        )?;
+
         // Move worker pool trigger out of this object and into ipc signal receiver
         let workpool_trigger = self
             .workpool_trigger
             .take()
             .expect("missing WorkerPoolTrigger instance");
-
-        // Connect to primary process
-        let (trigger_stream, ready_stream) =
-            connect_to_primary(self.local_agent_id, self.remote_addr);
         let sender = MioSocketSender::new(ready_stream);
 
-        self.ipc_receiver = Some(IpcSignalReceiver::new(trigger_stream, workpool_trigger));
+        self.ipc_receiver = Some(IpcSignalReceiver::new(
+            trigger_stream,
+            workpool_trigger,
+            self.recorder.take(),
+            self.signalling,
+            self.dry_run,
+        ));
+        self.ipc_sender = Some(sender);
         self.sync_time();
         info!("Time synchronization with primary agent done");
 
         self.ipc_receiver.as_mut().unwrap().run();
-
-        self.ipc_sender = Some(sender);
+        Ok(())
     }
 
+    /// Synchronize the local time with the primary agent
+    ///
+    /// After receiving the startup time, a number of NTP-style request/response exchanges
+    /// are done to estimate the offset between the local and the primary's clock.
     fn sync_time(&mut self) {
-        let sync_info = self
-            .ipc_receiver
+        let ipc_receiver = self.ipc_receiver.as_mut().expect("missing IPC receiver");
+        let ipc_sender = self.ipc_sender.as_mut().expect("missing IPC sender");
+        let sync_info = ipc_receiver.receive_sync();
+
+        let reference = std::time::Instant::now();
+        let mut samples = Vec::with_capacity(timestamp::SYNC_ROUNDS);
+        for _ in 0..timestamp::SYNC_ROUNDS {
+            let t1 = reference.elapsed();
+            ipc_sender
+                .send(Signal::SyncRequest((self.local_agent_id, Timestamp(t1))))
+                .unwrap_or_else(|e| panic!("failed to send sync request: {e:?}"));
+            let (t2, t3) = ipc_receiver.receive_sync_response();
+            let t4 = reference.elapsed();
+            samples.push(SyncSample { t1, t2, t3, t4 });
+        }
+
+        timestamp::initialize_from_exchange(sync_info, reference, &samples);
+
+        // Tell a recorder in this process that the startup time is set, it shares it with
+        // this agent
+        if let Some(recorder) = ipc_receiver.recorder.as_mut() {
+            recorder.record(Signal::StartupSync(sync_info));
+        }
+    }
+
+    // Send ready signal using the given Activity ID and cycle ID
+    pub fn send_ready(&mut self, activity_id: &ActivityId, cycle_id: CycleId) -> Result<(), Error> {
+        self.ipc_sender
+            .as_mut()
+            .expect("missing IPC sender")
+            .send(Signal::Ready((*activity_id, timestamp(), cycle_id)))
+    }
+
+    // Send the given signal to the primary agent
+    fn send_signal(&mut self, signal: Signal) -> Result<(), Error> {
+        self.ipc_sender
             .as_mut()
             .expect("missing IPC sender")
-            .receive_sync();
-        timestamp::initialize_from(sync_info);
+            .send(signal)
     }
 
-    // Send ready signal using the given Activity ID
-    pub fn send_ready(&mut self, activity_id: &ActivityId) -> Result<(), Error> {
+    // Send stalled signal for the given Activity ID
+    fn send_stalled(&mut self, activity_id: &ActivityId) -> Result<(), Error> {
         self.ipc_sender
             .as_mut()
             .expect("missing IPC sender")
-            .send(Signal::Ready((*activity_id, timestamp())))
+            .send(Signal::ActivityStalled((*activity_id, timestamp())))
+    }
+
+    /// Wait for the thread forwarding signals from the primary agent to exit
+    fn join(&mut self) -> Result<(), Error> {
+        match self.ipc_receiver.as_mut() {
+            Some(ipc_receiver) => ipc_receiver.join(),
+            None => Ok(()),
+        }
     }
 }
 
-pub fn run(mut agent: SecondaryAgent) {
-    agent.run();
+/// Run the given agent until the primary agent broadcasts a system shutdown, panicking on
+/// errors
+///
+/// See [`run_result`] for handling errors instead.
+pub fn run(agent: SecondaryAgent) {
+    run_result(agent).unwrap_or_else(|e| panic!("failed to run secondary agent: {e}"));
+}
+
+/// Run the given agent until the primary agent broadcasts a system shutdown
+///
+/// Fails if the agent cannot connect to the primary agent or, in a dry run, if the system is
+/// shut down before it is ready or an activity is stepped, see
+/// [`Builder::dry_run`](crate::configuration::secondary_agent::Builder::dry_run).
+pub fn run_result(agent: SecondaryAgent) -> Result<(), Error> {
+    agent.run()
+}
+
+/// Retry policy for connecting to the primary agent
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectRetry {
+    /// Maximum duration to retry connecting, shared by all streams to the primary agent
+    pub timeout: Duration,
+
+    /// Delay after the first failed attempt, doubled after each further failed attempt
+    pub initial_backoff: Duration,
+
+    /// Maximum delay between two attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for ConnectRetry {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
 }
 
 /// Common functionality used by secondary agents and recorders for connecting to the primary agent
 ///
-/// Returns an incoming stream and an outgoing stream
+/// Returns an incoming stream and an outgoing stream. Fails if the streams could not be
+/// connected and accepted within the timeout of the given retry policy. The given topology
+/// hash, if any, is sent in the hello messages for the primary agent to check, a mismatch
+/// fails with [`Error::Config`].
 pub fn connect_to_primary(
     local_agent_id: AgentId,
     remote_addr: SocketAddr,
-) -> (TcpStream, TcpStream) {
+    retry: &ConnectRetry,
+    signalling: &SignallingConfig,
+    topology: Option<TopologyHash>,
+) -> Result<(TcpStream, TcpStream), Error> {
     info!("Connecting to primary process at {}", remote_addr);
-    let mut in_stream = loop {
-        // Retry connecting in case of an error. This covers the scenario when the
-        // primary process has not been started yet. We do use a std::net::TcpStream
-        // instead of mio::net::TcpStream on purpose here and convert it accordingly
-        // once the connection is established. Reason for that: polling the asynchronous
-        // mio::net::TcpStream as suggested by mio's documentation turned out to behave
-        // differently cross-platform-wise
-        if let Ok(stream) = std::net::TcpStream::connect(remote_addr) {
-            stream
-                .make_nonblocking()
-                .expect("failed to make stream non-blocking");
-            break TcpStream::from_std(stream);
-        } else {
-            thread::sleep(Duration::from_millis(100));
-        }
-    };
+    let deadline = Instant::now() + retry.timeout;
+    let mut in_stream = connect_stream(remote_addr, retry, deadline)?;
     info!(
         "Connected to main process for incoming signals at {remote_addr}, sending 'hello_trigger'",
     );
@@ -258,27 +657,192 @@ pub fn connect_to_primary(
         .unwrap_or_else(|e| panic!("setting nodelay for stream failed: {e:?}"));
 
     let mut sender = MioSocketSender::new(&mut in_stream);
-    let hello_trigger = Signal::HelloTrigger(local_agent_id);
+    let hello_trigger = Signal::HelloTrigger((local_agent_id, topology));
     sender
         .send(&hello_trigger)
         .unwrap_or_else(|e| panic!("failed to send 'hello_trigger': {:?}", e));
+    finish_hello(&mut in_stream, hello_trigger, signalling, deadline)?;
 
-    let mut out_stream = TcpStream::connect(remote_addr).unwrap_or_else(|e| {
-        panic!(
-            "failed to connect ready stream to primary process at {}: {:?}",
-            remote_addr, e
-        )
-    });
+    let mut out_stream = connect_stream(remote_addr, retry, deadline)?;
     info!("Connected to main process for outgoing signals at {remote_addr}, sending 'hello_ready'",);
     out_stream
         .set_nodelay(true)
         .unwrap_or_else(|e| panic!("setting nodelay for stream failed: {e:?}"));
 
     let mut sender = MioSocketSender::new(&mut out_stream);
-    let hello_ready = Signal::HelloReady(local_agent_id);
+    let hello_ready = Signal::HelloReady((local_agent_id, topology));
     sender
         .send(&hello_ready)
         .unwrap_or_else(|e| panic!("failed to send 'hello_ready': {:?}", e));
+    finish_hello(&mut out_stream, hello_ready, signalling, deadline)?;
 
-    (in_stream, out_stream)
+    Ok((in_stream, out_stream))
+}
+
+/// Answer the authentication challenge of the primary agent to the given hello sent on the
+/// given stream, if a pre-shared key is configured, and wait for the primary agent to accept
+/// the hello until the deadline
+fn finish_hello(
+    stream: &mut TcpStream,
+    hello: Signal,
+    signalling: &SignallingConfig,
+    deadline: Instant,
+) -> Result<(), Error> {
+    let mut poll = Poll::new().map_err(|e| Error::Io((e, "failed to create poll instance")))?;
+    let mut events = signalling.events();
+    let mut receiver = MioSocketReceiver::new(stream, &mut poll, &mut events);
+    receiver
+        .register(0)
+        .map_err(|e| Error::Io((e, "failed to register stream for polling")))?;
+    #[cfg(feature = "signalling_auth")]
+    if let Some(key) = &signalling.psk {
+        receiver.answer_challenge(key, &SignalPdu::from(hello))?;
+    }
+
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    match Signal::try_from(receiver.recv_timeout(timeout)?)? {
+        Signal::HelloAccepted(_) => Ok(()),
+        Signal::HelloRejected(topology) => Err(Error::Config(format!(
+            "primary agent rejected {hello}, its topology is {topology}"
+        ))),
+        signal => Err(Error::Protocol(format!(
+            "unexpected signal {signal} in reply to {hello}"
+        ))),
+    }
+}
+
+/// Connect a stream to the primary agent, retrying with exponential backoff until the deadline
+fn connect_stream(
+    remote_addr: SocketAddr,
+    retry: &ConnectRetry,
+    deadline: Instant,
+) -> Result<TcpStream, Error> {
+    let mut backoff = retry.initial_backoff;
+    loop {
+        // Retry connecting in case of an error. This covers the scenario when the
+        // primary process has not been started yet. We do use a std::net::TcpStream
+        // instead of mio::net::TcpStream on purpose here and convert it accordingly
+        // once the connection is established. Reason for that: polling the asynchronous
+        // mio::net::TcpStream as suggested by mio's documentation turned out to behave
+        // differently cross-platform-wise
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let result = if remaining.is_zero() {
+            Err(io::Error::from(io::ErrorKind::TimedOut))
+        } else {
+            std::net::TcpStream::connect_timeout(&remote_addr, remaining)
+        };
+        match result {
+            Ok(stream) => {
+                stream
+                    .make_nonblocking()
+                    .expect("failed to make stream non-blocking");
+                return Ok(TcpStream::from_std(stream));
+            }
+            Err(e) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    error!("Failed to connect to primary process at {remote_addr}: {e}");
+                    return Err(Error::Io((e, "failed to connect to primary process")));
+                }
+                debug!("Failed to connect to primary process at {remote_addr}: {e}. Retrying in {backoff:?}");
+                thread::sleep(backoff.min(remaining));
+                backoff = (backoff * 2).min(retry.max_backoff);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{connect_to_primary, wait_next_ready, ConnectRetry, Watchdog};
+    use crate::activity::{Activity, ActivityId, ActivityIdAndBuilder};
+    use crate::error::Error;
+    use crate::signalling::{self, AgentId, CycleId, Signal, SignallingConfig};
+    use crate::timestamp::{self, timestamp};
+    use crate::worker_pool::{WorkerId, WorkerPool};
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Activity whose step never completes
+    struct Hanging(ActivityId);
+
+    impl Activity for Hanging {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            loop {
+                thread::park();
+            }
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_watchdog_fires() {
+        timestamp::initialize_for_test();
+        let activity_id = ActivityId::from(0);
+        let builders: Vec<ActivityIdAndBuilder> = vec![(
+            activity_id,
+            Box::new(|id| Box::new(Hanging(id)) as Box<dyn Activity>),
+        )];
+        let (ready_sender, ready_receiver) = signalling::channel();
+        let mut pool = WorkerPool::new(
+            HashMap::from([(WorkerId::from(0), builders)]),
+            &ready_sender,
+            None,
+            None,
+            None,
+        );
+        let mut listener = pool.listener(ready_receiver);
+        let watchdog = Watchdog {
+            cycles: 2,
+            ..Watchdog::new(Duration::from_millis(10))
+        };
+
+        // The startup completes in time
+        pool.trigger(Signal::Startup((activity_id, timestamp())));
+        assert_eq!(wait_next_ready(&mut listener, Some(&watchdog)), None);
+
+        // The hanging step is reported after the watchdog timeout
+        listener.clear_ready();
+        let start = Instant::now();
+        pool.trigger(Signal::Step((activity_id, timestamp(), CycleId(1))));
+        let stalled = wait_next_ready(&mut listener, Some(&watchdog));
+        assert_eq!(stalled, Some(vec![activity_id]));
+        assert!(start.elapsed() >= watchdog.timeout());
+    }
+
+    #[test]
+    fn test_connect_timeout() {
+        // Bind and drop a listener to get a local port nobody listens on
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let retry = ConnectRetry {
+            timeout: Duration::from_millis(300),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        };
+
+        let start = Instant::now();
+        let result = connect_to_primary(
+            AgentId::from(1),
+            addr,
+            &retry,
+            &SignallingConfig::default(),
+            None,
+        );
+        let elapsed = start.elapsed();
+        assert!(matches!(result, Err(Error::Io(_))));
+        assert!(elapsed >= retry.timeout, "gave up after {elapsed:?}");
+        assert!(elapsed < retry.timeout * 3, "gave up after {elapsed:?}");
+    }
 }
@@ -0,0 +1,89 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Async adapter for non real-time consumers of topics
+//!
+//! [`Input::recv_async`] returns a future resolving to the next payload of the topic. It is
+//! meant for consumers living in an async runtime, e.g. a bridge forwarding topics to a network
+//! service. Activities keep using the synchronous [`Input::read`].
+//!
+//! Backends able to notify on new payloads wake the future directly (see
+//! [`InputBackend::register_waker`]). For all other backends, the future is woken by a
+//! background waker bridge thread after [`POLL_INTERVAL`] and polls again. Neither way
+//! changes the sending side of a topic.
+
+use crate::com::interface::{Input, InputBackend, InputGuard};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+/// Interval in which the waker bridge wakes futures of backends without notification
+pub const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+impl<T, U: InputBackend<T>> Input<T, U> {
+    /// Wait for the next payload of the topic.
+    ///
+    /// Resolves immediately if a payload has been received since the last read.
+    pub fn recv_async(&self) -> Recv<'_, T, U> {
+        Recv { input: self }
+    }
+}
+
+/// Future returned by [`Input::recv_async`]
+#[must_use = "futures do nothing unless polled"]
+pub struct Recv<'a, T, U> {
+    input: &'a Input<T, U>,
+}
+
+impl<T, U: InputBackend<T>> Future for Recv<'_, T, U> {
+    type Output = InputGuard<T, U::Payload>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let input = self.input;
+        if let Some(guard) = input.read() {
+            return Poll::Ready(guard);
+        }
+
+        if !input.inner.register_waker(cx.waker()) {
+            wake_later(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        // A payload may have been sent before the waker got registered
+        match input.inner.receive() {
+            Some(payload) => Poll::Ready(InputGuard {
+                inner: payload,
+                _type: PhantomData,
+            }),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Wake the given waker after [`POLL_INTERVAL`] from the waker bridge thread
+fn wake_later(waker: Waker) {
+    static BRIDGE: OnceLock<Sender<Waker>> = OnceLock::new();
+
+    let bridge = BRIDGE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Waker>();
+        thread::Builder::new()
+            .name("feo-com-waker".into())
+            .spawn(move || {
+                while let Ok(waker) = receiver.recv() {
+                    thread::sleep(POLL_INTERVAL);
+                    waker.wake();
+                    receiver.try_iter().for_each(Waker::wake);
+                }
+            })
+            .expect("failed to spawn waker bridge thread");
+        sender
+    });
+    // The bridge thread never exits, so sending can't fail
+    let _ = bridge.send(waker);
+}
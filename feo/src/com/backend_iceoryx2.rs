@@ -2,211 +2,323 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::com::interface::{Input, InputGuard, Output, OutputGuard, TopicHandle};
-use crate::configuration::topics::Topic;
+use crate::com::error::ComError;
+use crate::com::interface::{
+    await_topic_with, InputBackend, OutputBackend, PayloadMut, PayloadMutUninit, ReceivedPayload,
+    SampleHeader, TopicHandle,
+};
+use crate::configuration::topics::{Overflow, Topic, TopicQos};
 use feo_log::{error, info};
 use iceoryx2::config::Config;
 use iceoryx2::node::{Node, NodeBuilder, NodeState};
 use iceoryx2::port::publisher::Publisher;
 use iceoryx2::port::subscriber::Subscriber;
-use iceoryx2::prelude::{CallbackProgression, NodeName};
+use iceoryx2::prelude::{
+    CallbackProgression, MessagingPattern, NodeName, PortFactory, Service, ServiceName,
+    UnableToDeliverStrategy,
+};
 use iceoryx2::sample::Sample;
 use iceoryx2::sample_mut::SampleMut;
 use iceoryx2::sample_mut_uninit::SampleMutUninit;
+use iceoryx2::service::builder::publish_subscribe::PublishSubscribeOpenError;
+use iceoryx2::service::builder::Builder;
 use iceoryx2::service::ipc;
-use std::marker::PhantomData;
+use iceoryx2::service::port_factory::publish_subscribe::PortFactory as PubSubPortFactory;
+use iceoryx2::service::static_config::messaging_pattern::MessagingPattern as StaticMessagingPattern;
 use std::mem::MaybeUninit;
 use std::process;
+use std::sync::Mutex;
+use std::time::Duration;
 
 pub type IpcPayload<T> = Sample<ipc::Service, T, ()>;
 pub type IpcPayloadMut<T> = SampleMut<ipc::Service, T, ()>;
 pub type IpcPayloadMutUninit<T> = SampleMutUninit<ipc::Service, MaybeUninit<T>, ()>;
 
-impl<T: std::fmt::Debug> Input<T, Subscriber<ipc::Service, T, ()>> {
-    /// Get an input handle by topic.
-    pub fn get(topic: &str) -> Self {
-        let subscriber = ipc_node()
-            .service_builder(
-                &topic
-                    .try_into()
-                    .unwrap_or_else(|_| panic!("invalid topic {topic}")),
-            )
+impl<T: std::fmt::Debug> InputBackend<T> for Subscriber<ipc::Service, T, ()> {
+    type Payload = IpcPayload<T>;
+
+    fn open(topic: &str) -> Result<Self, ComError> {
+        service_builder(topic)?
             .publish_subscribe::<T>()
             .open()
-            .unwrap_or_else(|e| panic!("failed to open subscriber for topic {topic}: {e}"))
+            .map_err(|e| open_error(topic, e))?
             .subscriber_builder()
             .create()
-            .unwrap_or_else(|_| panic!("failed to create subscriber for topic {topic}"));
+            .map_err(|e| backend_error(topic, "failed to create subscriber", e))
+    }
 
-        Self {
-            inner: subscriber,
-            _type: PhantomData,
-        }
+    fn receive(&self) -> Option<Self::Payload> {
+        Subscriber::receive(self).ok().flatten()
     }
+}
 
-    /// Get a guard with a payload to read.
-    pub fn read(&self) -> Option<InputGuard<T, IpcPayload<T>>> {
-        if let Ok(sample_opt) = self.inner.receive() {
-            return sample_opt.map(|s| InputGuard {
-                inner: s,
-                _type: PhantomData,
-            });
+impl<T: std::fmt::Debug> ReceivedPayload<T> for IpcPayload<T> {
+    /// The iceoryx2 sample header only carries the id of the publisher
+    fn header(&self) -> SampleHeader {
+        SampleHeader {
+            origin: Some(Sample::origin(self).value()),
+            ..SampleHeader::default()
         }
-
-        None
     }
 }
 
-impl<T: std::fmt::Debug> InputGuard<T, IpcPayload<T>> {
-    /// Get a reference to the payload.
-    pub fn get(&self) -> &T {
-        &self.inner
-    }
+/// Sending end of a topic
+///
+/// Keeps the port factory of the topic's service to query the state of the service without
+/// opening it again.
+#[derive(Debug)]
+pub struct IpcPublisher<T: std::fmt::Debug> {
+    publisher: Publisher<ipc::Service, T, ()>,
+    port_factory: PubSubPortFactory<ipc::Service, T, ()>,
 }
 
-impl<T: std::fmt::Debug> Output<T, Publisher<ipc::Service, T, ()>> {
-    /// Get an output handle by topic.
-    pub fn get(topic: &str) -> Self {
-        let publisher = ipc_node()
-            .service_builder(
-                &topic
-                    .try_into()
-                    .unwrap_or_else(|_| panic!("invalid topic {topic}")),
-            )
+impl<T: std::fmt::Debug> OutputBackend<T> for IpcPublisher<T> {
+    type PayloadMut = IpcPayloadMut<T>;
+    type PayloadMutUninit = IpcPayloadMutUninit<T>;
+
+    fn open(topic: &str) -> Result<Self, ComError> {
+        let port_factory = service_builder(topic)?
             .publish_subscribe::<T>()
             .open()
-            .unwrap_or_else(|e| panic!("failed to open subscriber for topic {topic}: {e}"))
+            .map_err(|e| open_error(topic, e))?;
+        let publisher = port_factory
             .publisher_builder()
+            // Without safe overflow, a full subscriber buffer keeps its payloads, see [`Overflow::Keep`]
+            .unable_to_deliver_strategy(UnableToDeliverStrategy::DiscardSample)
             .create()
-            .unwrap_or_else(|_| panic!("failed to create subscriber for topic {topic}"));
+            .map_err(|e| backend_error(topic, "failed to create publisher", e))?;
+        Ok(Self {
+            publisher,
+            port_factory,
+        })
+    }
 
-        Self {
-            inner: publisher,
-            _type: PhantomData,
-        }
+    fn loan(&self) -> Option<Self::PayloadMut>
+    where
+        T: Default,
+    {
+        self.publisher.loan().ok()
     }
-}
 
-impl<T: std::fmt::Debug + Default> Output<T, Publisher<ipc::Service, T, ()>> {
-    /// Get a guard with an initialized payload to write to.
-    ///
-    /// In most cases, you should prefer `write_uninit` to avoid the initialization cost.
-    pub fn write_init(&self) -> Option<OutputGuard<T, IpcPayloadMut<T>>> {
-        self.inner.loan().ok().map(|s| OutputGuard {
-            inner: s,
-            _type: PhantomData,
-        })
+    fn loan_uninit(&self) -> Option<Self::PayloadMutUninit> {
+        self.publisher.loan_uninit().ok()
     }
-}
 
-impl<T: std::fmt::Debug> Output<T, Publisher<ipc::Service, T, ()>> {
-    /// Get a guard with an uninitialized payload to write to.
-    pub fn write_uninit(&self) -> Option<OutputGuard<T, IpcPayloadMutUninit<T>>> {
-        self.inner.loan_uninit().ok().map(|s| OutputGuard {
-            inner: s,
-            _type: PhantomData,
-        })
+    fn subscriber_count(&self) -> usize {
+        // The publisher doesn't expose its connections, query the dynamic config of the service
+        self.port_factory.dynamic_config().number_of_subscribers()
     }
 }
 
-impl<T: std::fmt::Debug> OutputGuard<T, IpcPayloadMutUninit<T>> {
-    /// Write payload.
-    ///
-    /// To send the written payload, use `send`.
-    pub fn write_payload(self, payload: T) -> OutputGuard<T, IpcPayloadMut<T>> {
-        let inner = self.inner.write_payload(payload);
-        OutputGuard {
-            inner,
-            _type: PhantomData,
-        }
+impl<T: std::fmt::Debug> PayloadMutUninit<T> for IpcPayloadMutUninit<T> {
+    type Init = IpcPayloadMut<T>;
+
+    fn write_payload(self, payload: T) -> Self::Init {
+        SampleMutUninit::write_payload(self, payload)
     }
 
-    /// Mutably access the payload.
-    pub fn payload_mut(&mut self) -> &mut MaybeUninit<T> {
-        self.inner.payload_mut()
+    fn payload_mut(&mut self) -> &mut MaybeUninit<T> {
+        SampleMutUninit::payload_mut(self)
     }
 
-    /// Assume that the payload is initialized.
-    ///
-    /// # Safety
-    ///
-    /// The caller must ensure that the underlying `MaybeUninit` really is initialized.
-    /// Calling this when the content is not fully initialized causes immediate undefined behavior.
-    pub unsafe fn assume_init(self) -> OutputGuard<T, IpcPayloadMut<T>> {
-        let inner = self.inner.assume_init();
-        OutputGuard {
-            inner,
-            _type: PhantomData,
-        }
+    unsafe fn assume_init(self) -> Self::Init {
+        SampleMutUninit::assume_init(self)
     }
 }
 
-impl<T: std::fmt::Debug> OutputGuard<T, IpcPayloadMut<T>> {
-    /// Get a mutable reference to the payload.
-    ///
-    /// After writing the payload through the mutable reference, all `send` to send it out.
-    pub fn get_mut(&mut self) -> &mut T {
-        self.inner.payload_mut()
+impl<T: std::fmt::Debug> PayloadMut<T> for IpcPayloadMut<T> {
+    fn payload(&self) -> &T {
+        SampleMut::payload(self)
     }
 
-    /// Send payload.
-    pub fn send(self) {
-        self.inner.send().unwrap();
+    fn payload_mut(&mut self) -> &mut T {
+        SampleMut::payload_mut(self)
+    }
+
+    fn send(self) {
+        SampleMut::send(self).unwrap();
     }
 }
 
 /// Initialize topic with the given number of writers (publishers) and readers (subscribers).
+///
+/// Each reader only sees the latest payload, see [`init_topic_with_qos`].
 pub fn init_topic<T: std::fmt::Debug + 'static>(
     topic: Topic,
     writers: usize,
     readers: usize,
-) -> TopicHandle {
-    info!("Initializing topic {topic} for {writers} writers and {readers} readers");
-    let port_factory = ipc_node()
-        .service_builder(
-            &(*topic)
-                .try_into()
-                .unwrap_or_else(|_| panic!("invalid topic {topic}")),
-        )
+) -> Result<TopicHandle, ComError> {
+    init_topic_with_qos::<T>(topic, writers, readers, TopicQos::default())
+}
+
+/// Initialize topic with the given number of writers (publishers) and readers (subscribers)
+/// and the given quality of service.
+pub fn init_topic_with_qos<T: std::fmt::Debug + 'static>(
+    topic: Topic,
+    writers: usize,
+    readers: usize,
+    qos: TopicQos,
+) -> Result<TopicHandle, ComError> {
+    info!("Initializing topic {topic} for {writers} writers and {readers} readers with {qos:?}");
+    if qos.history_depth == 0 {
+        return Err(ComError::InvalidQos(topic.to_string()));
+    }
+    let port_factory = service_builder(topic)?
         .publish_subscribe::<T>()
         .max_publishers(writers)
         .max_subscribers(readers)
-        .enable_safe_overflow(true)
-        .subscriber_max_buffer_size(1)
+        .enable_safe_overflow(qos.overflow == Overflow::Drop)
+        .subscriber_max_buffer_size(qos.history_depth)
         .create()
-        .unwrap_or_else(|e| panic!("failed to create subscriber for topic {topic}: {e}"));
-    Box::new(port_factory).into()
-}
-
-fn ipc_node() -> &'static Node<ipc::Service> {
-    static ICEORYX_NODE: std::sync::OnceLock<Node<ipc::Service>> = std::sync::OnceLock::new();
-
-    ICEORYX_NODE.get_or_init(|| {
-        let config = {
-            let mut config = Config::default();
-            config.global.prefix = "feo_ipc".try_into().unwrap();
-            config
-        };
-
-        // Ensure there is no left-over state from dead nodes.
-        Node::<ipc::Service>::cleanup_dead_nodes(&config);
-        Node::<ipc::Service>::list(&config, |node_state| {
-            if let NodeState::<ipc::Service>::Dead(view) = node_state {
-                if let Err(e) = view.remove_stale_resources() {
-                    error!("Failed to clean iceoryx2 resources: {:?}", e);
-                }
-            }
-            CallbackProgression::Continue
+        .map_err(|e| backend_error(topic, "failed to create service", e))?;
+    Ok(Box::new(port_factory).into())
+}
+
+/// Initialize topic with the given number of writers (publishers) and readers (subscribers).
+///
+/// # Panics
+///
+/// Panics if the topic can't be initialized, see [`init_topic`].
+pub fn init_topic_or_panic<T: std::fmt::Debug + 'static>(
+    topic: Topic,
+    writers: usize,
+    readers: usize,
+) -> TopicHandle {
+    init_topic::<T>(topic, writers, readers)
+        .unwrap_or_else(|e| panic!("failed to initialize topic: {e}"))
+}
+
+/// Check whether the publish-subscribe service of the given topic exists
+///
+/// Uses the service discovery of iceoryx2, i.e. sees topics initialized by any process.
+pub fn topic_exists(topic: &str) -> bool {
+    let Ok(name) = service_name(topic) else {
+        return false;
+    };
+    let config = ipc_config();
+    ipc::Service::does_exist(&name, &config, MessagingPattern::PublishSubscribe)
+        .unwrap_or_else(|e| {
+            error!("Failed to look up service of topic {topic}: {e:?}");
+            false
         })
-        .expect("failed to clean iceoryx2 state");
+}
+
+/// List the topics whose publish-subscribe services exist
+///
+/// Uses the service discovery of iceoryx2, i.e. lists topics initialized by any process.
+pub fn topics() -> Vec<String> {
+    let config = ipc_config();
+    let mut topics = Vec::new();
+    let result = ipc::Service::list(&config, |service| {
+        if let StaticMessagingPattern::PublishSubscribe(_) =
+            service.static_details.messaging_pattern()
+        {
+            topics.push(service.static_details.name().as_str().to_string());
+        }
+        CallbackProgression::Continue
+    });
+    if let Err(e) = result {
+        error!("Failed to list services: {e:?}");
+    }
+    topics
+}
+
+/// Wait until the given topic has been initialized, at most for `timeout`
+///
+/// Fails with [`ComError::NotInitialized`] if the topic does not exist in time.
+pub fn await_topic(topic: &str, timeout: Duration) -> Result<(), ComError> {
+    await_topic_with(topic, timeout, topic_exists)
+}
+
+/// Convert a topic to an iceoryx2 service name
+fn service_name(topic: &str) -> Result<ServiceName, ComError> {
+    topic
+        .try_into()
+        .map_err(|_| ComError::InvalidTopic(topic.to_string()))
+}
+
+/// Convert an error opening the iceoryx2 service of the given topic
+fn open_error(topic: &str, e: PublishSubscribeOpenError) -> ComError {
+    match e {
+        PublishSubscribeOpenError::DoesNotExist => ComError::NotInitialized(topic.to_string()),
+        PublishSubscribeOpenError::IncompatibleTypes => ComError::TypeMismatch(topic.to_string()),
+        e => backend_error(topic, "failed to open service", e),
+    }
+}
+
+/// Wrap an iceoryx2 error of the given topic
+fn backend_error(topic: &str, context: &str, e: impl std::fmt::Debug) -> ComError {
+    ComError::Backend {
+        topic: topic.to_string(),
+        reason: format!("{context}: {e:?}"),
+    }
+}
+
+/// Process-global iceoryx2 node, created on first use and dropped by [`shutdown`]
+static ICEORYX_NODE: Mutex<Option<Node<ipc::Service>>> = Mutex::new(None);
+
+/// Run `f` on the process-global iceoryx2 node, creating the node if needed
+///
+/// The node is locked while `f` runs. Keep `f` short and clone what's needed out of the node,
+/// so that building services in several threads doesn't serialize on the lock.
+fn with_ipc_node<R>(f: impl FnOnce(&Node<ipc::Service>) -> R) -> R {
+    let mut node = ICEORYX_NODE.lock().unwrap();
+    f(node.get_or_insert_with(create_ipc_node))
+}
+
+/// Get a builder of the iceoryx2 service of the given topic
+///
+/// The builder keeps the node alive on its own, the node is not locked while building.
+fn service_builder(topic: &str) -> Result<Builder<ipc::Service>, ComError> {
+    let name = service_name(topic)?;
+    Ok(with_ipc_node(|node| node.service_builder(&name)))
+}
+
+/// Get the configuration of the process-global iceoryx2 node
+fn ipc_config() -> Config {
+    with_ipc_node(|node| node.config().clone())
+}
+
+/// Release the iceoryx2 resources of this process
+///
+/// Drops the process-global node, which removes its shared-memory resources instead of leaving
+/// them to the cleanup of dead nodes at the next startup. A later call to [`init_topic`] creates
+/// a new node.
+///
+/// The resources of the node are only released once all entities created via the node are gone,
+/// i.e. all [`Input`](crate::com::Input)s, [`Output`](crate::com::Output)s and
+/// [`TopicHandle`]s. The agents call this when shutting down.
+pub fn shutdown() {
+    if ICEORYX_NODE.lock().unwrap().take().is_some() {
+        info!("Released ipc node");
+    }
+}
 
-        let name =
-            NodeName::new(&format!("feo_node_{}", process::id())).expect("invalid node name");
+/// Create the iceoryx2 node of this process, cleaning up left-overs of dead nodes
+fn create_ipc_node() -> Node<ipc::Service> {
+    let config = {
+        let mut config = Config::default();
+        config.global.prefix = "feo_ipc".try_into().unwrap();
+        config
+    };
 
-        NodeBuilder::new()
-            .name(&name)
-            .config(&config)
-            .create::<ipc::Service>()
-            .expect("failed to create ipc node")
+    // Ensure there is no left-over state from dead nodes.
+    Node::<ipc::Service>::cleanup_dead_nodes(&config);
+    Node::<ipc::Service>::list(&config, |node_state| {
+        if let NodeState::<ipc::Service>::Dead(view) = node_state {
+            if let Err(e) = view.remove_stale_resources() {
+                error!("Failed to clean iceoryx2 resources: {:?}", e);
+            }
+        }
+        CallbackProgression::Continue
     })
+    .expect("failed to clean iceoryx2 state");
+
+    let name = NodeName::new(&format!("feo_node_{}", process::id())).expect("invalid node name");
+
+    NodeBuilder::new()
+        .name(&name)
+        .config(&config)
+        .create::<ipc::Service>()
+        .expect("failed to create ipc node")
 }
@@ -0,0 +1,782 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-process com backend based on per-subscriber queues
+//!
+//! This backend connects publishers and subscribers living in the same process, which is
+//! useful for single-process deployments and tests. It mimics the behavior of the topics
+//! created by the iceoryx2 backend: a sent payload is pushed to a mutex-protected queue of
+//! each subscriber, holding up to the history depth of the [`TopicQos`] of the topic, by
+//! default only the latest payload. When a queue is full, the [`Overflow`] of the topic
+//! either drops the oldest queued payload or does not deliver the new one to that subscriber.
+
+use crate::com::error::ComError;
+use crate::com::interface::{
+    await_topic_with, InputBackend, OutputBackend, PayloadMut, PayloadMutUninit, ReceivedPayload,
+    SampleHeader, TopicHandle,
+};
+use crate::configuration::topics::{Overflow, Topic, TopicQos};
+use feo_log::{debug, info};
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+#[cfg(feature = "async_com")]
+use std::task::Waker;
+use std::time::Duration;
+
+/// Received payload with the header of its sample, shared between all subscribers of a topic
+pub struct InProcPayload<T> {
+    pub(crate) payload: Arc<T>,
+    header: SampleHeader,
+}
+
+impl<T> Clone for InProcPayload<T> {
+    fn clone(&self) -> Self {
+        Self {
+            payload: Arc::clone(&self.payload),
+            header: self.header,
+        }
+    }
+}
+
+impl<T> Deref for InProcPayload<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.payload
+    }
+}
+
+impl<T> ReceivedPayload<T> for InProcPayload<T> {
+    fn header(&self) -> SampleHeader {
+        self.header
+    }
+}
+
+/// Payloads buffered for one subscriber
+type Queue<T> = Mutex<VecDeque<InProcPayload<T>>>;
+
+/// Source of the ids of the publishers of all topics
+static NEXT_PUBLISHER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Shared state of an in-process topic
+struct TopicState<T> {
+    max_publishers: usize,
+    max_subscribers: usize,
+    qos: TopicQos,
+    ports: Mutex<Ports<T>>,
+}
+
+/// Ports currently connected to an in-process topic
+struct Ports<T> {
+    publishers: usize,
+    subscribers: usize,
+    /// Buffers of the subscribers, dropped together with their subscriber
+    queues: Vec<Weak<Queue<T>>>,
+    /// Number of payloads sent so far, the sequence number of the next one
+    sequence: u64,
+    /// Wakers of async receivers waiting for the next payload
+    #[cfg(feature = "async_com")]
+    wakers: Vec<Waker>,
+}
+
+impl<T: Send + Sync + 'static> TopicState<T> {
+    /// Look up the state of the given topic
+    ///
+    /// Fails if the topic has not been initialized or has a different payload type
+    fn open(topic: &str) -> Result<Arc<Self>, ComError> {
+        let registry = registry().lock().unwrap();
+        let state = registry
+            .get(topic)
+            .and_then(Weak::upgrade)
+            .ok_or_else(|| ComError::NotInitialized(topic.to_string()))?;
+        state
+            .downcast::<Self>()
+            .map_err(|_| ComError::TypeMismatch(topic.to_string()))
+    }
+}
+
+/// Receiving end of an in-process topic
+pub struct InProcSubscriber<T> {
+    queue: Arc<Queue<T>>,
+    state: Arc<TopicState<T>>,
+}
+
+impl<T> fmt::Debug for InProcSubscriber<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InProcSubscriber").finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for InProcSubscriber<T> {
+    fn drop(&mut self) {
+        self.state.ports.lock().unwrap().subscribers -= 1;
+    }
+}
+
+impl<T: Send + Sync + 'static> InputBackend<T> for InProcSubscriber<T> {
+    type Payload = InProcPayload<T>;
+
+    fn open(topic: &str) -> Result<Self, ComError> {
+        let state = TopicState::<T>::open(topic)?;
+        let queue = Arc::new(Mutex::new(VecDeque::with_capacity(state.qos.history_depth)));
+        {
+            let mut ports = state.ports.lock().unwrap();
+            if ports.subscribers >= state.max_subscribers {
+                return Err(ComError::TooManyPorts(topic.to_string()));
+            }
+            ports.subscribers += 1;
+            ports.queues.push(Arc::downgrade(&queue));
+        }
+        Ok(Self { queue, state })
+    }
+
+    fn receive(&self) -> Option<Self::Payload> {
+        self.queue.lock().unwrap().pop_front()
+    }
+
+    #[cfg(feature = "async_com")]
+    fn register_waker(&self, waker: &Waker) -> bool {
+        let mut ports = self.state.ports.lock().unwrap();
+        if !ports.wakers.iter().any(|w| w.will_wake(waker)) {
+            ports.wakers.push(waker.clone());
+        }
+        true
+    }
+}
+
+/// Sending end of an in-process topic
+pub struct InProcPublisher<T> {
+    /// Id of the publisher, sent in the headers of its samples
+    origin: u128,
+    state: Arc<TopicState<T>>,
+}
+
+impl<T> fmt::Debug for InProcPublisher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InProcPublisher").finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for InProcPublisher<T> {
+    fn drop(&mut self) {
+        self.state.ports.lock().unwrap().publishers -= 1;
+    }
+}
+
+impl<T: Send + Sync + 'static> OutputBackend<T> for InProcPublisher<T> {
+    type PayloadMut = InProcPayloadMut<T>;
+    type PayloadMutUninit = InProcPayloadMutUninit<T>;
+
+    fn open(topic: &str) -> Result<Self, ComError> {
+        let state = TopicState::<T>::open(topic)?;
+        {
+            let mut ports = state.ports.lock().unwrap();
+            if ports.publishers >= state.max_publishers {
+                return Err(ComError::TooManyPorts(topic.to_string()));
+            }
+            ports.publishers += 1;
+        }
+        let origin = NEXT_PUBLISHER_ID.fetch_add(1, Ordering::Relaxed).into();
+        Ok(Self { origin, state })
+    }
+
+    fn loan(&self) -> Option<Self::PayloadMut>
+    where
+        T: Default,
+    {
+        Some(InProcPayloadMut {
+            payload: Box::default(),
+            origin: self.origin,
+            state: self.state.clone(),
+        })
+    }
+
+    fn loan_uninit(&self) -> Option<Self::PayloadMutUninit> {
+        Some(InProcPayloadMutUninit {
+            payload: Box::new_uninit(),
+            origin: self.origin,
+            state: self.state.clone(),
+        })
+    }
+
+    fn subscriber_count(&self) -> usize {
+        self.state.ports.lock().unwrap().subscribers
+    }
+}
+
+/// Initialized outgoing payload of an [`InProcPublisher`]
+pub struct InProcPayloadMut<T> {
+    payload: Box<T>,
+    origin: u128,
+    state: Arc<TopicState<T>>,
+}
+
+impl<T> PayloadMut<T> for InProcPayloadMut<T> {
+    fn payload(&self) -> &T {
+        &self.payload
+    }
+
+    fn payload_mut(&mut self) -> &mut T {
+        &mut self.payload
+    }
+
+    fn send(self) {
+        let qos = self.state.qos;
+        let mut ports = self.state.ports.lock().unwrap();
+        let payload: InProcPayload<T> = InProcPayload {
+            payload: self.payload.into(),
+            header: SampleHeader {
+                origin: Some(self.origin),
+                sequence: Some(ports.sequence),
+                timestamp: Some(feo_time::Instant::now()),
+            },
+        };
+        ports.sequence += 1;
+        // Drop subscribers which have gone away
+        ports.queues.retain(|queue| {
+            let Some(queue) = queue.upgrade() else {
+                return false;
+            };
+            let mut queue = queue.lock().unwrap();
+            if queue.len() < qos.history_depth {
+                queue.push_back(payload.clone());
+            } else if qos.overflow == Overflow::Drop {
+                queue.pop_front();
+                queue.push_back(payload.clone());
+            }
+            true
+        });
+        #[cfg(feature = "async_com")]
+        {
+            let wakers = std::mem::take(&mut ports.wakers);
+            drop(ports);
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// Uninitialized outgoing payload of an [`InProcPublisher`]
+pub struct InProcPayloadMutUninit<T> {
+    payload: Box<MaybeUninit<T>>,
+    origin: u128,
+    state: Arc<TopicState<T>>,
+}
+
+impl<T> PayloadMutUninit<T> for InProcPayloadMutUninit<T> {
+    type Init = InProcPayloadMut<T>;
+
+    fn write_payload(mut self, payload: T) -> Self::Init {
+        self.payload.write(payload);
+        // Safety: the payload has just been written
+        unsafe { self.assume_init() }
+    }
+
+    fn payload_mut(&mut self) -> &mut MaybeUninit<T> {
+        &mut self.payload
+    }
+
+    unsafe fn assume_init(self) -> Self::Init {
+        InProcPayloadMut {
+            payload: self.payload.assume_init(),
+            origin: self.origin,
+            state: self.state,
+        }
+    }
+}
+
+/// Initialize topic with the given number of writers (publishers) and readers (subscribers).
+///
+/// Each reader only sees the latest payload, see [`init_topic_with_qos`].
+pub fn init_topic<T: Send + Sync + 'static>(
+    topic: Topic,
+    writers: usize,
+    readers: usize,
+) -> Result<TopicHandle, ComError> {
+    init_topic_with_qos::<T>(topic, writers, readers, TopicQos::default())
+}
+
+/// Initialize topic with the given number of writers (publishers) and readers (subscribers)
+/// and the given quality of service.
+pub fn init_topic_with_qos<T: Send + Sync + 'static>(
+    topic: Topic,
+    writers: usize,
+    readers: usize,
+    qos: TopicQos,
+) -> Result<TopicHandle, ComError> {
+    info!("Initializing in-process topic {topic} for {writers} writers and {readers} readers with {qos:?}");
+    if qos.history_depth == 0 {
+        return Err(ComError::InvalidQos(topic.to_string()));
+    }
+    let state: Arc<TopicState<T>> = Arc::new(TopicState {
+        max_publishers: writers,
+        max_subscribers: readers,
+        qos,
+        ports: Mutex::new(Ports {
+            publishers: 0,
+            subscribers: 0,
+            queues: Vec::new(),
+            sequence: 0,
+            #[cfg(feature = "async_com")]
+            wakers: Vec::new(),
+        }),
+    });
+
+    let mut registry = registry().lock().unwrap();
+    let exists = registry.get(topic).is_some_and(|s| s.strong_count() > 0);
+    if exists {
+        return Err(ComError::AlreadyExists(topic.to_string()));
+    }
+    let weak: Weak<dyn Any + Send + Sync> = Arc::downgrade(&state) as Weak<_>;
+    registry.insert(topic.to_string(), weak);
+
+    Ok(Box::new(state).into())
+}
+
+/// Initialize topic with the given number of writers (publishers) and readers (subscribers).
+///
+/// # Panics
+///
+/// Panics if the topic can't be initialized, see [`init_topic`].
+pub fn init_topic_or_panic<T: Send + Sync + 'static>(
+    topic: Topic,
+    writers: usize,
+    readers: usize,
+) -> TopicHandle {
+    init_topic::<T>(topic, writers, readers)
+        .unwrap_or_else(|e| panic!("failed to initialize topic: {e}"))
+}
+
+/// Check whether the given topic has been initialized and not yet dropped
+pub fn topic_exists(topic: &str) -> bool {
+    let registry = registry().lock().unwrap();
+    registry.get(topic).is_some_and(|s| s.strong_count() > 0)
+}
+
+/// List the topics initialized so far and not dropped yet
+pub fn topics() -> Vec<String> {
+    let registry = registry().lock().unwrap();
+    registry
+        .iter()
+        .filter(|(_, state)| state.strong_count() > 0)
+        .map(|(topic, _)| topic.to_string())
+        .collect()
+}
+
+/// Wait until the given topic has been initialized, at most for `timeout`
+///
+/// Fails with [`ComError::NotInitialized`] if the topic does not exist in time.
+pub fn await_topic(topic: &str, timeout: Duration) -> Result<(), ComError> {
+    await_topic_with(topic, timeout, topic_exists)
+}
+
+/// Release the in-process topics which are no longer in use
+///
+/// Removes the registry entries of all dropped topics, i.e. topics without any
+/// [`Input`](crate::com::Input), [`Output`](crate::com::Output) or [`TopicHandle`] left.
+/// Topics still in use are kept. The agents call this when shutting down.
+pub fn shutdown() {
+    let mut registry = registry().lock().unwrap();
+    registry.retain(|topic, state| {
+        let in_use = state.strong_count() > 0;
+        if in_use {
+            debug!("Topic {topic} still in use on com shutdown");
+        }
+        in_use
+    });
+}
+
+/// Registry of all in-process topics
+///
+/// The registry holds weak references only, so a topic lives as long as its [`TopicHandle`]
+/// or any of its ports.
+fn registry() -> &'static Mutex<HashMap<String, Weak<dyn Any + Send + Sync>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Weak<dyn Any + Send + Sync>>>> =
+        OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        await_topic, init_topic, init_topic_with_qos, registry, shutdown, topic_exists,
+        InProcPublisher, InProcSubscriber,
+    };
+    use crate::activity::{Activity, ActivityId};
+    use crate::com::{ComError, Input, Output};
+    use crate::configuration::topics::{Overflow, TopicQos};
+    use std::mem::MaybeUninit;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    type TestInput<T> = Input<T, InProcSubscriber<T>>;
+    type TestOutput<T> = Output<T, InProcPublisher<T>>;
+
+    #[derive(Debug)]
+    struct CameraImage {
+        num_people: usize,
+        distance_obstacle: f64,
+    }
+
+    #[derive(Debug)]
+    struct Scene {
+        num_people: usize,
+        distance_obstacle: f64,
+    }
+
+    /// Camera activity emitting a deterministic image per step
+    struct Camera {
+        id: ActivityId,
+        output_image: TestOutput<CameraImage>,
+        counter: usize,
+    }
+
+    impl Activity for Camera {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            self.counter += 1;
+            let image = self.output_image.write_uninit().unwrap();
+            let image = image.write_payload(CameraImage {
+                num_people: self.counter,
+                distance_obstacle: 10.0 * self.counter as f64,
+            });
+            image.send();
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    /// Neural net activity inferring a scene from the camera image
+    struct NeuralNet {
+        id: ActivityId,
+        input_image: TestInput<CameraImage>,
+        output_scene: TestOutput<Scene>,
+    }
+
+    impl Activity for NeuralNet {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            let image = self.input_image.read();
+            let scene = self.output_scene.write_uninit();
+            if let (Some(image), Some(mut scene)) = (image, scene) {
+                let image = image.get();
+                let scene_ptr: &mut MaybeUninit<Scene> = scene.payload_mut();
+                scene_ptr.write(Scene {
+                    num_people: image.num_people,
+                    distance_obstacle: image.distance_obstacle,
+                });
+                // Safety: the scene has been fully initialized above
+                let scene = unsafe { scene.assume_init() };
+                scene.send();
+            }
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_camera_to_neural_net() {
+        let _image_topic = init_topic::<CameraImage>("test/inproc/camera", 1, 1).unwrap();
+        let _scene_topic = init_topic::<Scene>("test/inproc/scene", 1, 1).unwrap();
+
+        let mut camera = Camera {
+            id: 0.into(),
+            output_image: TestOutput::get("test/inproc/camera").unwrap(),
+            counter: 0,
+        };
+        let mut neural_net = NeuralNet {
+            id: 1.into(),
+            input_image: TestInput::get("test/inproc/camera").unwrap(),
+            output_scene: TestOutput::get("test/inproc/scene").unwrap(),
+        };
+        let scene_input = TestInput::<Scene>::get("test/inproc/scene").unwrap();
+
+        // Nothing has been sent yet
+        neural_net.step();
+        assert!(scene_input.read().is_none());
+
+        for cycle in 1..=3 {
+            camera.step();
+            neural_net.step();
+            let scene = scene_input.read().expect("missing scene");
+            assert_eq!(scene.get().num_people, cycle);
+            assert_eq!(scene.get().distance_obstacle, 10.0 * cycle as f64);
+            assert!(scene_input.read().is_none());
+        }
+    }
+
+    #[test]
+    fn test_write_struct() {
+        let topic = "test/inproc/write_struct";
+        let _topic = init_topic::<Scene>(topic, 1, 1).unwrap();
+        let output = TestOutput::<Scene>::get(topic).unwrap();
+        let input = TestInput::<Scene>::get(topic).unwrap();
+
+        // A scene written by the safe API is received as written
+        let scene = output.write_uninit().unwrap();
+        scene
+            .write_struct(Scene {
+                num_people: 3,
+                distance_obstacle: 12.5,
+            })
+            .send();
+        let scene = input.read().expect("missing scene");
+        assert_eq!(scene.get().num_people, 3);
+        assert_eq!(scene.get().distance_obstacle, 12.5);
+
+        // A scene populated in place is received after assuming it initialized
+        let mut scene = output.write_uninit().unwrap();
+        scene.write_with(|scene| {
+            scene.write(Scene {
+                num_people: 4,
+                distance_obstacle: 7.0,
+            });
+        });
+        // Safety: the scene has been fully initialized by the closure above
+        unsafe { scene.assume_init() }.send();
+        let scene = input.read().expect("missing scene");
+        assert_eq!(scene.get().num_people, 4);
+        assert_eq!(scene.get().distance_obstacle, 7.0);
+    }
+
+    #[test]
+    fn test_latest_payload_only() {
+        let _topic = init_topic::<u32>("test/inproc/latest", 1, 2).unwrap();
+        let output = TestOutput::<u32>::get("test/inproc/latest").unwrap();
+        let input_1 = TestInput::<u32>::get("test/inproc/latest").unwrap();
+        let input_2 = TestInput::<u32>::get("test/inproc/latest").unwrap();
+
+        for value in 0..5 {
+            output.write_uninit().unwrap().write_payload(value).send();
+        }
+        assert_eq!(*input_1.read().unwrap().get(), 4);
+        assert_eq!(*input_2.read().unwrap().get(), 4);
+        assert!(input_1.read().is_none());
+    }
+
+    #[test]
+    fn test_header() {
+        let topic = "test/inproc/header";
+        let qos = TopicQos {
+            history_depth: 3,
+            overflow: Overflow::Drop,
+        };
+        let _topic = init_topic_with_qos::<u32>(topic, 2, 1, qos).unwrap();
+        let output = TestOutput::<u32>::get(topic).unwrap();
+        let other = TestOutput::<u32>::get(topic).unwrap();
+        let input = TestInput::<u32>::get(topic).unwrap();
+
+        let start = feo_time::Instant::now();
+        output.write_uninit().unwrap().write_payload(1).send();
+        output.write_uninit().unwrap().write_payload(2).send();
+        other.write_uninit().unwrap().write_payload(3).send();
+        let first = input.read().unwrap().header();
+        let second = input.read().unwrap().header();
+        let third = input.read().unwrap().header();
+
+        // Samples are numbered in the order sent to the topic
+        assert_eq!(first.sequence, Some(0));
+        assert_eq!(second.sequence, Some(1));
+        assert_eq!(third.sequence, Some(2));
+
+        // ...tagged with their publisher and the time they have been sent
+        assert!(first.origin.is_some());
+        assert_eq!(first.origin, second.origin);
+        assert_ne!(first.origin, third.origin);
+        assert!(first.timestamp.unwrap() >= start);
+        assert!(second.timestamp.unwrap() >= first.timestamp.unwrap());
+        assert!(third.timestamp.unwrap() <= feo_time::Instant::now());
+    }
+
+    #[test]
+    fn test_qos() {
+        let depth_2 = |overflow| TopicQos {
+            history_depth: 2,
+            overflow,
+        };
+        let _keep =
+            init_topic_with_qos::<u32>("test/inproc/keep", 1, 1, depth_2(Overflow::Keep)).unwrap();
+        let _drop =
+            init_topic_with_qos::<u32>("test/inproc/drop", 1, 1, depth_2(Overflow::Drop)).unwrap();
+        for (topic, expected) in [("test/inproc/keep", [0, 1]), ("test/inproc/drop", [3, 4])] {
+            let output = TestOutput::<u32>::get(topic).unwrap();
+            let input = TestInput::<u32>::get(topic).unwrap();
+            for value in 0..5 {
+                output.write_uninit().unwrap().write_payload(value).send();
+            }
+            for value in expected {
+                assert_eq!(*input.read().unwrap().get(), value, "topic {topic}");
+            }
+            assert!(input.read().is_none());
+        }
+
+        let zero_depth = TopicQos {
+            history_depth: 0,
+            overflow: Overflow::Drop,
+        };
+        assert!(matches!(
+            init_topic_with_qos::<u32>("test/inproc/zero", 1, 1, zero_depth),
+            Err(ComError::InvalidQos(_))
+        ));
+    }
+
+    #[test]
+    fn test_loan_reuse() {
+        let _topic = init_topic::<[u8; 64]>("test/inproc/reuse", 1, 1).unwrap();
+        let output = TestOutput::<[u8; 64]>::get("test/inproc/reuse").unwrap();
+        let input = TestInput::<[u8; 64]>::get("test/inproc/reuse").unwrap();
+
+        // A released payload is handed out again by the next loan
+        let mut payload = output.loan_reuse().unwrap();
+        let addr = payload.payload_mut().as_ptr() as usize;
+        output.release(payload);
+        let payload = output.loan_reuse().unwrap();
+        let mut payload = payload.write_payload([1; 64]);
+        assert_eq!(payload.get_mut().as_ptr() as usize, addr);
+        payload.send();
+        assert_eq!(*input.read().unwrap().get(), [1; 64]);
+
+        // A sent payload is gone, the next loan is a new one
+        assert!(output.loan_reuse().is_some());
+    }
+
+    #[test]
+    fn test_discard() {
+        let _topic = init_topic::<u32>("test/inproc/discard", 1, 1).unwrap();
+        let output = TestOutput::<u32>::get("test/inproc/discard").unwrap();
+        let input = TestInput::<u32>::get("test/inproc/discard").unwrap();
+
+        // The written payload can be inspected, discarding it publishes nothing
+        let mut payload = output.write_uninit().unwrap().write_payload(1);
+        *payload.get_mut() += 1;
+        assert_eq!(*payload.get(), 2);
+        payload.discard();
+        assert!(input.read().is_none());
+
+        output.write_uninit().unwrap().write_payload(3).send();
+        assert_eq!(*input.read().unwrap().get(), 3);
+        assert!(input.read().is_none());
+    }
+
+    #[test]
+    fn test_subscriber_count() {
+        let _topic = init_topic::<u32>("test/inproc/subscribers", 1, 1).unwrap();
+        let output = TestOutput::<u32>::get("test/inproc/subscribers").unwrap();
+        assert_eq!(output.subscriber_count(), 0);
+
+        let input = TestInput::<u32>::get("test/inproc/subscribers").unwrap();
+        assert_eq!(output.subscriber_count(), 1);
+
+        drop(input);
+        assert_eq!(output.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_open_errors() {
+        let _topic = init_topic::<u32>("test/inproc/errors", 1, 1).unwrap();
+
+        // Opening a subscriber with another payload type fails instead of panicking
+        let err = TestInput::<u64>::get("test/inproc/errors").unwrap_err();
+        assert_eq!(err, ComError::TypeMismatch("test/inproc/errors".into()));
+        assert_eq!(err.to_string(), "type mismatch on topic test/inproc/errors");
+
+        // Other setup failures are reported as well
+        assert_eq!(
+            TestInput::<u32>::get("test/inproc/unknown").unwrap_err(),
+            ComError::NotInitialized("test/inproc/unknown".into())
+        );
+        let _input = TestInput::<u32>::get("test/inproc/errors").unwrap();
+        assert_eq!(
+            TestInput::<u32>::get("test/inproc/errors").unwrap_err(),
+            ComError::TooManyPorts("test/inproc/errors".into())
+        );
+        assert!(matches!(
+            init_topic::<u32>("test/inproc/errors", 1, 1),
+            Err(ComError::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let topic = init_topic::<u32>("test/inproc/shutdown", 1, 1).unwrap();
+        let output = TestOutput::<u32>::get("test/inproc/shutdown").unwrap();
+        let input = TestInput::<u32>::get("test/inproc/shutdown").unwrap();
+
+        // A topic in use is kept
+        shutdown();
+        assert!(registry()
+            .lock()
+            .unwrap()
+            .contains_key("test/inproc/shutdown"));
+
+        // Dropped topics are released and can be initialized again
+        drop((input, output, topic));
+        shutdown();
+        assert!(!registry()
+            .lock()
+            .unwrap()
+            .contains_key("test/inproc/shutdown"));
+        let _topic = init_topic::<u64>("test/inproc/shutdown", 1, 1).unwrap();
+        assert!(TestInput::<u64>::get("test/inproc/shutdown").is_ok());
+    }
+
+    #[test]
+    fn test_await_topic() {
+        let name = "test/inproc/await";
+        assert!(!topic_exists(name));
+        let result = await_topic(name, Duration::from_millis(10));
+        assert_eq!(result, Err(ComError::NotInitialized(name.to_string())));
+
+        // The topic is created shortly after starting to wait for it and kept until released
+        let (release, released) = mpsc::channel::<()>();
+        let creator = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let _handle = init_topic::<u32>(name, 1, 1).unwrap();
+            released.recv().unwrap();
+        });
+        await_topic(name, Duration::from_secs(5)).unwrap();
+        assert!(topic_exists(name));
+
+        // A dropped topic does not exist anymore
+        release.send(()).unwrap();
+        creator.join().unwrap();
+        assert!(!topic_exists(name));
+    }
+
+    #[cfg(feature = "async_com")]
+    #[test]
+    fn test_recv_async() {
+        let _topic = init_topic::<u32>("test/inproc/async", 1, 1).unwrap();
+        let input = TestInput::<u32>::get("test/inproc/async").unwrap();
+
+        let publisher = std::thread::spawn(|| {
+            let output = TestOutput::<u32>::get("test/inproc/async").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            output.write_uninit().unwrap().write_payload(42).send();
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let payload = runtime.block_on(input.recv_async());
+        assert_eq!(*payload.get(), 42);
+        publisher.join().unwrap();
+        assert!(input.read().is_none());
+    }
+}
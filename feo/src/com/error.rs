@@ -0,0 +1,45 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Errors of the com layer
+
+use std::fmt;
+
+/// Error setting up a topic or one of its ports
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComError {
+    /// The topic name is not valid for the backend
+    InvalidTopic(String),
+    /// The topic has not been initialized
+    NotInitialized(String),
+    /// The topic has been initialized already
+    AlreadyExists(String),
+    /// The topic has been initialized with another payload type
+    TypeMismatch(String),
+    /// The maximum number of publishers or subscribers of the topic is reached
+    TooManyPorts(String),
+    /// The quality of service is not supported for the topic
+    InvalidQos(String),
+    /// The backend failed to set up the topic or port
+    Backend { topic: String, reason: String },
+}
+
+impl fmt::Display for ComError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComError::InvalidTopic(topic) => write!(f, "invalid topic {topic}"),
+            ComError::NotInitialized(topic) => write!(f, "topic {topic} not initialized"),
+            ComError::AlreadyExists(topic) => write!(f, "topic {topic} already exists"),
+            ComError::TypeMismatch(topic) => write!(f, "type mismatch on topic {topic}"),
+            ComError::TooManyPorts(topic) => write!(f, "too many ports on topic {topic}"),
+            ComError::InvalidQos(topic) => {
+                write!(f, "invalid quality of service for topic {topic}")
+            }
+            ComError::Backend { topic, reason } => write!(f, "topic {topic}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ComError {}
@@ -3,23 +3,54 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Topic based communication
+//!
+//! Activities access topics through [`Input`] and [`Output`], which are generic over the com
+//! backend implementing [`InputBackend`] and [`OutputBackend`]. The backend used by the
+//! [`ActivityInput`] and [`ActivityOutput`] aliases is selected by feature:
+//! `ipc_iceoryx2` for inter-process communication via iceoryx2, or `ipc_inprocess`
+//! for communication within a single process. If both are enabled, iceoryx2 takes precedence.
 
+mod error;
 mod interface;
 
+#[cfg(feature = "async_com")]
+mod async_input;
+
 #[cfg(feature = "ipc_iceoryx2")]
 mod backend_iceoryx2;
 
+#[cfg(any(feature = "ipc_inprocess", test))]
+pub mod backend_inprocess;
+
 #[cfg(feature = "ipc_iceoryx2")]
-use ::iceoryx2::{
-    port::{publisher::Publisher, subscriber::Subscriber},
-    service::ipc,
+use ::iceoryx2::{port::subscriber::Subscriber, service::ipc};
+#[cfg(feature = "async_com")]
+pub use async_input::{Recv, POLL_INTERVAL};
+#[cfg(all(feature = "ipc_inprocess", not(feature = "ipc_iceoryx2")))]
+use backend_inprocess::{InProcPublisher, InProcSubscriber};
+pub use error::ComError;
+pub use interface::{
+    Input, InputBackend, InputGuard, Output, OutputBackend, OutputGuard, PayloadMut,
+    PayloadMutUninit, ReceivedPayload, SampleHeader, TopicHandle, TOPIC_POLL_INTERVAL,
 };
-pub use interface::{Input, InputGuard, Output, OutputGuard, TopicHandle};
 
 #[cfg(feature = "ipc_iceoryx2")]
 pub type ActivityInput<T> = Input<T, Subscriber<ipc::Service, T, ()>>;
 #[cfg(feature = "ipc_iceoryx2")]
-pub type ActivityOutput<T> = Output<T, Publisher<ipc::Service, T, ()>>;
+pub type ActivityOutput<T> = Output<T, IpcPublisher<T>>;
+
+#[cfg(all(feature = "ipc_inprocess", not(feature = "ipc_iceoryx2")))]
+pub type ActivityInput<T> = Input<T, InProcSubscriber<T>>;
+#[cfg(all(feature = "ipc_inprocess", not(feature = "ipc_iceoryx2")))]
+pub type ActivityOutput<T> = Output<T, InProcPublisher<T>>;
 
 #[cfg(feature = "ipc_iceoryx2")]
-pub use backend_iceoryx2::init_topic;
+pub use backend_iceoryx2::{
+    await_topic, init_topic, init_topic_or_panic, init_topic_with_qos, shutdown, topic_exists,
+    topics, IpcPublisher,
+};
+#[cfg(all(feature = "ipc_inprocess", not(feature = "ipc_iceoryx2")))]
+pub use backend_inprocess::{
+    await_topic, init_topic, init_topic_or_panic, init_topic_with_qos, shutdown, topic_exists,
+    topics,
+};
@@ -0,0 +1,409 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Human-readable description of the resolved configuration of a primary agent
+//!
+//! The description lists the agents with their workers and activities, the dependency graph
+//! of each task chain and the topic wiring, for operators to verify a multi-agent setup
+//! before a run. The primary agent logs it at startup.
+
+use crate::activity::ActivityId;
+use crate::agent::primary::PrimaryAgentConfig;
+use crate::configuration::topics::Direction;
+use crate::signalling::AgentId;
+use crate::worker_pool::WorkerId;
+use feo_time::Duration;
+use std::fmt::{self, Display, Write as _};
+
+/// Description of the configuration of a primary agent, see [`describe`]
+///
+/// The [`Display`] implementation renders a human-readable text, [`Description::to_json`]
+/// a JSON document. All lists are sorted by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Description {
+    primary: AgentId,
+    recorders: Vec<AgentId>,
+    agents: Vec<(AgentId, Workers)>,
+    task_chains: Vec<TaskChainDescription>,
+    topics: Vec<TopicDescription>,
+}
+
+/// Activities of each worker of an agent
+type Workers = Vec<(WorkerId, Vec<ActivityId>)>;
+
+/// Description of a task chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskChainDescription {
+    name: String,
+    cycle_time: Duration,
+    activities: Vec<ActivityDescription>,
+}
+
+/// Description of an activity within its task chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ActivityDescription {
+    id: ActivityId,
+    depends: Vec<ActivityId>,
+    optional_depends: Vec<ActivityId>,
+    rate_divisor: usize,
+}
+
+/// Description of the wiring of a topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TopicDescription {
+    topic: String,
+    writers: Vec<ActivityId>,
+    readers: Vec<ActivityId>,
+}
+
+/// Describe the given configuration of a primary agent
+pub fn describe(config: &PrimaryAgentConfig) -> Description {
+    let mut recorders: Vec<AgentId> = config
+        .recorders
+        .iter()
+        .flatten()
+        .chain(config.local_recorders.keys())
+        .copied()
+        .collect();
+    recorders.sort_by_key(|id| usize::from(*id));
+
+    let mut agents: Vec<_> = config
+        .agent_map
+        .iter()
+        .map(|(agent_id, workers)| {
+            let mut workers: Vec<_> = workers
+                .iter()
+                .map(|(worker_id, activities)| (*worker_id, sorted(activities)))
+                .collect();
+            workers.sort_by_key(|(id, _)| usize::from(*id));
+            (*agent_id, workers)
+        })
+        .collect();
+    agents.sort_by_key(|(id, _)| usize::from(*id));
+
+    let task_chains = config
+        .task_chains
+        .iter()
+        .map(|chain| {
+            let mut activities: Vec<_> = chain
+                .activity_depends
+                .iter()
+                .map(|(id, depends)| ActivityDescription {
+                    id: *id,
+                    depends: sorted(depends),
+                    optional_depends: chain
+                        .optional_depends
+                        .get(id)
+                        .map(|deps| sorted(deps))
+                        .unwrap_or_default(),
+                    rate_divisor: chain.rate_divisors.get(id).copied().unwrap_or(1),
+                })
+                .collect();
+            activities.sort_by_key(|activity| activity.id);
+            TaskChainDescription {
+                name: chain.name.clone(),
+                cycle_time: chain.cycle_time,
+                activities,
+            }
+        })
+        .collect();
+
+    let mut topics: Vec<_> = config
+        .topics
+        .iter()
+        .map(|(topic, peers)| {
+            let peers_in = |direction| {
+                let mut peers: Vec<_> = peers
+                    .iter()
+                    .filter(|(_, d)| *d == direction)
+                    .map(|(id, _)| *id)
+                    .collect();
+                peers.sort();
+                peers
+            };
+            TopicDescription {
+                topic: topic.to_string(),
+                writers: peers_in(Direction::Outgoing),
+                readers: peers_in(Direction::Incoming),
+            }
+        })
+        .collect();
+    topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+    Description {
+        primary: config.agent_id,
+        recorders,
+        agents,
+        task_chains,
+        topics,
+    }
+}
+
+impl Description {
+    /// Render the description as a JSON document
+    ///
+    /// Ids are given as numbers, cycle times in nanoseconds.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            "{{\"primary\":{},\"recorders\":{}",
+            usize::from(self.primary),
+            json_ids(&self.recorders)
+        );
+
+        json.push_str(",\"agents\":[");
+        for (i, (agent_id, workers)) in self.agents.iter().enumerate() {
+            let _ = write!(
+                json,
+                "{}{{\"id\":{},\"workers\":[",
+                json_comma(i),
+                usize::from(*agent_id)
+            );
+            for (j, (worker_id, activities)) in workers.iter().enumerate() {
+                let _ = write!(
+                    json,
+                    "{}{{\"id\":{},\"activities\":{}}}",
+                    json_comma(j),
+                    usize::from(*worker_id),
+                    json_ids(activities)
+                );
+            }
+            json.push_str("]}");
+        }
+
+        json.push_str("],\"task_chains\":[");
+        for (i, chain) in self.task_chains.iter().enumerate() {
+            let _ = write!(
+                json,
+                "{}{{\"name\":{},\"cycle_time_ns\":{},\"activities\":[",
+                json_comma(i),
+                json_string(&chain.name),
+                chain.cycle_time.as_nanos()
+            );
+            for (j, activity) in chain.activities.iter().enumerate() {
+                let _ = write!(
+                    json,
+                    "{}{{\"id\":{},\"depends\":{},\"optional_depends\":{},\"rate_divisor\":{}}}",
+                    json_comma(j),
+                    usize::from(activity.id),
+                    json_ids(&activity.depends),
+                    json_ids(&activity.optional_depends),
+                    activity.rate_divisor
+                );
+            }
+            json.push_str("]}");
+        }
+
+        json.push_str("],\"topics\":[");
+        for (i, topic) in self.topics.iter().enumerate() {
+            let _ = write!(
+                json,
+                "{}{{\"topic\":{},\"writers\":{},\"readers\":{}}}",
+                json_comma(i),
+                json_string(&topic.topic),
+                json_ids(&topic.writers),
+                json_ids(&topic.readers)
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Primary agent {}", self.primary)?;
+        if !self.recorders.is_empty() {
+            write!(f, ", recorders {}", List(&self.recorders))?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Agents:")?;
+        for (agent_id, workers) in &self.agents {
+            writeln!(f, "  {agent_id}")?;
+            for (worker_id, activities) in workers {
+                writeln!(f, "    {worker_id}: {}", List(activities))?;
+            }
+        }
+
+        for chain in &self.task_chains {
+            writeln!(
+                f,
+                "Task chain {} (cycle time {:?}):",
+                chain.name, chain.cycle_time
+            )?;
+            for activity in &chain.activities {
+                write!(f, "  {}", activity.id)?;
+                if !activity.depends.is_empty() {
+                    write!(f, " <- {}", List(&activity.depends))?;
+                }
+                if !activity.optional_depends.is_empty() {
+                    write!(f, " <- optional {}", List(&activity.optional_depends))?;
+                }
+                if activity.rate_divisor != 1 {
+                    write!(f, " (every {} cycles)", activity.rate_divisor)?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        if !self.topics.is_empty() {
+            writeln!(f, "Topics:")?;
+            for topic in &self.topics {
+                writeln!(
+                    f,
+                    "  {}: {} -> {}",
+                    topic.topic,
+                    List(&topic.writers),
+                    List(&topic.readers)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated list of ids
+struct List<'a, T>(&'a [T]);
+
+impl<T: Display> Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            write!(f, "{}{item}", comma(i))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sorted copy of the given activity ids
+fn sorted(ids: &[ActivityId]) -> Vec<ActivityId> {
+    let mut ids = ids.to_vec();
+    ids.sort();
+    ids
+}
+
+/// Separator in front of the item with the given index
+fn comma(index: usize) -> &'static str {
+    if index == 0 {
+        ""
+    } else {
+        ", "
+    }
+}
+
+/// Separator in front of the JSON array item with the given index
+fn json_comma(index: usize) -> &'static str {
+    if index == 0 {
+        ""
+    } else {
+        ","
+    }
+}
+
+/// JSON array of the given ids as numbers
+fn json_ids<T: Copy + Into<usize>>(ids: &[T]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| (*id).into().to_string()).collect();
+    format!("[{}]", ids.join(","))
+}
+
+/// JSON string literal of the given text
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use super::describe;
+    use crate::agent::primary::{PrimaryAgentConfig, TaskChainConfig};
+    use crate::configuration::topics::Direction;
+    use crate::signalling::{channel, AgentId, SignallingConfig};
+    use crate::worker_pool::WorkerId;
+    use feo_time::Duration;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_describe() {
+        let (intra_ready_sender, intra_ready_receiver) = channel();
+        let config = PrimaryAgentConfig {
+            agent_id: AgentId::from(0),
+            bind_addrs: vec![],
+            task_chains: vec![TaskChainConfig {
+                name: "main".into(),
+                cycle_time: Duration::from_millis(50),
+                watchdog_timeout: None,
+                flush_timeout: None,
+                activity_depends: HashMap::from([
+                    (0.into(), vec![]),
+                    (1.into(), vec![0.into()]),
+                    (2.into(), vec![1.into(), 0.into()]),
+                ]),
+                optional_depends: HashMap::from([(1.into(), vec![2.into()])]),
+                rate_divisors: HashMap::from([(2.into(), 2)]),
+                activity_deadlines: HashMap::new(),
+            }],
+            serial_startup: false,
+            accept_reconnects: false,
+            dry_run: false,
+            agent_map: HashMap::from([
+                (
+                    AgentId::from(0),
+                    HashMap::from([(WorkerId::from(0), vec![1.into(), 0.into()])]),
+                ),
+                (
+                    AgentId::from(1),
+                    HashMap::from([(WorkerId::from(1), vec![2.into()])]),
+                ),
+            ]),
+            recorders: Some(HashSet::from([AgentId::from(2)])),
+            local_recorders: HashMap::new(),
+            local_worker_pool: None,
+            intra_ready_sender,
+            intra_ready_receiver,
+            signalling: SignallingConfig::default(),
+            topics: vec![(
+                "test/describe",
+                vec![
+                    (1.into(), Direction::Incoming),
+                    (0.into(), Direction::Outgoing),
+                    (2.into(), Direction::Incoming),
+                ],
+            )],
+        };
+
+        let description = describe(&config);
+        assert_eq!(
+            description.to_string(),
+            "Primary agent A0, recorders A2\n\
+             Agents:\n  \
+               A0\n    \
+                 W0: A0, A1\n  \
+               A1\n    \
+                 W1: A2\n\
+             Task chain main (cycle time 50ms):\n  \
+               A0\n  \
+               A1 <- A0 <- optional A2\n  \
+               A2 <- A0, A1 (every 2 cycles)\n\
+             Topics:\n  \
+               test/describe: A0 -> A1, A2\n"
+        );
+        assert_eq!(
+            description.to_json(),
+            r#"{"primary":0,"recorders":[2],"agents":[{"id":0,"workers":[{"id":0,"activities":[0,1]}]},{"id":1,"workers":[{"id":1,"activities":[2]}]}],"task_chains":[{"name":"main","cycle_time_ns":50000000,"activities":[{"id":0,"depends":[],"optional_depends":[],"rate_divisor":1},{"id":1,"depends":[0],"optional_depends":[2],"rate_divisor":1},{"id":2,"depends":[0,1],"optional_depends":[],"rate_divisor":2}]}],"topics":[{"topic":"test/describe","writers":[0],"readers":[1,2]}]}"#
+        );
+    }
+}
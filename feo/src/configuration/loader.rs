@@ -0,0 +1,433 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Loader of a deployment from a declarative TOML file
+//!
+//! The file describes the agents with their workers and activities, the dependencies of the
+//! activities, the topic wiring and the cycle time:
+//!
+//! ```toml
+//! primary = 100
+//! cycle_time_ms = 10
+//! recorders = [900]
+//!
+//! [[agents]]
+//! id = 100
+//! workers = [
+//!     { id = 40, activities = [{ id = 0, type = "camera" }] },
+//! ]
+//!
+//! [[agents]]
+//! id = 101
+//! workers = [
+//!     { id = 41, activities = [{ id = 1, type = "neural_net", depends = [0] }] },
+//! ]
+//!
+//! [[topics]]
+//! name = "feo/com/vehicle/camera/front"
+//! writers = [0]
+//! readers = [1]
+//! ```
+//!
+//! Activities are still constructed in Rust: the `type` of each activity names a factory
+//! registered in an [`ActivityRegistry`], which builds the worker pool assignments of an agent,
+//! see [`Deployment::assignments`].
+
+use crate::activity::{Activity, ActivityId};
+use crate::configuration::primary_agent::ActivityDependencies;
+use crate::configuration::topics::{Direction, Topic, TopicWiring};
+use crate::configuration::worker_pool::WorkerPoolAssignments;
+use crate::error::Error;
+use crate::signalling::AgentId;
+use crate::worker_pool::WorkerId;
+use feo_time::Duration;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Factory building an activity with the given id
+type ActivityFactory = Arc<dyn Fn(ActivityId) -> Box<dyn Activity> + Send + Sync>;
+
+/// Factories of activities by the type names used in the configuration file
+#[derive(Default)]
+pub struct ActivityRegistry {
+    factories: HashMap<String, ActivityFactory>,
+}
+
+impl ActivityRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the factory of the given activity type, replacing a previous one
+    pub fn register<F>(&mut self, activity_type: &str, factory: F) -> &mut Self
+    where
+        F: Fn(ActivityId) -> Box<dyn Activity> + Send + Sync + 'static,
+    {
+        self.factories
+            .insert(activity_type.to_string(), Arc::new(factory));
+        self
+    }
+}
+
+/// Deployment described by a configuration file
+#[derive(Debug)]
+pub struct Deployment {
+    /// Id of the primary agent
+    pub primary: AgentId,
+    /// Cycle time of the task chain
+    pub cycle_time: Duration,
+    /// Ids of the recorders to expect
+    pub recorders: Vec<AgentId>,
+    /// Activities of each worker of each agent, see
+    /// [`primary_agent::Builder::agent_map`](super::primary_agent::Builder::agent_map)
+    pub agent_map: HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>,
+    /// Dependencies of each activity, see
+    /// [`primary_agent::Builder::activity_dependencies`](super::primary_agent::Builder::activity_dependencies)
+    pub activity_dependencies: ActivityDependencies,
+    /// Wiring of the topics, see
+    /// [`primary_agent::Builder::topic_wiring`](super::primary_agent::Builder::topic_wiring)
+    pub topics: Vec<TopicWiring>,
+    /// Type of each activity
+    activity_types: HashMap<ActivityId, String>,
+}
+
+impl Deployment {
+    /// Load the deployment from the given file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Io((e, "failed to read configuration file")))?;
+        Self::parse(&content)
+    }
+
+    /// Parse the deployment from the given content of a configuration file
+    ///
+    /// Fails if the content is malformed or inconsistent, e.g. if an id is used twice, the
+    /// primary agent is not one of the agents, a recorder uses the id of an agent or a
+    /// dependency or topic peer is not an activity of the deployment. The topic names are
+    /// leaked, as topics are named by static strings.
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let file: File =
+            toml::from_str(content).map_err(|e| Error::Config(format!("malformed file: {e}")))?;
+
+        let mut agent_map = HashMap::new();
+        let mut activity_dependencies = ActivityDependencies::new();
+        let mut activity_types = HashMap::new();
+        let mut worker_ids = HashSet::new();
+        for agent in file.agents {
+            let agent_id = AgentId::from(agent.id);
+            let mut workers = HashMap::new();
+            for worker in agent.workers {
+                let worker_id = WorkerId::from(worker.id);
+                if !worker_ids.insert(worker_id) {
+                    return Err(Error::Config(format!("duplicate worker {worker_id}")));
+                }
+                let mut activities = Vec::new();
+                for activity in worker.activities {
+                    let activity_id = ActivityId::from(activity.id);
+                    if activity_types.contains_key(&activity_id) {
+                        return Err(Error::Config(format!("duplicate activity {activity_id}")));
+                    }
+                    let depends = activity.depends.into_iter().map(ActivityId::from);
+                    activity_dependencies.insert(activity_id, depends.collect());
+                    activity_types.insert(activity_id, activity.activity_type);
+                    activities.push(activity_id);
+                }
+                workers.insert(worker_id, activities);
+            }
+            if agent_map.insert(agent_id, workers).is_some() {
+                return Err(Error::Config(format!("duplicate agent {agent_id}")));
+            }
+        }
+
+        let primary = AgentId::from(file.primary);
+        if !agent_map.contains_key(&primary) {
+            return Err(Error::Config(format!(
+                "primary agent {primary} is not one of the agents"
+            )));
+        }
+        let mut recorders = Vec::new();
+        for recorder in file.recorders.into_iter().map(AgentId::from) {
+            if agent_map.contains_key(&recorder) {
+                return Err(Error::Config(format!(
+                    "recorder {recorder} uses the id of an agent"
+                )));
+            }
+            if recorders.contains(&recorder) {
+                return Err(Error::Config(format!("duplicate recorder {recorder}")));
+            }
+            recorders.push(recorder);
+        }
+
+        let unknown = |activity_id: &ActivityId| !activity_types.contains_key(activity_id);
+        for (activity_id, depends) in &activity_dependencies {
+            if let Some(dependency) = depends.iter().find(|id| unknown(id)) {
+                return Err(Error::Config(format!(
+                    "activity {activity_id} depends on unknown activity {dependency}"
+                )));
+            }
+        }
+
+        let mut topics = Vec::new();
+        for topic in file.topics {
+            let writers = topic
+                .writers
+                .into_iter()
+                .map(|id| (id, Direction::Outgoing));
+            let readers = topic
+                .readers
+                .into_iter()
+                .map(|id| (id, Direction::Incoming));
+            let peers: Vec<_> = writers
+                .chain(readers)
+                .map(|(id, direction)| (ActivityId::from(id), direction))
+                .collect();
+            if let Some((peer, _)) = peers.iter().find(|(id, _)| unknown(id)) {
+                return Err(Error::Config(format!(
+                    "unknown activity {peer} on topic {}",
+                    topic.name
+                )));
+            }
+            let name: Topic = Box::leak(topic.name.into_boxed_str());
+            topics.push((name, peers));
+        }
+
+        Ok(Self {
+            primary,
+            cycle_time: Duration::from_millis(file.cycle_time_ms),
+            recorders,
+            agent_map,
+            activity_dependencies,
+            topics,
+            activity_types,
+        })
+    }
+
+    /// Worker pool assignments of the given agent, with the activities built by the factories
+    /// of their types
+    ///
+    /// Fails if the agent is not part of the deployment or the type of one of its activities
+    /// has not been registered.
+    pub fn assignments(
+        &self,
+        agent_id: AgentId,
+        registry: &ActivityRegistry,
+    ) -> Result<WorkerPoolAssignments, Error> {
+        let workers = self
+            .agent_map
+            .get(&agent_id)
+            .ok_or_else(|| Error::Config(format!("unknown agent {agent_id}")))?;
+        let mut assignments = WorkerPoolAssignments::new();
+        for (worker_id, activities) in workers {
+            let mut builders = Vec::new();
+            for activity_id in activities {
+                let activity_type = &self.activity_types[activity_id];
+                let factory = registry.factories.get(activity_type).ok_or_else(|| {
+                    Error::Config(format!(
+                        "unknown type {activity_type} of activity {activity_id}"
+                    ))
+                })?;
+                let factory = Arc::clone(factory);
+                builders.push((*activity_id, Box::new(move |id| factory(id)) as Box<_>));
+            }
+            assignments.insert(*worker_id, builders);
+        }
+        Ok(assignments)
+    }
+}
+
+/// Content of a configuration file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    primary: usize,
+    cycle_time_ms: u64,
+    #[serde(default)]
+    recorders: Vec<usize>,
+    agents: Vec<AgentEntry>,
+    #[serde(default)]
+    topics: Vec<TopicEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentEntry {
+    id: usize,
+    workers: Vec<WorkerEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkerEntry {
+    id: usize,
+    activities: Vec<ActivityEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActivityEntry {
+    id: usize,
+    #[serde(rename = "type")]
+    activity_type: String,
+    #[serde(default)]
+    depends: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TopicEntry {
+    name: String,
+    #[serde(default)]
+    writers: Vec<usize>,
+    #[serde(default)]
+    readers: Vec<usize>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ActivityRegistry, Deployment};
+    use crate::activity::{Activity, ActivityId};
+    use crate::configuration::topics::Direction;
+    use crate::error::Error;
+    use crate::signalling::AgentId;
+    use feo_time::Duration;
+    use std::collections::HashMap;
+    use std::{env, fs, process};
+
+    /// Activity doing nothing
+    struct Nop(ActivityId);
+
+    impl Activity for Nop {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {}
+
+        fn shutdown(&mut self) {}
+    }
+
+    fn nop(id: ActivityId) -> Box<dyn Activity> {
+        Box::new(Nop(id))
+    }
+
+    const CONFIG: &str = r#"
+primary = 100
+cycle_time_ms = 10
+
+[[agents]]
+id = 100
+workers = [
+    { id = 40, activities = [{ id = 0, type = "nop" }, { id = 1, type = "nop" }] },
+]
+
+[[agents]]
+id = 101
+workers = [
+    { id = 41, activities = [{ id = 2, type = "nop", depends = [0, 1] }] },
+    { id = 42, activities = [{ id = 3, type = "other", depends = [2] }] },
+]
+
+[[topics]]
+name = "test/loader/scene"
+writers = [2]
+readers = [3]
+"#;
+
+    #[test]
+    fn test_load() {
+        let path = env::temp_dir().join(format!("feo-test-loader-{}.toml", process::id()));
+        fs::write(&path, CONFIG).unwrap();
+        let deployment = Deployment::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(deployment.primary, AgentId::new(100));
+        assert_eq!(deployment.cycle_time, Duration::from_millis(10));
+        assert!(deployment.recorders.is_empty());
+        assert_eq!(
+            deployment.agent_map,
+            HashMap::from([
+                (
+                    100.into(),
+                    HashMap::from([(40.into(), vec![0.into(), 1.into()])])
+                ),
+                (
+                    101.into(),
+                    HashMap::from([(41.into(), vec![2.into()]), (42.into(), vec![3.into()])])
+                ),
+            ])
+        );
+        assert_eq!(
+            deployment.activity_dependencies,
+            HashMap::from([
+                (0.into(), vec![]),
+                (1.into(), vec![]),
+                (2.into(), vec![0.into(), 1.into()]),
+                (3.into(), vec![2.into()]),
+            ])
+        );
+        assert_eq!(
+            deployment.topics,
+            [(
+                "test/loader/scene",
+                vec![
+                    (2.into(), Direction::Outgoing),
+                    (3.into(), Direction::Incoming)
+                ]
+            )]
+        );
+
+        // Activities are built by the factories of their types
+        let mut registry = ActivityRegistry::new();
+        registry.register("nop", nop);
+        let assignments = deployment.assignments(100.into(), &registry).unwrap();
+        let builders = assignments.into_values().next().unwrap();
+        let activities: Vec<_> = builders
+            .into_iter()
+            .map(|(id, builder)| builder(id).id())
+            .collect();
+        assert_eq!(activities, [0.into(), 1.into()]);
+        assert!(matches!(
+            deployment.assignments(101.into(), &registry),
+            Err(Error::Config(e)) if e == "unknown type other of activity A3"
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unknown = CONFIG.replace("depends = [2]", "depends = [7]");
+        assert!(matches!(
+            Deployment::parse(&unknown),
+            Err(Error::Config(e)) if e == "activity A3 depends on unknown activity A7"
+        ));
+        let duplicate = CONFIG.replace("id = 3", "id = 2");
+        assert!(matches!(
+            Deployment::parse(&duplicate),
+            Err(Error::Config(e)) if e == "duplicate activity A2"
+        ));
+        let no_primary = CONFIG.replace("primary = 100", "primary = 102");
+        assert!(matches!(
+            Deployment::parse(&no_primary),
+            Err(Error::Config(e)) if e == "primary agent A102 is not one of the agents"
+        ));
+        let recorder_clash = CONFIG.replace(
+            "cycle_time_ms = 10",
+            "cycle_time_ms = 10\nrecorders = [101]",
+        );
+        assert!(matches!(
+            Deployment::parse(&recorder_clash),
+            Err(Error::Config(e)) if e == "recorder A101 uses the id of an agent"
+        ));
+        let malformed = CONFIG.replace("cycle_time_ms", "cycle_time");
+        assert!(matches!(
+            Deployment::parse(&malformed),
+            Err(Error::Config(e)) if e.starts_with("malformed file")
+        ));
+    }
+}
@@ -2,7 +2,11 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod describe;
+#[cfg(feature = "config_loader")]
+pub mod loader;
 pub mod primary_agent;
 pub mod secondary_agent;
 pub mod topics;
+pub mod topology;
 pub mod worker_pool;
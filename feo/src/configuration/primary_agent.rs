@@ -5,8 +5,9 @@
 //! Primary agent builder
 
 use crate::activity::ActivityId;
-use crate::agent::primary::{PrimaryAgent, PrimaryAgentConfig};
-use crate::signalling::{AgentId, IntraProcReceiver, IntraProcSender, Signal};
+use crate::agent::primary::{PrimaryAgent, PrimaryAgentConfig, TaskChainConfig};
+use crate::configuration::topics::{validate_wiring, DeclaredTopics, TopicWiring};
+use crate::signalling::{AgentId, IntraProcReceiver, IntraProcSender, Signal, SignallingConfig};
 use crate::worker_pool::{WorkerId, WorkerPool};
 use feo_time::Duration;
 use std::collections::{HashMap, HashSet};
@@ -111,13 +112,27 @@ pub type AgentConfig = (AgentId, WorkerPoolConfigInfo);
 #[derive(Default)]
 pub struct Builder {
     pub id: Option<AgentId>,
-    pub bind: Option<SocketAddr>,
+    pub bind: Vec<SocketAddr>,
     pub agent_map: Option<HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>>,
     pub recorders: Option<HashSet<AgentId>>,
+    pub hosted_recorders: HashSet<AgentId>,
+    pub local_recorders: HashMap<AgentId, IntraProcSender<Signal>>,
     pub activity_deps: Option<ActivityDependencies>,
+    pub optional_deps: ActivityDependencies,
+    pub rate_divisors: HashMap<ActivityId, usize>,
+    pub activity_deadlines: HashMap<ActivityId, Duration>,
     pub feo_cycle_time: Option<Duration>,
+    pub watchdog_timeout: Option<Duration>,
+    pub flush_timeout: Option<Duration>,
+    pub task_chains: Vec<TaskChainConfig>,
+    pub serial_startup: bool,
+    pub accept_reconnects: bool,
+    pub dry_run: bool,
     pub worker_pool: Option<WorkerPool>,
     pub intra_proc_ready_channel: Option<(IntraProcSender<Signal>, IntraProcReceiver<Signal>)>,
+    pub signalling: Option<SignallingConfig>,
+    pub topics: Vec<TopicWiring>,
+    pub declared_topics: Vec<DeclaredTopics>,
 }
 
 impl Builder {
@@ -133,12 +148,82 @@ impl Builder {
         self
     }
 
+    /// Set the time after which a stalled task chain cycle is reported
+    ///
+    /// Defaults to ten times the feo cycle time.
+    pub fn watchdog_timeout(mut self, watchdog_timeout: Duration) -> Self {
+        self.watchdog_timeout = Some(watchdog_timeout);
+        self
+    }
+
+    /// Set the time to wait for the recorders to signal ready after the end of a cycle
+    ///
+    /// Recorders not having signalled ready by then are logged and disconnected, the cycle
+    /// finishes without them. Defaults to the watchdog timeout.
+    pub fn flush_timeout(mut self, flush_timeout: Duration) -> Self {
+        self.flush_timeout = Some(flush_timeout);
+        self
+    }
+
+    /// Start up the activities one after the other in the order of their ids,
+    /// waiting for each to be ready before starting up the next one
+    ///
+    /// Defaults to starting up all activities concurrently.
+    pub fn serial_startup(mut self, serial_startup: bool) -> Self {
+        self.serial_startup = serial_startup;
+        self
+    }
+
+    /// Accept secondary agents reconnecting while running, e.g. after being restarted
+    ///
+    /// A reconnecting agent is synchronized again and its activities are started up again
+    /// before they are triggered. Until it reconnects, its activities are not triggered and
+    /// their task chains stall. Defaults to failing with [`Error::Disconnected`] once a
+    /// secondary agent cannot be sent to anymore.
+    ///
+    /// [`Error::Disconnected`]: crate::error::Error::Disconnected
+    pub fn accept_reconnects(mut self, accept_reconnects: bool) -> Self {
+        self.accept_reconnects = accept_reconnects;
+        self
+    }
+
+    /// Validate the deployment without running any cycles
+    ///
+    /// The agent connects and synchronizes all remote agents and recorders, checking their
+    /// topology hashes, starts up all activities and broadcasts the system ready. It then shuts
+    /// everything down again and returns, without stepping any activity. Useful for smoke
+    /// tests of a deployment, see also
+    /// [`secondary_agent::Builder::dry_run`](super::secondary_agent::Builder::dry_run).
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Set the optional local worker pool with intra-process receiver (can be None)
     pub fn worker_pool(mut self, worker_pool: Option<WorkerPool>) -> Self {
         self.worker_pool = worker_pool;
         self
     }
 
+    /// Set the wiring of the topics, used to describe the configuration at startup and
+    /// included in the hash of the topology checked when secondary agents connect
+    pub fn topic_wiring(mut self, topics: Vec<TopicWiring>) -> Self {
+        self.topics = topics;
+        self
+    }
+
+    /// Add the topics declared by activities, cross-checked against the
+    /// [topic wiring](Self::topic_wiring) when building the agent
+    ///
+    /// See [`DeclaredTopics::of`] and [`validate_wiring`].
+    pub fn declared_topics<K>(mut self, declared_topics: K) -> Self
+    where
+        K: IntoIterator<Item = DeclaredTopics>,
+    {
+        self.declared_topics.extend(declared_topics);
+        self
+    }
+
     /// Set sender and receiver to be used for intra-process transmission of agent signals
     pub fn intra_proc_ready_channel(
         mut self,
@@ -149,9 +234,25 @@ impl Builder {
         self
     }
 
-    /// Set the local bind address
+    /// Set the tunables of the signalling with secondary agents and recorders
+    pub fn signalling(mut self, signalling: SignallingConfig) -> Self {
+        self.signalling = Some(signalling);
+        self
+    }
+
+    /// Add a local bind address. Can be called multiple times to listen on several addresses,
+    /// e.g. on multiple network interfaces.
     pub fn bind(mut self, bind: SocketAddr) -> Self {
-        self.bind = Some(bind);
+        self.bind.push(bind);
+        self
+    }
+
+    /// Add the given local bind addresses
+    pub fn bind_all<A>(mut self, binds: A) -> Self
+    where
+        A: IntoIterator<Item = SocketAddr>,
+    {
+        self.bind.extend(binds);
         self
     }
 
@@ -166,6 +267,9 @@ impl Builder {
     }
 
     /// Set the recorder agents to expect
+    ///
+    /// Recorders hosted by a secondary agent are added with
+    /// [`hosted_recorder`](Self::hosted_recorder) instead.
     pub fn recorders<K>(mut self, recorders: K) -> Self
     where
         K: IntoIterator<Item = AgentId>,
@@ -175,36 +279,376 @@ impl Builder {
         self
     }
 
+    /// Expect a recorder hosted by the secondary agent with the given id
+    ///
+    /// The recorder uses the id of the secondary agent and shares its connection, see
+    /// [`secondary_agent::Builder::recorder`](super::secondary_agent::Builder::recorder).
+    pub fn hosted_recorder(mut self, agent_id: AgentId) -> Self {
+        self.hosted_recorders.insert(agent_id);
+        self
+    }
+
+    /// Add a recorder running in the process of the primary agent
+    ///
+    /// The primary agent sends the signals to record through `sender` instead of a TCP stream,
+    /// the recorder signals ready through the intra-process ready channel, see
+    /// `Recorder::new_local`.
+    pub fn local_recorder(mut self, agent_id: AgentId, sender: IntraProcSender<Signal>) -> Self {
+        self.local_recorders.insert(agent_id, sender);
+        self
+    }
+
     /// Set the activity dependencies
     pub fn activity_dependencies(mut self, activity_deps: ActivityDependencies) -> Self {
         self.activity_deps = Some(activity_deps);
         self
     }
 
+    /// Step the given activity only every `divisor`-th cycle
+    ///
+    /// On the other cycles, the activity is treated as ready right away, i.e. activities
+    /// depending on it see its previous output.
+    pub fn rate_divisor(mut self, activity_id: ActivityId, divisor: usize) -> Self {
+        self.rate_divisors.insert(activity_id, divisor);
+        self
+    }
+
+    /// Report the given activity whenever it signals ready later than `deadline` after the
+    /// start of a cycle
+    ///
+    /// The deadline is checked independently of the feo cycle time.
+    pub fn activity_deadline(mut self, activity_id: ActivityId, deadline: Duration) -> Self {
+        self.activity_deadlines.insert(activity_id, deadline);
+        self
+    }
+
+    /// Let the given activity optionally depend on `dependency`
+    ///
+    /// The activity is stepped without waiting for the dependency and sees its latest output.
+    pub fn optional_dependency(mut self, activity_id: ActivityId, dependency: ActivityId) -> Self {
+        self.optional_deps
+            .entry(activity_id)
+            .or_default()
+            .push(dependency);
+        self
+    }
+
+    /// Add a task chain scheduled independently of the others
+    ///
+    /// The task chain defined by the feo cycle time and the activity dependencies, if set,
+    /// is scheduled in addition under the name `main`.
+    pub fn task_chain(mut self, task_chain: TaskChainConfig) -> Self {
+        self.task_chains.push(task_chain);
+        self
+    }
+
     pub fn build(self) -> PrimaryAgent {
         let agent_id = self.id.expect("missing agent id");
-        let bind_addr = self.bind.expect("missing local socket address");
-        let feo_cycle_time = self.feo_cycle_time.expect("missing feo cycle time");
+        let bind_addrs = self.bind;
+        assert!(!bind_addrs.is_empty(), "missing local socket address");
         let agent_map = self.agent_map.expect("missing agent map");
         let recorders = self.recorders;
         let local_worker_pool = self.worker_pool;
-        let activity_depends = self.activity_deps.expect("missing activity dependency map");
+        let mut task_chains = self.task_chains;
+        if let Some(activity_depends) = self.activity_deps {
+            task_chains.insert(
+                0,
+                TaskChainConfig {
+                    name: "main".into(),
+                    cycle_time: self.feo_cycle_time.expect("missing feo cycle time"),
+                    watchdog_timeout: self.watchdog_timeout,
+                    flush_timeout: self.flush_timeout,
+                    activity_depends,
+                    optional_depends: self.optional_deps,
+                    rate_divisors: self.rate_divisors,
+                    activity_deadlines: self.activity_deadlines,
+                },
+            );
+        }
+        assert!(!task_chains.is_empty(), "missing activity dependency map");
+        for local in self.local_recorders.keys() {
+            assert!(
+                !recorders.iter().flatten().any(|recorder| recorder == local),
+                "Agent id {local} used for both a remote and a local recorder"
+            );
+        }
+        let local_recorders: HashSet<AgentId> = self.local_recorders.keys().copied().collect();
+        validate_ids(
+            agent_id,
+            &agent_map,
+            recorders.as_ref(),
+            &self.hosted_recorders,
+            &local_recorders,
+            &task_chains,
+        );
+        validate_topics(&self.topics, &self.declared_topics);
+        // Hosted recorders are remote recorders sharing the connection of their agent
+        let mut recorders = recorders;
+        if !self.hosted_recorders.is_empty() {
+            recorders
+                .get_or_insert_with(HashSet::new)
+                .extend(&self.hosted_recorders);
+        }
         let (intra_ready_sender, intra_ready_receiver) = self
             .intra_proc_ready_channel
             .expect("missing intra process channel");
 
         let configuration = PrimaryAgentConfig {
             agent_id,
-            bind_addr,
-            cycle_time: feo_cycle_time,
+            bind_addrs,
+            task_chains,
+            serial_startup: self.serial_startup,
+            accept_reconnects: self.accept_reconnects,
+            dry_run: self.dry_run,
             agent_map,
             recorders,
-            activity_depends,
+            local_recorders: self.local_recorders,
             local_worker_pool,
             intra_ready_sender,
             intra_ready_receiver,
+            signalling: self.signalling.unwrap_or_default(),
+            topics: self.topics,
         };
 
         PrimaryAgent::new(configuration)
     }
 }
+
+/// Check the declared topics against the topic wiring, panicking with a message listing all
+/// mismatches
+fn validate_topics(topics: &[TopicWiring], declared_topics: &[DeclaredTopics]) {
+    if let Err(errors) = validate_wiring(topics, declared_topics) {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        panic!("Invalid topic wiring: {}", errors.join(", "));
+    }
+}
+
+/// Check that the given ids are consistent, panicking with a message naming the offending id
+///
+/// Ids of different kinds share the numeric space of `usize`, so a mixed up id often is a
+/// valid id of its kind and would only fail later without reference to the configuration.
+///
+/// Only the recorders explicitly hosted by a secondary agent share the id of an agent.
+fn validate_ids(
+    agent_id: AgentId,
+    agent_map: &HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>,
+    recorders: Option<&HashSet<AgentId>>,
+    hosted_recorders: &HashSet<AgentId>,
+    local_recorders: &HashSet<AgentId>,
+    task_chains: &[TaskChainConfig],
+) {
+    let all_recorders = recorders
+        .into_iter()
+        .flatten()
+        .chain(hosted_recorders)
+        .chain(local_recorders);
+    for recorder in all_recorders {
+        assert_ne!(
+            *recorder, agent_id,
+            "Agent id {recorder} used for both the primary agent and a recorder"
+        );
+    }
+    for recorder in recorders.into_iter().flatten() {
+        assert!(
+            !agent_map.contains_key(recorder),
+            "Agent id {recorder} used for both a recorder and an agent running activities"
+        );
+        assert!(
+            !hosted_recorders.contains(recorder),
+            "Agent id {recorder} used for both a remote and a hosted recorder"
+        );
+    }
+    for recorder in hosted_recorders {
+        assert!(
+            agent_map.contains_key(recorder),
+            "Hosted recorder {recorder} has no secondary agent"
+        );
+    }
+    for recorder in local_recorders {
+        assert!(
+            !agent_map.contains_key(recorder),
+            "Agent id {recorder} used for both a local recorder and an agent running activities"
+        );
+        assert!(
+            !hosted_recorders.contains(recorder),
+            "Agent id {recorder} used for both a hosted and a local recorder"
+        );
+    }
+
+    // Each activity must be assigned to exactly one worker of one agent
+    let mut assigned: HashMap<ActivityId, (AgentId, WorkerId)> = HashMap::new();
+    for (agent_id, workers) in agent_map {
+        for (worker_id, activities) in workers {
+            for activity_id in activities {
+                if let Some((other_agent, other_worker)) =
+                    assigned.insert(*activity_id, (*agent_id, *worker_id))
+                {
+                    panic!(
+                        "Activity {activity_id} assigned to both worker {other_worker} of agent {other_agent} and worker {worker_id} of agent {agent_id}"
+                    );
+                }
+            }
+        }
+    }
+
+    // Each scheduled activity and dependency must be assigned and belong to the task chain
+    for chain in task_chains {
+        for (activity_id, dependencies) in &chain.activity_depends {
+            assert!(
+                assigned.contains_key(activity_id),
+                "Activity {activity_id} of task chain {} not assigned to any worker",
+                chain.name
+            );
+            for dependency in dependencies {
+                assert!(
+                    chain.activity_depends.contains_key(dependency),
+                    "Activity {activity_id} depends on activity {dependency} outside of task chain {}",
+                    chain.name
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate_ids, validate_topics};
+    use crate::agent::primary::TaskChainConfig;
+    use crate::configuration::topics::{DeclaredTopics, Direction};
+    use crate::signalling::AgentId;
+    use feo_time::Duration;
+    use std::collections::{HashMap, HashSet};
+
+    /// Task chain of the given activities, each depending on the given ones
+    fn chain(activity_depends: &[(usize, &[usize])]) -> TaskChainConfig {
+        TaskChainConfig {
+            name: "test".into(),
+            cycle_time: Duration::from_millis(10),
+            watchdog_timeout: None,
+            flush_timeout: None,
+            activity_depends: activity_depends
+                .iter()
+                .map(|(id, deps)| ((*id).into(), deps.iter().map(|d| (*d).into()).collect()))
+                .collect(),
+            optional_depends: HashMap::new(),
+            rate_divisors: HashMap::new(),
+            activity_deadlines: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_ids() {
+        let agent_map = HashMap::from([
+            (100.into(), HashMap::from([(40.into(), vec![0.into()])])),
+            (101.into(), HashMap::from([(41.into(), vec![1.into()])])),
+        ]);
+        let recorders = HashSet::from([AgentId::new(900)]);
+        let chains = [chain(&[(0, &[]), (1, &[0])])];
+        validate_ids(
+            100.into(),
+            &agent_map,
+            Some(&recorders),
+            &HashSet::new(),
+            &HashSet::new(),
+            &chains,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Activity A2 of task chain test not assigned")]
+    fn test_validate_ids_unassigned() {
+        // A worker id used as activity id is not assigned
+        let agent_map = HashMap::from([(100.into(), HashMap::from([(2.into(), vec![0.into()])]))]);
+        let chains = [chain(&[(0, &[]), (2, &[0])])];
+        validate_ids(
+            100.into(),
+            &agent_map,
+            None,
+            &HashSet::new(),
+            &HashSet::new(),
+            &chains,
+        );
+    }
+
+    #[test]
+    fn test_validate_ids_hosted_recorder() {
+        // The secondary agent also hosts a recorder
+        let agent_map = HashMap::from([
+            (100.into(), HashMap::from([(40.into(), vec![0.into()])])),
+            (101.into(), HashMap::from([(41.into(), vec![1.into()])])),
+        ]);
+        let hosted_recorders = HashSet::from([AgentId::new(101)]);
+        validate_ids(
+            100.into(),
+            &agent_map,
+            None,
+            &hosted_recorders,
+            &HashSet::new(),
+            &[chain(&[(0, &[])])],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Agent id A101 used for both a recorder and an agent")]
+    fn test_validate_ids_recorder_agent() {
+        // A recorder id clashing with a secondary agent not declared to host it
+        let agent_map = HashMap::from([
+            (100.into(), HashMap::from([(40.into(), vec![0.into()])])),
+            (101.into(), HashMap::from([(41.into(), vec![1.into()])])),
+        ]);
+        let recorders = HashSet::from([AgentId::new(101)]);
+        validate_ids(
+            100.into(),
+            &agent_map,
+            Some(&recorders),
+            &HashSet::new(),
+            &HashSet::new(),
+            &[chain(&[(0, &[])])],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Hosted recorder A102 has no secondary agent")]
+    fn test_validate_ids_hosted_recorder_without_agent() {
+        let agent_map = HashMap::from([(100.into(), HashMap::from([(40.into(), vec![0.into()])]))]);
+        let hosted_recorders = HashSet::from([AgentId::new(102)]);
+        validate_ids(
+            100.into(),
+            &agent_map,
+            None,
+            &hosted_recorders,
+            &HashSet::new(),
+            &[chain(&[(0, &[])])],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Agent id A101 used for both a local recorder and an agent")]
+    fn test_validate_ids_local_recorder_agent() {
+        let agent_map = HashMap::from([
+            (100.into(), HashMap::from([(40.into(), vec![0.into()])])),
+            (101.into(), HashMap::from([(41.into(), vec![1.into()])])),
+        ]);
+        let local_recorders = HashSet::from([AgentId::new(101)]);
+        validate_ids(
+            100.into(),
+            &agent_map,
+            None,
+            &HashSet::new(),
+            &local_recorders,
+            &[chain(&[(0, &[])])],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid topic wiring: activity A1 declares Incoming topic feo/test/unwired")]
+    fn test_validate_topics_unwired() {
+        let topics = [("feo/test/wired", vec![(1.into(), Direction::Incoming)])];
+        let declared = DeclaredTopics {
+            activity_id: 1.into(),
+            inputs: vec!["feo/test/wired".into(), "feo/test/unwired".into()],
+            outputs: Vec::new(),
+        };
+        validate_topics(&topics, &[declared]);
+    }
+}
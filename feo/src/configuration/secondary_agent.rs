@@ -4,8 +4,9 @@
 
 //! Secondary agent builder
 
-use crate::agent::secondary::SecondaryAgent;
-use crate::signalling::{AgentId, IntraProcReceiver, Signal};
+use crate::agent::secondary::{ConnectRetry, SecondaryAgent, Watchdog};
+use crate::configuration::topology::TopologyHash;
+use crate::signalling::{AgentId, IntraProcReceiver, IntraProcSender, Signal, SignallingConfig};
 use crate::worker_pool::WorkerPool;
 use std::net::SocketAddr;
 
@@ -15,6 +16,12 @@ pub struct Builder {
     pub id: Option<AgentId>,
     pub primary: Option<SocketAddr>,
     pub worker_pool: Option<(WorkerPool, IntraProcReceiver<Signal>)>,
+    pub connect_retry: Option<ConnectRetry>,
+    pub signalling: Option<SignallingConfig>,
+    pub watchdog: Option<Watchdog>,
+    pub recorder: Option<IntraProcSender<Signal>>,
+    pub topology: Option<TopologyHash>,
+    pub dry_run: bool,
 }
 
 impl Builder {
@@ -40,12 +47,133 @@ impl Builder {
         self
     }
 
+    /// Set the retry policy for connecting to the primary agent
+    pub fn connect_retry(mut self, connect_retry: ConnectRetry) -> Self {
+        self.connect_retry = Some(connect_retry);
+        self
+    }
+
+    /// Set the tunables of the signalling with the primary agent
+    pub fn signalling(mut self, signalling: SignallingConfig) -> Self {
+        self.signalling = Some(signalling);
+        self
+    }
+
+    /// Enable the watchdog of the local activities
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Host a recorder in the process of the agent
+    ///
+    /// The recorder is registered with the primary agent under the id of this agent and shares
+    /// the connection. The primary agent must expect it, see
+    /// [`primary_agent::Builder::hosted_recorder`](super::primary_agent::Builder::hosted_recorder). The agent
+    /// sends the signals to record through `sender`, the recorder signals ready through the
+    /// ready channel of the worker pool, see `Recorder::new_local`.
+    pub fn recorder(mut self, sender: IntraProcSender<Signal>) -> Self {
+        self.recorder = Some(sender);
+        self
+    }
+
+    /// Set the hash of the topology this agent has been configured with
+    ///
+    /// The hash is sent to the primary agent when connecting, which rejects the connection
+    /// if it differs from the hash of its own topology, see
+    /// [`Topology`](crate::configuration::topology::Topology). Without a hash, the agent is
+    /// accepted regardless of its configuration.
+    pub fn topology(mut self, topology: TopologyHash) -> Self {
+        self.topology = Some(topology);
+        self
+    }
+
+    /// Validate the deployment without stepping any activity
+    ///
+    /// The agent fails if the primary agent shuts the system down before it is ready or
+    /// triggers a step, i.e. unless the primary agent runs a dry run as well, see
+    /// [`primary_agent::Builder::dry_run`](super::primary_agent::Builder::dry_run). Use
+    /// [`secondary::run_result`](crate::agent::secondary::run_result) to get the outcome.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Build the secondary agent
     pub fn build(self) -> SecondaryAgent {
         let id = self.id.expect("missing agent id");
         let primary_addr = self.primary.expect("missing remote socket address");
         let (worker_pool, ready_receiver) = self.worker_pool.expect("missing worker pool");
 
-        SecondaryAgent::new(id, primary_addr, worker_pool, ready_receiver)
+        let connect_retry = self.connect_retry.unwrap_or_default();
+        let signalling = self.signalling.unwrap_or_default();
+
+        SecondaryAgent::new(
+            id,
+            primary_addr,
+            worker_pool,
+            ready_receiver,
+            connect_retry,
+            signalling,
+            self.watchdog,
+            self.recorder,
+            self.topology,
+            self.dry_run,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Builder;
+    use crate::activity::{Activity, ActivityId};
+    use crate::configuration::worker_pool;
+    use crate::signalling::AgentId;
+    use crate::worker_pool::WorkerId;
+
+    /// Activity doing nothing
+    struct Nop(ActivityId);
+
+    impl Activity for Nop {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {}
+
+        fn shutdown(&mut self) {}
+    }
+
+    fn nop(id: ActivityId) -> Box<dyn Activity> {
+        Box::new(Nop(id))
+    }
+
+    /// Builder with all required fields of a secondary agent except the worker pool
+    fn builder() -> Builder {
+        Builder::default()
+            .id(AgentId::new(101))
+            .primary("127.0.0.1:8081".parse().unwrap())
+    }
+
+    #[test]
+    fn test_build() {
+        let mut pool_builder = worker_pool::Builder::default();
+        pool_builder.agent_id(AgentId::new(101)).activity(
+            WorkerId::from(40),
+            ActivityId::from(1),
+            Box::new(nop),
+        );
+        let (pool, _, ready_receiver) = pool_builder.build().unwrap();
+
+        // Building does not connect to the primary agent yet
+        let _agent = builder().worker_pool(pool, ready_receiver).build();
+    }
+
+    #[test]
+    #[should_panic(expected = "missing worker pool")]
+    fn test_build_missing_worker_pool() {
+        builder().build();
     }
 }
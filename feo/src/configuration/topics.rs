@@ -2,12 +2,16 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::activity::ActivityId;
-use crate::com::TopicHandle;
+use crate::activity::{Activity, ActivityId};
+use crate::com::{ComError, TopicHandle};
+use std::fmt;
 
 pub type Topic = &'static str;
 
-#[derive(Debug, Default, Clone, Copy)]
+/// Peers of a topic with their communication direction, see [`TopicSpecification::wiring`]
+pub type TopicWiring = (Topic, Vec<(ActivityId, Direction)>);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 /// Describes the direction of the data flow for one topic of one component
 pub enum Direction {
     /// incoming / received data
@@ -18,10 +22,246 @@ pub enum Direction {
     Outgoing,
 }
 
+/// Behavior of a topic when a reader's buffer is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Keep the buffered payloads, the new payload is not delivered to the reader
+    Keep,
+
+    /// Drop the oldest buffered payload to make room for the new one
+    #[default]
+    Drop,
+}
+
+/// Quality of service of a topic
+///
+/// Each reader buffers up to `history_depth` payloads which it has not read yet. Buffers are
+/// per reader: a topic allocates up to `max_subscribers * history_depth` payloads, where
+/// `max_subscribers` is the number of readers the topic has been initialized for, and a reader
+/// not keeping up does not affect the others.
+///
+/// The default keeps the latest payload only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopicQos {
+    /// Number of payloads buffered for each reader, at least one
+    pub history_depth: usize,
+    /// Behavior when a reader's buffer is full
+    pub overflow: Overflow,
+}
+
+impl Default for TopicQos {
+    fn default() -> Self {
+        Self {
+            history_depth: 1,
+            overflow: Overflow::Drop,
+        }
+    }
+}
+
 /// Specification of a topic's peers and init function
 pub struct TopicSpecification {
+    /// Name of the topic
+    pub topic: Topic,
     /// Peers with [ActivityId] and communication [Direction] for this topic
     pub peers: Vec<(ActivityId, Direction)>,
-    /// Function to initialize this topic with the number of writers and readers as arguments
-    pub init_fn: Box<dyn FnOnce(usize, usize) -> TopicHandle>,
+    /// Quality of service of this topic, passed to `init_fn`
+    pub qos: TopicQos,
+    /// Function to initialize this topic with the number of writers and readers and the
+    /// quality of service as arguments
+    pub init_fn: Box<dyn FnOnce(usize, usize, TopicQos) -> Result<TopicHandle, ComError>>,
+}
+
+impl TopicSpecification {
+    /// The topic with its peers
+    pub fn wiring(&self) -> TopicWiring {
+        (self.topic, self.peers.clone())
+    }
+}
+
+/// Topics declared by one activity, see [`Activity::declared_inputs`] and
+/// [`Activity::declared_outputs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredTopics {
+    /// Declaring activity
+    pub activity_id: ActivityId,
+    /// Names of the topics read by the activity
+    pub inputs: Vec<String>,
+    /// Names of the topics written by the activity
+    pub outputs: Vec<String>,
+}
+
+impl DeclaredTopics {
+    /// Collect the declared topics of the given activity
+    pub fn of(activity: &dyn Activity) -> Self {
+        let owned = |topics: Vec<&str>| topics.into_iter().map(String::from).collect();
+        Self {
+            activity_id: activity.id(),
+            inputs: owned(activity.declared_inputs()),
+            outputs: owned(activity.declared_outputs()),
+        }
+    }
+
+    /// Iterate over all declared topics with their direction
+    fn iter(&self) -> impl Iterator<Item = (&str, Direction)> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|t| (t.as_str(), Direction::Incoming));
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|t| (t.as_str(), Direction::Outgoing));
+        inputs.chain(outputs)
+    }
+}
+
+/// Mismatch between the topics declared by an activity and the topic specifications
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WiringError {
+    /// The activity declares a topic which is not specified at all
+    Unwired {
+        activity_id: ActivityId,
+        topic: String,
+        direction: Direction,
+    },
+    /// The topic is specified, but not with the activity as a peer in the declared direction
+    Mismatched {
+        activity_id: ActivityId,
+        topic: String,
+        direction: Direction,
+    },
+}
+
+impl fmt::Display for WiringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WiringError::Unwired {
+                activity_id,
+                topic,
+                direction,
+            } => write!(
+                f,
+                "activity {activity_id} declares {direction:?} topic {topic} which is never initialized"
+            ),
+            WiringError::Mismatched {
+                activity_id,
+                topic,
+                direction,
+            } => write!(
+                f,
+                "activity {activity_id} declares {direction:?} topic {topic} which is not specified as {direction:?} for it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WiringError {}
+
+/// Cross-check the topics declared by activities against the topic specifications
+///
+/// Activities declaring no topics are not checked. Returns all found mismatches.
+///
+/// The wiring of a [`TopicSpecification`] is given by [`TopicSpecification::wiring`].
+pub fn validate_wiring(
+    wiring: &[TopicWiring],
+    declarations: &[DeclaredTopics],
+) -> Result<(), Vec<WiringError>> {
+    let mut errors = Vec::new();
+    for declaration in declarations {
+        let activity_id = declaration.activity_id;
+        for (topic, direction) in declaration.iter() {
+            let Some((_, peers)) = wiring.iter().find(|(name, _)| *name == topic) else {
+                errors.push(WiringError::Unwired {
+                    activity_id,
+                    topic: topic.to_string(),
+                    direction,
+                });
+                continue;
+            };
+            if !peers.contains(&(activity_id, direction)) {
+                errors.push(WiringError::Mismatched {
+                    activity_id,
+                    topic: topic.to_string(),
+                    direction,
+                });
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate_wiring, DeclaredTopics, Direction, TopicSpecification, WiringError};
+    use crate::activity::{Activity, ActivityId};
+    use crate::com::ComError;
+
+    struct Declaring {
+        id: ActivityId,
+    }
+
+    impl Activity for Declaring {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {}
+
+        fn shutdown(&mut self) {}
+
+        fn declared_inputs(&self) -> Vec<&str> {
+            vec!["feo/test/wired", "feo/test/unwired"]
+        }
+
+        fn declared_outputs(&self) -> Vec<&str> {
+            vec!["feo/test/wired"]
+        }
+    }
+
+    #[test]
+    fn test_validate_wiring() {
+        let specs = [TopicSpecification {
+            topic: "feo/test/wired",
+            peers: vec![
+                (0.into(), Direction::Outgoing),
+                (1.into(), Direction::Incoming),
+            ],
+            qos: Default::default(),
+            init_fn: Box::new(|_, _, _| Err(ComError::NotInitialized("feo/test/wired".into()))),
+        }];
+        let declarations = [DeclaredTopics::of(&Declaring { id: 1.into() })];
+
+        let wiring: Vec<_> = specs.iter().map(TopicSpecification::wiring).collect();
+        let errors = validate_wiring(&wiring, &declarations).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                WiringError::Unwired {
+                    activity_id: 1.into(),
+                    topic: "feo/test/unwired".into(),
+                    direction: Direction::Incoming,
+                },
+                WiringError::Mismatched {
+                    activity_id: 1.into(),
+                    topic: "feo/test/wired".into(),
+                    direction: Direction::Outgoing,
+                },
+            ]
+        );
+
+        // Activities not declaring anything are not checked
+        let undeclared = DeclaredTopics {
+            activity_id: 2.into(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        assert_eq!(validate_wiring(&wiring, &[undeclared]), Ok(()));
+    }
 }
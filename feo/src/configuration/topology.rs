@@ -0,0 +1,258 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stable hash of the configured topology of a deployment
+//!
+//! The topology consists of the agent map, the required activity dependencies of all task
+//! chains and the topic wiring. It is serialized canonically, i.e. independent of the order in
+//! which the configuration has been built, and hashed with FNV-1a.
+//!
+//! Secondary agents configured with a topology hash send it in their hello messages, the
+//! primary agent rejects those whose hash differs from the hash of its own configuration, see
+//! [`secondary_agent::Builder::topology`](super::secondary_agent::Builder::topology).
+
+use crate::activity::ActivityId;
+use crate::configuration::primary_agent::ActivityDependencies;
+use crate::configuration::topics::{Direction, TopicWiring};
+use crate::signalling::AgentId;
+use crate::worker_pool::WorkerId;
+#[cfg(feature = "recording")]
+use postcard::experimental::max_size::MaxSize;
+#[cfg(feature = "recording")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// FNV-1a 64 bit offset basis
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 64 bit prime
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Stable hash of a [`Topology`]
+#[cfg_attr(feature = "recording", derive(Serialize, Deserialize, MaxSize))]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct TopologyHash(pub u64);
+
+impl fmt::Display for TopologyHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Writers and readers of a topic
+type Peers = (BTreeSet<usize>, BTreeSet<usize>);
+
+/// Topology of a deployment in canonical order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Topology {
+    /// Activities of each worker of each agent
+    agents: BTreeMap<usize, BTreeMap<usize, BTreeSet<usize>>>,
+    /// Required dependencies of each activity, merged over all task chains
+    dependencies: BTreeMap<usize, BTreeSet<usize>>,
+    /// Writers and readers of each topic
+    topics: BTreeMap<String, Peers>,
+}
+
+impl Topology {
+    /// Create the topology of the given agent map, activity dependencies of the task chains
+    /// and topic wiring
+    pub fn new<'d, D>(
+        agent_map: &HashMap<AgentId, HashMap<WorkerId, Vec<ActivityId>>>,
+        dependencies: D,
+        topics: &[TopicWiring],
+    ) -> Self
+    where
+        D: IntoIterator<Item = &'d ActivityDependencies>,
+    {
+        let agents = agent_map
+            .iter()
+            .map(|(agent_id, workers)| {
+                let workers = workers
+                    .iter()
+                    .map(|(worker_id, activities)| {
+                        let activities = activities.iter().map(usize::from).collect();
+                        (usize::from(worker_id), activities)
+                    })
+                    .collect();
+                (usize::from(agent_id), workers)
+            })
+            .collect();
+
+        let mut merged: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for activity_depends in dependencies {
+            for (activity_id, depends) in activity_depends {
+                merged
+                    .entry(usize::from(activity_id))
+                    .or_default()
+                    .extend(depends.iter().map(usize::from));
+            }
+        }
+
+        let mut wiring: BTreeMap<String, Peers> = BTreeMap::new();
+        for (topic, peers) in topics {
+            let (writers, readers) = wiring.entry(topic.to_string()).or_default();
+            for (activity_id, direction) in peers {
+                match direction {
+                    Direction::Outgoing => writers.insert(usize::from(activity_id)),
+                    Direction::Incoming => readers.insert(usize::from(activity_id)),
+                };
+            }
+        }
+
+        Self {
+            agents,
+            dependencies: merged,
+            topics: wiring,
+        }
+    }
+
+    /// Serialize the topology canonically
+    ///
+    /// Ids and counts are encoded as big-endian `u64`, each list preceded by its length, all
+    /// lists sorted by id and topic name.
+    pub fn serialize(&self) -> Vec<u8> {
+        fn put(buf: &mut Vec<u8>, value: usize) {
+            buf.extend_from_slice(&(value as u64).to_be_bytes());
+        }
+        fn put_set(buf: &mut Vec<u8>, set: &BTreeSet<usize>) {
+            put(buf, set.len());
+            set.iter().for_each(|id| put(buf, *id));
+        }
+
+        let mut buf = Vec::new();
+
+        put(&mut buf, self.agents.len());
+        for (agent_id, workers) in &self.agents {
+            put(&mut buf, *agent_id);
+            put(&mut buf, workers.len());
+            for (worker_id, activities) in workers {
+                put(&mut buf, *worker_id);
+                put_set(&mut buf, activities);
+            }
+        }
+
+        put(&mut buf, self.dependencies.len());
+        for (activity_id, depends) in &self.dependencies {
+            put(&mut buf, *activity_id);
+            put_set(&mut buf, depends);
+        }
+
+        put(&mut buf, self.topics.len());
+        for (topic, (writers, readers)) in &self.topics {
+            put(&mut buf, topic.len());
+            buf.extend_from_slice(topic.as_bytes());
+            put_set(&mut buf, writers);
+            put_set(&mut buf, readers);
+        }
+        buf
+    }
+
+    /// Compute the stable hash of the topology
+    pub fn hash(&self) -> TopologyHash {
+        TopologyHash(fnv1a(&self.serialize()))
+    }
+}
+
+/// FNV-1a 64 bit hash of the given bytes
+///
+/// In contrast to the hashers of the standard library, the hash is stable across Rust
+/// versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fnv1a, Topology, TopologyHash};
+    use crate::configuration::topics::Direction;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fnv1a() {
+        // Test vectors of the FNV reference implementation
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+
+        // The empty topology consists of three zero counts
+        assert_eq!(Topology::default().serialize(), [0u8; 24]);
+        assert_eq!(
+            TopologyHash(0xaf63dc4c8601ec8c).to_string(),
+            "af63dc4c8601ec8c"
+        );
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let agent_map = HashMap::from([
+            (
+                0.into(),
+                HashMap::from([(10.into(), vec![0.into(), 1.into()])]),
+            ),
+            (1.into(), HashMap::from([(11.into(), vec![2.into()])])),
+        ]);
+        let reordered = HashMap::from([
+            (1.into(), HashMap::from([(11.into(), vec![2.into()])])),
+            (
+                0.into(),
+                HashMap::from([(10.into(), vec![1.into(), 0.into()])]),
+            ),
+        ]);
+        let depends = HashMap::from([
+            (1.into(), vec![0.into()]),
+            (2.into(), vec![0.into(), 1.into()]),
+        ]);
+        let depends_reordered = HashMap::from([
+            (2.into(), vec![1.into(), 0.into()]),
+            (1.into(), vec![0.into()]),
+        ]);
+        let topics = vec![(
+            "topic",
+            vec![
+                (0.into(), Direction::Outgoing),
+                (2.into(), Direction::Incoming),
+            ],
+        )];
+        let topics_reordered = vec![(
+            "topic",
+            vec![
+                (2.into(), Direction::Incoming),
+                (0.into(), Direction::Outgoing),
+            ],
+        )];
+
+        let hash = Topology::new(&agent_map, [&depends], &topics).hash();
+        assert_eq!(
+            Topology::new(&reordered, [&depends_reordered], &topics_reordered).hash(),
+            hash
+        );
+
+        // Moving an activity to another worker, dropping a dependency or reversing a topic
+        // changes the hash
+        let moved = HashMap::from([
+            (0.into(), HashMap::from([(10.into(), vec![0.into()])])),
+            (
+                1.into(),
+                HashMap::from([(11.into(), vec![1.into(), 2.into()])]),
+            ),
+        ]);
+        assert_ne!(Topology::new(&moved, [&depends], &topics).hash(), hash);
+        let fewer = HashMap::from([(2.into(), vec![0.into(), 1.into()])]);
+        assert_ne!(Topology::new(&agent_map, [&fewer], &topics).hash(), hash);
+        let reversed = vec![(
+            "topic",
+            vec![
+                (0.into(), Direction::Incoming),
+                (2.into(), Direction::Outgoing),
+            ],
+        )];
+        assert_ne!(
+            Topology::new(&agent_map, [&depends], &reversed).hash(),
+            hash
+        );
+    }
+}
@@ -5,7 +5,7 @@
 //! Worker pool builder
 
 use crate::activity::{ActivityBuilder, ActivityId, ActivityIdAndBuilder};
-use crate::signalling::{channel, IntraProcReceiver, IntraProcSender, Signal};
+use crate::signalling::{channel, AgentId, IntraProcReceiver, IntraProcSender, Signal};
 use crate::worker_pool::{WorkerId, WorkerPool};
 use std::collections::HashMap;
 
@@ -19,6 +19,10 @@ pub struct Builder {
     pub assignments: WorkerPoolAssignments,
     /// Workers' stack size
     stack_size: Option<usize>,
+    /// Id of the agent running the pool
+    agent_id: Option<AgentId>,
+    /// Number of threads running the workers, one per worker if not set
+    threads: Option<usize>,
 }
 
 /// Worker pool builder
@@ -28,6 +32,8 @@ impl Builder {
         Self {
             assignments,
             stack_size: None,
+            agent_id: None,
+            threads: None,
         }
     }
 
@@ -43,6 +49,23 @@ impl Builder {
         self
     }
 
+    /// Set the id of the agent running the pool, added to the workers' tracing spans
+    pub fn agent_id(&mut self, agent_id: AgentId) -> &mut Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    /// Multiplex the workers onto the given number of threads
+    ///
+    /// Activities of the same worker are still executed sequentially, but activities of
+    /// workers sharing a thread block each other. Useful for agents with many lightweight
+    /// activities. Workers added to the running pool get threads of their own, see
+    /// [`WorkerPool::add_activity`](crate::worker_pool::WorkerPool::add_activity).
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = Some(threads);
+        self
+    }
+
     /// Insert the given activity builder into the pool assignment map
     pub fn activity(
         &mut self,
@@ -108,7 +131,13 @@ impl Builder {
 
         // Create and return the worker pool together with receiver and sender
         Some((
-            WorkerPool::new(self.assignments, &intra_ready_sender, self.stack_size),
+            WorkerPool::new(
+                self.assignments,
+                &intra_ready_sender,
+                self.stack_size,
+                self.agent_id,
+                self.threads,
+            ),
             intra_ready_sender,
             intra_ready_receiver,
         ))
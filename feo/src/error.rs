@@ -4,21 +4,121 @@
 
 //! FEO Error implementation
 
+use crate::activity::ActivityId;
+use crate::signalling::AgentId;
+
 /// FEO Error type
 #[non_exhaustive]
 #[derive(Debug)]
 pub enum Error {
+    /// An intra-process channel failed, e.g. because its other end has been dropped
     Channel(&'static str),
+    /// A configuration is invalid
+    Config(String),
+    /// The given remote agent has closed its connection
+    Disconnected(AgentId),
+    /// An I/O operation failed
     Io((std::io::Error, &'static str)),
+    /// A value does not fit into its target type
+    OutOfRange(&'static str),
+    /// The primary agent has closed its connection
+    PrimaryDisconnected,
+    /// A peer violated the signalling protocol, e.g. by sending a malformed PDU
+    Protocol(String),
+    /// An operation did not complete in time
+    Timeout(&'static str),
+    /// The given activity could not be triggered
+    Trigger(ActivityId),
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io((e, _)) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Channel(description) => write!(f, "Channel error, {}", description),
+            Error::Config(description) => write!(f, "Invalid configuration, {}", description),
+            Error::Disconnected(agent_id) => write!(f, "Disconnected, agent {}", agent_id),
             Error::Io((e, description)) => write!(f, "Io error: {}, {}", description, e),
+            Error::OutOfRange(description) => write!(f, "Value out of range, {}", description),
+            Error::PrimaryDisconnected => write!(f, "Disconnected, primary agent"),
+            Error::Protocol(description) => write!(f, "Protocol error, {}", description),
+            Error::Timeout(description) => write!(f, "Timeout, {}", description),
+            Error::Trigger(activity_id) => write!(f, "Failed to trigger activity {}", activity_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Error;
+    use crate::activity::ActivityId;
+    use crate::signalling::AgentId;
+    use std::error::Error as _;
+    use std::io;
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            (Error::Channel("no signal"), "Channel error, no signal"),
+            (
+                Error::Config("no agent id".into()),
+                "Invalid configuration, no agent id",
+            ),
+            (
+                Error::Disconnected(AgentId::new(3)),
+                "Disconnected, agent A3",
+            ),
+            (
+                Error::Io((io::ErrorKind::BrokenPipe.into(), "failed to write")),
+                "Io error: failed to write, broken pipe",
+            ),
+            (
+                Error::OutOfRange("too large"),
+                "Value out of range, too large",
+            ),
+            (Error::PrimaryDisconnected, "Disconnected, primary agent"),
+            (
+                Error::Protocol("invalid tag".into()),
+                "Protocol error, invalid tag",
+            ),
+            (Error::Timeout("no pdu"), "Timeout, no pdu"),
+            (
+                Error::Trigger(ActivityId::from(7)),
+                "Failed to trigger activity A7",
+            ),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        // Only I/O errors wrap another error
+        let io = Error::Io((io::ErrorKind::BrokenPipe.into(), "failed to write"));
+        let source = io.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::BrokenPipe);
+
+        let others = [
+            Error::Channel("no signal"),
+            Error::Config("no agent id".into()),
+            Error::Disconnected(AgentId::new(3)),
+            Error::OutOfRange("too large"),
+            Error::PrimaryDisconnected,
+            Error::Protocol("invalid tag".into()),
+            Error::Timeout("no pdu"),
+            Error::Trigger(ActivityId::from(7)),
+        ];
+        for error in others {
+            assert!(error.source().is_none(), "{error}");
         }
     }
 }
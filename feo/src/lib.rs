@@ -30,6 +30,8 @@ pub mod error;
 #[cfg(feature = "recording")]
 pub mod recording;
 pub mod signalling;
+#[cfg(test)]
+mod span_capture;
 mod timestamp;
 pub mod worker_pool;
 
@@ -2,6 +2,12 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(feature = "recording_perfetto")]
+mod perfetto;
+
+#[cfg(feature = "recording")]
+pub mod reader;
+
 #[cfg(feature = "recording")]
 pub mod recorder;
 
@@ -0,0 +1,358 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Perfetto output of the data recorder
+//!
+//! Instead of the feo recording format, the recorder can emit a perfetto trace, such that
+//! recorded com data can be viewed in one tool with the traces of `feo-tracer`, see
+//! [`Recorder::perfetto`](super::recorder::Recorder::perfetto). The signals are instant events
+//! on a track of their own. The com data of each topic are instant events on a track named
+//! after the topic, with the fields of the payload as debug annotations. The uuids of the
+//! tracks are derived from the id of the recorder, such that the traces of several recorders
+//! can be merged.
+
+use crate::recording::recorder::{self, HeaderRecord};
+use crate::recording::transcoder::ComRecTranscoder;
+use crate::signalling::{AgentId, Signal};
+use crate::timestamp::{self, timestamp, Timestamp};
+use feo_log::{error, trace};
+use perfetto_model as idl;
+use prost::Message;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::time::UNIX_EPOCH;
+
+/// Encoder of the records of a recorder as perfetto trace packets
+pub(crate) struct PerfettoEncoder {
+    /// Id of the recorder, from which the uuids of the tracks are derived
+    agent_id: AgentId,
+
+    /// Name of the recorder, used as name of the track of the signals
+    name: String,
+
+    /// Id of the packet sequence, derived from the id of the recorder
+    sequence_id: u32,
+
+    /// Uuids of the tracks of the topics recorded so far
+    tracks: HashMap<&'static str, u64>,
+
+    /// Anchor of the timestamps to absolute time, if recording absolute time
+    header: Option<HeaderRecord>,
+}
+
+impl PerfettoEncoder {
+    /// Create an encoder for the given recorder
+    pub fn new(agent_id: AgentId) -> Self {
+        Self {
+            agent_id,
+            name: format!("Recorder {agent_id}"),
+            sequence_id: usize::from(agent_id) as u32 + 1,
+            tracks: HashMap::new(),
+            header: None,
+        }
+    }
+
+    /// Start the trace with the descriptor of the track of the signals
+    ///
+    /// If recording absolute time, event timestamps are the system time since the epoch
+    /// instead of the time since startup.
+    pub fn start(&mut self, absolute_time: bool, writer: &mut impl Write) {
+        self.header = absolute_time.then(|| HeaderRecord {
+            sync_info: timestamp::sync_info(),
+            speed: feo_time::get_speed(),
+        });
+        let track = track_uuid(self.agent_id, None);
+        Self::write(track_descriptor(track, &self.name), writer);
+    }
+
+    /// Record the given signal as instant event on the track of the signals
+    pub fn signal(&mut self, signal: Signal, writer: &mut impl Write) {
+        let full = signal.to_string();
+        let name = full.split('(').next().unwrap_or_default().to_owned();
+        let annotations = vec![annotation(Some("signal"), &Value::String(full))];
+        let track = track_uuid(self.agent_id, None);
+        let event = self.event(track, name, annotations, timestamp());
+        Self::write(event, writer);
+    }
+
+    /// Record the latest com data read by the given transcoders, each as instant event on
+    /// the track of its topic
+    ///
+    /// Payloads equal to the previously recorded ones are skipped if `last_hashes` is given,
+    /// see [`Recorder::dedup`](super::recorder::Recorder::dedup).
+    pub fn com_data(
+        &mut self,
+        transcoders: &[Box<dyn ComRecTranscoder>],
+        last_hashes: &mut Option<HashMap<&'static str, u64>>,
+        writer: &mut impl Write,
+    ) {
+        for transcoder in transcoders {
+            if let Some(value) = transcoder.read_value() {
+                let topic = transcoder.topic();
+                if recorder::unchanged(last_hashes, topic, value.to_string().as_bytes()) {
+                    continue;
+                }
+                self.data(topic, transcoder.type_name(), &value, timestamp(), writer);
+            }
+        }
+    }
+
+    /// Record the given payload of the given topic, describing the track of the topic first
+    /// if it is new
+    ///
+    /// The fields of a payload serialized as object are annotated one by one, any other
+    /// payload as single annotation named `value`.
+    fn data(
+        &mut self,
+        topic: &'static str,
+        type_name: &str,
+        value: &Value,
+        timestamp: Timestamp,
+        writer: &mut impl Write,
+    ) {
+        let agent_id = self.agent_id;
+        let track = *self.tracks.entry(topic).or_insert_with(|| {
+            let track = track_uuid(agent_id, Some(topic));
+            Self::write(track_descriptor(track, topic), writer);
+            track
+        });
+        let annotations = match value {
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| annotation(Some(key), value))
+                .collect(),
+            value => vec![annotation(Some("value"), value)],
+        };
+        trace!("Writing perfetto event of topic {topic}");
+        let event = self.event(track, type_name.to_owned(), annotations, timestamp);
+        Self::write(event, writer);
+    }
+
+    /// Create an instant event on the given track
+    fn event(
+        &self,
+        track: u64,
+        name: String,
+        debug_annotations: Vec<idl::DebugAnnotation>,
+        timestamp: Timestamp,
+    ) -> idl::TracePacket {
+        let timestamp = match self.header {
+            Some(header) => header
+                .system_time(timestamp)
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            None => timestamp.0,
+        };
+        let event = idl::TrackEvent {
+            r#type: Some(idl::track_event::Type::Instant.into()),
+            track_uuid: Some(track),
+            name_field: Some(idl::track_event::NameField::Name(name)),
+            debug_annotations,
+            ..Default::default()
+        };
+        idl::TracePacket {
+            timestamp: Some(timestamp.as_nanos() as u64),
+            data: Some(idl::trace_packet::Data::TrackEvent(event)),
+            optional_trusted_packet_sequence_id: Some(
+                idl::trace_packet::OptionalTrustedPacketSequenceId::TrustedPacketSequenceId(
+                    self.sequence_id,
+                ),
+            ),
+            ..Default::default()
+        }
+    }
+
+    /// Write the given packet as a trace of its own, logging failures
+    ///
+    /// Concatenated traces decode as one trace with all their packets.
+    fn write(packet: idl::TracePacket, writer: &mut impl Write) {
+        let buf = idl::Trace {
+            packet: vec![packet],
+        }
+        .encode_to_vec();
+        if let Err(e) = writer.write_all(&buf) {
+            error!("Failed to write perfetto packet: {e:?}");
+        }
+    }
+}
+
+/// Uuid of the track of the given topic, or of the signals, of the given recorder
+fn track_uuid(agent_id: AgentId, topic: Option<&str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (usize::from(agent_id), topic).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Create a packet describing the track with the given uuid and name
+fn track_descriptor(uuid: u64, name: &str) -> idl::TracePacket {
+    let descriptor = idl::TrackDescriptor {
+        uuid: Some(uuid),
+        static_or_dynamic_name: Some(idl::track_descriptor::StaticOrDynamicName::Name(
+            name.to_owned(),
+        )),
+        ..Default::default()
+    };
+    idl::TracePacket {
+        data: Some(idl::trace_packet::Data::TrackDescriptor(descriptor)),
+        ..Default::default()
+    }
+}
+
+/// Create a debug annotation of the given JSON value
+fn annotation(name: Option<&str>, value: &Value) -> idl::DebugAnnotation {
+    use idl::debug_annotation::Value as Annotated;
+
+    let name_field = name.map(|name| idl::debug_annotation::NameField::Name(name.to_owned()));
+    let mut result = idl::DebugAnnotation {
+        name_field,
+        ..Default::default()
+    };
+    match value {
+        Value::Null => (),
+        Value::Bool(b) => result.value = Some(Annotated::BoolValue(*b)),
+        Value::Number(number) => {
+            result.value = number
+                .as_i64()
+                .map(Annotated::IntValue)
+                .or_else(|| number.as_u64().map(Annotated::UintValue))
+                .or_else(|| number.as_f64().map(Annotated::DoubleValue))
+        }
+        Value::String(string) => result.value = Some(Annotated::StringValue(string.clone())),
+        Value::Array(values) => {
+            result.array_values = values.iter().map(|value| annotation(None, value)).collect()
+        }
+        Value::Object(map) => {
+            result.dict_entries = map
+                .iter()
+                .map(|(key, value)| annotation(Some(key), value))
+                .collect()
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{idl, track_uuid, PerfettoEncoder};
+    use crate::com::{init_topic, ActivityOutput};
+    use crate::recording::registry::TypeRegistry;
+    use crate::register_types;
+    use crate::signalling::{AgentId, Signal};
+    use crate::timestamp::{self, timestamp};
+    use idl::debug_annotation::{NameField, Value};
+    use idl::trace_packet::Data;
+    use idl::track_descriptor::StaticOrDynamicName;
+    use idl::track_event;
+    use postcard::experimental::max_size::MaxSize;
+    use prost::Message;
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_scene_annotations() {
+        #[derive(Debug, Serialize, MaxSize)]
+        struct Scene {
+            num_people: usize,
+            num_cars: usize,
+            distance_obstacle: f64,
+        }
+
+        timestamp::initialize_for_test();
+        let topic = "test/perfetto/scene";
+        let _topic = init_topic::<Scene>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<Scene>::get(topic).unwrap();
+        let mut registry = TypeRegistry::default();
+        register_types!(registry, Scene: "scene");
+        let transcoders = vec![(registry.info_name("scene").unwrap().comrec_builder)(topic)];
+        let scene = Scene {
+            num_people: 3,
+            num_cars: 7,
+            distance_obstacle: 12.5,
+        };
+        output.write_uninit().unwrap().write_payload(scene).send();
+
+        // Record a scene and the end of the task chain as the recorder does
+        let mut trace = Vec::new();
+        let agent_id = AgentId::new(900);
+        let mut encoder = PerfettoEncoder::new(agent_id);
+        let mut last_hashes = Some(HashMap::new());
+        encoder.start(false, &mut trace);
+        encoder.com_data(&transcoders, &mut last_hashes, &mut trace);
+        encoder.signal(Signal::TaskChainEnd(timestamp()), &mut trace);
+        // The unchanged scene is not recorded again
+        encoder.com_data(&transcoders, &mut last_hashes, &mut trace);
+
+        let packets = idl::Trace::decode(trace.as_slice()).unwrap().packet;
+        let tracks: Vec<_> = packets
+            .iter()
+            .filter_map(|packet| match &packet.data {
+                Some(Data::TrackDescriptor(track)) => {
+                    Some((track.uuid, track.static_or_dynamic_name.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        let name = |name: &str| Some(StaticOrDynamicName::Name(name.to_owned()));
+        let signal_track = track_uuid(agent_id, None);
+        let topic_track = track_uuid(agent_id, Some(topic));
+        assert_eq!(
+            tracks,
+            [
+                (Some(signal_track), name("Recorder A900")),
+                (Some(topic_track), name(topic))
+            ]
+        );
+        // Another recorder records to other tracks
+        let other = AgentId::new(901);
+        assert_ne!(track_uuid(other, None), signal_track);
+        assert_ne!(track_uuid(other, Some(topic)), topic_track);
+
+        let events: Vec<_> = packets
+            .iter()
+            .filter_map(|packet| match &packet.data {
+                Some(Data::TrackEvent(event)) => Some(event),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events.len(), 2);
+
+        // The scene is an event on the track of its topic, annotated with its fields
+        let event = events[0];
+        assert_eq!(event.r#type(), track_event::Type::Instant);
+        assert_eq!(event.track_uuid, Some(topic_track));
+        assert_eq!(
+            event.name_field,
+            Some(track_event::NameField::Name("scene".to_owned()))
+        );
+        let mut annotations: Vec<_> = event
+            .debug_annotations
+            .iter()
+            .map(|annotation| {
+                let Some(NameField::Name(name)) = &annotation.name_field else {
+                    panic!("unnamed annotation {annotation:?}");
+                };
+                (name.as_str(), annotation.value.clone())
+            })
+            .collect();
+        annotations.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            annotations,
+            [
+                ("distance_obstacle", Some(Value::DoubleValue(12.5))),
+                ("num_cars", Some(Value::IntValue(7))),
+                ("num_people", Some(Value::IntValue(3))),
+            ]
+        );
+
+        // The signal is an event on the track of the signals
+        let event = events[1];
+        assert_eq!(event.track_uuid, Some(signal_track));
+        assert_eq!(
+            event.name_field,
+            Some(track_event::NameField::Name("TaskChainEnd".to_owned()))
+        );
+    }
+}
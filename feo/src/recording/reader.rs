@@ -0,0 +1,486 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reader of recordings written by the [`Recorder`](super::recorder::Recorder)
+
+use crate::recording::recorder::{HeaderRecord, Record, FORMAT_VERSION};
+use crate::recording::registry::{TypeRegistry, VersionMismatch};
+use crate::recording::transcoder::RawRecordable;
+use crate::signalling::Signal;
+use crate::timestamp::Timestamp;
+use serde::de::DeserializeOwned;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fmt, fs, io, mem, ptr};
+
+/// Error reading a recording
+#[derive(Debug)]
+pub enum ReadError {
+    /// A record could not be decoded
+    Decode(postcard::Error),
+    /// A payload has been recorded with another schema version than registered for its type
+    Version(VersionMismatch),
+    /// A payload has been requested as another type than it has been recorded with
+    TypeMismatch {
+        /// Name of the recorded type
+        recorded: String,
+        /// Name of the requested type
+        requested: &'static str,
+    },
+    /// A payload has been requested in another encoding than it has been recorded with
+    Encoding {
+        /// Whether the payload has been recorded raw
+        raw: bool,
+    },
+    /// The recording has another format version than [`FORMAT_VERSION`], or none
+    Format(Option<u16>),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Decode(e) => write!(f, "failed to decode record: {e}"),
+            ReadError::Version(e) => write!(f, "{e}"),
+            ReadError::TypeMismatch {
+                recorded,
+                requested,
+            } => write!(f, "type {recorded} requested as {requested}"),
+            ReadError::Encoding { raw: true } => {
+                write!(f, "raw payload requested as serialized payload")
+            }
+            ReadError::Encoding { raw: false } => {
+                write!(f, "serialized payload requested as raw payload")
+            }
+            ReadError::Format(Some(version)) => write!(
+                f,
+                "recording format version {version}, expected version {FORMAT_VERSION}"
+            ),
+            ReadError::Format(None) => write!(
+                f,
+                "recording without format version, expected version {FORMAT_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<postcard::Error> for ReadError {
+    fn from(e: postcard::Error) -> Self {
+        ReadError::Decode(e)
+    }
+}
+
+/// How a recording ends, see [`RecordingReader::end`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordingEnd {
+    /// The recording has been closed by the recorder on system shutdown
+    Clean,
+    /// The recording ends without an [`EndOfRecording`](Record::EndOfRecording) record,
+    /// e.g. as the recorder has been killed
+    Truncated,
+}
+
+/// Check the format version at the start of the given recording, returning the records after it
+///
+/// An empty recording, e.g. of a recorder killed before writing anything, has no records.
+fn check_format(recording: &[u8]) -> Result<&[u8], ReadError> {
+    if recording.is_empty() {
+        return Ok(recording);
+    }
+    match postcard::take_from_bytes::<Record<'_>>(recording) {
+        Ok((Record::Format { version }, rest)) if version == FORMAT_VERSION => Ok(rest),
+        Ok((Record::Format { version }, _)) => Err(ReadError::Format(Some(version))),
+        _ => Err(ReadError::Format(None)),
+    }
+}
+
+/// Iterator over the records of a recording
+///
+/// Yields each record together with the serialized payload it refers to. For an
+/// [`Unchanged`](Record::Unchanged) record, the payload is the one held from the last
+/// [`DataDescription`](Record::DataDescription) record of the same topic.
+pub struct RecordingReader<'a> {
+    /// Bytes not yet read
+    remaining: &'a [u8],
+
+    /// Whether the format version at the start of the recording has been checked
+    checked: bool,
+
+    /// Whether the last record read has been the end of the recording
+    ended: bool,
+
+    /// Last recorded payload per topic
+    held: HashMap<&'a str, &'a [u8]>,
+
+    /// Registry to check the schema versions of recorded types against, if any
+    registry: Option<&'a TypeRegistry>,
+}
+
+impl<'a> RecordingReader<'a> {
+    /// Create a reader of the given recording
+    pub fn new(recording: &'a [u8]) -> Self {
+        Self {
+            remaining: recording,
+            checked: false,
+            ended: false,
+            held: HashMap::new(),
+            registry: None,
+        }
+    }
+
+    /// How the recording ends, once all records have been read
+    ///
+    /// Returns `None` while records are left. A recording failing to decode counts as
+    /// truncated.
+    pub fn end(&self) -> Option<RecordingEnd> {
+        self.remaining
+            .is_empty()
+            .then_some(recording_end(self.ended))
+    }
+
+    /// Check the schema version of each recorded payload against the given registry
+    pub fn registry(mut self, registry: &'a TypeRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Read the next record and its payload, if any
+    fn read_next(&mut self) -> Result<(Record<'a>, Option<&'a [u8]>), ReadError> {
+        let (record, remaining) = postcard::take_from_bytes::<Record<'a>>(self.remaining)?;
+        self.remaining = remaining;
+        self.ended = matches!(record, Record::EndOfRecording { .. });
+        let data = match &record {
+            Record::Signal(_) | Record::Header(_) | Record::EndOfRecording { .. } => None,
+            Record::Format { .. } => return Err(postcard::Error::DeserializeBadEncoding.into()),
+            Record::DataDescription(description) => {
+                if let Some(registry) = self.registry {
+                    registry
+                        .check_version(description.type_name, description.version)
+                        .map_err(ReadError::Version)?;
+                }
+                if self.remaining.len() < description.data_size {
+                    return Err(postcard::Error::DeserializeUnexpectedEnd.into());
+                }
+                let (data, remaining) = self.remaining.split_at(description.data_size);
+                self.remaining = remaining;
+                self.held.insert(description.topic, data);
+                Some(data)
+            }
+            Record::Unchanged(unchanged) => Some(
+                *self
+                    .held
+                    .get(unchanged.topic)
+                    .ok_or(postcard::Error::DeserializeBadEncoding)?,
+            ),
+        };
+        Ok((record, data))
+    }
+}
+
+impl<'a> Iterator for RecordingReader<'a> {
+    type Item = Result<(Record<'a>, Option<&'a [u8]>), ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.checked {
+            self.checked = true;
+            match check_format(self.remaining) {
+                Ok(rest) => self.remaining = rest,
+                Err(e) => {
+                    self.remaining = &[];
+                    return Some(Err(e));
+                }
+            }
+        }
+        if self.remaining.is_empty() {
+            return None;
+        }
+        let result = self.read_next();
+        if result.is_err() {
+            // Stop at the first corrupted record
+            self.remaining = &[];
+            self.ended = false;
+        }
+        Some(result)
+    }
+}
+
+/// Record of a recording owning its data
+///
+/// Unlike [`Record`], it does not borrow from the recording, so it can be kept beyond the
+/// iteration, e.g. by offline analysis tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedRecord {
+    /// A recorded signal
+    Signal {
+        timestamp: Timestamp,
+        signal: Signal,
+    },
+    /// A recorded payload
+    ///
+    /// The type id is resolved from the type name if read [`with_registry`](RecordIter::with_registry).
+    Data {
+        timestamp: Timestamp,
+        topic: String,
+        type_name: String,
+        type_id: Option<TypeId>,
+        version: Option<u16>,
+        raw: bool,
+        payload: Vec<u8>,
+    },
+    /// A payload equal to the previous one of the topic, resolved to that payload
+    Unchanged {
+        timestamp: Timestamp,
+        topic: String,
+        type_name: String,
+        type_id: Option<TypeId>,
+        raw: bool,
+        payload: Vec<u8>,
+    },
+    /// The header anchoring the timestamps to absolute time
+    Header(HeaderRecord),
+    /// The end of a recording closed cleanly
+    EndOfRecording { timestamp: Timestamp },
+}
+
+impl OwnedRecord {
+    /// Deserialize the payload of a data record into the given type
+    ///
+    /// Returns `None` for records without payload. The type the payload has been recorded with
+    /// must be `T`, identified by its type id if resolved from a registry, otherwise by the
+    /// system type name of `T`.
+    pub fn decode_as<T: DeserializeOwned + 'static>(&self) -> Option<Result<T, ReadError>> {
+        let payload = match self.payload_of::<T>(false)? {
+            Ok(payload) => payload,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(postcard::from_bytes(payload).map_err(ReadError::from))
+    }
+
+    /// Reconstruct a value of the given type from the raw payload of a data record
+    ///
+    /// Returns `None` for records without payload. The payload must have been recorded raw
+    /// as type `T`, see [`TypeRegistry::add_raw`] and [`decode_as`](Self::decode_as).
+    pub fn decode_raw<T: RawRecordable>(&self) -> Option<Result<T, ReadError>> {
+        let payload = match self.payload_of::<T>(true)? {
+            Ok(payload) => payload,
+            Err(e) => return Some(Err(e)),
+        };
+        if payload.len() != mem::size_of::<T>() {
+            return Some(Err(postcard::Error::DeserializeUnexpectedEnd.into()));
+        }
+        // SAFETY: The payload has the size of `T` and has been recorded from the bytes of a
+        // value of type `T`, which implementors of `RawRecordable` guarantee to be valid.
+        // The payload buffer may not be aligned for `T`.
+        Some(Ok(unsafe {
+            ptr::read_unaligned(payload.as_ptr().cast::<T>())
+        }))
+    }
+
+    /// Get the payload of a data record after checking its type and encoding
+    fn payload_of<T: 'static>(&self, raw: bool) -> Option<Result<&[u8], ReadError>> {
+        let (type_name, type_id, recorded_raw, payload) = match self {
+            OwnedRecord::Data {
+                type_name,
+                type_id,
+                raw,
+                payload,
+                ..
+            }
+            | OwnedRecord::Unchanged {
+                type_name,
+                type_id,
+                raw,
+                payload,
+                ..
+            } => (type_name, type_id, *raw, payload),
+            OwnedRecord::Signal { .. }
+            | OwnedRecord::Header(_)
+            | OwnedRecord::EndOfRecording { .. } => return None,
+        };
+        let requested = core::any::type_name::<T>();
+        let matches = match type_id {
+            Some(type_id) => *type_id == TypeId::of::<T>(),
+            None => type_name == requested,
+        };
+        if !matches {
+            return Some(Err(ReadError::TypeMismatch {
+                recorded: type_name.clone(),
+                requested,
+            }));
+        }
+        if recorded_raw != raw {
+            return Some(Err(ReadError::Encoding { raw: recorded_raw }));
+        }
+        Some(Ok(payload))
+    }
+}
+
+/// Last recorded payload of a topic with its type and encoding
+struct Held {
+    type_name: String,
+    type_id: Option<TypeId>,
+    raw: bool,
+    payload: Vec<u8>,
+}
+
+/// Iterator over the records of a recording yielding [`OwnedRecord`]s
+///
+/// Stops after the first record which cannot be decoded.
+pub struct RecordIter<'r> {
+    /// The recording
+    recording: Vec<u8>,
+
+    /// Whether the format version at the start of the recording has been checked
+    checked: bool,
+
+    /// Offset of the next record
+    offset: usize,
+
+    /// Whether the last record read has been the end of the recording
+    ended: bool,
+
+    /// Last recorded payload per topic
+    held: HashMap<String, Held>,
+
+    /// Registry to resolve and check recorded types with, if any
+    registry: Option<&'r TypeRegistry>,
+}
+
+impl<'r> RecordIter<'r> {
+    /// Create an iterator over the given recording
+    pub fn new(recording: Vec<u8>) -> Self {
+        Self {
+            recording,
+            checked: false,
+            offset: 0,
+            ended: false,
+            held: HashMap::new(),
+            registry: None,
+        }
+    }
+
+    /// Resolve the recorded types in the given registry
+    ///
+    /// The schema version of each recorded payload is checked against the registry and the
+    /// data records carry the id of the registered type, see [`OwnedRecord::decode_as`].
+    pub fn with_registry(mut self, registry: &'r TypeRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// How the recording ends, once all records have been read, see [`RecordingReader::end`]
+    pub fn end(&self) -> Option<RecordingEnd> {
+        (self.offset >= self.recording.len()).then_some(recording_end(self.ended))
+    }
+
+    /// Create an iterator over the recording in the given file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read(path).map(Self::new)
+    }
+
+    /// Read the next record
+    fn read_next(&mut self) -> Result<OwnedRecord, ReadError> {
+        let remaining = &self.recording[self.offset..];
+        let (record, rest) = postcard::take_from_bytes::<Record<'_>>(remaining)?;
+        let mut consumed = remaining.len() - rest.len();
+        let record = match record {
+            Record::Signal(record) => OwnedRecord::Signal {
+                timestamp: record.timestamp,
+                signal: record.signal,
+            },
+            Record::Header(header) => OwnedRecord::Header(header),
+            Record::EndOfRecording { timestamp } => OwnedRecord::EndOfRecording { timestamp },
+            Record::Format { .. } => return Err(postcard::Error::DeserializeBadEncoding.into()),
+            Record::DataDescription(description) => {
+                let mut type_id = None;
+                if let Some(registry) = self.registry {
+                    registry
+                        .check_version(description.type_name, description.version)
+                        .map_err(ReadError::Version)?;
+                    type_id = registry
+                        .info_name(description.type_name)
+                        .map(|info| info.type_id);
+                }
+                if rest.len() < description.data_size {
+                    return Err(postcard::Error::DeserializeUnexpectedEnd.into());
+                }
+                let payload = rest[..description.data_size].to_vec();
+                consumed += description.data_size;
+                self.held.insert(
+                    description.topic.to_string(),
+                    Held {
+                        type_name: description.type_name.to_string(),
+                        type_id,
+                        raw: description.raw,
+                        payload: payload.clone(),
+                    },
+                );
+                OwnedRecord::Data {
+                    timestamp: description.timestamp,
+                    topic: description.topic.to_string(),
+                    type_name: description.type_name.to_string(),
+                    type_id,
+                    version: description.version,
+                    raw: description.raw,
+                    payload,
+                }
+            }
+            Record::Unchanged(unchanged) => {
+                let held = self
+                    .held
+                    .get(unchanged.topic)
+                    .ok_or(postcard::Error::DeserializeBadEncoding)?;
+                OwnedRecord::Unchanged {
+                    timestamp: unchanged.timestamp,
+                    topic: unchanged.topic.to_string(),
+                    type_name: held.type_name.clone(),
+                    type_id: held.type_id,
+                    raw: held.raw,
+                    payload: held.payload.clone(),
+                }
+            }
+        };
+        self.offset += consumed;
+        self.ended = matches!(record, OwnedRecord::EndOfRecording { .. });
+        Ok(record)
+    }
+}
+
+impl Iterator for RecordIter<'_> {
+    type Item = Result<OwnedRecord, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.checked {
+            self.checked = true;
+            match check_format(&self.recording) {
+                Ok(rest) => self.offset = self.recording.len() - rest.len(),
+                Err(e) => {
+                    self.offset = self.recording.len();
+                    return Some(Err(e));
+                }
+            }
+        }
+        if self.offset >= self.recording.len() {
+            return None;
+        }
+        let result = self.read_next();
+        if result.is_err() {
+            // Stop at the first corrupted record
+            self.offset = self.recording.len();
+            self.ended = false;
+        }
+        Some(result)
+    }
+}
+
+/// End of a completely read recording depending on whether its last record has been the end
+fn recording_end(ended: bool) -> RecordingEnd {
+    if ended {
+        RecordingEnd::Clean
+    } else {
+        RecordingEnd::Truncated
+    }
+}
@@ -4,10 +4,18 @@
 
 //! FEO data recorder. Records communication for debugging and development purposes
 
+use crate::com;
+use crate::configuration::topology::TopologyHash;
+use crate::error::Error;
+#[cfg(feature = "recording_perfetto")]
+use crate::recording::perfetto::PerfettoEncoder;
 use crate::recording::registry::TypeRegistry;
 use crate::recording::transcoder::ComRecTranscoder;
-use crate::signalling::{AgentId, MioSocketReceiver, MioSocketSender, Receiver, Sender, Signal};
-use crate::timestamp::{timestamp, Timestamp};
+use crate::signalling::{
+    AgentId, IntraProcReceiver, IntraProcSender, MioSocketReceiver, MioSocketSender, Receiver,
+    Sender, Signal, SignallingConfig,
+};
+use crate::timestamp::{timestamp, SyncInfo, Timestamp};
 use crate::{agent, timestamp};
 use feo_log::{debug, error, info, trace};
 use io::Write;
@@ -16,20 +24,25 @@ use mio::{Events, Poll};
 use postcard::experimental::max_size::MaxSize;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::BufWriter;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
 use std::{fs, io};
 
 /// Maximum allowed length of topics and type names in the recording
 const TOPIC_TYPENAME_MAX_SIZE: usize = 256;
 
+/// Default interval in which the recording file is flushed in addition to each task chain end
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The data recorder.
 pub struct Recorder<'s> {
     // ID of the recorder
     local_agent_id: AgentId,
 
-    // Socket address of the primary process
-    primary: SocketAddr,
+    // Connection to the primary agent
+    primary: Primary,
 
     // A file writer receiving the data
     writer: BufWriter<fs::File>,
@@ -43,8 +56,8 @@ pub struct Recorder<'s> {
     // The TCP stream receiving events to record
     recorder_stream: Option<TcpStream>,
 
-    // The TCP stream sending ready signals
-    ready_stream: Option<TcpStream>,
+    // The sender of ready signals, kept for the connection to complete partially sent signals
+    ready_sender: Option<MioSocketSender<TcpStream>>,
 
     // Poll object for polling the TCP stream
     poll: Poll,
@@ -52,8 +65,30 @@ pub struct Recorder<'s> {
     // Events object to use with the Poll object
     events: Events,
 
+    // Tunables of the signalling with the primary agent
+    signalling: SignallingConfig,
+
+    // Hash of the configured topology sent to the primary agent, if any
+    topology: Option<TopologyHash>,
+
     // Transcoders reading and serializing com data
     transcoders: Vec<Box<dyn ComRecTranscoder>>,
+
+    // Hash of the last recorded payload per topic, if recording only changed payloads
+    last_hashes: Option<HashMap<&'static str, u64>>,
+
+    // Whether to start the recording with a header anchoring timestamps to absolute time
+    absolute_time: bool,
+
+    // Interval in which to flush the recording file, if any
+    flush_interval: Option<Duration>,
+
+    // Whether to record the signals only, without com data
+    timing_only: bool,
+
+    // Encoder of a perfetto trace, if recording one instead of the feo format
+    #[cfg(feature = "recording_perfetto")]
+    perfetto: Option<PerfettoEncoder>,
 }
 
 impl<'s> Recorder<'s> {
@@ -64,6 +99,46 @@ impl<'s> Recorder<'s> {
         record_file: &'static str,
         rules: RecordingRules,
         registry: &'t TypeRegistry,
+    ) -> io::Result<Self> {
+        Self::with_primary(
+            local_agent_id,
+            Primary::Remote(primary),
+            record_file,
+            rules,
+            registry,
+        )
+    }
+
+    /// Create a new data recorder running in the process of the primary agent
+    ///
+    /// The recorder receives the signals to record through `receiver`, whose sender is given
+    /// to the primary agent as local recorder, and signals ready through `ready_sender`, the
+    /// intra-process ready channel of the primary agent. Timestamps are shared with the
+    /// primary agent, there is no time synchronization.
+    pub fn new_local<'t: 's>(
+        local_agent_id: AgentId,
+        receiver: IntraProcReceiver<Signal>,
+        ready_sender: IntraProcSender<Signal>,
+        record_file: &'static str,
+        rules: RecordingRules,
+        registry: &'t TypeRegistry,
+    ) -> io::Result<Self> {
+        Self::with_primary(
+            local_agent_id,
+            Primary::Local(Some(receiver), ready_sender),
+            record_file,
+            rules,
+            registry,
+        )
+    }
+
+    /// Create a new data recorder connecting to the given primary agent
+    fn with_primary<'t: 's>(
+        local_agent_id: AgentId,
+        primary: Primary,
+        record_file: &'static str,
+        rules: RecordingRules,
+        registry: &'t TypeRegistry,
     ) -> io::Result<Self> {
         // Create the recording file
         let file = fs::File::create(record_file)?;
@@ -71,7 +146,8 @@ impl<'s> Recorder<'s> {
 
         // Create poller and events object
         let poll = Poll::new()?;
-        let events = Events::with_capacity(1024);
+        let signalling = SignallingConfig::default();
+        let events = signalling.events();
 
         Ok(Self {
             local_agent_id,
@@ -80,38 +156,118 @@ impl<'s> Recorder<'s> {
             rules,
             registry,
             recorder_stream: None,
-            ready_stream: None,
+            ready_sender: None,
             poll,
             events,
+            signalling,
+            topology: None,
             transcoders: vec![],
+            last_hashes: None,
+            absolute_time: false,
+            flush_interval: Some(DEFAULT_FLUSH_INTERVAL),
+            timing_only: false,
+            #[cfg(feature = "recording_perfetto")]
+            perfetto: None,
         })
     }
 
-    /// Run the recording
+    /// Set the tunables of the signalling with the primary agent
+    pub fn signalling(mut self, signalling: SignallingConfig) -> Self {
+        self.events = signalling.events();
+        self.signalling = signalling;
+        self
+    }
+
+    /// Set the hash of the topology this recorder has been configured with
+    ///
+    /// The hash is sent to the primary agent when connecting, which rejects the recorder if it
+    /// differs from the hash of its own topology, see
+    /// [`Topology`](crate::configuration::topology::Topology). Without a hash, the recorder is
+    /// accepted regardless of its configuration.
+    pub fn topology(mut self, topology: TopologyHash) -> Self {
+        self.topology = Some(topology);
+        self
+    }
+
+    /// Start the recording with a [`HeaderRecord`] from which the absolute system time of
+    /// each record can be reconstructed
+    ///
+    /// Records keep their timestamps relative to startup, which remain the basis for ordering.
+    pub fn absolute_time(mut self, absolute_time: bool) -> Self {
+        self.absolute_time = absolute_time;
+        self
+    }
+
+    /// Flush the recording file in the given interval in addition to each task chain end
+    ///
+    /// Bounds the data lost on a crash by the interval rather than the cycle time. Defaults to
+    /// 100 ms, `None` flushes only at task chain ends.
+    pub fn flush_interval(mut self, flush_interval: Option<Duration>) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Record only changed payloads
+    ///
+    /// A payload identical to the previously recorded one of the same topic is recorded as
+    /// an [`UnchangedRecord`] instead of a [`DataDescriptionRecord`] with the payload appended.
+    /// Readers reconstruct the held value with [`RecordingReader`](super::reader::RecordingReader).
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.last_hashes = dedup.then(HashMap::new);
+        self
+    }
+
+    /// Record the signals only, skipping the com data of all topics
+    ///
+    /// The recording keeps the timeline of steps, ready signals and task chain boundaries
+    /// while staying small. The recording rules are ignored, no topic is opened.
+    pub fn timing_only(mut self, timing_only: bool) -> Self {
+        self.timing_only = timing_only;
+        self
+    }
+
+    /// Record a perfetto trace instead of the feo recording format
+    ///
+    /// The signals and the com data of each topic are recorded as instant events on tracks of
+    /// their own, with the fields of the payloads as debug annotations, such that the recording
+    /// can be viewed alongside the traces of `feo-tracer`. The trace cannot be read back with
+    /// [`RecordingReader`](super::reader::RecordingReader). With [`dedup`](Self::dedup), no event
+    /// is recorded for an unchanged payload.
+    #[cfg(feature = "recording_perfetto")]
+    pub fn perfetto(mut self, perfetto: bool) -> Self {
+        self.perfetto = perfetto.then(|| PerfettoEncoder::new(self.local_agent_id));
+        self
+    }
+
+    /// Run the recording until the primary agent broadcasts a system shutdown
     pub fn run(&mut self) {
+        if let Primary::Local(receiver, _) = &mut self.primary {
+            let mut receiver = receiver.take().expect("local recorder has already run");
+            Self::wait_local_startup(&mut receiver);
+            self.start_recording();
+            self.record_signals(&mut receiver);
+            return;
+        }
+
         self.connect_primary();
+        self.start_recording();
 
-        // Create socket signal receiver and register it with the poller
-        let recorder_stream = self
+        // Create socket signal receiver and register it with a poller of its own
+        let mut recorder_stream = self
             .recorder_stream
-            .as_mut()
+            .take()
             .expect("recorder signal stream not available");
-        let mut receiver =
-            MioSocketReceiver::new(recorder_stream, &mut self.poll, &mut self.events);
+        let mut poll = Poll::new().expect("failed to create poll instance");
+        let mut events = self.signalling.events();
+        let mut receiver = MioSocketReceiver::new(&mut recorder_stream, &mut poll, &mut events);
         receiver.register(0).unwrap();
+        self.record_signals(&mut receiver);
+    }
 
+    /// Record the signals received from the primary agent until a system shutdown
+    fn record_signals<T: TryInto<Signal>>(&mut self, receiver: &mut impl Receiver<T>) {
         // Create transcoders reading from the required topics
-        debug!("Creating transcoders");
-        for (topic, type_name) in self.rules.iter() {
-            let info = self
-                .registry
-                .info_name(type_name)
-                .unwrap_or_else(|| panic!("type name {type_name} not in registry"));
-            let transcoder_builder = &info.comrec_builder;
-            let transcoder = transcoder_builder(topic);
-            debug!("Creating transcoder: {topic}, {type_name}");
-            self.transcoders.push(transcoder);
-        }
+        self.transcoders = Self::build_transcoders(&self.rules, self.registry, self.timing_only);
 
         debug!("Starting main loop");
         let msg_buf_size = self
@@ -121,11 +277,21 @@ impl<'s> Recorder<'s> {
             .max()
             .unwrap_or_default();
         let mut msg_buf = vec![0; msg_buf_size];
+        let mut next_flush = self
+            .flush_interval
+            .map(|interval| Instant::now() + interval);
         loop {
             // Receive the next signal from the primary process
             trace!("Waiting for next signal to record");
-            let signal_pdu = receiver.recv().expect("failed to receive");
-            let Ok(signal) = signal_pdu.try_into() else {
+            let Some(message) = Self::recv_or_flush(
+                receiver,
+                &mut self.writer,
+                self.flush_interval,
+                &mut next_flush,
+            ) else {
+                continue;
+            };
+            let Ok(signal) = message.try_into() else {
                 error!("Failed to decode signal pdu, trying to continue");
                 self.writer
                     .flush()
@@ -139,31 +305,128 @@ impl<'s> Recorder<'s> {
                 // record the current latest change of com data, then record the signal.
                 // Also, flush the recording file at whenever the end of the task chain is reached.
                 Signal::Step(_) => {
-                    Self::record_com_data(&mut self.transcoders, &mut self.writer, &mut msg_buf);
-                    Self::record_signal(signal, &mut self.writer);
+                    self.write_com_data(&mut msg_buf);
+                    self.write_signal(signal);
                 }
                 Signal::TaskChainEnd(_) => {
-                    Self::record_com_data(&mut self.transcoders, &mut self.writer, &mut msg_buf);
-                    Self::record_signal(signal, &mut self.writer);
+                    self.write_com_data(&mut msg_buf);
+                    self.write_signal(signal);
+                    Self::flush(&mut self.writer);
+                    self.send_recorder_ready();
+                }
+
+                // On system shutdown, close the recording and tell the primary agent
+                Signal::SystemShutdown(_) => {
+                    self.write_signal(signal);
+                    self.write_end();
                     Self::flush(&mut self.writer);
-                    Self::send_recorder_ready(self.local_agent_id, self.ready_stream.as_mut());
+                    if let Err(e) = self.writer.get_ref().sync_all() {
+                        error!("Failed to sync recording file: {e:?}");
+                    }
+                    self.send_recorder_ready();
+                    info!("Recording closed on system shutdown");
+                    break;
                 }
 
                 // Otherwise, only record the signal
                 _ => {
-                    Self::record_signal(signal, &mut self.writer);
+                    self.write_signal(signal);
                 }
             }
         }
     }
 
+    /// Start the recording with its format version, followed by a header if recording
+    /// absolute time
+    fn start_recording(&mut self) {
+        #[cfg(feature = "recording_perfetto")]
+        if let Some(perfetto) = self.perfetto.as_mut() {
+            perfetto.start(self.absolute_time, &mut self.writer);
+            return;
+        }
+        Self::record_format(&mut self.writer);
+        if self.absolute_time {
+            Self::record_header(&mut self.writer);
+        }
+    }
+
+    /// Record the latest changes of com data in the format of the recording
+    fn write_com_data(&mut self, msg_buf: &mut [u8]) {
+        #[cfg(feature = "recording_perfetto")]
+        if let Some(perfetto) = self.perfetto.as_mut() {
+            perfetto.com_data(&self.transcoders, &mut self.last_hashes, &mut self.writer);
+            return;
+        }
+        Self::record_com_data(
+            &self.transcoders,
+            &mut self.last_hashes,
+            &mut self.writer,
+            msg_buf,
+        );
+    }
+
+    /// Record the given signal in the format of the recording
+    fn write_signal(&mut self, signal: Signal) {
+        #[cfg(feature = "recording_perfetto")]
+        if let Some(perfetto) = self.perfetto.as_mut() {
+            perfetto.signal(signal, &mut self.writer);
+            return;
+        }
+        Self::record_signal(signal, &mut self.writer);
+    }
+
+    /// Record the end of the recording, a perfetto trace has no terminator
+    fn write_end(&mut self) {
+        #[cfg(feature = "recording_perfetto")]
+        if self.perfetto.is_some() {
+            return;
+        }
+        Self::record_end(&mut self.writer);
+    }
+
+    /// Create the transcoders reading from the topics of the given rules, none if timing only
+    fn build_transcoders(
+        rules: &RecordingRules,
+        registry: &TypeRegistry,
+        timing_only: bool,
+    ) -> Vec<Box<dyn ComRecTranscoder>> {
+        if timing_only {
+            info!("Recording signals only");
+            return vec![];
+        }
+
+        debug!("Creating transcoders");
+        resolve_rules(rules, &com::topics(), registry)
+            .unwrap_or_else(|e| panic!("failed to resolve recording rules: {e}"))
+            .into_iter()
+            .map(|(topic, type_name)| {
+                let info = registry
+                    .info_name(type_name)
+                    .expect("resolved type name not in registry");
+                let transcoder_builder = &info.comrec_builder;
+                debug!("Creating transcoder: {topic}, {type_name}");
+                transcoder_builder(topic)
+            })
+            .collect()
+    }
+
     /// Set up the event recording stream to the primary agent
     pub fn connect_primary(&mut self) {
-        let (mut recorder_stream, ready_stream) =
-            agent::secondary::connect_to_primary(self.local_agent_id, self.primary);
+        let Primary::Remote(primary) = self.primary else {
+            panic!("a local recorder does not connect to the primary agent");
+        };
+        let retry = agent::secondary::ConnectRetry::default();
+        let (mut recorder_stream, ready_stream) = agent::secondary::connect_to_primary(
+            self.local_agent_id,
+            primary,
+            &retry,
+            &self.signalling,
+            self.topology,
+        )
+        .unwrap_or_else(|e| panic!("failed to connect to primary agent: {e}"));
 
         let mut sender = MioSocketSender::new(&mut recorder_stream);
-        let hello_recorder = Signal::HelloTrigger(self.local_agent_id);
+        let hello_recorder = Signal::HelloTrigger((self.local_agent_id, self.topology));
         sender
             .send(&hello_recorder)
             .unwrap_or_else(|e| panic!("failed to send 'hello_recorder': {:?}", e));
@@ -172,7 +435,7 @@ impl<'s> Recorder<'s> {
         info!("Time synchronization with primary agent done");
 
         self.recorder_stream = Some(recorder_stream);
-        self.ready_stream = Some(ready_stream);
+        self.ready_sender = Some(MioSocketSender::new(ready_stream));
     }
 
     /// Wait for synchronization event from primary agent and do time synchronization
@@ -206,6 +469,48 @@ impl<'s> Recorder<'s> {
         timestamp::initialize_from(sync_info);
     }
 
+    /// Wait for the startup synchronization signal of the primary agent in the same process
+    ///
+    /// Timestamps are shared with the primary agent, the signal only tells it has started.
+    fn wait_local_startup(receiver: &mut IntraProcReceiver<Signal>) {
+        debug!("Waiting for startup synchronization signal");
+        let signal = receiver.recv().expect("failed to receive");
+        debug!("Received signal {signal}");
+        assert!(
+            matches!(signal, Signal::StartupSync(_)),
+            "received unexpected signal {signal}"
+        );
+    }
+
+    /// Receive the next signal message or flush the recording file when the flush deadline passes
+    ///
+    /// Returns `None` if no pdu has been received until the flush deadline or after a timed
+    /// flush. Records are written completely between receptions, so a timed flush never splits
+    /// a record.
+    fn recv_or_flush<T>(
+        receiver: &mut impl Receiver<T>,
+        writer: &mut BufWriter<fs::File>,
+        flush_interval: Option<Duration>,
+        next_flush: &mut Option<Instant>,
+    ) -> Option<T> {
+        let (Some(interval), Some(deadline)) = (flush_interval, *next_flush) else {
+            return Some(receiver.recv().expect("failed to receive"));
+        };
+        // Flush first if the deadline passed, even if signals keep arriving
+        let now = Instant::now();
+        if now >= deadline {
+            trace!("Flushing recording file after {interval:?}");
+            Self::flush(writer);
+            *next_flush = Some(now + interval);
+            return None;
+        }
+        match receiver.recv_timeout(deadline - now) {
+            Ok(pdu) => Some(pdu),
+            Err(Error::Timeout(_)) => None,
+            Err(e) => panic!("failed to receive: {e:?}"),
+        }
+    }
+
     /// Flush the recording file
     fn flush(writer: &mut BufWriter<fs::File>) {
         let result = writer.flush();
@@ -214,52 +519,112 @@ impl<'s> Recorder<'s> {
         }
     }
 
-    // Record the latest changes of com data
+    // Record the latest changes of com data, only marking payloads equal to the previously
+    // recorded ones as unchanged if `last_hashes` is given
     fn record_com_data(
-        transcoders: &mut Vec<Box<dyn ComRecTranscoder>>,
-        writer: &mut BufWriter<fs::File>,
+        transcoders: &[Box<dyn ComRecTranscoder>],
+        last_hashes: &mut Option<HashMap<&'static str, u64>>,
+        writer: &mut impl Write,
         data_buffer: &mut [u8],
     ) {
         for transcoder in transcoders.iter() {
-            let data = transcoder.read_transcode(data_buffer);
-            if let Some(serialized_data) = data {
-                // create serialized data description record
-                assert!(
-                    transcoder.type_name().len() <= TOPIC_TYPENAME_MAX_SIZE,
-                    "serialized type name exceeds maximal size of {TOPIC_TYPENAME_MAX_SIZE}"
-                );
-                assert!(
-                    transcoder.topic().len() <= TOPIC_TYPENAME_MAX_SIZE,
-                    "serialized type name exceeds maximal size of {TOPIC_TYPENAME_MAX_SIZE}"
-                );
-                let description = DataDescriptionRecord {
-                    timestamp: timestamp(),
-                    type_name: transcoder.type_name(),
-                    data_size: serialized_data.len(),
-                    topic: transcoder.topic(),
-                };
-                let data_desc_record = Record::DataDescription(description);
-                let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
-                let serialized_header =
-                    postcard::to_slice(&data_desc_record, &mut buf).expect("serialization failed");
-
-                trace!("Writing data: {description:?}");
-
-                // Write description record and subsequent data block
-                // In case of failure, log an error message and continue
-                // (which may result in a corrupted file)
-                if let Err(e) = writer
-                    .write_all(serialized_header)
-                    .and_then(|_| writer.write_all(serialized_data))
-                {
-                    error!("Failed to write data: {e:?}");
-                }
-            }
+            transcoder.read_record(data_buffer, &mut |data| {
+                Self::record_payload(transcoder.as_ref(), data, last_hashes, writer)
+            });
+        }
+    }
+
+    // Record the given payload read by the given transcoder
+    fn record_payload(
+        transcoder: &dyn ComRecTranscoder,
+        serialized_data: &[u8],
+        last_hashes: &mut Option<HashMap<&'static str, u64>>,
+        writer: &mut impl Write,
+    ) {
+        if unchanged(last_hashes, transcoder.topic(), serialized_data) {
+            Self::record_unchanged(transcoder.topic(), writer);
+            return;
+        }
+
+        // create serialized data description record
+        assert!(
+            transcoder.type_name().len() <= TOPIC_TYPENAME_MAX_SIZE,
+            "serialized type name exceeds maximal size of {TOPIC_TYPENAME_MAX_SIZE}"
+        );
+        assert!(
+            transcoder.topic().len() <= TOPIC_TYPENAME_MAX_SIZE,
+            "serialized type name exceeds maximal size of {TOPIC_TYPENAME_MAX_SIZE}"
+        );
+        let description = DataDescriptionRecord {
+            timestamp: timestamp(),
+            type_name: transcoder.type_name(),
+            data_size: serialized_data.len(),
+            topic: transcoder.topic(),
+            version: transcoder.version(),
+            raw: transcoder.raw(),
+        };
+        let data_desc_record = Record::DataDescription(description);
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized_header =
+            postcard::to_slice(&data_desc_record, &mut buf).expect("serialization failed");
+
+        trace!("Writing data: {description:?}");
+
+        // Write description record and subsequent data block
+        // In case of failure, log an error message and continue
+        // (which may result in a corrupted file)
+        if let Err(e) = writer
+            .write_all(serialized_header)
+            .and_then(|_| writer.write_all(serialized_data))
+        {
+            error!("Failed to write data: {e:?}");
+        }
+    }
+
+    /// Record the version of the recording format, see [`FORMAT_VERSION`]
+    fn record_format(writer: &mut impl Write) {
+        let record = Record::Format {
+            version: FORMAT_VERSION,
+        };
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized = postcard::to_slice(&record, &mut buf).expect("serialization failed");
+        if let Err(e) = writer.write_all(serialized) {
+            error!("Failed to write format version: {e:?}");
+        }
+    }
+
+    /// Record the header anchoring the timestamps of the recording to absolute time
+    fn record_header(writer: &mut impl Write) {
+        let header = HeaderRecord {
+            sync_info: timestamp::sync_info(),
+            speed: feo_time::get_speed(),
+        };
+        debug!("Writing header: {header:?}");
+        let record = Record::Header(header);
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized = postcard::to_slice(&record, &mut buf).expect("serialization failed");
+        if let Err(e) = writer.write_all(serialized) {
+            error!("Failed to write header: {e:?}");
+        }
+    }
+
+    /// Record that the payload of the given topic is unchanged
+    fn record_unchanged(topic: &'static str, writer: &mut impl Write) {
+        let unchanged = UnchangedRecord {
+            timestamp: timestamp(),
+            topic,
+        };
+        trace!("Writing data: {unchanged:?}");
+        let record = Record::Unchanged(unchanged);
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized = postcard::to_slice(&record, &mut buf).expect("serialization failed");
+        if let Err(e) = writer.write_all(serialized) {
+            error!("Failed to write data: {e:?}");
         }
     }
 
     /// Record the given signal
-    fn record_signal(signal: Signal, writer: &mut BufWriter<fs::File>) {
+    fn record_signal(signal: Signal, writer: &mut impl Write) {
         let signal_record = Record::Signal(SignalRecord {
             signal,
             timestamp: timestamp(),
@@ -272,17 +637,61 @@ impl<'s> Recorder<'s> {
         }
     }
 
+    /// Record the end of the recording
+    fn record_end(writer: &mut impl Write) {
+        let record = Record::EndOfRecording {
+            timestamp: timestamp(),
+        };
+        debug!("Writing end of recording");
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized = postcard::to_slice(&record, &mut buf).expect("serialization failed");
+        if let Err(e) = writer.write_all(serialized) {
+            error!("Failed to write end of recording: {e:?}");
+        }
+    }
+
     // Send RecorderReady signal to the primary agent
-    fn send_recorder_ready(agent_id: AgentId, ready_stream: Option<&mut TcpStream>) {
-        let ready_stream = ready_stream.expect("missing TCP stream");
-        let mut sender = MioSocketSender::new(ready_stream);
-        let signal = Signal::RecorderReady((agent_id, timestamp()));
-        sender
-            .send(&signal)
-            .unwrap_or_else(|e| panic!("failed to send 'recorder_ready': {:?}", e));
+    fn send_recorder_ready(&mut self) {
+        let signal = Signal::RecorderReady((self.local_agent_id, timestamp()));
+        let result = match &mut self.primary {
+            Primary::Remote(_) => {
+                let ready_sender = self.ready_sender.as_mut().expect("missing TCP stream");
+                ready_sender.send(&signal)
+            }
+            Primary::Local(_, ready_sender) => ready_sender.send(signal),
+        };
+        result.unwrap_or_else(|e| panic!("failed to send 'recorder_ready': {:?}", e));
     }
 }
 
+/// Check whether the given payload of the given topic equals the last one recorded,
+/// remembering it otherwise
+///
+/// Always false if not recording only changed payloads, see [`Recorder::dedup`].
+pub(crate) fn unchanged(
+    last_hashes: &mut Option<HashMap<&'static str, u64>>,
+    topic: &'static str,
+    payload: &[u8],
+) -> bool {
+    let Some(last_hashes) = last_hashes.as_mut() else {
+        return false;
+    };
+    let mut hasher = DefaultHasher::new();
+    payload.hash(&mut hasher);
+    let hash = hasher.finish();
+    last_hashes.insert(topic, hash) == Some(hash)
+}
+
+/// Connection of a recorder to the primary agent
+enum Primary {
+    /// Primary agent in another process listening on the given socket address
+    Remote(SocketAddr),
+
+    /// Primary agent in the same process with the receiver of the signals to record, taken
+    /// when running, and the sender of ready signals
+    Local(Option<IntraProcReceiver<Signal>>, IntraProcSender<Signal>),
+}
+
 impl Drop for Recorder<'_> {
     fn drop(&mut self) {
         // Try to flush pending data.
@@ -292,15 +701,138 @@ impl Drop for Recorder<'_> {
 
 /// Set of recording rules
 ///
-/// Maps every topic to be recorded to a corresponding type name from the type registry
+/// Maps every topic to be recorded to a corresponding type name from the type registry.
+/// A topic ending with `*` is a prefix rule, covering all topics starting with the part before
+/// the `*`, e.g. `feo/com/vehicle/camera/*`. Prefix rules are resolved to the topics existing
+/// at recorder startup, see [`resolve_rules`].
 pub type RecordingRules = HashMap<&'static str, &'static str>;
 
+/// Error resolving the recording rules to the topics to record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    /// The type name of the rule of the topic is not in the type registry
+    UnknownType {
+        topic: &'static str,
+        type_name: &'static str,
+    },
+    /// The topic is covered by prefix rules with different type names
+    Ambiguous {
+        topic: String,
+        type_names: Vec<&'static str>,
+    },
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::UnknownType { topic, type_name } => {
+                write!(f, "type name {type_name} of topic {topic} not in registry")
+            }
+            RuleError::Ambiguous { topic, type_names } => {
+                write!(f, "ambiguous type names {type_names:?} for topic {topic}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Resolve the recording rules to the topics to record with their type names
+///
+/// Exact rules are taken as they are, prefix rules match the given existing `topics`. An exact
+/// rule takes precedence over the prefix rules covering the same topic, while prefix rules
+/// covering the same topic must agree on the type name. All type names must be registered.
+///
+/// The names of topics matched by prefix rules are leaked, as transcoders need static topics.
+pub fn resolve_rules(
+    rules: &RecordingRules,
+    topics: &[String],
+    registry: &TypeRegistry,
+) -> Result<Vec<(&'static str, &'static str)>, RuleError> {
+    let (prefixes, exact): (Vec<_>, Vec<_>) = rules
+        .iter()
+        .map(|(topic, type_name)| (*topic, *type_name))
+        .partition(|(topic, _)| topic.ends_with('*'));
+
+    let mut resolved = exact;
+    let mut matched: Vec<&String> = topics
+        .iter()
+        .filter(|topic| !rules.contains_key(topic.as_str()))
+        .collect();
+    matched.sort();
+    matched.dedup();
+    for topic in matched {
+        let mut type_names: Vec<&'static str> = prefixes
+            .iter()
+            .filter(|(prefix, _)| topic.starts_with(prefix.trim_end_matches('*')))
+            .map(|(_, type_name)| *type_name)
+            .collect();
+        type_names.sort_unstable();
+        type_names.dedup();
+        match type_names[..] {
+            [] => continue,
+            [type_name] => {
+                let topic: &'static str = Box::leak(topic.clone().into_boxed_str());
+                debug!("Prefix rule matches topic {topic} of type {type_name}");
+                resolved.push((topic, type_name));
+            }
+            _ => {
+                return Err(RuleError::Ambiguous {
+                    topic: topic.clone(),
+                    type_names,
+                })
+            }
+        }
+    }
+
+    if let Some((topic, type_name)) = resolved
+        .iter()
+        .find(|(_, type_name)| !registry.contains(type_name))
+    {
+        return Err(RuleError::UnknownType { topic, type_name });
+    }
+    Ok(resolved)
+}
+
+/// Version of the recording format, recorded as first record of each recording
+///
+/// Increment on any incompatible change of the records, readers reject recordings of other
+/// versions instead of misinterpreting them.
+pub const FORMAT_VERSION: u16 = 1;
+
 /// Possible records in the recording file
 #[derive(Debug, Serialize, Deserialize, MaxSize)]
 pub enum Record<'s> {
     Signal(SignalRecord),
     #[serde(borrow)]
     DataDescription(DataDescriptionRecord<'s>),
+    #[serde(borrow)]
+    Unchanged(UnchangedRecord<'s>),
+    Header(HeaderRecord),
+    /// Terminator of a recording closed cleanly on system shutdown, telling a complete
+    /// recording from a truncated one
+    EndOfRecording {
+        // The monotonic time at the moment of recording as duration since the epoch
+        timestamp: Timestamp,
+    },
+    /// First record of a recording, see [`FORMAT_VERSION`]. Consumed by the readers.
+    Format { version: u16 },
+}
+
+/// Header at the start of a recording anchoring its timestamps to absolute time
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, MaxSize)]
+pub struct HeaderRecord {
+    // The startup time of the primary agent, i.e. the system time at timestamp zero
+    pub sync_info: SyncInfo,
+    // The feo-time speed factor the timestamps are scaled with, if any
+    pub speed: Option<i32>,
+}
+
+impl HeaderRecord {
+    /// Reconstruct the absolute system time of the given timestamp of the recording
+    pub fn system_time(&self, timestamp: Timestamp) -> SystemTime {
+        timestamp.to_system_time(self.sync_info, self.speed)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, MaxSize)]
@@ -323,6 +855,27 @@ pub struct DataDescriptionRecord<'s> {
     #[serde(borrow)]
     /// restricted to 256 chars
     pub topic: &'s str,
+    /// schema version of the type, if registered with one
+    pub version: Option<u16>,
+    /// whether the data is the raw in-memory representation of the type instead of its
+    /// postcard serialization, see [`RawRecordable`](super::transcoder::RawRecordable)
+    pub raw: bool,
+}
+
+/// Marker of a payload equal to the previously recorded one of the same topic
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct UnchangedRecord<'s> {
+    // The monotonic time at the moment of recording as duration since the epoch
+    pub timestamp: Timestamp,
+    #[serde(borrow)]
+    /// restricted to 256 chars
+    pub topic: &'s str,
+}
+
+impl MaxSize for UnchangedRecord<'_> {
+    const POSTCARD_MAX_SIZE: usize = Timestamp::POSTCARD_MAX_SIZE +
+        usize::POSTCARD_MAX_SIZE + // len
+        TOPIC_TYPENAME_MAX_SIZE; // restrict to 256 bytes
 }
 
 impl MaxSize for DataDescriptionRecord<'_> {
@@ -331,13 +884,39 @@ impl MaxSize for DataDescriptionRecord<'_> {
         2*( // type_name, topic
             usize::POSTCARD_MAX_SIZE + // len
                 TOPIC_TYPENAME_MAX_SIZE * u8::POSTCARD_MAX_SIZE // restrict to 256 bytes
-        );
+        ) +
+        Option::<u16>::POSTCARD_MAX_SIZE + // version
+        bool::POSTCARD_MAX_SIZE; // raw
 }
 
 #[cfg(test)]
 mod test {
-    use super::{DataDescriptionRecord, MaxSize, Timestamp, TOPIC_TYPENAME_MAX_SIZE};
-    use std::time::Duration;
+    use super::{
+        resolve_rules, DataDescriptionRecord, Deserialize, HeaderRecord, MaxSize, Record, Recorder,
+        RuleError, Serialize, Timestamp, FORMAT_VERSION, TOPIC_TYPENAME_MAX_SIZE,
+    };
+    use crate::com::{init_topic, ActivityOutput};
+    use crate::recording::reader::{
+        OwnedRecord, ReadError, RecordIter, RecordingEnd, RecordingReader,
+    };
+    use crate::recording::registry::TypeRegistry;
+    use crate::recording::transcoder::{RawRecordable, RecordingTranscoder};
+    use crate::register_types;
+    use crate::signalling::inter_proc_socket::SignalPdu;
+    use crate::signalling::{self, CycleId, Signal};
+    use crate::timestamp::{self, timestamp, SyncInfo};
+    use std::collections::HashMap;
+    use std::io::{BufWriter, Write};
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+    use std::{env, fs, mem, process};
+
+    /// Create a recording starting with the format version, like the recorder does
+    fn new_recording() -> Vec<u8> {
+        let mut recording = Vec::new();
+        Recorder::record_format(&mut recording);
+        recording
+    }
+
     #[test]
     fn test_max_size_for_data_description_record() {
         let s = String::from_utf8(vec![b'a'; TOPIC_TYPENAME_MAX_SIZE]).expect("valid string");
@@ -346,8 +925,522 @@ mod test {
             data_size: usize::MAX,
             type_name: &s,
             topic: &s,
+            version: Some(u16::MAX),
+            raw: true,
         };
         let mut buf = [0u8; DataDescriptionRecord::POSTCARD_MAX_SIZE];
         postcard::to_slice(&record, &mut buf).expect("should fit");
     }
+
+    #[test]
+    fn test_dedup_constant_topic() {
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/constant";
+        let _topic = init_topic::<u32>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<u32>::get(topic).unwrap();
+        let transcoders = vec![RecordingTranscoder::<u32>::build(topic, "u32", None)];
+        let mut last_hashes = Some(HashMap::new());
+        let mut recording = new_recording();
+        let mut buf = [0u8; 8];
+
+        // The same value is published and recorded in three steps
+        for _ in 0..3 {
+            output.write_uninit().unwrap().write_payload(42).send();
+            Recorder::record_com_data(&transcoders, &mut last_hashes, &mut recording, &mut buf);
+        }
+
+        let records: Vec<_> = RecordingReader::new(&recording)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let full = records
+            .iter()
+            .filter(|(record, _)| matches!(record, Record::DataDescription(_)))
+            .count();
+        assert_eq!(full, 1);
+        assert_eq!(records.len(), 3);
+
+        // The reader reconstructs the held value for each step
+        for (_, data) in records {
+            assert_eq!(postcard::from_bytes::<u32>(data.unwrap()).unwrap(), 42);
+        }
+    }
+
+    #[test]
+    fn test_version_mismatch_detected() {
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/versioned";
+        let _topic = init_topic::<u32>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<u32>::get(topic).unwrap();
+        let mut recorded_registry = TypeRegistry::default();
+        register_types!(recorded_registry, u32: "versioned_u32" => 2);
+        let transcoders = vec![RecordingTranscoder::<u32>::build(
+            topic,
+            "versioned_u32",
+            recorded_registry
+                .info_name("versioned_u32")
+                .unwrap()
+                .version,
+        )];
+        let mut recording = new_recording();
+        let mut buf = [0u8; 8];
+        output.write_uninit().unwrap().write_payload(7).send();
+        Recorder::record_com_data(&transcoders, &mut None, &mut recording, &mut buf);
+
+        // The version is recorded and accepted by a registry of the same version
+        let records: Vec<_> = RecordingReader::new(&recording)
+            .registry(&recorded_registry)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let Record::DataDescription(description) = &records[0].0 else {
+            panic!("expected data description record");
+        };
+        assert_eq!(description.version, Some(2));
+
+        // A registry with another version rejects the recording
+        let mut player_registry = TypeRegistry::default();
+        register_types!(player_registry, u32: "versioned_u32" => 3);
+        let err = RecordingReader::new(&recording)
+            .registry(&player_registry)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, ReadError::Version(_)));
+        assert_eq!(
+            err.to_string(),
+            "type versioned_u32 recorded as version 2, registry has version 3"
+        );
+    }
+
+    #[test]
+    fn test_timing_only() {
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/timing";
+        let _topic = init_topic::<u32>(topic, 1, 2).unwrap();
+        let output = ActivityOutput::<u32>::get(topic).unwrap();
+        let mut registry = TypeRegistry::default();
+        register_types!(registry, u32: "u32");
+        let rules = HashMap::from([(topic, "u32")]);
+
+        // Record a cycle of one step as the recorder does, with and without com data
+        let record = |timing_only| {
+            let transcoders = Recorder::build_transcoders(&rules, &registry, timing_only);
+            let mut recording = new_recording();
+            let mut buf = [0u8; 8];
+            output.write_uninit().unwrap().write_payload(42).send();
+            let signals = [
+                Signal::TaskChainStart(timestamp()),
+                Signal::Step((0.into(), timestamp(), CycleId(1))),
+                Signal::Ready((0.into(), timestamp(), CycleId(1))),
+                Signal::TaskChainEnd(timestamp()),
+            ];
+            for signal in signals {
+                if matches!(signal, Signal::Step(_) | Signal::TaskChainEnd(_)) {
+                    Recorder::record_com_data(&transcoders, &mut None, &mut recording, &mut buf);
+                }
+                Recorder::record_signal(signal, &mut recording);
+            }
+            RecordingReader::new(&recording)
+                .map(|record| matches!(record.unwrap().0, Record::Signal(_)))
+                .collect::<Vec<_>>()
+        };
+
+        // The full recording contains the payload, the timing-only recording signals only
+        assert_eq!(record(false), [true, false, true, true, true]);
+        assert_eq!(record(true), [true; 4]);
+    }
+
+    #[test]
+    fn test_prefix_rule() {
+        timestamp::initialize_for_test();
+        let topics = ["test/recorder/prefix/a", "test/recorder/prefix/b"];
+        let _topics: Vec<_> = topics
+            .iter()
+            .map(|topic| init_topic::<u32>(topic, 1, 2).unwrap())
+            .collect();
+        let _other = init_topic::<u32>("test/recorder/other", 1, 1).unwrap();
+        let outputs: Vec<_> = topics
+            .iter()
+            .map(|topic| ActivityOutput::<u32>::get(topic).unwrap())
+            .collect();
+        let mut registry = TypeRegistry::default();
+        register_types!(registry, u32: "u32");
+        let rules = HashMap::from([("test/recorder/prefix/*", "u32")]);
+
+        // The prefix rule resolves to both topics below the prefix, read by one transcoder each
+        let transcoders = Recorder::build_transcoders(&rules, &registry, false);
+        let mut recorded: Vec<_> = transcoders.iter().map(|t| t.topic()).collect();
+        recorded.sort();
+        assert_eq!(recorded, topics);
+
+        for (value, output) in outputs.iter().enumerate() {
+            output
+                .write_uninit()
+                .unwrap()
+                .write_payload(value as u32)
+                .send();
+        }
+        let mut recording = new_recording();
+        let mut buf = [0u8; 8];
+        Recorder::record_com_data(&transcoders, &mut None, &mut recording, &mut buf);
+        let mut records: Vec<_> = RecordingReader::new(&recording)
+            .map(|record| {
+                let (record, data) = record.unwrap();
+                let Record::DataDescription(description) = record else {
+                    panic!("expected data description record");
+                };
+                let value = postcard::from_bytes::<u32>(data.unwrap()).unwrap();
+                (description.topic.to_string(), description.type_name, value)
+            })
+            .collect();
+        records.sort();
+        assert_eq!(
+            records,
+            [
+                (topics[0].to_string(), "u32", 0),
+                (topics[1].to_string(), "u32", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefix_rule_errors() {
+        let mut registry = TypeRegistry::default();
+        register_types!(registry, u32: "u32", u64: "u64");
+        let topics = ["test/rules/a".to_string(), "test/rules/b".to_string()];
+
+        // Overlapping prefix rules with different types are ambiguous, unless an exact rule
+        // decides
+        let rules = HashMap::from([("test/*", "u32"), ("test/rules/*", "u64")]);
+        assert_eq!(
+            resolve_rules(&rules, &topics, &registry),
+            Err(RuleError::Ambiguous {
+                topic: "test/rules/a".to_string(),
+                type_names: vec!["u32", "u64"],
+            })
+        );
+        let rules = HashMap::from([
+            ("test/*", "u32"),
+            ("test/rules/*", "u32"),
+            ("test/rules/b", "u64"),
+        ]);
+        let mut resolved = resolve_rules(&rules, &topics, &registry).unwrap();
+        resolved.sort();
+        assert_eq!(resolved, [("test/rules/a", "u32"), ("test/rules/b", "u64")]);
+
+        // Each matched topic needs a registered type
+        let rules = HashMap::from([("test/rules/*", "unknown")]);
+        assert!(matches!(
+            resolve_rules(&rules, &topics, &registry),
+            Err(RuleError::UnknownType {
+                type_name: "unknown",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_timed_flush() {
+        timestamp::initialize_for_test();
+        let path = env::temp_dir().join(format!("feo-test-timed-flush-{}.bin", process::id()));
+        let mut writer = BufWriter::new(fs::File::create(&path).unwrap());
+        Recorder::record_format(&mut writer);
+        let step = Signal::Step((0.into(), timestamp(), CycleId(1)));
+        Recorder::record_signal(step, &mut writer);
+        assert!(fs::read(&path).unwrap().is_empty());
+
+        // No task chain end is received, the record is persisted after the flush interval
+        let (_sender, mut receiver) = signalling::channel::<SignalPdu>();
+        let interval = Duration::from_millis(50);
+        let start = Instant::now();
+        let mut next_flush = Some(start + interval);
+        while fs::read(&path).unwrap().is_empty() {
+            let pdu = Recorder::recv_or_flush(
+                &mut receiver,
+                &mut writer,
+                Some(interval),
+                &mut next_flush,
+            );
+            assert!(pdu.is_none());
+            assert!(start.elapsed() < interval * 4, "not flushed in time");
+        }
+        assert!(start.elapsed() >= interval);
+
+        // The record has been flushed completely
+        let recording = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let records: Vec<_> = RecordingReader::new(&recording)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(matches!(
+            records[..],
+            [(Record::Signal(record), None)] if matches!(record.signal, Signal::Step(_))
+        ));
+    }
+
+    #[test]
+    fn test_record_iter() {
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/iter";
+        let _topic = init_topic::<u32>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<u32>::get(topic).unwrap();
+        let transcoders = vec![RecordingTranscoder::<u32>::build(topic, "u32", None)];
+        let mut last_hashes = Some(HashMap::new());
+        let mut buf = [0u8; 8];
+
+        let path = env::temp_dir().join(format!("feo-test-record-iter-{}.bin", process::id()));
+        let mut writer = BufWriter::new(fs::File::create(&path).unwrap());
+        Recorder::record_format(&mut writer);
+        Recorder::record_header(&mut writer);
+        let step = Signal::Step((0.into(), timestamp(), CycleId(1)));
+        Recorder::record_signal(step, &mut writer);
+        for _ in 0..2 {
+            output.write_uninit().unwrap().write_payload(42).send();
+            Recorder::record_com_data(&transcoders, &mut last_hashes, &mut writer, &mut buf);
+        }
+        let shutdown = Signal::SystemShutdown(timestamp());
+        Recorder::record_signal(shutdown, &mut writer);
+        writer.flush().unwrap();
+        drop(writer);
+
+        let records: Vec<_> = RecordIter::open(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            &records[..],
+            [
+                OwnedRecord::Header(_),
+                OwnedRecord::Signal {
+                    signal: Signal::Step(_),
+                    ..
+                },
+                OwnedRecord::Data { .. },
+                OwnedRecord::Unchanged { .. },
+                OwnedRecord::Signal {
+                    signal: Signal::SystemShutdown(_),
+                    ..
+                },
+            ]
+        ));
+
+        // The payload and type name of unchanged records are resolved
+        for record in &records[2..4] {
+            let (OwnedRecord::Data {
+                topic: record_topic,
+                type_name,
+                payload,
+                ..
+            }
+            | OwnedRecord::Unchanged {
+                topic: record_topic,
+                type_name,
+                payload,
+                ..
+            }) = record
+            else {
+                unreachable!();
+            };
+            assert_eq!(record_topic, topic);
+            assert_eq!(type_name, "u32");
+            assert_eq!(postcard::from_bytes::<u32>(payload).unwrap(), 42);
+        }
+    }
+
+    #[test]
+    fn test_end_of_recording() {
+        timestamp::initialize_for_test();
+        let mut recording = new_recording();
+        let step = Signal::Step((0.into(), timestamp(), CycleId(1)));
+        Recorder::record_signal(step, &mut recording);
+        Recorder::record_signal(step, &mut recording);
+
+        // A recorder killed after writing part of its last record leaves a truncated recording
+        let killed = &recording[..recording.len() - 1];
+        let mut reader = RecordingReader::new(killed);
+        assert_eq!(reader.end(), None);
+        assert!(reader.by_ref().last().unwrap().is_err());
+        assert_eq!(reader.end(), Some(RecordingEnd::Truncated));
+
+        // So does one killed between records
+        let mut iter = RecordIter::new(recording.clone());
+        assert!(iter.by_ref().all(|record| record.is_ok()));
+        assert_eq!(iter.end(), Some(RecordingEnd::Truncated));
+
+        // A recording closed on system shutdown ends cleanly
+        Recorder::record_signal(Signal::SystemShutdown(timestamp()), &mut recording);
+        Recorder::record_end(&mut recording);
+        let mut reader = RecordingReader::new(&recording);
+        assert!(matches!(
+            reader.by_ref().last().unwrap().unwrap().0,
+            Record::EndOfRecording { .. }
+        ));
+        assert_eq!(reader.end(), Some(RecordingEnd::Clean));
+        let mut iter = RecordIter::new(recording);
+        assert!(iter.by_ref().all(|record| record.is_ok()));
+        assert_eq!(iter.end(), Some(RecordingEnd::Clean));
+    }
+
+    #[test]
+    fn test_format_version_checked() {
+        timestamp::initialize_for_test();
+        let step = Signal::Step((0.into(), timestamp(), CycleId(1)));
+        let mut recording = new_recording();
+        assert_eq!(RecordingReader::new(&recording).count(), 0);
+        Recorder::record_signal(step, &mut recording);
+        assert!(RecordingReader::new(&recording).all(|record| record.is_ok()));
+
+        // A recording of another format version is rejected as a whole
+        let mut other = Vec::new();
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let version = FORMAT_VERSION + 1;
+        other.extend_from_slice(postcard::to_slice(&Record::Format { version }, &mut buf).unwrap());
+        Recorder::record_signal(step, &mut other);
+        let records: Vec<_> = RecordingReader::new(&other).collect();
+        assert!(matches!(
+            records[..],
+            [Err(ReadError::Format(Some(v)))] if v == version
+        ));
+
+        // So is a recording without format version
+        let mut legacy = Vec::new();
+        Recorder::record_signal(step, &mut legacy);
+        let records: Vec<_> = RecordIter::new(legacy).collect();
+        assert!(matches!(records[..], [Err(ReadError::Format(None))]));
+    }
+
+    #[test]
+    fn test_record_iter_decode() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize, MaxSize)]
+        struct CameraImage {
+            num_people: usize,
+            num_cars: usize,
+            distance_obstacle: f64,
+        }
+
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/camera";
+        let _topic = init_topic::<CameraImage>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<CameraImage>::get(topic).unwrap();
+        let mut registry = TypeRegistry::default();
+        register_types!(registry, CameraImage: "camera_image" => 1, u32);
+        let info = registry.info_name("camera_image").unwrap();
+        let transcoders = vec![(info.comrec_builder)(topic)];
+        let mut recording = new_recording();
+        let mut buf = [0u8; CameraImage::POSTCARD_MAX_SIZE];
+        let image = || CameraImage {
+            num_people: 3,
+            num_cars: 7,
+            distance_obstacle: 12.5,
+        };
+        output.write_uninit().unwrap().write_payload(image()).send();
+        Recorder::record_com_data(&transcoders, &mut None, &mut recording, &mut buf);
+
+        let records: Vec<_> = RecordIter::new(recording)
+            .with_registry(&registry)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        let decoded = records[0].decode_as::<CameraImage>().unwrap().unwrap();
+        assert_eq!(decoded, image());
+
+        // Decoding as another registered type is rejected
+        let err = records[0].decode_as::<u32>().unwrap().unwrap_err();
+        assert!(matches!(err, ReadError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_record_raw() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize, MaxSize)]
+        #[repr(C)]
+        struct Scene {
+            num_people: usize,
+            num_cars: usize,
+            distance_obstacle: f64,
+            distance_left_lane: f64,
+            distance_right_lane: f64,
+        }
+
+        // SAFETY: `Scene` is repr(C) and consists of 8-byte fields only, without padding
+        unsafe impl RawRecordable for Scene {}
+
+        timestamp::initialize_for_test();
+        let topic = "test/recorder/scene";
+        let _topic = init_topic::<Scene>(topic, 1, 1).unwrap();
+        let output = ActivityOutput::<Scene>::get(topic).unwrap();
+        let mut registry = TypeRegistry::default();
+        registry.add_raw::<Scene>(Some("scene"), Some(1));
+        let info = registry.info_name("scene").unwrap();
+        let transcoders = vec![(info.comrec_builder)(topic)];
+        let mut recording = new_recording();
+        let mut buf = [0u8; mem::size_of::<Scene>()];
+        let scene = || Scene {
+            num_people: 3,
+            num_cars: 7,
+            distance_obstacle: 12.5,
+            distance_left_lane: 0.75,
+            distance_right_lane: 1.25,
+        };
+        for _ in 0..2 {
+            output.write_uninit().unwrap().write_payload(scene()).send();
+            Recorder::record_com_data(&transcoders, &mut None, &mut recording, &mut buf);
+        }
+
+        let records: Vec<_> = RecordIter::new(recording)
+            .with_registry(&registry)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        for record in &records {
+            let OwnedRecord::Data { raw, payload, .. } = record else {
+                panic!("unexpected record {record:?}");
+            };
+            assert!(raw);
+
+            // The raw payload skips serialization at the cost of size: the varint encoding
+            // of the counts makes the postcard serialization of this scene 26 bytes long
+            assert_eq!(payload.len(), 40);
+            let mut serialized = [0u8; Scene::POSTCARD_MAX_SIZE];
+            let serialized = postcard::to_slice(&scene(), &mut serialized).unwrap();
+            assert_eq!(serialized.len(), 26);
+
+            let decoded = record.decode_raw::<Scene>().unwrap().unwrap();
+            assert_eq!(decoded, scene());
+
+            // Raw payloads cannot be deserialized
+            let err = record.decode_as::<Scene>().unwrap().unwrap_err();
+            assert!(matches!(err, ReadError::Encoding { raw: true }));
+        }
+    }
+
+    #[test]
+    fn test_header_absolute_time() {
+        let startup = Duration::from_secs(1_700_000_000);
+        let header = HeaderRecord {
+            sync_info: SyncInfo::from(startup.as_nanos() as u64),
+            speed: Some(-2),
+        };
+        let mut buf = [0u8; Record::POSTCARD_MAX_SIZE];
+        let serialized = postcard::to_slice(&Record::Header(header), &mut buf).unwrap();
+        let Record::Header(header) = postcard::from_bytes(serialized).unwrap() else {
+            panic!("expected header record");
+        };
+
+        // Timestamps are slowed down by a factor of two
+        let timestamp = Timestamp(Duration::from_millis(1500));
+        assert_eq!(
+            header.system_time(timestamp),
+            UNIX_EPOCH + startup + Duration::from_millis(3000)
+        );
+
+        let header = HeaderRecord {
+            speed: None,
+            ..header
+        };
+        assert_eq!(
+            header.system_time(timestamp),
+            UNIX_EPOCH + startup + Duration::from_millis(1500)
+        );
+    }
 }
@@ -3,8 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Type registry
-use crate::recording::transcoder::{ComRecTranscoderBuilder, RecordingTranscoder};
+use crate::recording::transcoder::{
+    ComRecTranscoderBuilder, RawRecordable, RawTranscoder, RecordingTranscoder,
+};
 use serde::Serialize;
+use std::any::TypeId;
 use std::collections::HashMap;
 
 /// Registry of types used in the com layer
@@ -43,17 +46,75 @@ impl TypeRegistry {
     /// - a type with identical type id (i.e. the same type) has already been registered
     /// - the explicitly or implicitly provided type name is not unique
     pub fn add<
-        T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug + 'static,
+        T: Serialize
+            + postcard::experimental::max_size::MaxSize
+            + std::fmt::Debug
+            + Send
+            + Sync
+            + 'static,
     >(
         &mut self,
         type_name: Option<&'static str>,
+    ) -> &mut Self {
+        self.add_versioned::<T>(type_name, None)
+    }
+
+    /// Add the given type with an optional schema version to the registry
+    ///
+    /// The version is written with each recorded payload of the type and checked when reading
+    /// the recording (see [`check_version`](Self::check_version)). Increase it whenever the
+    /// serialized layout of the type changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`add`](Self::add)
+    pub fn add_versioned<
+        T: Serialize
+            + postcard::experimental::max_size::MaxSize
+            + std::fmt::Debug
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &mut self,
+        type_name: Option<&'static str>,
+        version: Option<u16>,
     ) -> &mut Self {
         let type_name = type_name.unwrap_or(core::any::type_name::<T>());
-        let decser_builder =
-            Box::new(|topic: &'static str| RecordingTranscoder::<T>::build(topic, type_name))
-                as Box<dyn ComRecTranscoderBuilder>;
+        let decser_builder = Box::new(move |topic: &'static str| {
+            RecordingTranscoder::<T>::build(topic, type_name, version)
+        }) as Box<dyn ComRecTranscoderBuilder>;
         let type_info = TypeInfo {
             type_name,
+            type_id: TypeId::of::<T>(),
+            version,
+            comrec_builder: decser_builder,
+        };
+        self.add_helper(type_info)
+    }
+
+    /// Add the given type with an optional schema version to the registry, recording the
+    /// raw in-memory bytes of its values
+    ///
+    /// Recording copies the bytes of the com layer sample instead of serializing them. Read
+    /// the payloads back with [`OwnedRecord::decode_raw`](crate::recording::reader::OwnedRecord::decode_raw).
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`add`](Self::add)
+    pub fn add_raw<T: RawRecordable>(
+        &mut self,
+        type_name: Option<&'static str>,
+        version: Option<u16>,
+    ) -> &mut Self {
+        let type_name = type_name.unwrap_or(core::any::type_name::<T>());
+        let decser_builder = Box::new(move |topic: &'static str| {
+            RawTranscoder::<T>::build(topic, type_name, version)
+        }) as Box<dyn ComRecTranscoderBuilder>;
+        let type_info = TypeInfo {
+            type_name,
+            type_id: TypeId::of::<T>(),
+            version,
             comrec_builder: decser_builder,
         };
         self.add_helper(type_info)
@@ -71,6 +132,41 @@ impl TypeRegistry {
     pub fn info_name(&self, type_name: &str) -> Option<&TypeInfo> {
         self.map.get(type_name)
     }
+
+    /// Check that the given type has been recorded with the schema version registered for it
+    ///
+    /// Types not in the registry are not checked.
+    pub fn check_version(
+        &self,
+        type_name: &str,
+        recorded: Option<u16>,
+    ) -> Result<(), VersionMismatch> {
+        match self.map.get(type_name) {
+            Some(info) if info.version != recorded => Err(VersionMismatch {
+                type_name: info.type_name,
+                recorded,
+                registered: info.version,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Iterate over the names of all registered types, in no particular order
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.map.keys().copied()
+    }
+
+    /// Check if a type with the given name is registered
+    pub fn contains(&self, type_name: &str) -> bool {
+        self.map.contains_key(type_name)
+    }
+
+    /// Remove the type with the given name from the registry
+    ///
+    /// Returns whether the type had been registered.
+    pub fn remove(&mut self, type_name: &str) -> bool {
+        self.map.remove(type_name).is_some()
+    }
 }
 
 impl Default for TypeRegistry {
@@ -87,6 +183,12 @@ pub struct TypeInfo {
     // Human-readable type name
     pub type_name: &'static str,
 
+    // Id of the registered type
+    pub type_id: TypeId,
+
+    // Schema version of the serialized type, if any
+    pub version: Option<u16>,
+
     // Corresponding [`ComRecTranscoderBuilder`] object
     pub comrec_builder: Box<dyn ComRecTranscoderBuilder>,
 }
@@ -94,14 +196,54 @@ pub struct TypeInfo {
 impl std::fmt::Debug for TypeInfo {
     fn fmt(&self, writer: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writer.write_fmt(format_args!(
-            "[ type_name: {:?}, decser_builder: Box(?) ]",
-            self.type_name
+            "[ type_name: {:?}, version: {:?}, decser_builder: Box(?) ]",
+            self.type_name, self.version
         ))
     }
 }
 
+/// Error of a type recorded with another schema version than registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// Name of the type
+    pub type_name: &'static str,
+    /// Version the type has been recorded with
+    pub recorded: Option<u16>,
+    /// Version registered for the type
+    pub registered: Option<u16>,
+}
+
+impl std::fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = |v: Option<u16>| v.map_or("none".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "type {} recorded as version {}, registry has version {}",
+            self.type_name,
+            version(self.recorded),
+            version(self.registered)
+        )
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+/// Register a type, optionally with a type name and a schema version
+///
+/// ```ignore
+/// register_type!(registry, Scene);
+/// register_type!(registry, Scene: "scene");
+/// register_type!(registry, Scene => 2);
+/// register_type!(registry, Scene: "scene" => 2);
+/// ```
 #[macro_export]
 macro_rules! register_type {
+    ($registry:ident, $type:ty: $name:expr => $version:expr) => {
+        $registry.add_versioned::<$type>(Some($name), Some($version))
+    };
+    ($registry:ident, $type:ty => $version:expr) => {
+        $registry.add_versioned::<$type>(None, Some($version))
+    };
     ($registry:ident, $type:ty: $name:expr) => {
         $registry.add::<$type>(Some($name))
     };
@@ -110,9 +252,10 @@ macro_rules! register_type {
     };
 }
 
+/// Register a list of types, each optionally with a type name and a schema version
 #[macro_export]
 macro_rules! register_types {
-    ($registry:ident, $($type:ty $(:$name:expr)?),+ $(,)?) => {$(register_type!($registry, $type $(:$name)?));+};
+    ($registry:ident, $($type:ty $(:$name:expr)? $(=> $version:expr)?),+ $(,)?) => {$($crate::register_type!($registry, $type $(:$name)? $(=> $version)?));+};
 }
 
 /////////////
@@ -151,3 +294,45 @@ fn test_type_registry() {
     assert!(!registry.map.contains_key(&type_name));
     assert!(registry.info_name(type_name).is_none());
 }
+
+#[test]
+fn test_type_registry_names_and_remove() {
+    #[derive(Debug, serde::Serialize, postcard::experimental::max_size::MaxSize)]
+    struct TestType1 {}
+
+    #[derive(Debug, serde::Serialize, postcard::experimental::max_size::MaxSize)]
+    struct TestType2 {}
+
+    let mut registry = TypeRegistry::default();
+    assert_eq!(registry.type_names().count(), 0);
+    register_types!(registry, TestType1, TestType2: "my_test_type2_name");
+
+    // list and contains
+    let type_name1 = core::any::type_name::<TestType1>();
+    let mut names: Vec<&str> = registry.type_names().collect();
+    names.sort();
+    let mut expected = vec![type_name1, "my_test_type2_name"];
+    expected.sort();
+    assert_eq!(names, expected);
+    assert!(registry.contains(type_name1));
+    assert!(registry.contains("my_test_type2_name"));
+    assert!(!registry.contains("foo"));
+
+    // remove an existing name once
+    assert!(registry.remove(type_name1));
+    assert!(!registry.contains(type_name1));
+    assert!(registry.info_name(type_name1).is_none());
+    assert!(!registry.remove(type_name1));
+    assert_eq!(
+        registry.type_names().collect::<Vec<_>>(),
+        ["my_test_type2_name"]
+    );
+
+    // remove a non-existent name
+    assert!(!registry.remove("foo"));
+    assert_eq!(registry.type_names().count(), 1);
+
+    // a removed type can be registered again
+    register_type!(registry, TestType1);
+    assert!(registry.contains(type_name1));
+}
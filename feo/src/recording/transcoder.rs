@@ -6,23 +6,57 @@
 
 use crate::com::ActivityInput;
 use serde::Serialize;
+use std::{mem, slice};
+
+/// Type recorded as its raw in-memory bytes instead of a postcard serialization
+///
+/// Recording a raw type copies the bytes of the com layer sample to the recording without
+/// serializing it. The raw representation may be larger than the serialization (e.g. integers
+/// are not varint encoded) and can only be read back on a platform with the same layout.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` without padding, pointers, references or drop glue,
+/// such that every byte of a value is initialized and any recorded value read back from its
+/// bytes is a valid value.
+pub unsafe trait RawRecordable: std::fmt::Debug + Send + Sync + 'static {}
+
+/// Get the raw in-memory bytes of the given value
+fn raw_bytes<T: RawRecordable>(value: &T) -> &[u8] {
+    // SAFETY: Implementors of `RawRecordable` guarantee that all bytes of the value are
+    // initialized. The slice borrows the value.
+    unsafe { slice::from_raw_parts((value as *const T).cast::<u8>(), mem::size_of::<T>()) }
+}
 
 /// Transcode data of the given type from com layer representation to recording serialization
-pub(crate) struct RecordingTranscoder<T: Serialize + 'static + std::fmt::Debug> {
+pub(crate) struct RecordingTranscoder<T: Serialize + std::fmt::Debug + Send + Sync + 'static> {
     input: ActivityInput<T>,
     topic: &'static str,
     type_name: &'static str,
+    version: Option<u16>,
 }
 
-impl<T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug>
-    RecordingTranscoder<T>
+impl<
+        T: Serialize
+            + postcard::experimental::max_size::MaxSize
+            + std::fmt::Debug
+            + Send
+            + Sync
+            + 'static,
+    > RecordingTranscoder<T>
 {
-    /// Create a transcoder reading from the given com layer topic
-    pub fn build(topic: &'static str, type_name: &'static str) -> Box<dyn ComRecTranscoder> {
+    /// Create a transcoder reading from the given com layer topic, recording the given
+    /// schema version of the type
+    pub fn build(
+        topic: &'static str,
+        type_name: &'static str,
+        version: Option<u16>,
+    ) -> Box<dyn ComRecTranscoder> {
         Box::new(RecordingTranscoder::<T> {
-            input: ActivityInput::get(topic),
+            input: ActivityInput::get_or_panic(topic),
             topic,
             type_name,
+            version,
         })
     }
 
@@ -39,11 +73,99 @@ impl<T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug>
     }
 }
 
+/// Transcode data of the given type from com layer representation to its raw bytes for recording
+pub(crate) struct RawTranscoder<T: RawRecordable> {
+    input: ActivityInput<T>,
+    topic: &'static str,
+    type_name: &'static str,
+    version: Option<u16>,
+}
+
+impl<T: RawRecordable> RawTranscoder<T> {
+    /// Create a transcoder reading from the given com layer topic, recording the given
+    /// schema version of the type
+    pub fn build(
+        topic: &'static str,
+        type_name: &'static str,
+        version: Option<u16>,
+    ) -> Box<dyn ComRecTranscoder> {
+        Box::new(RawTranscoder::<T> {
+            input: ActivityInput::get_or_panic(topic),
+            topic,
+            type_name,
+            version,
+        })
+    }
+}
+
+impl<T: RawRecordable> ComRecTranscoder for RawTranscoder<T> {
+    fn buffer_size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    fn read_transcode<'a>(&self, buf: &'a mut [u8]) -> Option<&'a mut [u8]> {
+        let input = self.input.read()?;
+        let bytes = raw_bytes(input.get());
+        let buf = &mut buf[..bytes.len()];
+        buf.copy_from_slice(bytes);
+        Some(buf)
+    }
+
+    fn read_record(&self, _buf: &mut [u8], record: &mut dyn FnMut(&[u8])) {
+        // Record the bytes of the com layer sample without copying them
+        if let Some(input) = self.input.read() {
+            record(raw_bytes(input.get()));
+        }
+    }
+
+    #[cfg(feature = "recording_perfetto")]
+    fn read_value(&self) -> Option<serde_json::Value> {
+        // Raw types are not serializable, their debug representation stands in
+        let input = self.input.read()?;
+        Some(serde_json::Value::String(format!("{:?}", input.get())))
+    }
+
+    fn raw(&self) -> bool {
+        true
+    }
+
+    fn topic(&self) -> &'static str {
+        self.topic
+    }
+
+    fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    fn version(&self) -> Option<u16> {
+        self.version
+    }
+}
+
 /// Trait implementing reading and transcoding of com data for recording
 pub trait ComRecTranscoder {
     /// Read com layer data and serialize them for recording
     fn read_transcode<'a>(&self, buf: &'a mut [u8]) -> Option<&'a mut [u8]>;
 
+    /// Read com layer data and pass the bytes to record to `record`, if any
+    ///
+    /// Defaults to the serialization of [`read_transcode`](Self::read_transcode) in `buf`.
+    fn read_record(&self, buf: &mut [u8], record: &mut dyn FnMut(&[u8])) {
+        if let Some(data) = self.read_transcode(buf) {
+            record(data);
+        }
+    }
+
+    /// Read com layer data as JSON value for the debug annotations of a perfetto trace
+    #[cfg(feature = "recording_perfetto")]
+    fn read_value(&self) -> Option<serde_json::Value>;
+
+    /// Whether the recorded bytes are the raw in-memory representation of the type
+    /// instead of its postcard serialization, see [`RawRecordable`]
+    fn raw(&self) -> bool {
+        false
+    }
+
     /// Maximum buffer size required for serialization
     fn buffer_size(&self) -> usize;
 
@@ -52,11 +174,20 @@ pub trait ComRecTranscoder {
 
     // Get the type name of data this transcoder is transcoding
     fn type_name(&self) -> &'static str;
+
+    // Get the schema version of the type, if any
+    fn version(&self) -> Option<u16>;
 }
 
 /// Implement the recording-and-serialization trait for all [`RecordingTranscoder`] types
-impl<T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug> ComRecTranscoder
-    for RecordingTranscoder<T>
+impl<
+        T: Serialize
+            + postcard::experimental::max_size::MaxSize
+            + std::fmt::Debug
+            + Send
+            + Sync
+            + 'static,
+    > ComRecTranscoder for RecordingTranscoder<T>
 {
     fn buffer_size(&self) -> usize {
         T::POSTCARD_MAX_SIZE
@@ -65,6 +196,14 @@ impl<T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug>
         self.read_and_serialize(buf)
     }
 
+    #[cfg(feature = "recording_perfetto")]
+    fn read_value(&self) -> Option<serde_json::Value> {
+        let input = self.input.read()?;
+        serde_json::to_value(input.get())
+            .inspect_err(|e| feo_log::error!("Failed to convert {:?}: {e}", input.get()))
+            .ok()
+    }
+
     fn topic(&self) -> &'static str {
         self.topic
     }
@@ -73,6 +212,10 @@ impl<T: Serialize + postcard::experimental::max_size::MaxSize + std::fmt::Debug>
     fn type_name(&self) -> &'static str {
         self.type_name
     }
+
+    fn version(&self) -> Option<u16> {
+        self.version
+    }
 }
 
 /// Builder trait for a [`ComRecTranscoder`] object
@@ -83,5 +226,6 @@ pub trait ComRecTranscoderBuilder: Fn(&'static str) -> Box<dyn ComRecTranscoder>
 
 /// Implement the builder trait for any function matching the [`ComRecTranscoderBuilder`] builder trait.
 ///
-/// In particular, this will apply to the [`build`] method of [`RecordingTranscoder`]
+/// In particular, this will apply to the closures wrapping the [`build`](RecordingTranscoder::build)
+/// method of [`RecordingTranscoder`] in the type registry
 impl<T: Fn(&'static str) -> Box<dyn ComRecTranscoder> + Send> ComRecTranscoderBuilder for T {}
@@ -0,0 +1,108 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Pre-shared key authentication of agents connecting to the primary agent
+//!
+//! After receiving the hello of a connecting agent or recorder, the primary agent sends a
+//! random challenge. The peer answers with an HMAC-SHA256 over the challenge and its hello,
+//! keyed with the pre-shared key. Peers failing to answer correctly are disconnected.
+//!
+//! The signals exchanged after the hello are not authenticated nor encrypted.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::time::Duration;
+
+/// Size of a pre-shared key in bytes
+pub const KEY_SIZE: usize = 32;
+
+/// Size of a challenge in bytes
+pub(crate) const CHALLENGE_SIZE: usize = 16;
+
+/// Size of the answer to a challenge in bytes
+pub(crate) const RESPONSE_SIZE: usize = 32;
+
+/// Time to wait for a challenge or its answer
+pub(crate) const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Key shared by all agents and recorders of a deployment
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PreSharedKey([u8; KEY_SIZE]);
+
+impl PreSharedKey {
+    /// Create a key from the given bytes
+    pub const fn new(key: [u8; KEY_SIZE]) -> Self {
+        Self(key)
+    }
+}
+
+impl fmt::Debug for PreSharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PreSharedKey(..)")
+    }
+}
+
+/// Create a new random challenge
+///
+/// Challenges must not repeat to prevent replaying recorded answers, so they are taken from
+/// the random number generator of the operating system.
+pub(crate) fn challenge() -> [u8; CHALLENGE_SIZE] {
+    let mut challenge = [0u8; CHALLENGE_SIZE];
+    getrandom::getrandom(&mut challenge).expect("failed to get random challenge");
+    challenge
+}
+
+/// Answer the given challenge for the given hello
+pub(crate) fn respond(key: &PreSharedKey, challenge: &[u8], hello: &[u8]) -> [u8; RESPONSE_SIZE] {
+    mac(key, challenge, hello).finalize().into_bytes().into()
+}
+
+/// Check the answer to the given challenge for the given hello in constant time
+pub(crate) fn verify(key: &PreSharedKey, challenge: &[u8], hello: &[u8], response: &[u8]) -> bool {
+    mac(key, challenge, hello).verify_slice(response).is_ok()
+}
+
+/// HMAC-SHA256 over the given challenge and hello, keyed with the given key
+fn mac(key: &PreSharedKey, challenge: &[u8], hello: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key.0).expect("HMAC takes keys of any size");
+    mac.update(challenge);
+    mac.update(hello);
+    mac
+}
+
+#[cfg(test)]
+mod test {
+    use super::{challenge, respond, verify, PreSharedKey, KEY_SIZE};
+
+    #[test]
+    fn test_respond() {
+        // Test case 2 of RFC 4231, with the key padded to the size of a pre-shared key
+        let mut key = [0; KEY_SIZE];
+        key[..4].copy_from_slice(b"Jefe");
+        assert_eq!(
+            respond(&PreSharedKey::new(key), b"what do ya want ", b"for nothing?"),
+            [
+                0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+                0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+                0x64, 0xec, 0x38, 0x43
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = PreSharedKey::new([7; KEY_SIZE]);
+        let other_key = PreSharedKey::new([8; KEY_SIZE]);
+        let challenge = challenge();
+        let hello = [1, 0, 0, 0, 0, 0, 0, 0, 0, 101];
+
+        let response = respond(&key, &challenge, &hello);
+        assert!(verify(&key, &challenge, &hello, &response));
+        assert!(!verify(&other_key, &challenge, &hello, &response));
+        assert!(!verify(&key, &challenge, &hello[1..], &response));
+        assert!(!verify(&key, &challenge, &hello, &response[1..]));
+        assert_ne!(super::challenge(), challenge);
+    }
+}
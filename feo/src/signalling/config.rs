@@ -0,0 +1,65 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tunables of the inter-process signalling
+
+#[cfg(feature = "signalling_auth")]
+use crate::signalling::PreSharedKey;
+use mio::Events;
+
+/// Default number of events handled per poll
+const DEFAULT_EVENTS_CAPACITY: usize = 1024;
+
+/// Configuration of the inter-process signalling of an agent or recorder
+///
+/// The buffers for reading PDUs are not configurable, their size is fixed by the PDU format.
+#[derive(Debug, Clone, Copy)]
+pub struct SignallingConfig {
+    /// Maximum number of events handled per poll of a socket.
+    ///
+    /// Further events are handled by the next poll, so a small capacity only costs additional
+    /// polls. The memory of an event buffer is allocated up front. A capacity of 0 is taken as
+    /// 1, a poll without room for events would never report any.
+    pub events_capacity: usize,
+
+    /// Key to authenticate agents and recorders connecting to the primary agent with.
+    ///
+    /// Must be set to the same key on all agents and recorders. Without a key, connecting
+    /// peers are not authenticated.
+    #[cfg(feature = "signalling_auth")]
+    pub psk: Option<PreSharedKey>,
+}
+
+impl SignallingConfig {
+    /// Create an event buffer of the configured capacity, at least 1
+    pub fn events(&self) -> Events {
+        Events::with_capacity(self.events_capacity.max(1))
+    }
+}
+
+impl Default for SignallingConfig {
+    fn default() -> Self {
+        Self {
+            events_capacity: DEFAULT_EVENTS_CAPACITY,
+            #[cfg(feature = "signalling_auth")]
+            psk: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SignallingConfig;
+
+    #[test]
+    fn test_zero_events_capacity() {
+        // Further fields depend on the enabled features
+        #[allow(clippy::needless_update)]
+        let config = SignallingConfig {
+            events_capacity: 0,
+            ..Default::default()
+        };
+        assert_eq!(config.events().capacity(), 1);
+    }
+}
@@ -3,19 +3,86 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::activity::ActivityId;
+use crate::configuration::topology::TopologyHash;
 use crate::error::Error;
-use crate::error::Error::Io;
-use crate::signalling::{AgentId, Receiver, Sender, Signal};
+use crate::error::Error::{Disconnected, Io, PrimaryDisconnected, Protocol, Timeout};
+#[cfg(feature = "signalling_auth")]
+use crate::signalling::{auth, PreSharedKey};
+use crate::signalling::{AgentId, CycleId, Receiver, Sender, Signal};
 use crate::timestamp::{SyncInfo, Timestamp};
 use feo_log::trace;
 use mio::net::TcpStream;
 use mio::{Events, Interest, Poll, Token};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{ErrorKind, Read as _, Write};
 use std::mem;
-use std::os::fd::AsRawFd;
+use std::os::fd::{AsFd, AsRawFd};
+use std::time::{Duration, Instant};
 
-const MAX_PDU_DATA_SIZE: usize = 16;
+/// Maximum size of the PDU data, fitting the answer to an authentication challenge
+const MAX_PDU_DATA_SIZE: usize = 32;
+
+/// Size of the PDU header, i.e. the tag and the data length
+const PDU_HEADER_SIZE: usize = size_of::<SignalTag>() + size_of::<u16>();
+
+/// Maximum size of an encoded PDU
+const MAX_PDU_SIZE: usize = PDU_HEADER_SIZE + MAX_PDU_DATA_SIZE;
+
+/// Time to wait for a backpressured stream to get writable before giving up sending a PDU
+const SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Version of the PDU format, sent in the hello messages
+///
+/// Increment on any change of the PDU format, including new signal tags:
+/// - 2: topology hash in the hello messages
+/// - 3: system ready signal
+/// - 4: replies of the primary agent to the hello messages
+/// - 5: HMAC-SHA256 answers to authentication challenges
+const PROTOCOL_VERSION: u8 = 5;
+
+/// Whether the PDU data is encoded in native byte order instead of big-endian
+const NATIVE_ENDIAN: bool = cfg!(feature = "signalling_native_endian");
+
+/// Byte order of the PDU data, sent in the hello messages
+///
+/// The PDU header is always big-endian.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ByteOrder {
+    Big,
+    Little,
+}
+
+/// Byte order of the PDU data of this agent
+const BYTE_ORDER: ByteOrder = if NATIVE_ENDIAN && cfg!(target_endian = "little") {
+    ByteOrder::Little
+} else {
+    ByteOrder::Big
+};
+
+/// Check the protocol version and byte order received in a hello message
+///
+/// Peers must use the same PDU format, otherwise all following signals would be misinterpreted.
+fn check_hello(version: u8, byte_order: u8) -> Result<()> {
+    if version != PROTOCOL_VERSION {
+        return Err(Protocol("signalling protocol version mismatch".into()));
+    }
+    if byte_order != BYTE_ORDER as u8 {
+        return Err(Protocol("signalling byte order mismatch".into()));
+    }
+    Ok(())
+}
+
+/// Decode the optional topology hash of a hello message from its presence flag and value
+fn decode_topology(present: u8, hash: u64) -> Result<Option<TopologyHash>> {
+    match present {
+        0 => Ok(None),
+        1 => Ok(Some(TopologyHash(hash))),
+        _ => Err(Protocol(
+            "failed to decode pdu: invalid topology flag".into(),
+        )),
+    }
+}
 
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
@@ -41,6 +108,24 @@ pub enum SignalTag {
     Ready,
     /// RecorderReady signal message
     RecorderReady,
+    /// Time synchronization request message
+    SyncRequest,
+    /// Time synchronization response message
+    SyncResponse,
+    /// System shutdown message
+    SystemShutdown,
+    /// Stalled activity message
+    ActivityStalled,
+    /// Authentication challenge sent in response to a hello message
+    AuthChallenge,
+    /// Answer to an authentication challenge
+    AuthResponse,
+    /// System ready message
+    SystemReady,
+    /// Reply to an accepted hello message
+    HelloAccepted,
+    /// Reply to a hello message announcing another topology
+    HelloRejected,
 }
 
 impl TryFrom<u8> for SignalTag {
@@ -58,14 +143,24 @@ impl TryFrom<u8> for SignalTag {
             v if v == SignalTag::Shutdown as u8 => SignalTag::Shutdown,
             v if v == SignalTag::Ready as u8 => SignalTag::Ready,
             v if v == SignalTag::RecorderReady as u8 => SignalTag::RecorderReady,
+            v if v == SignalTag::SyncRequest as u8 => SignalTag::SyncRequest,
+            v if v == SignalTag::SyncResponse as u8 => SignalTag::SyncResponse,
+            v if v == SignalTag::SystemShutdown as u8 => SignalTag::SystemShutdown,
+            v if v == SignalTag::ActivityStalled as u8 => SignalTag::ActivityStalled,
+            v if v == SignalTag::AuthChallenge as u8 => SignalTag::AuthChallenge,
+            v if v == SignalTag::AuthResponse as u8 => SignalTag::AuthResponse,
+            v if v == SignalTag::SystemReady as u8 => SignalTag::SystemReady,
+            v if v == SignalTag::HelloAccepted as u8 => SignalTag::HelloAccepted,
+            v if v == SignalTag::HelloRejected as u8 => SignalTag::HelloRejected,
             _ => {
-                return Err(Io((ErrorKind::InvalidData.into(), "invalid SignalPdu tag")));
+                return Err(Protocol("invalid SignalPdu tag".into()));
             }
         };
         Ok(s)
     }
 }
 
+/// Protocol data unit of a signal sent between agents
 #[derive(Debug, Default)]
 pub struct SignalPdu {
     tag: SignalTag,
@@ -105,24 +200,125 @@ impl Drop for MioSocketReceiver<'_, '_, '_> {
     }
 }
 
-impl Receiver<SignalPdu> for MioSocketReceiver<'_, '_, '_> {
-    fn recv(&mut self) -> Result<SignalPdu> {
+impl MioSocketReceiver<'_, '_, '_> {
+    /// Receive the next PDU, waiting until `deadline` if given
+    ///
+    /// Fails with [`ErrorKind::UnexpectedEof`] if the peer closed the connection.
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<SignalPdu> {
         let mut pdu = SignalPdu::default();
         loop {
-            if is_readable(self.stream) {
-                // TODO: This will block until the PDU has been fully received => add timeout
-                pdu.read(self.stream, self.poll, self.events)?;
+            // Mio readiness is edge-triggered: read until the stream would block before polling
+            // TODO: This will block until the PDU has been fully received => add timeout
+            let _span = trace::recv_span(None);
+            if let ReadOutcome::Received { .. } =
+                pdu.try_read(self.stream, self.poll, self.events)?
+            {
+                trace::record_tag(&_span, pdu.tag);
                 return Ok(pdu);
             }
+            let timeout = remaining(deadline)?;
             self.poll
-                .poll(self.events, None)
+                .poll(self.events, timeout)
                 .map_err(|e| Io((e, "error while polling in MioSocketReceiver")))?;
         }
     }
 }
 
+#[cfg(feature = "signalling_auth")]
+impl MioSocketReceiver<'_, '_, '_> {
+    /// Answer the challenge of the primary agent for the given hello sent to it
+    pub(crate) fn answer_challenge(&mut self, key: &PreSharedKey, hello: &SignalPdu) -> Result<()> {
+        let challenge = self.recv_timeout(auth::RESPONSE_TIMEOUT)?;
+        if challenge.tag != SignalTag::AuthChallenge {
+            return Err(Protocol("expected authentication challenge".into()));
+        }
+        let response = auth::respond(key, challenge.data(), hello.data());
+        SignalPdu::raw(SignalTag::AuthResponse, &response).send(&mut *self.stream)
+    }
+}
+
+impl Receiver<SignalPdu> for MioSocketReceiver<'_, '_, '_> {
+    fn recv(&mut self) -> Result<SignalPdu> {
+        self.recv_until(None)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<SignalPdu> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+}
+
+/// PDU read piecewise from a non-blocking stream, without waiting for missing bytes
+///
+/// Lets a single poll serve many streams, e.g. those of connecting agents before their hello
+/// has been received.
+#[derive(Debug)]
+pub(crate) struct PartialPdu {
+    buffer: [u8; MAX_PDU_SIZE],
+    len: usize,
+}
+
+impl Default for PartialPdu {
+    fn default() -> Self {
+        Self {
+            buffer: [0; MAX_PDU_SIZE],
+            len: 0,
+        }
+    }
+}
+
+impl PartialPdu {
+    /// Read the available bytes of the PDU, returning it once complete
+    ///
+    /// Never reads beyond the end of the PDU. Fails with [`ErrorKind::UnexpectedEof`] if the
+    /// peer closed the connection.
+    pub(crate) fn read(&mut self, stream: &mut TcpStream) -> Result<Option<SignalPdu>> {
+        loop {
+            let pdu_len = if self.len < PDU_HEADER_SIZE {
+                PDU_HEADER_SIZE
+            } else {
+                let data_len = u16::from_be_bytes(self.buffer[1..3].try_into().unwrap()) as usize;
+                if data_len > MAX_PDU_DATA_SIZE {
+                    return Err(Protocol("received PDU length exceeds buffer size".into()));
+                }
+                PDU_HEADER_SIZE + data_len
+            };
+            if self.len == pdu_len && self.len >= PDU_HEADER_SIZE {
+                let mut pdu = SignalPdu {
+                    tag: self.buffer[0].try_into()?,
+                    data_len: (pdu_len - PDU_HEADER_SIZE) as u16,
+                    ..Default::default()
+                };
+                pdu.data[..pdu.data_len as usize]
+                    .copy_from_slice(&self.buffer[PDU_HEADER_SIZE..pdu_len]);
+                self.len = 0;
+                trace!("Received {:?}", pdu);
+                return Ok(Some(pdu));
+            }
+
+            match stream.read(&mut self.buffer[self.len..pdu_len]) {
+                Ok(0) => {
+                    return Err(Io((
+                        ErrorKind::UnexpectedEof.into(),
+                        "connection closed by peer",
+                    )))
+                }
+                Ok(n) => self.len += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Io((e, "failed to read SignalPdu"))),
+            }
+        }
+    }
+}
+
+/// Receiver of PDUs from the streams of multiple agents
+///
+/// Each stream is registered with the ID of its agent as token. After a poll, only the streams
+/// whose tokens fired are read, so receiving costs O(ready) rather than O(agents).
 pub struct MioMultiSocketReceiver<'p, 'q> {
     streams: HashMap<AgentId, TcpStream>,
+    /// Streams which may have data to read, i.e. which did not block since their last event
+    readable: VecDeque<AgentId>,
     poll: &'p mut Poll,
     events: &'q mut Events,
 }
@@ -134,22 +330,55 @@ impl<'p, 'q> MioMultiSocketReceiver<'p, 'q> {
     {
         // convert input to hash map
         let streams: HashMap<AgentId, TcpStream> = streams.into_iter().collect();
+        // Data may have arrived before registration, so initially try reading from all streams
+        let readable = streams.keys().copied().collect();
         MioMultiSocketReceiver {
             streams,
+            readable,
             poll,
             events,
         }
     }
 
+    /// Register all streams with the poll, each with the ID of its agent as token
     pub fn register(&mut self) -> std::io::Result<()> {
-        for (_, stream) in self.streams.iter_mut() {
-            self.poll
-                .registry()
-                .register(stream, Token(0), Interest::READABLE)?;
+        for (agent_id, stream) in self.streams.iter_mut() {
+            self.poll.registry().register(
+                stream,
+                Token(usize::from(*agent_id)),
+                Interest::READABLE,
+            )?;
         }
         Ok(())
     }
 
+    /// Number of streams still connected
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Check if all streams have been closed by their peers
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Mark the streams reported by the last poll as readable, or all streams if `all` is set
+    fn mark_readable(&mut self, all: bool) {
+        let agent_ids: Vec<AgentId> = if all {
+            self.streams.keys().copied().collect()
+        } else {
+            self.events
+                .iter()
+                .map(|event| AgentId::from(event.token().0))
+                .collect()
+        };
+        for agent_id in agent_ids {
+            if self.streams.contains_key(&agent_id) && !self.readable.contains(&agent_id) {
+                self.readable.push_back(agent_id);
+            }
+        }
+    }
+
     pub fn deregister(&mut self) -> std::io::Result<()> {
         for (_, stream) in self.streams.iter_mut() {
             self.poll.registry().deregister(stream)?;
@@ -164,37 +393,94 @@ impl Drop for MioMultiSocketReceiver<'_, '_> {
     }
 }
 
-impl Receiver<(AgentId, SignalPdu)> for MioMultiSocketReceiver<'_, '_> {
-    fn recv(&mut self) -> Result<(AgentId, SignalPdu)> {
+impl MioMultiSocketReceiver<'_, '_> {
+    /// Receive the next PDU from any stream, waiting until `deadline` if given
+    ///
+    /// A stream failing to read, e.g. closed by its peer, is removed and the error is reported
+    /// once. A closed stream is reported with [`ErrorKind::UnexpectedEof`].
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<(AgentId, SignalPdu)> {
         let mut pdu = SignalPdu::default();
         loop {
-            for (agent_id, stream) in self.streams.iter_mut() {
-                if is_readable(stream) {
-                    // TODO: This will block until the PDU has been fully received
-                    //       => add timeout, try reading other streams in parallel?
-                    pdu.read(stream, self.poll, self.events)?;
-                    return Ok((*agent_id, pdu));
+            // Only read from streams which got readable and did not block since
+            while let Some(agent_id) = self.readable.pop_front() {
+                let Some(stream) = self.streams.get_mut(&agent_id) else {
+                    continue;
+                };
+                let _span = trace::recv_span(Some(agent_id));
+                // TODO: This will block until the PDU has been fully received
+                //       => add timeout, try reading other streams in parallel?
+                match pdu.try_read(stream, self.poll, self.events) {
+                    Ok(ReadOutcome::WouldBlock) => (),
+                    Ok(ReadOutcome::Received { polled }) => {
+                        trace::record_tag(&_span, pdu.tag);
+                        // More PDUs may be buffered, read again after the other streams
+                        self.readable.push_back(agent_id);
+                        if polled {
+                            // Events of other streams may have been consumed within the PDU
+                            self.mark_readable(true);
+                        }
+                        return Ok((agent_id, pdu));
+                    }
+                    Err(e) => {
+                        if let Some(mut stream) = self.streams.remove(&agent_id) {
+                            _ = self.poll.registry().deregister(&mut stream);
+                        }
+                        self.mark_readable(true);
+                        return Err(e);
+                    }
                 }
             }
 
-            // if we did not receive data on any stream, wait until a stream gets readable
+            // no stream has data, wait until a stream gets readable
+            let timeout = remaining(deadline)?;
             self.poll
-                .poll(self.events, None)
+                .poll(self.events, timeout)
                 .map_err(|e| Io((e, "error while polling in MioMultiSocketReceiver")))?;
+            self.mark_readable(false);
         }
     }
 }
 
+impl Receiver<(AgentId, SignalPdu)> for MioMultiSocketReceiver<'_, '_> {
+    fn recv(&mut self) -> Result<(AgentId, SignalPdu)> {
+        self.recv_until(None)
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<(AgentId, SignalPdu)> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+}
+
+/// Time left until `deadline`, if any. Fails if the deadline has passed.
+fn remaining(deadline: Option<Instant>) -> Result<Option<Duration>> {
+    match deadline {
+        None => Ok(None),
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+            _ => Err(Timeout("no pdu received in time")),
+        },
+    }
+}
+
 /// Helper trait allowing MioSocketSender to accept a TcpStream either by value or as a mutable reference
-pub trait IsTcpStreamOrMutRef: Send + Write {}
+pub trait IsTcpStreamOrMutRef: Send + Write + AsFd {}
 impl IsTcpStreamOrMutRef for TcpStream {}
 impl IsTcpStreamOrMutRef for &mut TcpStream {}
 
+/// Sender of PDUs on a single stream
+///
+/// Sending after the peer closed the stream fails with [`PrimaryDisconnected`] if the peer is
+/// the primary agent, or with [`Disconnected`] if it is the agent given to
+/// [`to_agent`](Self::to_agent).
 pub struct MioSocketSender<K>
 where
     K: IsTcpStreamOrMutRef,
 {
     stream: K,
+    /// Peer of the stream, `None` for the primary agent
+    peer: Option<AgentId>,
+    /// Bytes of a previous PDU not yet written, see [`write_pending`]
+    pending: Vec<u8>,
 }
 
 /// Signal sender based on mio::TcpStream (by value or mutable reference)
@@ -202,20 +488,44 @@ impl<K> MioSocketSender<K>
 where
     K: IsTcpStreamOrMutRef,
 {
+    /// Create a sender to the primary agent
     pub fn new(stream: K) -> Self {
-        MioSocketSender { stream }
+        MioSocketSender {
+            stream,
+            peer: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Create a sender to the agent with the given id, e.g. of the primary agent to a
+    /// secondary agent
+    pub fn to_agent(stream: K, agent_id: AgentId) -> Self {
+        MioSocketSender {
+            stream,
+            peer: Some(agent_id),
+            pending: Vec::new(),
+        }
     }
 }
 impl<T: Into<SignalPdu>, K: IsTcpStreamOrMutRef> Sender<T> for MioSocketSender<K> {
     fn send(&mut self, t: T) -> Result<()> {
-        let pdu = t.into();
-        pdu.send(&mut self.stream)?;
-        Ok(())
+        let pdu: SignalPdu = t.into();
+        let _span = trace::send_span(pdu.tag, self.peer);
+        pdu.queue(&mut self.pending)?;
+        write_pending(&mut self.stream, &mut self.pending).map_err(|e| match e {
+            Io((e, _)) if closed_by_peer(&e) => self.peer.map_or(PrimaryDisconnected, Disconnected),
+            e => e,
+        })
     }
 }
 
+/// Sender of PDUs to several agents, each on its own stream
+///
+/// Sending to an agent which closed its stream fails with [`Disconnected`].
 pub struct MioMultiSocketSender {
     streams: HashMap<AgentId, TcpStream>,
+    /// Bytes of previous PDUs not yet written per stream, see [`write_pending`]
+    pending: HashMap<AgentId, Vec<u8>>,
 }
 
 impl MioMultiSocketSender {
@@ -225,72 +535,179 @@ impl MioMultiSocketSender {
     {
         // convert input to hash map
         let streams: HashMap<AgentId, TcpStream> = streams.into_iter().collect();
-        MioMultiSocketSender { streams }
+        MioMultiSocketSender {
+            streams,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Replace the stream to the given agent, e.g. after the agent reconnected
+    ///
+    /// Bytes still pending for the previous stream are discarded, they belong to PDUs the
+    /// new stream's peer has not seen the beginning of.
+    pub fn replace(&mut self, agent_id: AgentId, stream: TcpStream) {
+        self.pending.remove(&agent_id);
+        self.streams.insert(agent_id, stream);
+    }
+
+    /// Remove and close the stream to the given agent
+    pub fn remove(&mut self, agent_id: AgentId) {
+        self.pending.remove(&agent_id);
+        self.streams.remove(&agent_id);
     }
 }
 
 impl<T: Into<SignalPdu>> Sender<(AgentId, T)> for MioMultiSocketSender {
     fn send(&mut self, t: (AgentId, T)) -> Result<()> {
         let agent_id = t.0;
-        let pdu = t.1.into();
+        let pdu: SignalPdu = t.1.into();
+        let _span = trace::send_span(pdu.tag, Some(agent_id));
         let stream = self
             .streams
             .get_mut(&agent_id)
             .ok_or_else(|| Io((ErrorKind::InvalidInput.into(), "unknown agent id")))?;
-        pdu.send(stream)?;
-        Ok(())
+        let pending = self.pending.entry(agent_id).or_default();
+        pdu.queue(pending)?;
+        write_pending(stream, pending).map_err(|e| match e {
+            Io((e, _)) if closed_by_peer(&e) => Disconnected(agent_id),
+            e => e,
+        })
     }
 }
 
+#[cfg(feature = "signalling_auth")]
 impl SignalPdu {
-    pub fn send(&self, writer: &mut dyn Write) -> Result<()> {
-        trace!("sending {:?}", self);
-        if self.data_len as usize > MAX_PDU_DATA_SIZE {
+    /// Challenge the peer to prove the pre-shared key, see [`auth`](super::auth)
+    ///
+    /// Returns the challenge sent, for [`verify_answer`](Self::verify_answer).
+    pub(crate) fn send_challenge(stream: &mut TcpStream) -> Result<[u8; auth::CHALLENGE_SIZE]> {
+        let challenge = auth::challenge();
+        SignalPdu::raw(SignalTag::AuthChallenge, &challenge).send(stream)?;
+        Ok(challenge)
+    }
+
+    /// Check this PDU to be the answer to the given challenge for the given hello received
+    /// from the peer
+    ///
+    /// Fails with [`ErrorKind::PermissionDenied`] if the peer answered incorrectly.
+    pub(crate) fn verify_answer(
+        &self,
+        key: &PreSharedKey,
+        challenge: &[u8],
+        hello: &SignalPdu,
+    ) -> Result<()> {
+        let valid = self.tag == SignalTag::AuthResponse
+            && auth::verify(key, challenge, hello.data(), self.data());
+        if !valid {
             return Err(Io((
-                ErrorKind::InvalidData.into(),
-                "max pdu data size exceeded",
+                ErrorKind::PermissionDenied.into(),
+                "peer failed authentication",
             )));
         }
+        Ok(())
+    }
+}
 
-        const BUF_SIZE: usize = size_of::<SignalTag>() + size_of::<u16>();
-        let mut buffer: [u8; BUF_SIZE] = [0; BUF_SIZE];
+impl SignalPdu {
+    /// Create a PDU with the given tag carrying the given bytes
+    #[cfg(feature = "signalling_auth")]
+    fn raw(tag: SignalTag, data: &[u8]) -> Self {
+        let mut pdu = SignalPdu::default();
+        pdu.data[..data.len()].copy_from_slice(data);
+        encode_header(&mut pdu, tag, data.len());
+        pdu
+    }
 
-        buffer[0] = self.tag as u8;
-        let len_as_bytes = u16::to_be_bytes(self.data_len);
-        buffer[1..3].copy_from_slice(&len_as_bytes);
+    /// The data carried by the PDU
+    #[cfg(feature = "signalling_auth")]
+    fn data(&self) -> &[u8] {
+        &self.data[..self.data_len as usize]
+    }
 
+    /// Write the PDU to a blocking writer
+    ///
+    /// Non-blocking streams are written by the senders, which handle a stream that would block.
+    pub fn send(&self, writer: &mut dyn Write) -> Result<()> {
+        let mut buffer = [0; MAX_PDU_SIZE];
+        let encoded = self.encode(&mut buffer)?;
         writer
-            .write_all(&buffer)
-            .map_err(|e| Io((e, "failed to write pdu header")))?;
-        writer
-            .write_all(&self.data[0..self.data_len as usize])
-            .map_err(|e| Io((e, "failed to write pdu data")))?;
-        writer.flush().unwrap();
+            .write_all(encoded)
+            .map_err(|e| Io((e, "failed to write pdu")))?;
+        writer.flush().map_err(|e| Io((e, "failed to flush pdu")))
+    }
 
+    /// Append the encoded PDU to the given bytes pending to be written
+    fn queue(&self, pending: &mut Vec<u8>) -> Result<()> {
+        let mut buffer = [0; MAX_PDU_SIZE];
+        pending.extend_from_slice(self.encode(&mut buffer)?);
         Ok(())
     }
 
+    /// Encode the PDU into the given buffer, returning the used part
+    fn encode<'b>(&self, buffer: &'b mut [u8; MAX_PDU_SIZE]) -> Result<&'b [u8]> {
+        trace!("sending {:?}", self);
+        let data_len = self.data_len as usize;
+        if data_len > MAX_PDU_DATA_SIZE {
+            return Err(Protocol("max pdu data size exceeded".into()));
+        }
+
+        buffer[0] = self.tag as u8;
+        let len_as_bytes = u16::to_be_bytes(self.data_len);
+        buffer[1..PDU_HEADER_SIZE].copy_from_slice(&len_as_bytes);
+        buffer[PDU_HEADER_SIZE..PDU_HEADER_SIZE + data_len].copy_from_slice(&self.data[..data_len]);
+        Ok(&buffer[..PDU_HEADER_SIZE + data_len])
+    }
+
     pub fn read(
         &mut self,
         stream: &mut TcpStream,
         poll: &mut Poll,
         events: &mut Events,
     ) -> Result<()> {
-        const BUF_SIZE: usize = size_of::<SignalTag>() + size_of::<u16>();
-        let mut buffer: [u8; BUF_SIZE] = [0; BUF_SIZE];
+        loop {
+            if let ReadOutcome::Received { .. } = self.try_read(stream, poll, events)? {
+                return Ok(());
+            }
+            poll.poll(events, None)
+                .map_err(|e| Io((e, "error while polling for SignalPdu")))?;
+        }
+    }
 
-        read_buffer(&mut buffer, stream, poll, events)
+    /// Read a PDU from a non-blocking stream, if data is available
+    ///
+    /// Once the first byte has been read, waits on `poll` until the PDU is complete.
+    /// Fails with [`ErrorKind::UnexpectedEof`] if the peer closed the connection.
+    fn try_read(
+        &mut self,
+        stream: &mut TcpStream,
+        poll: &mut Poll,
+        events: &mut Events,
+    ) -> Result<ReadOutcome> {
+        let mut buffer = [0; PDU_HEADER_SIZE];
+
+        let start = loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(Io((
+                        ErrorKind::UnexpectedEof.into(),
+                        "connection closed by peer",
+                    )))
+                }
+                Ok(n) => break n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(ReadOutcome::WouldBlock),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Io((e, "failed to read SignalPdu header"))),
+            }
+        };
+        let mut polled = read_buffer(&mut buffer[start..], stream, poll, events)
             .map_err(|e| Io((e, "failed to read SignalPdu header")))?;
 
         let data_len = u16::from_be_bytes(buffer[1..3].try_into().unwrap());
         if data_len as usize > MAX_PDU_DATA_SIZE {
-            return Err(Io((
-                ErrorKind::InvalidData.into(),
-                "received PDU length exceeds buffer size",
-            )));
+            return Err(Protocol("received PDU length exceeds buffer size".into()));
         }
 
-        read_buffer(&mut self.data[0..data_len as usize], stream, poll, events)
+        polled |= read_buffer(&mut self.data[0..data_len as usize], stream, poll, events)
             .map_err(|e| Io((e, "failed to read SignalPdu data")))?;
 
         let tag: SignalTag = buffer[0].try_into()?;
@@ -300,40 +717,107 @@ impl SignalPdu {
 
         trace!("Received {:?}", self);
 
-        Ok(())
+        Ok(ReadOutcome::Received { polled })
     }
 }
 
-fn is_readable(stream: &TcpStream) -> bool {
-    let mut buf: [u8; 1] = [0; 1];
-    let result = stream.peek(&mut buf);
-    result.is_ok() && result.unwrap() > 0
+/// Check if the given error writing a PDU means that the peer closed the connection
+fn closed_by_peer(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::WriteZero
+    )
+}
+
+/// Write the pending bytes to the given non-blocking stream, removing the written ones
+///
+/// Waits for the stream to get writable whenever it would block, at most [`SEND_TIMEOUT`] in
+/// total. Bytes left unwritten on failure stay pending, so the next send completes a partially
+/// written PDU before writing its own.
+fn write_pending(stream: &mut (impl Write + AsFd), pending: &mut Vec<u8>) -> Result<()> {
+    let deadline = Instant::now() + SEND_TIMEOUT;
+    while !pending.is_empty() {
+        match stream.write(pending) {
+            Ok(0) => return Err(Io((ErrorKind::WriteZero.into(), "failed to write pdu"))),
+            Ok(n) => {
+                pending.drain(..n);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => wait_writable(stream, deadline)?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Err(Io((e, "failed to write pdu"))),
+        }
+    }
+    stream.flush().map_err(|e| Io((e, "failed to flush pdu")))
 }
 
-fn wait_readable(stream: &TcpStream, poll: &mut Poll, events: &mut Events) -> std::io::Result<()> {
-    while !is_readable(stream) {
-        poll.poll(events, None)?;
+/// Wait until the given stream is writable, at most until the given deadline
+///
+/// The stream is polled directly instead of registering it with a [`Poll`], as it may already
+/// be registered for reading.
+fn wait_writable(stream: &impl AsFd, deadline: Instant) -> Result<()> {
+    let timeout = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or(Timeout("no pdu sent in time"))?;
+    let mut pollfd = libc::pollfd {
+        fd: stream.as_fd().as_raw_fd(),
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    // Round up, a timeout of zero would not wait at all
+    let timeout_ms = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+    // Safety: pollfd is a valid pollfd struct for the open file descriptor of the stream
+    let result = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+    if result < 0 {
+        let e = std::io::Error::last_os_error();
+        if e.kind() != ErrorKind::Interrupted {
+            return Err(Io((e, "error while polling for writability")));
+        }
     }
     Ok(())
 }
 
-// Try to read as may bytes from the given TcpStream as needed to completely fill the given buffer
+/// Outcome of reading a PDU from a non-blocking stream
+enum ReadOutcome {
+    /// No data available
+    WouldBlock,
+    /// A PDU has been read. `polled` tells if the poll had to be waited on for the rest of the
+    /// PDU, possibly consuming events of other streams registered with the same poll.
+    Received { polled: bool },
+}
+
+// Try to read as may bytes from the given TcpStream as needed to completely fill the given buffer.
+// Waits on the poll if the stream would block. Returns whether the poll has been waited on.
 fn read_buffer(
     buffer: &mut [u8],
     stream: &mut TcpStream,
     poll: &mut Poll,
     events: &mut Events,
-) -> std::io::Result<()> {
+) -> std::io::Result<bool> {
     let len = buffer.len();
     let mut total_read = 0usize;
+    let mut polled = false;
     while total_read < len {
         // read next bytes, starting at current position up to the end of the buffer
         let num_read = match stream.read(buffer[total_read..len].as_mut()) {
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "connection closed by peer",
+                ));
+            }
             Ok(n) => n,
             Err(e) if e.kind() == ErrorKind::WouldBlock => {
-                wait_readable(stream, poll, events)?;
+                // Readiness is edge-triggered: the next event signals new data
+                poll.poll(events, None)?;
+                polled = true;
                 0usize
             }
+            Err(e) if e.kind() == ErrorKind::Interrupted => 0usize,
             Err(e) => {
                 return Err(e);
             }
@@ -344,7 +828,7 @@ fn read_buffer(
     assert!(total_read <= len, "buffer overflow");
     assert_eq!(total_read, len, "buffer not fully read");
 
-    Ok(())
+    Ok(polled)
 }
 
 fn encode_header(pdu: &mut SignalPdu, tag: SignalTag, data_len: usize) {
@@ -365,11 +849,20 @@ macro_rules! decode_pdu_data {
             $(
                 {
                     let size: usize = mem::size_of::<$intype>();
-                    assert!(_offset + size <= data_len, "failed to decode pdu: insufficient data");
-                    let value: $outtype = <$intype>::from_be_bytes($pdu.data[_offset.._offset + size]
+                    if _offset + size > data_len {
+                        return Err(Protocol("failed to decode pdu: insufficient data".into()));
+                    }
+                    let bytes = $pdu.data[_offset.._offset + size]
                         .try_into()
-                        .map_err(|_| Io((ErrorKind::InvalidData.into(), "failed to decode pdu")))?)
-                        .into();
+                        .map_err(|_| Protocol("failed to decode pdu".into()))?;
+                    let raw = if NATIVE_ENDIAN {
+                        <$intype>::from_ne_bytes(bytes)
+                    } else {
+                        <$intype>::from_be_bytes(bytes)
+                    };
+                    let value = <$outtype>::try_from(raw).map_err(|_| {
+                        Protocol("failed to decode pdu: value out of range".into())
+                    })?;
                     _offset += size;
                     value
                 }
@@ -386,21 +879,33 @@ impl TryFrom<&SignalPdu> for Signal {
         trace!("Decoding {:?}", pdu);
 
         let signal = match pdu.tag {
-            SignalTag::HelloTrigger => {
-                let id = decode_pdu_data!(pdu, usize => AgentId);
-                Signal::HelloTrigger(id)
-            }
-            SignalTag::HelloReady => {
-                let id = decode_pdu_data!(pdu, usize => AgentId);
-                Signal::HelloReady(id)
+            SignalTag::HelloTrigger | SignalTag::HelloReady => {
+                // Check the version before the data, whose layout depends on it
+                let (version, byte_order) = decode_pdu_data!(pdu, u8 => u8, u8 => u8);
+                check_hello(version, byte_order)?;
+                let (_, _, id, present, hash) = decode_pdu_data!(
+                    pdu,
+                    u8 => u8,
+                    u8 => u8,
+                    usize => AgentId,
+                    u8 => u8,
+                    u64 => u64,
+                );
+                let hello = (id, decode_topology(present, hash)?);
+                if pdu.tag == SignalTag::HelloTrigger {
+                    Signal::HelloTrigger(hello)
+                } else {
+                    Signal::HelloReady(hello)
+                }
             }
             SignalTag::StartupSync => {
                 let info = decode_pdu_data!(pdu, u64 => SyncInfo);
                 Signal::StartupSync(info)
             }
             SignalTag::Ready => {
-                let (id, t) = decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp);
-                Signal::Ready((id, t))
+                let (id, t, c) =
+                    decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp, u64 => CycleId);
+                Signal::Ready((id, t, c))
             }
             SignalTag::TaskChainStart => {
                 let t = decode_pdu_data!(pdu, u64 => Timestamp);
@@ -415,8 +920,9 @@ impl TryFrom<&SignalPdu> for Signal {
                 Signal::Startup((id, t))
             }
             SignalTag::Step => {
-                let (id, t) = decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp);
-                Signal::Step((id, t))
+                let (id, t, c) =
+                    decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp, u64 => CycleId);
+                Signal::Step((id, t, c))
             }
             SignalTag::Shutdown => {
                 let (id, t) = decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp);
@@ -426,6 +932,37 @@ impl TryFrom<&SignalPdu> for Signal {
                 let (id, t) = decode_pdu_data!(pdu, usize => AgentId, u64 => Timestamp);
                 Signal::RecorderReady((id, t))
             }
+            SignalTag::SyncRequest => {
+                let (id, t) = decode_pdu_data!(pdu, usize => AgentId, u64 => Timestamp);
+                Signal::SyncRequest((id, t))
+            }
+            SignalTag::SyncResponse => {
+                let (t2, t3) = decode_pdu_data!(pdu, u64 => Timestamp, u64 => Timestamp);
+                Signal::SyncResponse((t2, t3))
+            }
+            SignalTag::SystemShutdown => {
+                let t = decode_pdu_data!(pdu, u64 => Timestamp);
+                Signal::SystemShutdown(t)
+            }
+            SignalTag::ActivityStalled => {
+                let (id, t) = decode_pdu_data!(pdu, usize => ActivityId, u64 => Timestamp);
+                Signal::ActivityStalled((id, t))
+            }
+            SignalTag::SystemReady => {
+                let t = decode_pdu_data!(pdu, u64 => Timestamp);
+                Signal::SystemReady(t)
+            }
+            SignalTag::HelloAccepted => {
+                let id = decode_pdu_data!(pdu, usize => AgentId);
+                Signal::HelloAccepted(id)
+            }
+            SignalTag::HelloRejected => {
+                let hash = decode_pdu_data!(pdu, u64 => u64);
+                Signal::HelloRejected(TopologyHash(hash))
+            }
+            SignalTag::AuthChallenge | SignalTag::AuthResponse => {
+                return Err(Protocol("unexpected authentication pdu".into()));
+            }
         };
 
         Ok(signal)
@@ -446,7 +983,12 @@ macro_rules! encode_pdu {
         let mut offset: usize = 0usize;
 
         $(
-            let bytes = <$type>::to_be_bytes($value.into());
+            let value: $type = $value.into();
+            let bytes = if NATIVE_ENDIAN {
+                value.to_ne_bytes()
+            } else {
+                value.to_be_bytes()
+            };
             pdu.data[offset..offset + bytes.len()].copy_from_slice(&bytes);
             offset += bytes.len();
         )+
@@ -459,13 +1001,27 @@ macro_rules! encode_pdu {
 impl From<&Signal> for SignalPdu {
     fn from(signal: &Signal) -> Self {
         match signal {
-            Signal::HelloTrigger(id) => encode_pdu!(SignalTag::HelloTrigger, *id => usize),
-            Signal::HelloReady(id) => encode_pdu!(SignalTag::HelloReady, *id => usize),
+            Signal::HelloTrigger((id, topology)) => encode_pdu!(
+                SignalTag::HelloTrigger,
+                PROTOCOL_VERSION => u8,
+                BYTE_ORDER as u8 => u8,
+                *id => usize,
+                topology.is_some() => u8,
+                topology.map_or(0, |h| h.0) => u64,
+            ),
+            Signal::HelloReady((id, topology)) => encode_pdu!(
+                SignalTag::HelloReady,
+                PROTOCOL_VERSION => u8,
+                BYTE_ORDER as u8 => u8,
+                *id => usize,
+                topology.is_some() => u8,
+                topology.map_or(0, |h| h.0) => u64,
+            ),
             Signal::StartupSync(sync_info) => {
                 encode_pdu!(SignalTag::StartupSync, *sync_info => u64)
             }
-            Signal::Ready((id, t)) => {
-                encode_pdu!(SignalTag::Ready, *id => usize, *t => u64)
+            Signal::Ready((id, t, c)) => {
+                encode_pdu!(SignalTag::Ready, *id => usize, *t => u64, *c => u64)
             }
             Signal::TaskChainStart(t) => {
                 encode_pdu!(SignalTag::TaskChainStart, *t => u64)
@@ -474,8 +1030,8 @@ impl From<&Signal> for SignalPdu {
             Signal::Startup((id, t)) => {
                 encode_pdu!(SignalTag::Startup, *id => usize, *t => u64)
             }
-            Signal::Step((id, t)) => {
-                encode_pdu!(SignalTag::Step, *id => usize, *t => u64)
+            Signal::Step((id, t, c)) => {
+                encode_pdu!(SignalTag::Step, *id => usize, *t => u64, *c => u64)
             }
             Signal::Shutdown((id, t)) => {
                 encode_pdu!(SignalTag::Shutdown, *id => usize, *t => u64)
@@ -483,6 +1039,21 @@ impl From<&Signal> for SignalPdu {
             Signal::RecorderReady((id, t)) => {
                 encode_pdu!(SignalTag::RecorderReady, *id => usize, *t => u64)
             }
+            Signal::SyncRequest((id, t)) => {
+                encode_pdu!(SignalTag::SyncRequest, *id => usize, *t => u64)
+            }
+            Signal::SyncResponse((t2, t3)) => {
+                encode_pdu!(SignalTag::SyncResponse, *t2 => u64, *t3 => u64)
+            }
+            Signal::SystemShutdown(t) => encode_pdu!(SignalTag::SystemShutdown, *t => u64),
+            Signal::ActivityStalled((id, t)) => {
+                encode_pdu!(SignalTag::ActivityStalled, *id => usize, *t => u64)
+            }
+            Signal::SystemReady(t) => encode_pdu!(SignalTag::SystemReady, *t => u64),
+            Signal::HelloAccepted(id) => encode_pdu!(SignalTag::HelloAccepted, *id => usize),
+            Signal::HelloRejected(topology) => {
+                encode_pdu!(SignalTag::HelloRejected, topology.0 => u64)
+            }
         }
     }
 }
@@ -525,4 +1096,514 @@ where
     }
 }
 
+/// Trace spans around the transmission of PDUs
+///
+/// Spans are only emitted with the `tracing` feature; otherwise these helpers compile to nothing.
+#[cfg(any(feature = "tracing", test))]
+mod trace {
+    use super::SignalTag;
+    use crate::signalling::AgentId;
+    use feo_tracing::tracing::field::{self, Empty};
+    use feo_tracing::tracing::span::EnteredSpan;
+    use feo_tracing::{span, Level};
+
+    /// Enter a span for sending a PDU with the given tag, optionally to the given agent
+    pub(super) fn send_span(tag: SignalTag, agent_id: Option<AgentId>) -> EnteredSpan {
+        span!(Level::INFO, "SendPdu", tag = ?tag, agent_id = agent_id.map(usize::from)).entered()
+    }
+
+    /// Enter a span for receiving a PDU, optionally from the given agent
+    ///
+    /// The tag is not known before reception; it is recorded with [`record_tag`].
+    pub(super) fn recv_span(agent_id: Option<AgentId>) -> EnteredSpan {
+        span!(
+            Level::INFO,
+            "RecvPdu",
+            tag = Empty,
+            agent_id = agent_id.map(usize::from)
+        )
+        .entered()
+    }
+
+    /// Record the tag of a received PDU in its span
+    pub(super) fn record_tag(span: &EnteredSpan, tag: SignalTag) {
+        span.record("tag", field::debug(tag));
+    }
+}
+
+#[cfg(not(any(feature = "tracing", test)))]
+mod trace {
+    use super::SignalTag;
+    use crate::signalling::AgentId;
+
+    /// Placeholder for a span when tracing is disabled
+    pub(super) struct NoSpan;
+
+    pub(super) fn send_span(_tag: SignalTag, _agent_id: Option<AgentId>) -> NoSpan {
+        NoSpan
+    }
+
+    pub(super) fn recv_span(_agent_id: Option<AgentId>) -> NoSpan {
+        NoSpan
+    }
+
+    pub(super) fn record_tag(_span: &NoSpan, _tag: SignalTag) {}
+}
+
 type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ByteOrder, MioMultiSocketReceiver, MioSocketReceiver, MioSocketSender, PartialPdu,
+        SignalPdu, SignalTag, BYTE_ORDER, MAX_PDU_SIZE, PROTOCOL_VERSION,
+    };
+    use crate::configuration::topology::TopologyHash;
+    use crate::error::Error;
+    #[cfg(feature = "signalling_auth")]
+    use crate::signalling::{auth, PreSharedKey, KEY_SIZE};
+    use crate::signalling::{AgentId, Receiver, Sender, Signal, SignallingConfig};
+    use crate::timestamp::Timestamp;
+    use mio::{Events, Poll};
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::os::fd::AsRawFd;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{subscriber, Event, Metadata, Subscriber};
+
+    /// Subscriber counting the created spans with a given name
+    struct SpanCounter {
+        name: &'static str,
+        count: Arc<AtomicUsize>,
+        next_id: AtomicU64,
+    }
+
+    impl Subscriber for SpanCounter {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            if span.metadata().name() == self.name {
+                self.count.fetch_add(1, Ordering::Relaxed);
+            }
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_span_per_send() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let _peer = listener.accept().unwrap();
+        let mut sender = MioSocketSender::new(mio::net::TcpStream::from_std(stream));
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = SpanCounter {
+            name: "SendPdu",
+            count: count.clone(),
+            next_id: AtomicU64::new(1),
+        };
+        subscriber::with_default(counter, || {
+            for i in 0..3 {
+                sender
+                    .send(Signal::TaskChainStart(Timestamp(
+                        feo_time::Duration::from_millis(i),
+                    )))
+                    .unwrap();
+            }
+        });
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+    }
+
+    /// Connect a non-blocking mio stream to a std stream acting as peer
+    fn connected_pair(listener: &TcpListener) -> (mio::net::TcpStream, TcpStream) {
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        (mio::net::TcpStream::from_std(stream), peer)
+    }
+
+    /// Authenticate a peer sending a hello and answering the challenge with the given key,
+    /// if any, against the given key
+    #[cfg(feature = "signalling_auth")]
+    fn authenticate_peer(key: PreSharedKey, peer_key: Option<PreSharedKey>) -> Result<(), Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (mut stream, peer) = connected_pair(&listener);
+        let peer = thread::spawn(move || {
+            peer.set_nonblocking(true).unwrap();
+            let mut peer = mio::net::TcpStream::from_std(peer);
+            let hello = SignalPdu::from(Signal::HelloTrigger((AgentId::new(101), None)));
+            hello.send(&mut peer).unwrap();
+            let mut poll = Poll::new().unwrap();
+            let mut events = Events::with_capacity(16);
+            let mut receiver = MioSocketReceiver::new(&mut peer, &mut poll, &mut events);
+            receiver.register(0).unwrap();
+            match peer_key {
+                Some(peer_key) => _ = receiver.answer_challenge(&peer_key, &hello),
+                // Receive the challenge without answering it
+                None => _ = receiver.recv_timeout(Duration::from_secs(5)),
+            }
+        });
+
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(16);
+        let mut receiver = MioSocketReceiver::new(&mut stream, &mut poll, &mut events);
+        receiver.register(0).unwrap();
+        let hello = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let result = SignalPdu::send_challenge(receiver.stream).and_then(|challenge| {
+            let answer = receiver.recv_timeout(auth::RESPONSE_TIMEOUT)?;
+            answer.verify_answer(&key, &challenge, &hello)
+        });
+        drop(receiver);
+        drop(stream);
+        peer.join().unwrap();
+        result
+    }
+
+    #[cfg(feature = "signalling_auth")]
+    #[test]
+    fn test_hello_without_key_rejected() {
+        let key = PreSharedKey::new([7; KEY_SIZE]);
+        assert!(authenticate_peer(key, Some(key)).is_ok());
+
+        // A peer with another key is rejected
+        let other_key = PreSharedKey::new([8; KEY_SIZE]);
+        assert!(matches!(
+            authenticate_peer(key, Some(other_key)),
+            Err(Error::Io((e, _))) if e.kind() == ErrorKind::PermissionDenied
+        ));
+
+        // A peer without key does not answer the challenge
+        assert!(authenticate_peer(key, None).is_err());
+    }
+
+    #[test]
+    fn test_partial_pdu() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (mut stream, mut peer) = connected_pair(&listener);
+
+        let signal = Signal::HelloTrigger((AgentId::new(101), None));
+        let mut buffer = [0; MAX_PDU_SIZE];
+        let encoded = SignalPdu::from(signal).encode(&mut buffer).unwrap();
+        let (last, head) = encoded.split_last().unwrap();
+        let mut pdu = PartialPdu::default();
+
+        // Nothing is returned before the last byte has arrived
+        for byte in head {
+            peer.write_all(&[*byte]).unwrap();
+            thread::sleep(Duration::from_millis(1));
+            assert!(pdu.read(&mut stream).unwrap().is_none());
+        }
+        peer.write_all(&[*last]).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let received = loop {
+            if let Some(received) = pdu.read(&mut stream).unwrap() {
+                break received;
+            }
+            assert!(Instant::now() < deadline, "PDU not received");
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(Signal::try_from(&received).unwrap(), signal);
+    }
+
+    #[test]
+    fn test_peer_close_detected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (mut stream, peer) = connected_pair(&listener);
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(16);
+        let mut receiver = MioSocketReceiver::new(&mut stream, &mut poll, &mut events);
+        receiver.register(0).unwrap();
+
+        drop(peer);
+        let result = receiver.recv_timeout(Duration::from_secs(5));
+        assert!(matches!(result, Err(Error::Io((e, _))) if e.kind() == ErrorKind::UnexpectedEof));
+    }
+
+    /// Send until the send fails, the first sends after the peer closed may still succeed
+    fn send_until_failed(sender: &mut MioSocketSender<mio::net::TcpStream>) -> Error {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Err(e) = sender.send(Signal::HelloReady((AgentId::new(1), None))) {
+                return e;
+            }
+            assert!(Instant::now() < deadline, "peer close not detected");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_send_peer_close_detected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let (stream, peer) = connected_pair(&listener);
+        let mut sender = MioSocketSender::new(stream);
+        drop(peer);
+        let error = send_until_failed(&mut sender);
+        assert!(matches!(error, Error::PrimaryDisconnected), "{error}");
+
+        let (stream, peer) = connected_pair(&listener);
+        let mut sender = MioSocketSender::to_agent(stream, AgentId::new(3));
+        drop(peer);
+        let error = send_until_failed(&mut sender);
+        assert!(
+            matches!(error, Error::Disconnected(id) if id == AgentId::new(3)),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_multi_peer_close_detected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (stream_1, peer_1) = connected_pair(&listener);
+        let (stream_2, peer_2) = connected_pair(&listener);
+        let (agent_1, agent_2) = (AgentId::new(1), AgentId::new(2));
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(16);
+        let mut receiver = MioMultiSocketReceiver::new(
+            [(agent_1, stream_1), (agent_2, stream_2)],
+            &mut poll,
+            &mut events,
+        );
+        receiver.register().unwrap();
+
+        // Agent 1 keeps sending while agent 2 closes its connection
+        let mut sender = MioSocketSender::new(mio::net::TcpStream::from_std(peer_1));
+        sender.send(Signal::HelloReady((agent_1, None))).unwrap();
+        drop(peer_2);
+
+        let mut received = Vec::new();
+        let mut closed = Vec::new();
+        for _ in 0..2 {
+            match receiver.recv_timeout(Duration::from_secs(5)) {
+                Ok((agent_id, pdu)) => received.push((agent_id, Signal::try_from(&pdu).unwrap())),
+                Err(Error::Io((e, _))) if e.kind() == ErrorKind::UnexpectedEof => {
+                    closed.push(receiver.len())
+                }
+                Err(e) => panic!("unexpected error {e}"),
+            }
+        }
+        assert_eq!(received.len(), 1);
+        assert!(matches!(received[0], (id, Signal::HelloReady(_)) if id == agent_1));
+        assert_eq!(closed, [1]);
+
+        // The closed stream is not spun on, the remaining one is still served
+        let start = Instant::now();
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_millis(50)),
+            Err(Error::Timeout(_))
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        sender.send(Signal::HelloReady((agent_1, None))).unwrap();
+        let (agent_id, _) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(agent_id, agent_1);
+    }
+
+    #[test]
+    fn test_multi_only_ready_stream_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (streams, peers): (Vec<_>, Vec<_>) = (0..5)
+            .map(|n| {
+                let (stream, peer) = connected_pair(&listener);
+                ((AgentId::new(n), stream), peer)
+            })
+            .unzip();
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(16);
+        let mut receiver = MioMultiSocketReceiver::new(streams, &mut poll, &mut events);
+        receiver.register().unwrap();
+
+        // Initially all streams are tried once, none has data
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(Error::Timeout(_))
+        ));
+        assert!(receiver.readable.is_empty());
+
+        // Only the stream of agent 3 gets data
+        let agent_id = AgentId::new(3);
+        let peer = peers[3].try_clone().unwrap();
+        let mut sender = MioSocketSender::new(mio::net::TcpStream::from_std(peer));
+        sender.send(Signal::HelloReady((agent_id, None))).unwrap();
+
+        let (received_from, _) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received_from, agent_id);
+        // Only the stream whose token fired has been queued for reading
+        assert_eq!(receiver.readable, [agent_id]);
+    }
+
+    #[test]
+    fn test_small_events_capacity() {
+        // Further fields depend on the enabled features
+        #[allow(clippy::needless_update)]
+        let config = SignallingConfig {
+            events_capacity: 1,
+            ..Default::default()
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (streams, peers): (Vec<_>, Vec<_>) = (0..3)
+            .map(|n| {
+                let (stream, peer) = connected_pair(&listener);
+                ((AgentId::new(n), stream), peer)
+            })
+            .unzip();
+        let mut poll = Poll::new().unwrap();
+        let mut events = config.events();
+        let mut receiver = MioMultiSocketReceiver::new(streams, &mut poll, &mut events);
+        receiver.register().unwrap();
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(Error::Timeout(_))
+        ));
+
+        // All agents send at once, more events than fit into a single poll
+        let mut senders: Vec<_> = peers
+            .into_iter()
+            .map(|peer| MioSocketSender::new(mio::net::TcpStream::from_std(peer)))
+            .collect();
+        for (n, sender) in senders.iter_mut().enumerate() {
+            for _ in 0..2 {
+                sender
+                    .send(Signal::HelloReady((AgentId::new(n), None)))
+                    .unwrap();
+            }
+        }
+
+        let mut received = [0; 3];
+        for _ in 0..6 {
+            let (agent_id, _) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            received[usize::from(agent_id)] += 1;
+        }
+        assert_eq!(received, [2, 2, 2]);
+    }
+
+    #[test]
+    fn test_decode_truncated_pdu() {
+        let mut pdu = SignalPdu::from(Signal::RecorderReady((
+            AgentId::new(3),
+            Timestamp(feo_time::Duration::from_millis(5)),
+        )));
+        assert!(Signal::try_from(&pdu).is_ok());
+
+        // Claim less data than required for the signal type
+        pdu.data_len = 4;
+        assert!(Signal::try_from(&pdu).is_err());
+
+        let pdu = SignalPdu {
+            tag: SignalTag::TaskChainStart,
+            ..Default::default()
+        };
+        assert!(Signal::try_from(&pdu).is_err());
+    }
+
+    #[test]
+    fn test_hello_mismatch_rejected() {
+        let pdu = SignalPdu::from(Signal::HelloReady((AgentId::new(7), None)));
+        assert_eq!(pdu.data[..2], [PROTOCOL_VERSION, BYTE_ORDER as u8]);
+        assert!(matches!(
+            Signal::try_from(&pdu),
+            Ok(Signal::HelloReady((id, None))) if id == AgentId::new(7)
+        ));
+
+        // The topology hash is transmitted, an invalid presence flag is rejected
+        let topology = Some(TopologyHash(0x0123_4567_89ab_cdef));
+        let mut pdu = SignalPdu::from(Signal::HelloTrigger((AgentId::new(7), topology)));
+        assert!(matches!(
+            Signal::try_from(&pdu),
+            Ok(Signal::HelloTrigger((id, t))) if id == AgentId::new(7) && t == topology
+        ));
+        pdu.data[2 + size_of::<usize>()] = 2;
+        assert!(matches!(Signal::try_from(&pdu), Err(Error::Protocol(_))));
+
+        // A peer with another protocol version is rejected
+        let mut other_version = SignalPdu::from(Signal::HelloTrigger((AgentId::new(7), None)));
+        other_version.data[0] = PROTOCOL_VERSION + 1;
+        assert!(matches!(
+            Signal::try_from(&other_version),
+            Err(Error::Protocol(_))
+        ));
+
+        // A peer with another byte order is rejected
+        let other_order = match BYTE_ORDER {
+            ByteOrder::Big => ByteOrder::Little,
+            ByteOrder::Little => ByteOrder::Big,
+        };
+        let mut other_order_pdu = SignalPdu::from(Signal::HelloReady((AgentId::new(7), None)));
+        other_order_pdu.data[1] = other_order as u8;
+        assert!(matches!(
+            Signal::try_from(&other_order_pdu),
+            Err(Error::Protocol(_))
+        ));
+
+        // The replies of the primary agent carry the agent id and the primary's topology
+        for reply in [
+            Signal::HelloAccepted(AgentId::new(7)),
+            Signal::HelloRejected(TopologyHash(0x0123_4567_89ab_cdef)),
+        ] {
+            assert_eq!(Signal::try_from(SignalPdu::from(reply)).unwrap(), reply);
+        }
+    }
+
+    /// Shrink the kernel buffer of the given socket option of the given stream
+    fn shrink_buffer(stream: &impl AsRawFd, option: libc::c_int) {
+        let size: libc::c_int = 4096;
+        // Safety: the file descriptor is open and size is a valid c_int of the given length
+        let result = unsafe {
+            libc::setsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                option,
+                (&size as *const libc::c_int).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_send_backpressured() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+        shrink_buffer(&stream, libc::SO_SNDBUF);
+        shrink_buffer(&peer, libc::SO_RCVBUF);
+        stream.set_nonblocking(true).unwrap();
+        let mut sender = MioSocketSender::new(mio::net::TcpStream::from_std(stream));
+
+        // The reader starts late, so the buffers fill up and the stream would block
+        let reader = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let mut received = Vec::new();
+            peer.read_to_end(&mut received).unwrap();
+            received
+        });
+        let signal = Signal::TaskChainStart(Timestamp(feo_time::Duration::from_millis(1)));
+        let count = 10_000;
+        for _ in 0..count {
+            sender.send(signal).unwrap();
+        }
+        drop(sender);
+
+        // All PDUs have been received completely
+        let received = reader.join().unwrap();
+        let mut buffer = [0; MAX_PDU_SIZE];
+        let encoded = SignalPdu::from(signal).encode(&mut buffer).unwrap();
+        assert_eq!(received.len(), count * encoded.len());
+        assert!(received.chunks(encoded.len()).all(|pdu| pdu == encoded));
+    }
+}
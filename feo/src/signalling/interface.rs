@@ -3,11 +3,22 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::error::Error;
+use std::time::Duration;
 
 pub trait Receiver<T>: Send {
     fn recv(&mut self) -> Result<T, Error>;
+
+    /// Receive, waiting at most for `timeout`. Returns [`Error::Timeout`] if nothing has been
+    /// received in time.
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<T, Error>;
 }
 
 pub trait Sender<T>: Send {
     fn send(&mut self, t: T) -> Result<(), Error>;
 }
+
+impl<T, S: Sender<T> + ?Sized> Sender<T> for Box<S> {
+    fn send(&mut self, t: T) -> Result<(), Error> {
+        (**self).send(t)
+    }
+}
@@ -4,8 +4,9 @@
 
 use super::{Receiver, Sender};
 use crate::error::Error;
-use crate::error::Error::Channel;
-use std::sync::mpsc;
+use crate::error::Error::{Channel, Timeout};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
 pub fn channel<T>() -> (IntraProcSender<T>, IntraProcReceiver<T>) {
     let (sender, receiver) = mpsc::channel();
@@ -31,6 +32,13 @@ impl<T: Send> Receiver<T> for IntraProcReceiver<T> {
             .recv()
             .map_err(|_| Channel("failed to receive signal"))
     }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<T> {
+        self.receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => Timeout("no signal received in time"),
+            RecvTimeoutError::Disconnected => Channel("failed to receive signal"),
+        })
+    }
 }
 
 pub struct IntraProcSender<T> {
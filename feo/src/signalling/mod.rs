@@ -2,14 +2,26 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(feature = "signalling_auth")]
+mod auth;
+mod config;
 pub(crate) mod inter_proc_socket;
 mod interface;
 mod intra_proc_mpsc;
 mod signals;
+#[cfg(any(feature = "testing", test))]
+mod sync_queue;
 
+#[cfg(feature = "signalling_auth")]
+pub(crate) use auth::CHALLENGE_SIZE;
+#[cfg(feature = "signalling_auth")]
+pub use auth::{PreSharedKey, KEY_SIZE};
+pub use config::SignallingConfig;
 pub use inter_proc_socket::{
-    MioMultiSocketReceiver, MioMultiSocketSender, MioSocketReceiver, MioSocketSender,
+    MioMultiSocketReceiver, MioMultiSocketSender, MioSocketReceiver, MioSocketSender, SignalPdu,
 };
 pub use interface::{Receiver, Sender};
 pub use intra_proc_mpsc::{channel, IntraProcReceiver, IntraProcSender};
 pub use signals::*;
+#[cfg(any(feature = "testing", test))]
+pub use sync_queue::{queue, QueueReceiver, QueueSender};
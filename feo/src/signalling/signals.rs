@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::configuration::topology::TopologyHash;
 use crate::prelude::ActivityId;
 use crate::timestamp::{SyncInfo, Timestamp};
 #[cfg(feature = "recording")]
@@ -9,8 +10,18 @@ use postcard::experimental::max_size::MaxSize;
 #[cfg(feature = "recording")]
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::num::ParseIntError;
+use std::str::FromStr;
 
 /// Identifies an Agent / Process
+///
+/// Not interchangeable with activity or worker ids, see [`ActivityId`]:
+///
+/// ```compile_fail,E0277
+/// use feo::prelude::{ActivityId, AgentId};
+///
+/// let agent_id: AgentId = ActivityId::from(3).into();
+/// ```
 #[cfg_attr(feature = "recording", derive(Serialize, Deserialize, MaxSize))]
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct AgentId(pub usize);
@@ -27,6 +38,15 @@ impl Display for AgentId {
     }
 }
 
+impl FromStr for AgentId {
+    type Err = ParseIntError;
+
+    /// Parse from either the bare number (e.g. `3`) or the [`Display`] form (e.g. `A3`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('A').unwrap_or(s).parse().map(Self)
+    }
+}
+
 impl From<usize> for AgentId {
     fn from(value: usize) -> Self {
         Self(value)
@@ -45,17 +65,46 @@ impl From<AgentId> for usize {
     }
 }
 
+/// Identifies a task chain cycle
+///
+/// Assigned by the scheduler in increasing order at the start of each cycle and carried by the
+/// step and ready signals of the cycle. The default id `C0` denotes signals outside of a cycle,
+/// i.e. startup and shutdown.
+#[cfg_attr(feature = "recording", derive(Serialize, Deserialize, MaxSize))]
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CycleId(pub u64);
+
+impl Display for CycleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "C{}", self.0)
+    }
+}
+
+impl From<u64> for CycleId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<CycleId> for u64 {
+    fn from(value: CycleId) -> Self {
+        value.0
+    }
+}
+
 /// Signal types sent between threads or processes
 #[cfg_attr(feature = "recording", derive(Serialize, Deserialize, MaxSize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Signal {
     // Signal sent from a secondary agent to the primary agent during initialization phase
-    // to open the channel on which it will send its ready signals later on.
-    HelloReady(AgentId),
+    // to open the channel on which it will send its ready signals later on, with the hash
+    // of the topology the secondary has been configured with, if any
+    HelloReady((AgentId, Option<TopologyHash>)),
 
     // Signal sent from a secondary agent to the primary agent during initialization phase
-    // to open the channel on which it will receive trigger signals later on.
-    HelloTrigger(AgentId),
+    // to open the channel on which it will receive trigger signals later on, with the hash
+    // of the topology the secondary has been configured with, if any
+    HelloTrigger((AgentId, Option<TopologyHash>)),
 
     // Signal sent from the primary agent to each secondary agent containing synchronization info
     StartupSync(SyncInfo),
@@ -73,13 +122,44 @@ pub enum Signal {
     Shutdown((ActivityId, Timestamp)),
 
     // Signal sent by the scheduler on the primary agent to trigger an activity's step method
-    Step((ActivityId, Timestamp)),
+    // in the given cycle
+    Step((ActivityId, Timestamp, CycleId)),
 
-    // Signal sent to indicate that a previously triggered activity method has finished
-    Ready((ActivityId, Timestamp)),
+    // Signal sent to indicate that a previously triggered activity method has finished,
+    // carrying the cycle id of the step signal (or the default id for startup and shutdown)
+    Ready((ActivityId, Timestamp, CycleId)),
 
     // Signal sent to indicate that a recorder operation has finished
     RecorderReady((AgentId, Timestamp)),
+
+    // Signal sent from a secondary agent to the primary agent during time synchronization,
+    // containing the secondary's local send time (NTP-style exchange, see timestamp::SyncSample)
+    SyncRequest((AgentId, Timestamp)),
+
+    // Signal sent from the primary agent in response to a SyncRequest, containing
+    // the primary's (unscaled) receive and send times since startup
+    SyncResponse((Timestamp, Timestamp)),
+
+    // Signal broadcast by the primary agent to all secondary agents and recorders after
+    // all activities have been shut down, telling them to stop
+    SystemShutdown(Timestamp),
+
+    // Signal sent from a secondary agent to the primary agent when the watchdog of the
+    // secondary found the activity still pending
+    ActivityStalled((ActivityId, Timestamp)),
+
+    // Signal broadcast once by the primary agent to all secondary agents and recorders after
+    // all of them have connected and all activities have completed their startup, i.e.
+    // right before the first cycle
+    SystemReady(Timestamp),
+
+    // Signal sent from the primary agent in response to a hello message it accepted, with
+    // the id of the agent
+    HelloAccepted(AgentId),
+
+    // Signal sent from the primary agent in response to a hello message announcing another
+    // topology, with the hash of the primary's own topology. The stream is closed afterwards.
+    HelloRejected(TopologyHash),
 }
 
 /// The id type wrapped in a Signal
@@ -118,9 +198,22 @@ impl Signal {
             Signal::Startup((_, tstamp)) => Some(*tstamp),
             #[allow(unreachable_patterns)]
             Signal::Shutdown((_, tstamp)) => Some(*tstamp),
-            Signal::Step((_, tstamp)) => Some(*tstamp),
-            Signal::Ready((_, tstamp)) => Some(*tstamp),
+            Signal::Step((_, tstamp, _)) => Some(*tstamp),
+            Signal::Ready((_, tstamp, _)) => Some(*tstamp),
             Signal::RecorderReady((_, tstamp)) => Some(*tstamp),
+            Signal::SyncRequest((_, tstamp)) => Some(*tstamp),
+            Signal::SystemShutdown(tstamp) => Some(*tstamp),
+            Signal::ActivityStalled((_, tstamp)) => Some(*tstamp),
+            Signal::SystemReady(tstamp) => Some(*tstamp),
+            _ => None,
+        }
+    }
+
+    /// Return the wrapped cycle id
+    pub fn cycle_id(&self) -> Option<CycleId> {
+        match self {
+            Signal::Step((_, _, cycle_id)) => Some(*cycle_id),
+            Signal::Ready((_, _, cycle_id)) => Some(*cycle_id),
             _ => None,
         }
     }
@@ -136,16 +229,23 @@ impl Signal {
     /// Determine the id type wrapped in the signal
     fn wrapped_id(&self) -> Option<SignalWrappedId> {
         match self {
-            Signal::HelloReady(id) => Some(SignalWrappedId::AgentId(*id)),
-            Signal::HelloTrigger(id) => Some(SignalWrappedId::AgentId(*id)),
+            Signal::HelloReady((id, _)) => Some(SignalWrappedId::AgentId(*id)),
+            Signal::HelloTrigger((id, _)) => Some(SignalWrappedId::AgentId(*id)),
             Signal::StartupSync(_) => None,
             Signal::TaskChainStart(_) => None,
             Signal::TaskChainEnd(_) => None,
             Signal::Startup((id, _)) => Some(SignalWrappedId::ActivityId(*id)),
             Signal::Shutdown((id, _)) => Some(SignalWrappedId::ActivityId(*id)),
-            Signal::Step((id, _)) => Some(SignalWrappedId::ActivityId(*id)),
-            Signal::Ready((id, _)) => Some(SignalWrappedId::ActivityId(*id)),
+            Signal::Step((id, _, _)) => Some(SignalWrappedId::ActivityId(*id)),
+            Signal::Ready((id, _, _)) => Some(SignalWrappedId::ActivityId(*id)),
             Signal::RecorderReady((id, _)) => Some(SignalWrappedId::AgentId(*id)),
+            Signal::SyncRequest((id, _)) => Some(SignalWrappedId::AgentId(*id)),
+            Signal::SyncResponse(_) => None,
+            Signal::SystemShutdown(_) => None,
+            Signal::ActivityStalled((id, _)) => Some(SignalWrappedId::ActivityId(*id)),
+            Signal::SystemReady(_) => None,
+            Signal::HelloAccepted(id) => Some(SignalWrappedId::AgentId(*id)),
+            Signal::HelloRejected(_) => None,
         }
     }
 }
@@ -153,16 +253,39 @@ impl Signal {
 impl Display for Signal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Signal::HelloReady(id) => write!(f, "HelloReady({id})"),
-            Signal::HelloTrigger(id) => write!(f, "HelloTrigger({id})"),
+            Signal::HelloReady((id, None)) => write!(f, "HelloReady({id})"),
+            Signal::HelloReady((id, Some(h))) => write!(f, "HelloReady({id}, {h})"),
+            Signal::HelloTrigger((id, None)) => write!(f, "HelloTrigger({id})"),
+            Signal::HelloTrigger((id, Some(h))) => write!(f, "HelloTrigger({id}, {h})"),
             Signal::StartupSync(t) => write!(f, "StartupSync({t:?})"),
-            Signal::TaskChainStart(t) => write!(f, "TaskChainStart({t:?})"),
-            Signal::TaskChainEnd(t) => write!(f, "TaskChainEnd({t:?})"),
-            Signal::Startup((id, t)) => write!(f, "Startup({id}, {t:?})"),
-            Signal::Shutdown((id, t)) => write!(f, "Shutdown({id}, {t:?})"),
-            Signal::Step((id, t)) => write!(f, "Step({id}, {t:?})"),
-            Signal::Ready((id, t)) => write!(f, "Ready({id}, {t:?})"),
-            Signal::RecorderReady((id, t)) => write!(f, "RecorderReady({id}, {t:?})"),
+            Signal::TaskChainStart(t) => write!(f, "TaskChainStart({t})"),
+            Signal::TaskChainEnd(t) => write!(f, "TaskChainEnd({t})"),
+            Signal::Startup((id, t)) => write!(f, "Startup({id}, {t})"),
+            Signal::Shutdown((id, t)) => write!(f, "Shutdown({id}, {t})"),
+            Signal::Step((id, t, c)) => write!(f, "Step({id}, {t}, {c})"),
+            Signal::Ready((id, t, c)) => write!(f, "Ready({id}, {t}, {c})"),
+            Signal::RecorderReady((id, t)) => write!(f, "RecorderReady({id}, {t})"),
+            Signal::SyncRequest((id, t)) => write!(f, "SyncRequest({id}, {t})"),
+            Signal::SyncResponse((t2, t3)) => write!(f, "SyncResponse({t2}, {t3})"),
+            Signal::SystemShutdown(t) => write!(f, "SystemShutdown({t})"),
+            Signal::ActivityStalled((id, t)) => write!(f, "ActivityStalled({id}, {t})"),
+            Signal::SystemReady(t) => write!(f, "SystemReady({t})"),
+            Signal::HelloAccepted(id) => write!(f, "HelloAccepted({id})"),
+            Signal::HelloRejected(h) => write!(f, "HelloRejected({h})"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::AgentId;
+
+    #[test]
+    fn test_parse_agent_id() {
+        let id = AgentId::from(42);
+        assert_eq!(id, id.to_string().parse().unwrap());
+        assert_eq!(id, "42".parse().unwrap());
+        assert!("A".parse::<AgentId>().is_err());
+        assert!("x42".parse::<AgentId>().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Synchronous queue-backed signalling for deterministic single-threaded tests
+//!
+//! [`QueueSender::send`] pushes to a shared queue and [`QueueReceiver::recv`] pops from it
+//! without ever blocking. A test can thus drive the scheduler side and the "workers" in turns
+//! from one thread, each receiving exactly what the other side has sent before.
+
+use super::{Receiver, Sender};
+use crate::error::Error;
+use crate::error::Error::{Channel, Timeout};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Create a connected pair of queue-backed sender and receiver
+pub fn queue<T>() -> (QueueSender<T>, QueueReceiver<T>) {
+    let queue = Arc::new(Mutex::new(VecDeque::new()));
+    (
+        QueueSender {
+            queue: queue.clone(),
+        },
+        QueueReceiver { queue },
+    )
+}
+
+/// Receiving end of a queue created by [`queue`]
+pub struct QueueReceiver<T> {
+    queue: Arc<Mutex<VecDeque<T>>>,
+}
+
+impl<T> QueueReceiver<T> {
+    /// Number of queued items
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    /// Check if no items are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Send> Receiver<T> for QueueReceiver<T> {
+    /// Pop the oldest item. Returns [`Error::Channel`] if the queue is empty, since nobody
+    /// could push while waiting on the same thread.
+    fn recv(&mut self) -> Result<T> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(Channel("no signal queued"))
+    }
+
+    /// Pop the oldest item. Returns [`Error::Timeout`] immediately if the queue is empty.
+    fn recv_timeout(&mut self, _timeout: Duration) -> Result<T> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(Timeout("no signal queued"))
+    }
+}
+
+/// Sending end of a queue created by [`queue`]
+pub struct QueueSender<T> {
+    queue: Arc<Mutex<VecDeque<T>>>,
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> QueueSender<T> {
+        QueueSender {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Send> Sender<T> for QueueSender<T> {
+    fn send(&mut self, t: T) -> Result<()> {
+        self.queue.lock().unwrap().push_back(t);
+        Ok(())
+    }
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod test {
+    use super::queue;
+    use crate::activity::ActivityId;
+    use crate::error::Error;
+    use crate::signalling::{CycleId, Receiver, Sender, Signal};
+    use crate::timestamp::{self, timestamp};
+    use std::time::Duration;
+
+    #[test]
+    fn test_trigger_ready_exchange() {
+        timestamp::initialize_for_test();
+        let activity_id = ActivityId::from(0);
+        let (mut trigger_sender, mut trigger_receiver) = queue();
+        let (mut ready_sender, mut ready_receiver) = queue();
+        let mut steps = 0;
+
+        for cycle in 1..=3 {
+            // Scheduler triggers the activity
+            trigger_sender
+                .send(Signal::Step((activity_id, timestamp(), CycleId(cycle))))
+                .unwrap();
+            assert_eq!(trigger_receiver.len(), 1);
+
+            // Worker steps the activity and reports it ready
+            let signal = trigger_receiver.recv().unwrap();
+            assert!(matches!(signal, Signal::Step((id, _, _)) if id == activity_id));
+            steps += 1;
+            ready_sender
+                .send(Signal::Ready((activity_id, timestamp(), CycleId(cycle))))
+                .unwrap();
+
+            // Scheduler receives the ready signal, nothing else is pending
+            let signal = ready_receiver.recv().unwrap();
+            assert_eq!(signal.activity_id(), Some(activity_id));
+            assert_eq!(signal.cycle_id(), Some(CycleId(cycle)));
+            assert!(trigger_receiver.is_empty());
+            assert!(matches!(
+                ready_receiver.recv_timeout(Duration::from_secs(1)),
+                Err(Error::Timeout(_))
+            ));
+        }
+        assert_eq!(steps, 3);
+    }
+}
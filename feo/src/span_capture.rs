@@ -0,0 +1,103 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tracing subscriber capturing spans in tests
+//!
+//! Install it with [`tracing::subscriber::with_default`]. Worker threads created in its scope
+//! capture their spans to it as well, see [`Worker::multiplexed`](crate::worker_pool::Worker::multiplexed).
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata};
+
+/// Span name with its fields in the order recorded
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SpanData {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl SpanData {
+    /// Value of the given field, if recorded
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Visit for SpanData {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields
+            .push((field.name().into(), format!("{value:?}")));
+    }
+}
+
+/// What happened to a span
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SpanEvent {
+    New(SpanData),
+    Enter,
+    Exit,
+}
+
+/// Subscriber capturing the span events of all its threads in the order they happened
+#[derive(Default, Clone)]
+pub(crate) struct SpanCapture {
+    next_id: Arc<AtomicU64>,
+    events: Arc<Mutex<Vec<(u64, SpanEvent)>>>,
+}
+
+impl SpanCapture {
+    /// Data of the captured spans in the order of their creation
+    pub fn spans(&self) -> Vec<SpanData> {
+        self.events()
+            .iter()
+            .filter_map(|(_, event)| match event {
+                SpanEvent::New(data) => Some(data.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Captured span events with the ids of their spans
+    pub fn events(&self) -> MutexGuard<'_, Vec<(u64, SpanEvent)>> {
+        self.events.lock().unwrap()
+    }
+}
+
+impl tracing::Subscriber for SpanCapture {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut data = SpanData {
+            name: span.metadata().name().into(),
+            fields: Vec::new(),
+        };
+        span.record(&mut data);
+        self.events().push((id, SpanEvent::New(data)));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.events().push((span.into_u64(), SpanEvent::Enter));
+    }
+
+    fn exit(&self, span: &Id) {
+        self.events().push((span.into_u64(), SpanEvent::Exit));
+    }
+}
@@ -2,11 +2,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use feo_time::Scaled;
+use crate::error::Error;
+use feo_log::debug;
 #[cfg(feature = "recording")]
 use postcard::experimental::max_size::MaxSize;
 #[cfg(feature = "recording")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::sync::OnceLock;
 use std::{self};
 
@@ -16,7 +18,13 @@ const MAX_DELAY: std::time::Duration = std::time::Duration::from_nanos(100);
 /// Maximal number of tries when determining startup time info
 const MAX_TRIES: i32 = 10;
 
+/// Number of NTP-style request/response exchanges done by a secondary agent during startup
+pub const SYNC_ROUNDS: usize = 8;
+
 /// Startup time info (initialized from std::time i.e. without any scaling)
+///
+/// Startup time info and synchronization between agents are in real time; scaling is only
+/// applied when calculating a [`Timestamp`].
 #[derive(Debug)]
 struct TimeInfo {
     systime: std::time::SystemTime,
@@ -37,15 +45,29 @@ pub fn initialize() {
         .expect("failed to initialize startup time");
 }
 
-/// Initialize the instant of system startup from a given
+/// Initialize the instant of system startup unless already initialized
+///
+/// Unit tests share a process and can't rely on being the first to initialize.
+#[cfg(test)]
+pub(crate) fn initialize_for_test() {
+    STARTUP_TIME.get_or_init(time_info_now);
+}
+
+/// Initialize the instant of system startup from a given [`SyncInfo`]
 ///
 /// # Panics:
 ///
 /// Panics if the method has been called before
 pub fn initialize_from(sync_info: SyncInfo) {
-    // Get current system time and corresponding instant
-    let time_info_now = time_info_now();
+    let startup_time_info = startup_time_info_from(sync_info, time_info_now());
+    STARTUP_TIME
+        .set(startup_time_info)
+        .expect("failed to initialize startup time");
+}
 
+/// Calculate the startup time info of the primary agent from the given sync info and the
+/// current local time info
+fn startup_time_info_from(sync_info: SyncInfo, time_info_now: TimeInfo) -> TimeInfo {
     // Calculate the startup time of the primary agent
     let startup_time = std::time::SystemTime::UNIX_EPOCH + sync_info.since_epoch;
 
@@ -66,16 +88,104 @@ pub fn initialize_from(sync_info: SyncInfo) {
         .checked_sub(elapsed_since_startup)
         .expect("failed to synchronize startup time");
 
-    // Set the startup time info
-    let startup_time_info = TimeInfo {
+    TimeInfo {
         instant: startup_instant,
         systime: startup_time,
+    }
+}
+
+/// Initialize the instant of system startup from a given [`SyncInfo`] and a series of
+/// NTP-style synchronization exchanges with the primary agent
+///
+/// In contrast to [`initialize_from()`], this does not rely on synchronized system clocks:
+/// the local instant of the primary's startup is derived from the sample with the smallest
+/// round trip delay (see [`estimate_offset()`]). The system time of startup is still taken
+/// from `sync_info`. Falls back to [`initialize_from()`] if `samples` is empty.
+///
+/// # Panics:
+///
+/// Panics if the method has been called before
+pub fn initialize_from_exchange(
+    sync_info: SyncInfo,
+    reference: std::time::Instant,
+    samples: &[SyncSample],
+) {
+    let Some((offset, delay)) = estimate_offset(samples) else {
+        return initialize_from(sync_info);
+    };
+    debug!("Estimated clock offset to primary agent: {offset} ns (round trip delay {delay:?})");
+
+    // The primary started up at local elapsed time -offset relative to the reference instant
+    let offset_abs = std::time::Duration::from_nanos(
+        u64::try_from(offset.unsigned_abs()).expect("clock offset too large"),
+    );
+    let startup_instant = if offset >= 0 {
+        reference.checked_sub(offset_abs)
+    } else {
+        reference.checked_add(offset_abs)
+    }
+    .expect("failed to synchronize startup time");
+
+    let startup_time_info = TimeInfo {
+        instant: startup_instant,
+        systime: std::time::SystemTime::UNIX_EPOCH + sync_info.since_epoch,
     };
     STARTUP_TIME
         .set(startup_time_info)
         .expect("failed to initialize startup time");
 }
 
+/// Return the real (i.e. unscaled) time elapsed since startup
+///
+/// This is used as the primary's clock in synchronization exchanges.
+///
+/// # Panics
+///
+/// Panics, if neither [`initialize()`] nor [`initialize_from()`] has been called
+pub fn elapsed_since_startup() -> std::time::Duration {
+    startup_instant().elapsed()
+}
+
+/// Timestamps of one NTP-style synchronization exchange between a secondary and the primary agent
+///
+/// `t1` (request sent) and `t4` (response received) are taken on the secondary's monotonic clock
+/// relative to a common reference instant; `t2` (request received) and `t3` (response sent) are
+/// the primary's [`elapsed_since_startup()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SyncSample {
+    pub t1: std::time::Duration,
+    pub t2: std::time::Duration,
+    pub t3: std::time::Duration,
+    pub t4: std::time::Duration,
+}
+
+impl SyncSample {
+    /// Round trip delay, excluding the processing time on the primary agent
+    pub fn delay(&self) -> std::time::Duration {
+        self.t4
+            .saturating_sub(self.t1)
+            .saturating_sub(self.t3.saturating_sub(self.t2))
+    }
+
+    /// Estimated offset of the primary's clock relative to the local clock in nanoseconds
+    ///
+    /// The error of the estimate is bounded by half the round trip [`delay()`](Self::delay).
+    pub fn offset(&self) -> i128 {
+        let nanos = |d: std::time::Duration| d.as_nanos() as i128;
+        ((nanos(self.t2) - nanos(self.t1)) + (nanos(self.t3) - nanos(self.t4))) / 2
+    }
+}
+
+/// Estimate the clock offset (in nanoseconds) from the sample with the smallest round trip delay
+///
+/// Returns the estimated offset and the corresponding delay, or None if there are no samples.
+pub fn estimate_offset(samples: &[SyncSample]) -> Option<(i128, std::time::Duration)> {
+    samples
+        .iter()
+        .min_by_key(|s| s.delay())
+        .map(|s| (s.offset(), s.delay()))
+}
+
 /// Return the startup instant
 ///
 /// # Panics
@@ -104,17 +214,91 @@ pub fn sync_info() -> SyncInfo {
 }
 
 /// A timestamp: Duration since system startup
+///
+/// Timestamps are in feo-time, i.e. the real time elapsed since startup sped up or slowed down
+/// by the factor set with [`feo_time::speed()`]. All agents share the same (real) startup
+/// instant, so their timestamps agree as long as they run with the same speed factor.
 #[cfg_attr(feature = "recording", derive(Serialize, Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Timestamp(pub feo_time::Duration);
 
+/// Return the current timestamp
+///
+/// # Panics
+///
+/// Panics, if neither [`initialize()`] nor [`initialize_from()`] has been called
 pub fn timestamp() -> Timestamp {
-    // get real time duration since startup and scale it with feo-time speed factor
-    let real_duration = std::time::Instant::now().duration_since(startup_instant());
-    let feo_duration: feo_time::Duration = real_duration.scaled();
+    timestamp_at(
+        startup_instant(),
+        std::time::Instant::now(),
+        feo_time::get_speed(),
+    )
+}
+
+/// Calculate the timestamp of the (real) instant `now` with the given feo-time speed factor
+///
+/// Scaling is applied to the whole duration since startup, assuming the speed factor has been
+/// set before startup.
+fn timestamp_at(
+    startup: std::time::Instant,
+    now: std::time::Instant,
+    speed: Option<i32>,
+) -> Timestamp {
+    let real_duration = now.saturating_duration_since(startup);
+    let feo_duration = match speed {
+        Some(factor) if factor > 0 => real_duration * factor.unsigned_abs(),
+        Some(factor) => real_duration / factor.unsigned_abs(),
+        None => real_duration,
+    };
     Timestamp(feo_duration)
 }
 
+impl Timestamp {
+    /// Format as hours, minutes, seconds and milliseconds since startup, e.g. `01:02:03.456`
+    ///
+    /// Same as the [`Display`](fmt::Display) output. Hours are not wrapped into days.
+    pub fn as_hms_millis(&self) -> String {
+        self.to_string()
+    }
+
+    /// Convert to the absolute system time, given the startup time of the primary agent and
+    /// the feo-time speed factor the timestamp is scaled with, if any
+    ///
+    /// The duration since startup is unscaled to real time, so the result is the time of the
+    /// OS clock at which the timestamp was taken.
+    pub fn to_system_time(self, anchor: SyncInfo, speed: Option<i32>) -> std::time::SystemTime {
+        let real = feo_time::scale::unscale(self.0, speed.unwrap_or_default());
+        anchor.system_time() + real
+    }
+
+    /// Convert an absolute system time to a timestamp, given the startup time of the primary
+    /// agent and the feo-time speed factor to scale the timestamp with, if any
+    ///
+    /// Inverse of [`to_system_time`](Self::to_system_time), up to the rounding of the scaling.
+    /// Fails for a system time before startup.
+    pub fn from_system_time(
+        system_time: std::time::SystemTime,
+        anchor: SyncInfo,
+        speed: Option<i32>,
+    ) -> Result<Timestamp, Error> {
+        system_time
+            .duration_since(anchor.system_time())
+            .map(|real| Timestamp(feo_time::scale::scale(real, speed.unwrap_or_default())))
+            .map_err(|_| Error::OutOfRange("system time before startup"))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis();
+        let hours = millis / 3_600_000;
+        let minutes = millis / 60_000 % 60;
+        let seconds = millis / 1_000 % 60;
+        let millis = millis % 1_000;
+        write!(f, "{hours:02}:{minutes:02}:{seconds:02}.{millis:03}")
+    }
+}
+
 #[cfg(feature = "recording")]
 impl MaxSize for Timestamp {
     const POSTCARD_MAX_SIZE: usize = u64::POSTCARD_MAX_SIZE + u32::POSTCARD_MAX_SIZE;
@@ -154,6 +338,13 @@ fn time_info_now() -> TimeInfo {
     }
 }
 
+impl SyncInfo {
+    /// Startup time of the primary agent as system time
+    pub fn system_time(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + self.since_epoch
+    }
+}
+
 #[cfg(feature = "recording")]
 impl MaxSize for SyncInfo {
     const POSTCARD_MAX_SIZE: usize = u64::POSTCARD_MAX_SIZE + u32::POSTCARD_MAX_SIZE;
@@ -173,12 +364,14 @@ impl From<SyncInfo> for u64 {
     }
 }
 
-impl From<u128> for SyncInfo {
-    fn from(nanos: u128) -> SyncInfo {
-        assert!(nanos <= u64::MAX.into(), "input value too large");
-        SyncInfo {
-            since_epoch: std::time::Duration::from_nanos(nanos as u64),
-        }
+impl TryFrom<u128> for SyncInfo {
+    type Error = Error;
+
+    fn try_from(nanos: u128) -> Result<SyncInfo, Error> {
+        let nanos = u64::try_from(nanos).map_err(|_| Error::OutOfRange("sync info too large"))?;
+        Ok(SyncInfo {
+            since_epoch: std::time::Duration::from_nanos(nanos),
+        })
     }
 }
 
@@ -204,10 +397,12 @@ impl From<Timestamp> for u64 {
     }
 }
 
-impl From<u128> for Timestamp {
-    fn from(nanos: u128) -> Timestamp {
-        assert!(nanos <= u64::MAX.into(), "input value too large");
-        Timestamp(feo_time::Duration::from_nanos(nanos as u64))
+impl TryFrom<u128> for Timestamp {
+    type Error = Error;
+
+    fn try_from(nanos: u128) -> Result<Timestamp, Error> {
+        let nanos = u64::try_from(nanos).map_err(|_| Error::OutOfRange("timestamp too large"))?;
+        Ok(Timestamp(feo_time::Duration::from_nanos(nanos)))
     }
 }
 
@@ -220,7 +415,57 @@ impl From<u64> for Timestamp {
 #[cfg(test)]
 mod test {
     #[cfg(feature = "recording")]
-    use super::{MaxSize, Timestamp};
+    use super::MaxSize;
+    use super::{
+        estimate_offset, startup_time_info_from, timestamp_at, SyncInfo, SyncSample, TimeInfo,
+        Timestamp,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_hms_millis() {
+        let duration = Duration::from_secs(3 * 3600 + 25 * 60 + 7) + Duration::from_micros(89_999);
+        assert_eq!(Timestamp(duration).as_hms_millis(), "03:25:07.089");
+        assert_eq!(Timestamp(Duration::ZERO).to_string(), "00:00:00.000");
+        assert_eq!(
+            Timestamp(Duration::from_secs(100 * 3600)).to_string(),
+            "100:00:00.000"
+        );
+    }
+
+    #[test]
+    fn test_system_time_round_trip() {
+        let anchor = SyncInfo::from(1_700_000_000_123_456_789u64);
+        let timestamp = Timestamp(Duration::from_millis(1_500) + Duration::from_nanos(8));
+        let system_time = timestamp.to_system_time(anchor, None);
+        assert_eq!(
+            system_time,
+            std::time::UNIX_EPOCH + Duration::from_nanos(1_700_000_001_623_456_797)
+        );
+        assert_eq!(
+            Timestamp::from_system_time(system_time, anchor, None).unwrap(),
+            timestamp
+        );
+
+        // Timestamps scaled by a speed factor are unscaled to real time
+        for (speed, real) in [(2, 750_000_004), (-2, 3_000_000_016)] {
+            let system_time = timestamp.to_system_time(anchor, Some(speed));
+            assert_eq!(system_time, anchor.system_time() + Duration::from_nanos(real));
+            assert_eq!(
+                Timestamp::from_system_time(system_time, anchor, Some(speed)).unwrap(),
+                timestamp
+            );
+        }
+
+        // Startup itself is timestamp zero, earlier system times have no timestamp
+        let startup = Timestamp(Duration::ZERO).to_system_time(anchor, None);
+        assert_eq!(
+            Timestamp::from_system_time(startup, anchor, None).unwrap(),
+            Timestamp(Duration::ZERO)
+        );
+        let before = startup - Duration::from_nanos(1);
+        assert!(Timestamp::from_system_time(before, anchor, None).is_err());
+    }
 
     #[cfg(feature = "recording")]
     #[test]
@@ -229,4 +474,95 @@ mod test {
         let mut buf = [0u8; Timestamp::POSTCARD_MAX_SIZE];
         postcard::to_slice(&time_stamp, &mut buf).expect("should fit");
     }
+
+    /// Simulate an exchange with the given true offset and one-way delays (all in ms)
+    fn sample(local_send: u64, offset: u64, forward: u64, backward: u64) -> SyncSample {
+        const PROCESSING: u64 = 1;
+        let t1 = local_send;
+        let t2 = t1 + forward + offset;
+        let t3 = t2 + PROCESSING;
+        let t4 = t3 - offset + backward;
+        SyncSample {
+            t1: Duration::from_millis(t1),
+            t2: Duration::from_millis(t2),
+            t3: Duration::from_millis(t3),
+            t4: Duration::from_millis(t4),
+        }
+    }
+
+    #[test]
+    fn test_estimate_offset_within_delay_bound() {
+        const OFFSET_MS: u64 = 5_000;
+        let samples = [
+            sample(0, OFFSET_MS, 40, 2),
+            sample(100, OFFSET_MS, 3, 1),
+            sample(200, OFFSET_MS, 2, 90),
+        ];
+
+        let (offset, delay) = estimate_offset(&samples).unwrap();
+        // The minimum delay sample is selected
+        assert_eq!(delay, Duration::from_millis(4));
+        // The error of the estimate is bounded by half the delay
+        let error = (offset - Duration::from_millis(OFFSET_MS).as_nanos() as i128).unsigned_abs();
+        assert!(error <= delay.as_nanos() / 2, "error {error} ns too large");
+    }
+
+    #[test]
+    fn test_estimate_offset_symmetric_delay_is_exact() {
+        let samples = [sample(10, 1_234, 7, 7)];
+        let (offset, _) = estimate_offset(&samples).unwrap();
+        assert_eq!(offset, Duration::from_millis(1_234).as_nanos() as i128);
+        assert!(estimate_offset(&[]).is_none());
+    }
+
+    #[test]
+    fn test_try_from_out_of_range() {
+        let max = u128::from(u64::MAX);
+        assert!(Timestamp::try_from(max + 1).is_err());
+        assert!(Timestamp::try_from(u128::MAX).is_err());
+        assert!(SyncInfo::try_from(max + 1).is_err());
+        assert!(SyncInfo::try_from(u128::MAX).is_err());
+
+        let tstamp = Timestamp::try_from(max).unwrap();
+        assert_eq!(u128::from(tstamp), max);
+        let info = SyncInfo::try_from(max).unwrap();
+        assert_eq!(u128::from(info), max);
+    }
+
+    #[test]
+    fn test_timestamps_scaled_and_agreeing() {
+        const SPEED: Option<i32> = Some(2);
+
+        // Primary agent starting up
+        let primary = TimeInfo {
+            systime: std::time::SystemTime::now(),
+            instant: std::time::Instant::now(),
+        };
+        let sync_info = SyncInfo {
+            since_epoch: primary
+                .systime
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap(),
+        };
+
+        // Secondary agent synchronizing one second later
+        let elapsed = Duration::from_secs(1);
+        let secondary_now = TimeInfo {
+            systime: primary.systime + elapsed,
+            instant: primary.instant + elapsed,
+        };
+        let secondary = startup_time_info_from(sync_info, secondary_now);
+        assert_eq!(secondary.instant, primary.instant);
+
+        // Timestamps advance at twice the real rate and agree on both agents
+        let now = primary.instant + Duration::from_millis(1500);
+        let primary_tstamp = timestamp_at(primary.instant, now, SPEED);
+        let secondary_tstamp = timestamp_at(secondary.instant, now, SPEED);
+        assert_eq!(primary_tstamp, Timestamp(Duration::from_secs(3)));
+        assert_eq!(primary_tstamp, secondary_tstamp);
+
+        // Slowing down halves the rate
+        let slow = timestamp_at(primary.instant, now, Some(-2));
+        assert_eq!(slow, Timestamp(Duration::from_millis(750)));
+    }
 }
@@ -5,5 +5,5 @@
 mod pool;
 mod worker;
 
-pub use pool::{WorkerPool, WorkerPoolListener, WorkerPoolTrigger};
-pub use worker::WorkerId;
+pub use pool::{TriggerError, WorkerPool, WorkerPoolListener, WorkerPoolTrigger};
+pub use worker::{ActivityStates, Worker, WorkerContext, WorkerId};
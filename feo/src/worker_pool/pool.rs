@@ -2,40 +2,109 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use super::worker::{Worker, WorkerId};
-use crate::activity::{ActivityId, ActivityIdAndBuilder};
-use crate::signalling::{self, Sender, Signal};
-use std::collections::HashMap;
+use super::worker::{ActivityStates, Worker, WorkerId};
+use crate::activity::{ActivityBuilder, ActivityId, ActivityIdAndBuilder};
+use crate::error::Error;
+use crate::signalling::{self, AgentId, CycleId, Sender, Signal};
+use crate::timestamp::timestamp;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Set of activities triggered but not yet signalled ready, shared by trigger and listener
+type Pending = Arc<Mutex<HashSet<ActivityId>>>;
+
+/// Factory of senders of ready signals for workers added to a running pool
+type ReadySenderFactory = Box<dyn Fn() -> Box<dyn Sender<Signal>> + Send>;
+
+/// Failure to trigger an activity in a worker pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerError {
+    /// The signal does not trigger an activity
+    NoActivity(Signal),
+    /// The activity is not part of the pool
+    UnknownActivity(ActivityId),
+}
+
+impl fmt::Display for TriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerError::NoActivity(signal) => {
+                write!(f, "signal {signal} does not trigger an activity")
+            }
+            TriggerError::UnknownActivity(activity_id) => {
+                write!(f, "activity {activity_id} is not part of the worker pool")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TriggerError {}
 
 /// Trigger that can trigger an activity in a worker pool
 pub struct WorkerPoolTrigger {
     trigger_senders: HashMap<ActivityId, Box<dyn Sender<Signal>>>,
+    ready_sender: Box<dyn Sender<Signal>>,
+    pending: Pending,
 }
 
 impl WorkerPoolTrigger {
     /// Trigger an activity in the pool using the given signal
+    ///
+    /// Panics if the signal does not target an activity of the pool, see [`Self::try_trigger`].
     pub fn trigger(&mut self, signal: Signal) {
-        let activity_id = signal.activity_id().unwrap_or_else(|| {
-            panic!("received unexpected trigger signal {signal:?} for worker pool")
-        });
+        self.try_trigger(signal)
+            .unwrap_or_else(|e| panic!("failed to trigger worker pool: {e}"));
+    }
+
+    /// Trigger an activity in the pool using the given signal
+    ///
+    /// Fails if the signal does not target an activity of the pool.
+    pub fn try_trigger(&mut self, signal: Signal) -> Result<(), TriggerError> {
+        let activity_id = signal
+            .activity_id()
+            .ok_or(TriggerError::NoActivity(signal))?;
 
         // Determine the sender to the target activity
         let sender = self
             .trigger_senders
             .get_mut(&activity_id)
-            .unwrap_or_else(|| panic!("failed to trigger unknown activity id {activity_id}"));
+            .ok_or(TriggerError::UnknownActivity(activity_id))?;
 
         // send the signal
+        self.pending.lock().unwrap().insert(activity_id);
         sender
             .send(signal)
             .expect("failed to transmit signal to activity in worker pool");
+        Ok(())
+    }
+
+    /// Stop all workers of the pool
+    ///
+    /// The workers exit once they have finished their current operation. The listener of the
+    /// pool is notified by a [`Signal::SystemShutdown`], see [`WorkerPoolListener::is_shutdown`].
+    pub fn shutdown(mut self) {
+        // Workers exit when their trigger channel is closed
+        self.trigger_senders.clear();
+        self.ready_sender
+            .send(Signal::SystemShutdown(timestamp()))
+            .expect("failed to notify worker pool listener");
     }
 }
 
 /// Listener that can wait for events or test the state of a worker pool
 pub struct WorkerPoolListener {
     activities_ready: HashMap<ActivityId, bool>,
+    /// Cycle id of the last ready signal of each activity
+    cycle_ids: HashMap<ActivityId, CycleId>,
     ready_receiver: Box<dyn signalling::Receiver<Signal>>,
+    /// Whether the pool has been shut down
+    shutdown: bool,
+    /// Activities triggered but not yet signalled ready
+    pending: Pending,
+    /// Ready signals of a recorder sharing the ready channel, not yet taken
+    recorder_ready: Vec<Signal>,
 }
 
 impl WorkerPoolListener {
@@ -56,11 +125,15 @@ impl WorkerPoolListener {
 
         WorkerPoolListener {
             activities_ready,
+            cycle_ids: Default::default(),
             ready_receiver: Box::new(ready_receiver),
+            shutdown: false,
+            pending: Default::default(),
+            recorder_ready: Vec::new(),
         }
     }
 
-    /// Wait until next ready flag has been received
+    /// Wait until next ready flag has been received or the pool has been shut down
     pub fn wait_next_ready(&mut self) {
         // Wait for next ready signal from one of the workers
         loop {
@@ -68,14 +141,71 @@ impl WorkerPoolListener {
                 .ready_receiver
                 .recv()
                 .expect("failed to get signal from worker");
-            if let Signal::Ready((activity_id, _)) = signal {
-                // Set corresponding ready flag and return
-                self.activities_ready.insert(activity_id, true);
+            if self.handle_signal(signal) {
                 break;
             }
         }
     }
 
+    /// Check if the pool has been shut down by [`WorkerPoolTrigger::shutdown`]
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Wait until next ready flag has been received or `timeout` has elapsed
+    ///
+    /// Returns [`Error::Timeout`] if no activity signalled ready in time, e.g. because an
+    /// activity is stuck.
+    pub fn wait_next_ready_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let signal = self.ready_receiver.recv_timeout(remaining)?;
+            if self.handle_signal(signal) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Update the state of the pool with the given signal from a worker
+    ///
+    /// Returns whether the signal is a ready signal, of an activity or a recorder, or a
+    /// shutdown of the pool.
+    fn handle_signal(&mut self, signal: Signal) -> bool {
+        match signal {
+            Signal::Ready((activity_id, _, cycle_id)) => {
+                // Set corresponding ready flag
+                self.pending.lock().unwrap().remove(&activity_id);
+                self.activities_ready.insert(activity_id, true);
+                self.cycle_ids.insert(activity_id, cycle_id);
+                true
+            }
+            Signal::SystemShutdown(_) => {
+                self.shutdown = true;
+                true
+            }
+            Signal::RecorderReady(_) => {
+                self.recorder_ready.push(signal);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the activities triggered but not yet signalled ready, sorted by id
+    ///
+    /// Only tracked for listeners created by [`WorkerPool::listener`].
+    pub fn pending(&self) -> Vec<ActivityId> {
+        let mut pending: Vec<_> = self.pending.lock().unwrap().iter().copied().collect();
+        pending.sort();
+        pending
+    }
+
+    /// Take the ready signals received from a recorder sharing the ready channel of the pool
+    pub fn take_recorder_ready(&mut self) -> Vec<Signal> {
+        std::mem::take(&mut self.recorder_ready)
+    }
+
     /// Clear all ready flags
     pub fn clear_ready(&mut self) {
         self.activities_ready.values_mut().for_each(|v| *v = false);
@@ -89,8 +219,13 @@ impl WorkerPoolListener {
             .all(|(_, v)| *v)
     }
 
+    /// Get the cycle id of the last ready signal of the given activity
+    pub fn cycle_id(&self, activity_id: &ActivityId) -> CycleId {
+        self.cycle_ids.get(activity_id).copied().unwrap_or_default()
+    }
+
     /// Return an iterator to the map of ready flags
-    pub fn ready_iter(&self) -> std::collections::hash_map::Iter<ActivityId, bool> {
+    pub fn ready_iter(&self) -> std::collections::hash_map::Iter<'_, ActivityId, bool> {
         self.activities_ready.iter()
     }
 }
@@ -99,32 +234,63 @@ impl WorkerPoolListener {
 pub struct WorkerPool {
     workers: Vec<Worker>,
     activity_ids: Vec<ActivityId>,
+    /// Lifecycle states of the activities, maintained by the workers
+    states: ActivityStates,
     workpool_trigger: WorkerPoolTrigger,
+    /// Agent id added to the tracing spans of the workers
+    agent_id: Option<AgentId>,
+    /// Stack size of the worker threads
+    stack_size: Option<usize>,
+    /// Ready senders of workers added by [`Self::add_activity`]
+    ready_senders: ReadySenderFactory,
 }
 
 impl WorkerPool {
     /// Create a new worker pool
+    ///
+    /// The agent id, if given, is added to the tracing spans of the workers. By default, each
+    /// worker runs on its own thread. If a number of threads is given, the workers are
+    /// distributed round-robin by id onto at most that many threads, see
+    /// [`Worker::multiplexed`]. Workers added later by [`Self::add_activity`] are not limited
+    /// by the number of threads.
     pub fn new(
         builder_map: HashMap<WorkerId, Vec<ActivityIdAndBuilder>>,
         ready_sender: &(impl Sender<Signal> + Clone + 'static),
         stack_size: Option<usize>,
+        agent_id: Option<AgentId>,
+        threads: Option<usize>,
     ) -> WorkerPool {
         assert!(
             !builder_map.is_empty(),
             "cannot create worker pool from empty configuration"
         );
+        assert_ne!(
+            threads,
+            Some(0),
+            "cannot create worker pool without threads"
+        );
 
         let mut trigger_senders: HashMap<ActivityId, Box<dyn Sender<Signal>>> = Default::default();
         let mut workers: Vec<Worker> = vec![];
         let mut activity_ids: Vec<ActivityId> = vec![];
+        let states = ActivityStates::default();
+
+        // Assign the workers to threads in the order of their ids
+        let mut builder_map: Vec<_> = builder_map.into_iter().collect();
+        builder_map.sort_by_key(|(worker_id, _)| usize::from(worker_id));
+        let threads = threads.unwrap_or(builder_map.len()).min(builder_map.len());
+        let mut thread_map: Vec<Vec<_>> = (0..threads).map(|_| Vec::new()).collect();
+        for (index, worker) in builder_map.into_iter().enumerate() {
+            thread_map[index % threads].push(worker);
+        }
 
-        // Loop over all required worker ids, create worker with trigger channel and ready channel
-        for (worker_id, builders) in builder_map {
-            // Create channel for triggering activities in the given worker
+        // Loop over all threads, create thread with trigger channel and ready channel
+        for thread_workers in thread_map {
+            // Create channel for triggering activities in the workers of the given thread
             let (trigger_sender, trigger_receiver) = signalling::channel();
 
-            // Loop over all activities to be executed by the current worker
-            for (act_id, _) in &builders {
+            // Loop over all activities to be executed by the current thread
+            for (act_id, _) in thread_workers.iter().flat_map(|(_, builders)| builders) {
                 // Store sender to use for triggering this activity
                 let previous = trigger_senders.insert(*act_id, Box::new(trigger_sender.clone()));
 
@@ -135,28 +301,100 @@ impl WorkerPool {
                 activity_ids.push(*act_id);
             }
 
-            workers.push(Worker::new(
-                worker_id,
+            workers.push(Worker::multiplexed(
+                thread_workers,
+                agent_id,
                 stack_size,
-                builders,
+                states.clone(),
                 trigger_receiver,
                 ready_sender.clone(),
             ));
         }
 
+        let ready_sender = ready_sender.clone();
         WorkerPool {
             workers,
             activity_ids,
-            workpool_trigger: WorkerPoolTrigger { trigger_senders },
+            states,
+            workpool_trigger: WorkerPoolTrigger {
+                trigger_senders,
+                ready_sender: Box::new(ready_sender.clone()),
+                pending: Default::default(),
+            },
+            agent_id,
+            stack_size,
+            ready_senders: Box::new(move || Box::new(ready_sender.clone())),
         }
     }
 
+    /// Add an activity to the pool, run by a new worker with the given id
+    ///
+    /// The activity is built on the new worker thread and can be triggered right away,
+    /// starting with its startup. Listeners created before are not aware of the activity.
+    ///
+    /// The new worker always gets a thread of its own, even if the pool has been created with
+    /// a limited number of threads: the running threads have built their activities already and
+    /// cannot take on another one. Isolating the new worker also keeps a replacement of a stuck
+    /// activity from being blocked by it.
+    pub fn add_activity(
+        &mut self,
+        worker_id: WorkerId,
+        activity_id: ActivityId,
+        builder: Box<dyn ActivityBuilder>,
+    ) {
+        assert!(
+            !self.has_worker(worker_id),
+            "duplicate worker id {worker_id}"
+        );
+        let (trigger_sender, trigger_receiver) = signalling::channel();
+        let trigger_senders = &mut self.workpool_trigger.trigger_senders;
+        let previous = trigger_senders.insert(activity_id, Box::new(trigger_sender));
+        assert!(previous.is_none(), "duplicate activity id");
+        self.activity_ids.push(activity_id);
+
+        self.workers.push(Worker::new(
+            worker_id,
+            self.agent_id,
+            self.stack_size,
+            vec![(activity_id, builder)],
+            self.states.clone(),
+            trigger_receiver,
+            (self.ready_senders)(),
+        ));
+    }
+
+    /// Remove an activity from the pool, e.g. one added by [`Self::add_activity`] that is stuck
+    ///
+    /// The activity cannot be triggered anymore. Its worker exits once its current operation
+    /// has returned, if it runs no other activity.
+    pub fn remove_activity(&mut self, activity_id: ActivityId) {
+        self.workpool_trigger.trigger_senders.remove(&activity_id);
+        self.workpool_trigger.pending.lock().unwrap().remove(&activity_id);
+        self.activity_ids.retain(|id| *id != activity_id);
+    }
+
+    /// Check whether the pool has a worker with the given id
+    pub fn has_worker(&self, worker_id: WorkerId) -> bool {
+        self.workers
+            .iter()
+            .any(|worker| worker.ids().contains(&worker_id))
+    }
+
     /// Create a listener to this worker pool
     pub fn listener(
         &self,
         ready_receiver: impl signalling::Receiver<Signal> + 'static,
     ) -> WorkerPoolListener {
-        WorkerPoolListener::new(&self.activity_ids, ready_receiver)
+        let mut listener = WorkerPoolListener::new(&self.activity_ids, ready_receiver);
+        listener.pending = self.workpool_trigger.pending.clone();
+        listener
+    }
+
+    /// Lifecycle states of the activities of the pool
+    ///
+    /// The returned handle stays valid after the pool has been split or moved into an agent.
+    pub fn activity_states(&self) -> ActivityStates {
+        self.states.clone()
     }
 
     /// Split the worker pool into a set of workers and a WorkerPoolTrigger object
@@ -168,4 +406,261 @@ impl WorkerPool {
     pub fn trigger(&mut self, signal: Signal) {
         self.workpool_trigger.trigger(signal)
     }
+
+    /// Trigger an activity in the pool using the given signal, see [`WorkerPoolTrigger::try_trigger`]
+    pub fn try_trigger(&mut self, signal: Signal) -> Result<(), TriggerError> {
+        self.workpool_trigger.try_trigger(signal)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TriggerError, WorkerPool};
+    use crate::activity::{Activity, ActivityId, ActivityIdAndBuilder, ActivityState};
+    use crate::error::Error;
+    use crate::signalling::{self, CycleId, Receiver, Signal};
+    use crate::timestamp::{self, timestamp};
+    use crate::worker_pool::{WorkerContext, WorkerId};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use std::time::Duration;
+
+    /// Step of an activity as logged by [`Probe`]: worker, activity, step count and thread
+    type Step = (WorkerId, ActivityId, usize, ThreadId);
+
+    /// Activity logging its steps together with the worker and thread stepping it
+    struct Probe {
+        id: ActivityId,
+        steps: usize,
+        log: Arc<Mutex<Vec<Step>>>,
+    }
+
+    impl Activity for Probe {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            self.steps += 1;
+            let worker_id = WorkerContext::current().unwrap().worker_id;
+            let thread_id = thread::current().id();
+            self.log
+                .lock()
+                .unwrap()
+                .push((worker_id, self.id, self.steps, thread_id));
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    /// Activity whose step never completes
+    struct Stuck(ActivityId);
+
+    impl Activity for Stuck {
+        fn id(&self) -> ActivityId {
+            self.0
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            loop {
+                thread::park();
+            }
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_wait_next_ready_timeout() {
+        timestamp::initialize_for_test();
+        let activity_id = ActivityId::from(0);
+        let builders: Vec<ActivityIdAndBuilder> = vec![(
+            activity_id,
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        )];
+        let (ready_sender, ready_receiver) = signalling::channel();
+        let mut pool = WorkerPool::new(
+            HashMap::from([(WorkerId::from(0), builders)]),
+            &ready_sender,
+            None,
+            None,
+            None,
+        );
+        let mut listener = pool.listener(ready_receiver);
+
+        pool.trigger(Signal::Startup((activity_id, timestamp())));
+        listener
+            .wait_next_ready_timeout(Duration::from_secs(5))
+            .expect("startup did not complete");
+        assert!(listener.is_all_ready(&[activity_id]));
+        assert!(listener.pending().is_empty());
+
+        listener.clear_ready();
+        pool.trigger(Signal::Step((activity_id, timestamp(), CycleId(1))));
+        let result = listener.wait_next_ready_timeout(Duration::from_millis(50));
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(!listener.is_all_ready(&[activity_id]));
+        assert_eq!(listener.pending(), [activity_id]);
+    }
+
+    #[test]
+    fn test_multiplexed_workers() {
+        timestamp::initialize_for_test();
+        const CYCLES: usize = 3;
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let activities = |worker: usize| {
+            [
+                ActivityId::from(2 * worker),
+                ActivityId::from(2 * worker + 1),
+            ]
+        };
+        let builder_map: HashMap<WorkerId, Vec<ActivityIdAndBuilder>> = (0..4)
+            .map(|worker| {
+                let builders = activities(worker)
+                    .into_iter()
+                    .map(|activity_id| -> ActivityIdAndBuilder {
+                        let log = log.clone();
+                        let builder = move |id| {
+                            let steps = 0;
+                            Box::new(Probe { id, steps, log }) as Box<dyn Activity>
+                        };
+                        (activity_id, Box::new(builder))
+                    })
+                    .collect();
+                (WorkerId::from(worker), builders)
+            })
+            .collect();
+        let (ready_sender, mut ready_receiver) = signalling::channel();
+        let mut pool = WorkerPool::new(builder_map, &ready_sender, None, None, Some(2));
+
+        // Trigger all steps of all cycles at once, interleaving the workers
+        for cycle in 1..=CYCLES {
+            for activity_id in (0..4).flat_map(activities) {
+                pool.trigger(Signal::Step((
+                    activity_id,
+                    timestamp(),
+                    CycleId(cycle as u64),
+                )));
+            }
+        }
+        for _ in 0..4 * 2 * CYCLES {
+            ready_receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("activity did not become ready");
+        }
+
+        let log = log.lock().unwrap();
+        let mut threads = HashMap::new();
+        for worker in 0..4 {
+            let worker_id = WorkerId::from(worker);
+            let steps: Vec<_> = log.iter().filter(|step| step.0 == worker_id).collect();
+
+            // The activities of each worker are stepped in the order of their triggers
+            let expected: Vec<_> = (1..=CYCLES)
+                .flat_map(|cycle| activities(worker).map(|activity_id| (activity_id, cycle)))
+                .collect();
+            let actual: Vec<_> = steps.iter().map(|step| (step.1, step.2)).collect();
+            assert_eq!(actual, expected, "steps of {worker_id}");
+
+            // ...on the same thread
+            let thread_ids: HashSet<ThreadId> = steps.iter().map(|step| step.3).collect();
+            assert_eq!(thread_ids.len(), 1, "threads of {worker_id}");
+            threads.insert(worker_id, *thread_ids.iter().next().unwrap());
+        }
+
+        // The workers are distributed round-robin onto two threads
+        assert_eq!(threads.values().collect::<HashSet<_>>().len(), 2);
+        assert_eq!(threads[&WorkerId::from(0)], threads[&WorkerId::from(2)]);
+        assert_eq!(threads[&WorkerId::from(1)], threads[&WorkerId::from(3)]);
+        assert!((0..4).all(|worker| pool.has_worker(WorkerId::from(worker))));
+    }
+
+    #[test]
+    fn test_try_trigger_unknown_activity() {
+        timestamp::initialize_for_test();
+        let activity_id = ActivityId::from(0);
+        let unknown_id = ActivityId::from(1);
+        let builders: Vec<ActivityIdAndBuilder> = vec![(
+            activity_id,
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        )];
+        let (ready_sender, ready_receiver) = signalling::channel();
+        let mut pool = WorkerPool::new(
+            HashMap::from([(WorkerId::from(0), builders)]),
+            &ready_sender,
+            None,
+            None,
+            None,
+        );
+        let listener = pool.listener(ready_receiver);
+
+        let result = pool.try_trigger(Signal::Step((unknown_id, timestamp(), CycleId(1))));
+        assert_eq!(result, Err(TriggerError::UnknownActivity(unknown_id)));
+        let signal = Signal::TaskChainStart(timestamp());
+        assert_eq!(
+            pool.try_trigger(signal),
+            Err(TriggerError::NoActivity(signal))
+        );
+        assert!(listener.pending().is_empty());
+    }
+
+    #[test]
+    fn test_add_activity() {
+        timestamp::initialize_for_test();
+        let builders: Vec<ActivityIdAndBuilder> = vec![(
+            ActivityId::from(0),
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        )];
+        let (ready_sender, mut ready_receiver) = signalling::channel();
+        let mut pool = WorkerPool::new(
+            HashMap::from([(WorkerId::from(0), builders)]),
+            &ready_sender,
+            None,
+            None,
+            None,
+        );
+
+        // The added activity runs on its own worker, reporting ready on the pool's channel
+        let activity_id = ActivityId::from(1);
+        pool.add_activity(
+            WorkerId::from(1),
+            activity_id,
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        );
+        assert!(pool.has_worker(WorkerId::from(1)));
+        pool.trigger(Signal::Startup((activity_id, timestamp())));
+        let signal = ready_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(signal, Signal::Ready((id, _, _)) if id == activity_id));
+        assert_eq!(
+            pool.activity_states().get(&activity_id),
+            Some(ActivityState::StartedUp)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate worker id W0")]
+    fn test_add_activity_duplicate_worker() {
+        let builders: Vec<ActivityIdAndBuilder> = vec![(
+            ActivityId::from(0),
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        )];
+        let (ready_sender, _) = signalling::channel();
+        let mut pool = WorkerPool::new(
+            HashMap::from([(WorkerId::from(0), builders)]),
+            &ready_sender,
+            None,
+            None,
+            None,
+        );
+        pool.add_activity(
+            WorkerId::from(0),
+            ActivityId::from(1),
+            Box::new(|id| Box::new(Stuck(id)) as Box<dyn Activity>),
+        );
+    }
 }
@@ -2,16 +2,32 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::activity::{Activity, ActivityId, ActivityIdAndBuilder};
-use crate::signalling::{Receiver, Sender, Signal};
+use crate::activity::{self, Activity, ActivityId, ActivityIdAndBuilder, ActivityState};
+use crate::signalling::{AgentId, Receiver, Sender, Signal};
 use crate::timestamp::timestamp;
-use feo_log::debug;
+use feo_log::{debug, error};
+use feo_tracing::tracing::dispatcher;
+use feo_tracing::tracing::field::display;
+use feo_tracing::tracing::subscriber::NoSubscriber;
+use feo_tracing::tracing::Span;
 use feo_tracing::{span, Level};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::thread;
 
 /// Worker id type. This id is unique to each worker thread.
+///
+/// Not interchangeable with activity or agent ids, see [`ActivityId`]:
+///
+/// ```compile_fail,E0308
+/// use feo::prelude::{AgentId, WorkerId};
+///
+/// let worker_id: WorkerId = AgentId::new(40);
+/// ```
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct WorkerId(usize);
 
@@ -39,104 +55,595 @@ impl Display for WorkerId {
     }
 }
 
+impl FromStr for WorkerId {
+    type Err = ParseIntError;
+
+    /// Parse from either the bare number (e.g. `3`) or the [`Display`] form (e.g. `W3`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('W').unwrap_or(s).parse().map(Self)
+    }
+}
+
 type ActivityBuilders = Vec<ActivityIdAndBuilder>;
 
+/// Activity builders of each worker multiplexed onto one thread
+type WorkerBuilders = Vec<(WorkerId, ActivityBuilders)>;
+
+thread_local! {
+    /// Context of the worker running on the current thread, if any
+    static CONTEXT: Cell<Option<WorkerContext>> = const { Cell::new(None) };
+}
+
+/// Context of a worker thread
+///
+/// Activities can query the context of the worker stepping them with
+/// [`WorkerContext::current`], e.g. to add it to their own tracing spans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerContext {
+    /// Id of the worker
+    pub worker_id: WorkerId,
+    /// Id of the agent running the worker, if known
+    pub agent_id: Option<AgentId>,
+}
+
+impl WorkerContext {
+    /// Context of the worker running on the current thread, `None` outside of workers
+    pub fn current() -> Option<WorkerContext> {
+        CONTEXT.get()
+    }
+}
+
+/// Lifecycle states of the activities of a worker pool, maintained by its workers
+///
+/// Clones share the same states, e.g. to observe them from a health monitor while the agent
+/// runs.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityStates(Arc<Mutex<HashMap<ActivityId, ActivityState>>>);
+
+impl ActivityStates {
+    /// Current state of the given activity, `None` if it is not part of the pool
+    pub fn get(&self, activity_id: &ActivityId) -> Option<ActivityState> {
+        self.0.lock().unwrap().get(activity_id).copied()
+    }
+
+    /// Snapshot of the current states of all activities of the pool
+    pub fn snapshot(&self) -> HashMap<ActivityId, ActivityState> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn set(&self, activity_id: ActivityId, state: ActivityState) {
+        self.0.lock().unwrap().insert(activity_id, state);
+    }
+}
+
+/// Guard marking an activity as failed if dropped during a panic, i.e. if a call to the
+/// activity panicked
+struct FailGuard<'a> {
+    states: &'a ActivityStates,
+    activity_id: ActivityId,
+}
+
+impl Drop for FailGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.states.set(self.activity_id, ActivityState::Failed);
+        }
+    }
+}
+
 /// A worker thread that steps activities.
+///
+/// The thread runs either a single worker or several workers multiplexed onto it, see
+/// [`Worker::multiplexed`].
 #[allow(unused)]
 pub struct Worker {
-    id: WorkerId,
+    ids: Vec<WorkerId>,
     thread: thread::JoinHandle<()>,
 }
 
 #[allow(unused)]
 impl Worker {
+    /// Id of the (first) worker run by the thread
     pub fn id(&self) -> WorkerId {
-        self.id
+        self.ids[0]
+    }
+
+    /// Ids of all workers run by the thread
+    pub fn ids(&self) -> &[WorkerId] {
+        &self.ids
     }
 
     /// Create a new worker thread that will build and execute activities.
     ///
-    /// This function spawns a new thread.
+    /// This function spawns a new thread named after the worker, see [`thread_name`]. The
+    /// worker maintains the lifecycle states of its activities in `states`.
     pub fn new<R, S>(
         id: WorkerId,
+        agent_id: Option<AgentId>,
         stack_size: Option<usize>,
         builders: ActivityBuilders,
-        mut trigger: R,
-        mut ready: S,
+        states: ActivityStates,
+        trigger: R,
+        ready: S,
     ) -> Worker
     where
         R: Receiver<Signal> + 'static,
         S: Sender<Signal> + 'static,
     {
-        let thread_name = format!("feo-{id}").to_lowercase();
+        Self::multiplexed(
+            vec![(id, builders)],
+            agent_id,
+            stack_size,
+            states,
+            trigger,
+            ready,
+        )
+    }
+
+    /// Create a new thread running several workers
+    ///
+    /// The activities of all workers are triggered through the same channel and executed one
+    /// after the other in the order of their triggers. Thus the activities of each worker are
+    /// still executed sequentially, but an activity blocks the activities of all other workers
+    /// on the thread while being executed. The thread is named after the first worker.
+    ///
+    /// Spans of the thread go to the tracing subscriber of the calling thread, including one
+    /// set by [`with_default`](feo_tracing::tracing::subscriber::with_default).
+    ///
+    /// Panics if no worker is given.
+    pub fn multiplexed<R, S>(
+        workers: WorkerBuilders,
+        agent_id: Option<AgentId>,
+        stack_size: Option<usize>,
+        states: ActivityStates,
+        trigger: R,
+        ready: S,
+    ) -> Worker
+    where
+        R: Receiver<Signal> + 'static,
+        S: Sender<Signal> + 'static,
+    {
+        assert!(
+            !workers.is_empty(),
+            "cannot create worker thread without workers"
+        );
+        for (activity_id, _) in workers.iter().flat_map(|(_, builders)| builders) {
+            states.set(*activity_id, ActivityState::Created);
+        }
+
+        let ids: Vec<WorkerId> = workers.iter().map(|(id, _)| *id).collect();
+        let thread_name = thread_name(ids[0]);
         let mut builder = thread::Builder::new().name(thread_name.clone());
         if let Some(stack_size) = stack_size {
             builder = builder.stack_size(stack_size);
         }
+        // Without a subscriber yet, the thread picks up a global one set later on
+        let dispatch = dispatcher::get_default(|dispatch| {
+            (!dispatch.is::<NoSubscriber>()).then(|| dispatch.clone())
+        });
         let thread = builder
             .spawn(move || {
-                run(id, thread_name, builders, trigger, ready);
+                let run = || run(agent_id, thread_name, workers, states, trigger, ready);
+                match dispatch {
+                    Some(dispatch) => dispatcher::with_default(&dispatch, run),
+                    None => run(),
+                }
             })
             .expect("could not spawn thread");
 
-        Worker { thread, id }
+        Worker { thread, ids }
+    }
+
+    /// Wait for the worker thread to exit, i.e. until its trigger channel has been closed
+    pub fn join(self) {
+        let id = self.id();
+        if self.thread.join().is_err() {
+            error!("Worker {id} panicked");
+        }
     }
 }
 
+/// OS name of the thread of the given worker, e.g. `feo-worker-3`
+///
+/// Tracing tools like the feo-tracer resolve thread names via procfs. Linux limits thread
+/// names to 15 bytes, which fits worker ids up to 9999.
+fn thread_name(id: WorkerId) -> String {
+    format!("feo-worker-{}", usize::from(id))
+}
+
 /// Worker thread main function
 fn run<R, S>(
-    wid: WorkerId,
+    agent_id: Option<AgentId>,
     thread_name: String,
-    builders: ActivityBuilders,
+    workers: WorkerBuilders,
+    states: ActivityStates,
     mut trigger: R,
     mut ready: S,
 ) where
     R: Receiver<Signal> + 'static,
     S: Sender<Signal> + 'static,
 {
-    // instantiate all activities and keep them in a map
-    let mut activities: HashMap<ActivityId, Box<dyn Activity>> = builders
+    // instantiate all activities in the context of their worker and keep them in a map
+    let mut activities: HashMap<ActivityId, (WorkerContext, Box<dyn Activity>)> = workers
         .into_iter()
-        .map(|(id, builder)| (id, builder(id)))
+        .flat_map(|(worker_id, builders)| {
+            let context = WorkerContext {
+                worker_id,
+                agent_id,
+            };
+            builders.into_iter().map(move |(id, builder)| {
+                CONTEXT.set(Some(context));
+                (id, (context, builder(id)))
+            })
+        })
         .collect();
 
     loop {
-        // Receive next activity to step
-        let signal = trigger.recv().expect("failed to receive trigger signal");
+        // Receive next activity to step, exit when the trigger channel has been closed
+        let Ok(signal) = trigger.recv() else {
+            debug!("Trigger channel of thread {thread_name} closed, exiting");
+            break;
+        };
         let activity_id = signal.activity_id().expect("received unexpected signal");
-        if let Some(activity) = activities.get_mut(&activity_id) {
-            match signal {
+        let cycle_id = signal.cycle_id().unwrap_or_default();
+        if let Some((context, activity)) = activities.get_mut(&activity_id) {
+            let context = *context;
+            let wid = context.worker_id;
+            CONTEXT.set(Some(context));
+            let _guard = FailGuard {
+                states: &states,
+                activity_id,
+            };
+            let state = match signal {
                 Signal::Startup(_) => {
                     debug!(
                         "Starting up activity {activity_id} in worker {wid} (thread {thread_name})"
                     );
-                    let _span = span!(Level::INFO, "Startup", id = %activity_id, worker_id = %wid)
-                        .entered();
+                    let _span = activity_span(&signal, activity_id, context).entered();
                     activity.startup();
+                    ActivityState::StartedUp
                 }
                 Signal::Step(_) => {
                     debug!(
                         "Stepping activity {activity_id} in worker {wid} (thread {thread_name})"
                     );
-                    let _span =
-                        span!(Level::INFO, "Step", id = %activity_id, worker_id = %wid).entered();
+                    let _span = activity_span(&signal, activity_id, context).entered();
                     activity.step();
+                    ActivityState::Running
                 }
                 Signal::Shutdown(_) => {
                     debug!("Shutting down activity {activity_id} in worker {wid} (thread {thread_name})");
-                    let _span = span!(Level::INFO, "Shutdown", id = %activity_id, worker_id = %wid)
-                        .entered();
+                    let _span = activity_span(&signal, activity_id, context).entered();
                     activity.shutdown();
+                    ActivityState::ShutDown
                 }
                 _ => panic!("received unexpected trigger signal {signal:?}"),
             };
+            states.set(activity_id, state);
+
+            // Hand errors reported during the call to the activity
+            for err in activity::take_errors() {
+                debug!("Activity {activity_id} in worker {wid} reported error: {err}");
+                activity.on_error(err);
+            }
         } else {
             panic!("received trigger {signal} for unknown activity id {activity_id}");
         }
 
         // Operation finished => send ready signal with timestamp
         ready
-            .send(Signal::Ready((activity_id, timestamp())))
+            .send(Signal::Ready((activity_id, timestamp(), cycle_id)))
+            .unwrap();
+    }
+}
+
+/// Create the span of the given trigger signal of an activity
+///
+/// The span carries the ids of the activity, the worker and, if known, the agent, so that
+/// traces of multiple agents are self-describing.
+fn activity_span(signal: &Signal, activity_id: ActivityId, context: WorkerContext) -> Span {
+    let activity_id = display(activity_id);
+    let worker_id = display(context.worker_id);
+    let agent_id = context.agent_id.map(display);
+    match signal {
+        Signal::Startup(_) => {
+            span!(Level::INFO, "Startup", activity_id, worker_id, agent_id)
+        }
+        Signal::Step(_) => span!(Level::INFO, "Step", activity_id, worker_id, agent_id),
+        Signal::Shutdown(_) => {
+            span!(Level::INFO, "Shutdown", activity_id, worker_id, agent_id)
+        }
+        _ => Span::none(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{activity_span, thread_name, ActivityStates, Worker, WorkerContext, WorkerId};
+    use crate::activity::{
+        Activity, ActivityError, ActivityId, ActivityIdAndBuilder, ActivityState,
+    };
+    use crate::com::backend_inprocess::{init_topic, InProcSubscriber};
+    use crate::com::Input;
+    use crate::signalling::{self, AgentId, CycleId, Receiver, Sender, Signal};
+    use crate::span_capture::{SpanCapture, SpanData};
+    use crate::timestamp::{self, timestamp};
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Activity requiring an input which is never written, recording the reported errors
+    struct Consumer {
+        id: ActivityId,
+        input: Input<u32, InProcSubscriber<u32>>,
+        errors: Arc<Mutex<Vec<ActivityError>>>,
+    }
+
+    impl Activity for Consumer {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            assert!(self.input.read_required().is_none());
+        }
+
+        fn shutdown(&mut self) {}
+
+        fn on_error(&mut self, err: ActivityError) {
+            self.errors.lock().unwrap().push(err);
+        }
+    }
+
+    #[test]
+    fn test_parse_worker_id() {
+        let id = WorkerId::from(42);
+        assert_eq!(id, id.to_string().parse().unwrap());
+        assert_eq!(id, "42".parse().unwrap());
+        assert!("W".parse::<WorkerId>().is_err());
+        assert!("x42".parse::<WorkerId>().is_err());
+    }
+
+    /// Activity recording the OS name of the thread it is stepped on
+    struct ThreadNameProbe {
+        id: ActivityId,
+        name: Arc<Mutex<Option<String>>>,
+    }
+
+    impl Activity for ThreadNameProbe {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            let name = fs::read_to_string("/proc/thread-self/comm").unwrap();
+            *self.name.lock().unwrap() = Some(name.trim_end().to_string());
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    /// Activity panicking in its step if told to
+    struct Fragile {
+        id: ActivityId,
+        fail: bool,
+    }
+
+    impl Activity for Fragile {
+        fn id(&self) -> ActivityId {
+            self.id
+        }
+
+        fn startup(&mut self) {}
+
+        fn step(&mut self) {
+            assert!(!self.fail, "activity {} failed", self.id);
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn test_activity_states() {
+        timestamp::initialize_for_test();
+        let healthy = ActivityId::from(0);
+        let failing = ActivityId::from(1);
+        let builder = |fail| -> ActivityIdAndBuilder {
+            let id = if fail { failing } else { healthy };
+            (
+                id,
+                Box::new(move |id| Box::new(Fragile { id, fail }) as Box<dyn Activity>),
+            )
+        };
+        let states = ActivityStates::default();
+        let (mut trigger_sender, trigger_receiver) = signalling::channel();
+        let (ready_sender, mut ready_receiver) = signalling::channel();
+        let worker = Worker::new(
+            WorkerId::from(0),
+            None,
+            None,
+            vec![builder(false), builder(true)],
+            states.clone(),
+            trigger_receiver,
+            ready_sender,
+        );
+        assert_eq!(states.get(&healthy), Some(ActivityState::Created));
+        assert_eq!(states.get(&ActivityId::from(2)), None);
+
+        // Step the healthy activity through its lifecycle
+        for (signal, expected) in [
+            (
+                Signal::Startup((healthy, timestamp())),
+                ActivityState::StartedUp,
+            ),
+            (
+                Signal::Step((healthy, timestamp(), CycleId(1))),
+                ActivityState::Running,
+            ),
+            (
+                Signal::Step((healthy, timestamp(), CycleId(2))),
+                ActivityState::Running,
+            ),
+            (
+                Signal::Shutdown((healthy, timestamp())),
+                ActivityState::ShutDown,
+            ),
+        ] {
+            trigger_sender.send(signal).unwrap();
+            ready_receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("activity did not become ready");
+            assert_eq!(states.get(&healthy), Some(expected));
+        }
+        assert_eq!(states.get(&failing), Some(ActivityState::Created));
+
+        // A panicking step marks the activity failed
+        trigger_sender
+            .send(Signal::Startup((failing, timestamp())))
+            .unwrap();
+        ready_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("activity did not become ready");
+        trigger_sender
+            .send(Signal::Step((failing, timestamp(), CycleId(3))))
+            .unwrap();
+        drop(trigger_sender);
+        worker.join();
+
+        let snapshot = states.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[&healthy], ActivityState::ShutDown);
+        assert_eq!(snapshot[&failing], ActivityState::Failed);
+    }
+
+    #[test]
+    fn test_worker_thread_name() {
+        timestamp::initialize_for_test();
+        let name = Arc::new(Mutex::new(None));
+        let activity_id = ActivityId::from(0);
+        let builders: Vec<ActivityIdAndBuilder> = vec![(activity_id, {
+            let name = name.clone();
+            Box::new(move |id| Box::new(ThreadNameProbe { id, name }) as Box<dyn Activity>)
+        })];
+        let (mut trigger_sender, trigger_receiver) = signalling::channel();
+        let (ready_sender, mut ready_receiver) = signalling::channel();
+        let worker_id = WorkerId::from(1234);
+        let _worker = Worker::new(
+            worker_id,
+            None,
+            None,
+            builders,
+            ActivityStates::default(),
+            trigger_receiver,
+            ready_sender,
+        );
+
+        trigger_sender
+            .send(Signal::Step((activity_id, timestamp(), CycleId(1))))
             .unwrap();
+        ready_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("activity did not become ready");
+
+        assert_eq!(thread_name(worker_id), "feo-worker-1234");
+        assert_eq!(name.lock().unwrap().as_deref(), Some("feo-worker-1234"));
+    }
+
+    #[test]
+    fn test_on_error_missing_input() {
+        timestamp::initialize_for_test();
+        let _topic = init_topic::<u32>("test/worker/missing", 1, 1).unwrap();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let activity_id = ActivityId::from(0);
+        let builders: Vec<ActivityIdAndBuilder> = vec![(activity_id, {
+            let errors = errors.clone();
+            Box::new(move |id| {
+                Box::new(Consumer {
+                    id,
+                    input: Input::get("test/worker/missing").unwrap(),
+                    errors,
+                }) as Box<dyn Activity>
+            })
+        })];
+        let (mut trigger_sender, trigger_receiver) = signalling::channel();
+        let (ready_sender, mut ready_receiver) = signalling::channel();
+        let _worker = Worker::new(
+            WorkerId::from(0),
+            None,
+            None,
+            builders,
+            ActivityStates::default(),
+            trigger_receiver,
+            ready_sender,
+        );
+
+        for signal in [
+            Signal::Startup((activity_id, timestamp())),
+            Signal::Step((activity_id, timestamp(), CycleId(1))),
+        ] {
+            trigger_sender.send(signal).unwrap();
+            let ready = ready_receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("activity did not become ready");
+            assert!(matches!(ready, Signal::Ready((id, _, _)) if id == activity_id));
+            assert_eq!(
+                ready.cycle_id(),
+                Some(signal.cycle_id().unwrap_or_default())
+            );
+        }
+
+        // Only the step read the input
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [ActivityError::MissingInput("test/worker/missing".into())]
+        );
+    }
+
+    #[test]
+    fn test_activity_span_fields() {
+        timestamp::initialize_for_test();
+        let capture = SpanCapture::default();
+        let activity_id = ActivityId::from(3);
+        let context = WorkerContext {
+            worker_id: WorkerId::from(7),
+            agent_id: Some(AgentId::from(101)),
+        };
+        let anonymous = WorkerContext {
+            agent_id: None,
+            ..context
+        };
+        tracing::subscriber::with_default(capture.clone(), || {
+            let step = Signal::Step((activity_id, timestamp(), CycleId(1)));
+            let _span = activity_span(&step, activity_id, context);
+            let startup = Signal::Startup((activity_id, timestamp()));
+            let _span = activity_span(&startup, activity_id, anonymous);
+        });
+
+        let field = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            capture.spans(),
+            [
+                SpanData {
+                    name: "Step".into(),
+                    fields: vec![
+                        field("activity_id", "A3"),
+                        field("worker_id", "W7"),
+                        field("agent_id", "A101"),
+                    ],
+                },
+                // The agent id is left out if unknown
+                SpanData {
+                    name: "Startup".into(),
+                    fields: vec![field("activity_id", "A3"), field("worker_id", "W7")],
+                },
+            ]
+        );
+
+        // No context outside of workers
+        assert_eq!(WorkerContext::current(), None);
     }
 }
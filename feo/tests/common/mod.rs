@@ -0,0 +1,124 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Harness of the tests running each agent in its own process
+//!
+//! Each agent initializes the process global startup time, so each one runs in its own process.
+//! A test spawns its own test binary once per agent, selecting the role by environment.
+
+// Not every test binary uses every item
+#![allow(dead_code)]
+
+use feo::activity::{Activity, ActivityId};
+use std::net::{SocketAddr, TcpListener};
+use std::process::{Child, Command, ExitStatus};
+use std::time::Instant;
+use std::{env, thread};
+
+/// Environment variable selecting the agent to run in a child process
+const ROLE: &str = "FEO_TEST_ROLE";
+/// Environment variable with the address of the primary agent
+const ADDR: &str = "FEO_TEST_ADDR";
+/// Environment variable with a file path shared by the agents, e.g. of a recording
+const FILE: &str = "FEO_TEST_FILE";
+
+/// Maximum time for all agents to exit
+pub const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Agent to run in a child process, see [`role`]
+pub struct Role {
+    /// Name of the role, as passed to [`run_agents`]
+    pub name: String,
+    /// Address of the primary agent
+    pub addr: SocketAddr,
+    /// File path passed to [`run_agents`], if any
+    pub file: Option<String>,
+}
+
+/// Get the agent to run if this process is a child process spawned by [`run_agents`]
+pub fn role() -> Option<Role> {
+    let name = env::var(ROLE).ok()?;
+    let addr = env::var(ADDR).unwrap().parse().unwrap();
+    let file = env::var(FILE).ok();
+    Some(Role { name, addr, file })
+}
+
+/// Run the given test in a child process per role and wait until all of them have exited
+///
+/// Returns the exit status of each role, `None` if it did not exit within [`TIMEOUT`] and has
+/// been killed, see [`assert_success`].
+pub fn run_agents(
+    test: &str,
+    roles: &[&'static str],
+    file: Option<&str>,
+) -> Vec<(&'static str, Option<ExitStatus>)> {
+    // Bind and drop a listener to get a local port nobody listens on
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut children: Vec<(&str, Child)> = roles
+        .iter()
+        .map(|role| (*role, spawn(test, role, addr, file)))
+        .collect();
+
+    let deadline = Instant::now() + TIMEOUT;
+    let mut statuses = Vec::new();
+    for (role, child) in children.iter_mut() {
+        let status = loop {
+            if let Some(status) = child.try_wait().unwrap() {
+                break Some(status);
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                break None;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        };
+        statuses.push((*role, status));
+    }
+    statuses
+}
+
+/// Assert that all agents run by [`run_agents`] exited cleanly on their own
+pub fn assert_success(statuses: Vec<(&str, Option<ExitStatus>)>) {
+    for (role, status) in statuses {
+        let status = status.unwrap_or_else(|| panic!("{role} did not exit within {TIMEOUT:?}"));
+        assert!(status.success(), "{role} exited with {status}");
+    }
+}
+
+/// Spawn the given test in a child process running the given agent
+fn spawn(test: &str, role: &str, addr: SocketAddr, file: Option<&str>) -> Child {
+    let mut command = Command::new(env::current_exe().unwrap());
+    command
+        .args([test, "--exact", "--nocapture"])
+        .env(ROLE, role)
+        .env(ADDR, addr.to_string());
+    if let Some(file) = file {
+        command.env(FILE, file);
+    }
+    command.spawn().unwrap()
+}
+
+/// Activity doing nothing, e.g. to fill a deployment under test
+pub struct Nop(ActivityId);
+
+impl Activity for Nop {
+    fn id(&self) -> ActivityId {
+        self.0
+    }
+
+    fn startup(&mut self) {}
+
+    fn step(&mut self) {}
+
+    fn shutdown(&mut self) {}
+}
+
+/// Build a [`Nop`] activity, e.g. as builder of a worker pool assignment
+pub fn nop(id: ActivityId) -> Box<dyn Activity> {
+    Box::new(Nop(id))
+}
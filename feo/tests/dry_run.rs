@@ -0,0 +1,130 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Dry run of a primary and a secondary agent validating the deployment without any cycle
+//!
+//! Each agent runs in its own process, see [`common`].
+
+mod common;
+
+use feo::activity::{Activity, ActivityId, ActivityState};
+use feo::configuration::primary_agent::{ActivityDependencies, AgentConfig};
+use feo::configuration::topology::{Topology, TopologyHash};
+use feo::configuration::{primary_agent, secondary_agent, worker_pool};
+use feo::prelude::{secondary, AgentId, WorkerId};
+use feo_time::Duration;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::process;
+
+const PRIMARY_ID: AgentId = AgentId::new(0);
+const SECONDARY_ID: AgentId = AgentId::new(1);
+
+/// Activity exiting the process with a failure when stepped
+struct NoStep(ActivityId);
+
+impl Activity for NoStep {
+    fn id(&self) -> ActivityId {
+        self.0
+    }
+
+    fn startup(&mut self) {}
+
+    fn step(&mut self) {
+        eprintln!("Activity {} stepped in dry run", self.0);
+        process::exit(2);
+    }
+
+    fn shutdown(&mut self) {}
+}
+
+fn no_step(id: ActivityId) -> Box<dyn Activity> {
+    Box::new(NoStep(id))
+}
+
+fn agent_map() -> [AgentConfig; 2] {
+    [
+        (PRIMARY_ID, HashMap::from([(0.into(), vec![0.into()])])),
+        (SECONDARY_ID, HashMap::from([(1.into(), vec![1.into()])])),
+    ]
+}
+
+fn dependencies() -> ActivityDependencies {
+    HashMap::from([(0.into(), vec![]), (1.into(), vec![0.into()])])
+}
+
+/// Hash of the topology both agents are configured with
+fn topology() -> TopologyHash {
+    let agent_map = HashMap::from(agent_map());
+    Topology::new(&agent_map, [&dependencies()], &[]).hash()
+}
+
+fn run_primary(addr: SocketAddr) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(PRIMARY_ID).activity(
+        WorkerId::from(0),
+        ActivityId::from(0),
+        Box::new(no_step),
+    );
+    let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+
+    let mut agent = primary_agent::Builder::default()
+        .id(PRIMARY_ID)
+        .cycle_time(Duration::from_millis(10))
+        .bind(addr)
+        .agent_map(agent_map())
+        .worker_pool(Some(pool))
+        .activity_dependencies(dependencies())
+        .intra_proc_ready_channel(ready_sender, ready_receiver)
+        .dry_run(true)
+        .build();
+    let states = agent.activity_states().unwrap();
+    agent.run_result().unwrap();
+
+    // The local activity has been started up and shut down again
+    assert_eq!(
+        states.get(&ActivityId::from(0)),
+        Some(ActivityState::ShutDown)
+    );
+}
+
+fn run_secondary(addr: SocketAddr) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(SECONDARY_ID).activity(
+        WorkerId::from(1),
+        ActivityId::from(1),
+        Box::new(no_step),
+    );
+    let (pool, _, ready_receiver) = pool_builder.build().unwrap();
+
+    let agent = secondary_agent::Builder::default()
+        .id(SECONDARY_ID)
+        .primary(addr)
+        .worker_pool(pool, ready_receiver)
+        .topology(topology())
+        .dry_run(true)
+        .build();
+    let states = agent.activity_states();
+    secondary::run_result(agent).unwrap();
+    assert_eq!(
+        states.get(&ActivityId::from(1)),
+        Some(ActivityState::ShutDown)
+    );
+}
+
+#[test]
+fn test_dry_run() {
+    if let Some(role) = common::role() {
+        match role.name.as_str() {
+            "primary" => run_primary(role.addr),
+            "secondary" => run_secondary(role.addr),
+            _ => panic!("unknown role {}", role.name),
+        }
+        return;
+    }
+
+    // Both agents exit cleanly on their own
+    let statuses = common::run_agents("test_dry_run", &["primary", "secondary"], None);
+    common::assert_success(statuses);
+}
@@ -0,0 +1,90 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scheduling on the manual clock of feo-time
+//!
+//! The clock mode of feo-time is process global and can be set only once, so this test runs in
+//! its own test binary.
+
+use feo::activity::{Activity, ActivityId};
+use feo::configuration::{primary_agent, worker_pool};
+use feo::prelude::{AgentId, WorkerId};
+use feo_time::Duration;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Cycle time far beyond the test's real time, so cycles are driven by the manual clock only
+const CYCLE_TIME: Duration = Duration::from_secs(3600);
+const CYCLES: usize = 3;
+
+/// Maximum real time to wait for the scheduler to react to the manual clock
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Activity counting its steps
+struct Counting(ActivityId, Arc<AtomicUsize>);
+
+impl Activity for Counting {
+    fn id(&self) -> ActivityId {
+        self.0
+    }
+
+    fn startup(&mut self) {}
+
+    fn step(&mut self) {
+        self.1.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn shutdown(&mut self) {}
+}
+
+/// Wait in real time until the activity has been stepped `n` times
+fn wait_steps(steps: &AtomicUsize, n: usize) {
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    while steps.load(Ordering::SeqCst) < n {
+        assert!(std::time::Instant::now() < deadline, "no step {n}");
+        thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn test_cycles_on_manual_clock() {
+    feo_time::manual();
+
+    let steps = Arc::new(AtomicUsize::new(0));
+    let (done_sender, done_receiver) = mpsc::channel();
+    let agent_steps = steps.clone();
+    thread::spawn(move || {
+        let agent_id = AgentId::new(0);
+        let mut pool_builder = worker_pool::Builder::default();
+        pool_builder.agent_id(agent_id).activity(
+            WorkerId::from(0),
+            ActivityId::from(0),
+            Box::new(move |id| Box::new(Counting(id, agent_steps)) as Box<dyn Activity>),
+        );
+        let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+        let mut agent = primary_agent::Builder::default()
+            .id(agent_id)
+            .cycle_time(CYCLE_TIME)
+            .bind("127.0.0.1:0".parse().unwrap())
+            .agent_map([(agent_id, HashMap::from([(0.into(), vec![0.into()])]))])
+            .worker_pool(Some(pool))
+            .activity_dependencies(HashMap::from([(0.into(), vec![])]))
+            .intra_proc_ready_channel(ready_sender, ready_receiver)
+            .build();
+        done_sender.send(agent.run_cycles(CYCLES)).unwrap();
+    });
+
+    // Each cycle starts only once the clock has been advanced by the cycle time
+    for cycle in 1..=CYCLES {
+        wait_steps(&steps, cycle);
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(steps.load(Ordering::SeqCst), cycle);
+        feo_time::advance(CYCLE_TIME);
+    }
+
+    done_receiver.recv_timeout(TIMEOUT).unwrap().unwrap();
+    assert_eq!(steps.load(Ordering::SeqCst), CYCLES);
+}
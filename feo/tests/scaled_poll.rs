@@ -0,0 +1,92 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Poll timeouts under time scaling
+//!
+//! The speed factor of feo-time is process global and can be set only once, so these tests
+//! run in their own test binary.
+
+mod common;
+
+use feo::agent::poll_timeout;
+use feo_time::Duration;
+use mio::{Events, Poll};
+use std::sync::Once;
+use std::time::Instant;
+
+/// Speed factor of all tests
+const SPEED: i32 = 2;
+
+/// Set the speed factor once for all tests
+fn init_speed() {
+    static SPEED_SET: Once = Once::new();
+    SPEED_SET.call_once(|| feo_time::speed(SPEED));
+}
+
+#[test]
+fn test_poll_timeout_scaled() {
+    init_speed();
+
+    assert_eq!(poll_timeout(None), None);
+    let timeout = poll_timeout(Some(Duration::from_millis(100)));
+    assert_eq!(timeout, Some(std::time::Duration::from_millis(50)));
+
+    // Nothing is registered, so the poll returns after the timeout
+    let mut poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(1);
+    let start = Instant::now();
+    poll.poll(&mut events, timeout).unwrap();
+    let elapsed = start.elapsed();
+
+    assert!(events.is_empty());
+    assert!(
+        elapsed >= std::time::Duration::from_millis(45),
+        "polled for {elapsed:?}"
+    );
+    assert!(
+        elapsed < std::time::Duration::from_millis(90),
+        "polled for {elapsed:?}"
+    );
+}
+
+#[test]
+fn test_cycle_time_scaled() {
+    use crate::common::nop;
+    use feo::activity::ActivityId;
+    use feo::configuration::{primary_agent, worker_pool};
+    use feo::prelude::{AgentId, WorkerId};
+    use std::collections::HashMap;
+
+    const CYCLES: u32 = 4;
+    const CYCLE_TIME: Duration = Duration::from_millis(100);
+
+    init_speed();
+
+    let agent_id = AgentId::new(0);
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder
+        .agent_id(agent_id)
+        .activity(WorkerId::from(0), ActivityId::from(0), Box::new(nop));
+    let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+    let mut agent = primary_agent::Builder::default()
+        .id(agent_id)
+        .cycle_time(CYCLE_TIME)
+        .bind("127.0.0.1:0".parse().unwrap())
+        .agent_map([(agent_id, HashMap::from([(0.into(), vec![0.into()])]))])
+        .worker_pool(Some(pool))
+        .activity_dependencies(HashMap::from([(0.into(), vec![])]))
+        .intra_proc_ready_channel(ready_sender, ready_receiver)
+        .build();
+
+    // The scheduler waits for the start of each cycle in feo-time. Waiting the cycle time in
+    // real time instead would take `SPEED` times as long in feo-time.
+    let start = feo_time::Instant::now();
+    agent.run_cycles(CYCLES as usize).unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= CYCLE_TIME * (CYCLES - 1), "ran for {elapsed:?}");
+    assert!(
+        elapsed < CYCLE_TIME * (CYCLES - 1) * SPEED as u32,
+        "ran for {elapsed:?}"
+    );
+}
@@ -0,0 +1,147 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Secondary agent hosting a recorder, sharing its connection to the primary agent
+//!
+//! Each agent runs in its own process, see [`common`].
+
+#![cfg(all(feature = "recording", feature = "ipc_inprocess"))]
+
+mod common;
+
+use common::nop;
+use feo::activity::ActivityId;
+use feo::configuration::{primary_agent, secondary_agent, worker_pool};
+use feo::prelude::{secondary, AgentId, WorkerId};
+use feo::recording::reader::{RecordingEnd, RecordingReader};
+use feo::recording::recorder::{Record, Recorder, RecordingRules};
+use feo::recording::registry::TypeRegistry;
+use feo::signalling::{channel, Signal};
+use feo_time::Duration;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::{env, fs, thread};
+
+const PRIMARY_ID: AgentId = AgentId::new(0);
+/// Id of the secondary agent, also used by the recorder it hosts
+const SECONDARY_ID: AgentId = AgentId::new(1);
+
+/// Number of cycles to run
+const CYCLES: usize = 3;
+
+fn run_primary(addr: SocketAddr) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(PRIMARY_ID).activity(
+        WorkerId::from(0),
+        ActivityId::from(0),
+        Box::new(nop),
+    );
+    let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+
+    let mut agent = primary_agent::Builder::default()
+        .id(PRIMARY_ID)
+        .cycle_time(Duration::from_millis(10))
+        .bind(addr)
+        .agent_map([
+            (PRIMARY_ID, HashMap::from([(0.into(), vec![0.into()])])),
+            (SECONDARY_ID, HashMap::from([(1.into(), vec![1.into()])])),
+        ])
+        .hosted_recorder(SECONDARY_ID)
+        .worker_pool(Some(pool))
+        .activity_dependencies(HashMap::from([
+            (0.into(), vec![]),
+            (1.into(), vec![0.into()]),
+        ]))
+        .intra_proc_ready_channel(ready_sender, ready_receiver)
+        .build();
+    agent.run_cycles(CYCLES).unwrap();
+}
+
+fn run_secondary(addr: SocketAddr, file: String) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(SECONDARY_ID).activity(
+        WorkerId::from(1),
+        ActivityId::from(1),
+        Box::new(nop),
+    );
+    let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+
+    // The recorder runs on a thread of the secondary agent's process
+    let (record_sender, record_receiver) = channel();
+    let path: &'static str = Box::leak(file.into_boxed_str());
+    let recorder = thread::spawn(move || {
+        let registry = TypeRegistry::new();
+        let mut recorder = Recorder::new_local(
+            SECONDARY_ID,
+            record_receiver,
+            ready_sender,
+            path,
+            RecordingRules::new(),
+            &registry,
+        )
+        .unwrap();
+        recorder.run();
+    });
+
+    let agent = secondary_agent::Builder::default()
+        .id(SECONDARY_ID)
+        .primary(addr)
+        .worker_pool(pool, ready_receiver)
+        .recorder(record_sender)
+        .build();
+    secondary::run(agent);
+    recorder.join().unwrap();
+
+    // The steps of the activities of both agents are recorded, closed cleanly on shutdown
+    let recording = fs::read(path).unwrap();
+    let mut reader = RecordingReader::new(&recording);
+    let signals: Vec<Signal> = reader
+        .by_ref()
+        .filter_map(|record| match record.unwrap().0 {
+            Record::Signal(record) => Some(record.signal),
+            _ => None,
+        })
+        .collect();
+    for id in [ActivityId::from(0), ActivityId::from(1)] {
+        let steps = signals
+            .iter()
+            .filter(|s| matches!(s, Signal::Step((step_id, _, _)) if *step_id == id))
+            .count();
+        assert_eq!(steps, CYCLES, "steps of {id} in {signals:?}");
+    }
+    let chain_ends = signals
+        .iter()
+        .filter(|s| matches!(s, Signal::TaskChainEnd(_)))
+        .count();
+    assert_eq!(chain_ends, CYCLES, "{signals:?}");
+    assert!(matches!(signals.last(), Some(Signal::SystemShutdown(_))));
+    assert_eq!(reader.end(), Some(RecordingEnd::Clean));
+}
+
+#[test]
+fn test_secondary_recorder() {
+    if let Some(role) = common::role() {
+        match role.name.as_str() {
+            "primary" => run_primary(role.addr),
+            "secondary" => run_secondary(role.addr, role.file.unwrap()),
+            _ => panic!("unknown role {}", role.name),
+        }
+        return;
+    }
+
+    let file = env::temp_dir().join(format!(
+        "feo-test-secondary-recorder-{}.bin",
+        std::process::id()
+    ));
+    let file = file.to_str().unwrap();
+
+    // Both agents exit cleanly on their own
+    let statuses = common::run_agents(
+        "test_secondary_recorder",
+        &["primary", "secondary"],
+        Some(file),
+    );
+    let _ = fs::remove_file(file);
+    common::assert_success(statuses);
+}
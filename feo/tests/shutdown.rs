@@ -0,0 +1,121 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Distributed shutdown of a primary agent, a secondary agent and a recorder
+//!
+//! Each agent runs in its own process, see [`common`].
+
+#![cfg(all(feature = "recording", feature = "ipc_inprocess"))]
+
+mod common;
+
+use common::nop;
+use feo::activity::ActivityId;
+use feo::configuration::{primary_agent, secondary_agent, worker_pool};
+use feo::prelude::{secondary, AgentId, WorkerId};
+use feo::recording::reader::{RecordingEnd, RecordingReader};
+use feo::recording::recorder::{Record, Recorder, RecordingRules};
+use feo::recording::registry::TypeRegistry;
+use feo::signalling::Signal;
+use feo_time::Duration;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::{env, fs};
+
+const PRIMARY_ID: AgentId = AgentId::new(0);
+const SECONDARY_ID: AgentId = AgentId::new(1);
+const RECORDER_ID: AgentId = AgentId::new(2);
+
+fn run_primary(addr: SocketAddr) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(PRIMARY_ID).activity(
+        WorkerId::from(0),
+        ActivityId::from(0),
+        Box::new(nop),
+    );
+    let (pool, ready_sender, ready_receiver) = pool_builder.build().unwrap();
+
+    let mut agent = primary_agent::Builder::default()
+        .id(PRIMARY_ID)
+        .cycle_time(Duration::from_millis(10))
+        .bind(addr)
+        .agent_map([
+            (PRIMARY_ID, HashMap::from([(0.into(), vec![0.into()])])),
+            (SECONDARY_ID, HashMap::from([(1.into(), vec![1.into()])])),
+        ])
+        .recorders([RECORDER_ID])
+        .worker_pool(Some(pool))
+        .activity_dependencies(HashMap::from([
+            (0.into(), vec![]),
+            (1.into(), vec![0.into()]),
+        ]))
+        .intra_proc_ready_channel(ready_sender, ready_receiver)
+        .build();
+    agent.run_cycles(3).unwrap();
+}
+
+fn run_secondary(addr: SocketAddr) {
+    let mut pool_builder = worker_pool::Builder::default();
+    pool_builder.agent_id(SECONDARY_ID).activity(
+        WorkerId::from(1),
+        ActivityId::from(1),
+        Box::new(nop),
+    );
+    let (pool, _, ready_receiver) = pool_builder.build().unwrap();
+
+    let agent = secondary_agent::Builder::default()
+        .id(SECONDARY_ID)
+        .primary(addr)
+        .worker_pool(pool, ready_receiver)
+        .build();
+    secondary::run(agent);
+}
+
+fn run_recorder(addr: SocketAddr, file: String) {
+    let registry = TypeRegistry::new();
+    let path: &'static str = Box::leak(file.into_boxed_str());
+    let mut recorder =
+        Recorder::new(RECORDER_ID, addr, path, RecordingRules::new(), &registry).unwrap();
+    recorder.run();
+    drop(recorder);
+
+    // The recording is complete up to the shutdown and closed cleanly
+    let recording = fs::read(path).unwrap();
+    let mut reader = RecordingReader::new(&recording);
+    let records: Vec<_> = reader.by_ref().map(Result::unwrap).collect();
+    assert!(matches!(
+        records[records.len() - 2].0,
+        Record::Signal(record) if matches!(record.signal, Signal::SystemShutdown(_))
+    ));
+    assert!(matches!(
+        records[records.len() - 1].0,
+        Record::EndOfRecording { .. }
+    ));
+    assert_eq!(reader.end(), Some(RecordingEnd::Clean));
+}
+
+#[test]
+fn test_system_shutdown() {
+    if let Some(role) = common::role() {
+        match role.name.as_str() {
+            "primary" => run_primary(role.addr),
+            "secondary" => run_secondary(role.addr),
+            "recorder" => run_recorder(role.addr, role.file.unwrap()),
+            _ => panic!("unknown role {}", role.name),
+        }
+        return;
+    }
+
+    let file = env::temp_dir().join(format!("feo-test-shutdown-{}.bin", std::process::id()));
+    let file = file.to_str().unwrap();
+
+    // All agents exit cleanly on their own
+    let statuses = common::run_agents(
+        "test_system_shutdown",
+        &["primary", "secondary", "recorder"],
+        Some(file),
+    );
+    let _ = fs::remove_file(file);
+    common::assert_success(statuses);
+}
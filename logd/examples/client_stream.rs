@@ -6,7 +6,6 @@
 
 use anyhow::Error;
 use logd::UNIX_STREAM_PATH;
-use std::io::Write;
 use std::os::unix::net;
 use std::{thread, time};
 
@@ -24,9 +23,7 @@ fn main() -> Result<(), Error> {
             tid: 19,
             args: b"hello again unix via unix stream",
         };
-        let len = record.encoded_len() as u32;
-        stream.write_all(&len.to_be_bytes())?;
-        record.encode(&mut stream)?;
+        record.encode_framed(&mut stream)?;
 
         thread::sleep(time::Duration::from_secs(1));
     }
@@ -112,8 +112,9 @@ async fn connection<S: Stream<Item = io::Result<OwnedRecord>>>(
     }
 }
 
-/// A codec for decoding log records from a stream. The stream contains length-prefixed records.
-/// The length is a u32 in big-endian format.
+/// A codec for decoding log records from a stream. The stream contains length-prefixed records
+/// as written by [`Record::encode_framed`](feo_logger::record::Record::encode_framed).
+/// The length is a u32 in big-endian format. Records are reassembled across reads.
 #[derive(Debug)]
 pub struct LogStreamCodec {
     inner: LengthDelimitedCodec,
@@ -124,6 +125,7 @@ impl Default for LogStreamCodec {
         let inner = LengthDelimitedCodec::builder()
             .big_endian()
             .length_field_type::<u32>()
+            .max_frame_length(MAX_RECORD_SIZE)
             .new_codec();
         Self { inner }
     }
@@ -140,3 +142,60 @@ impl codec::Decoder for LogStreamCodec {
             .transpose()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::LogStreamCodec;
+    use bytes::BytesMut;
+    use feo_log::Level;
+    use feo_logger::record::Record;
+    use feo_time::SystemTime;
+    use tokio_util::codec::Decoder;
+
+    #[test]
+    fn test_reassemble_split_records() {
+        let record = |target, args| {
+            Record::new(
+                SystemTime::now(),
+                Level::Info,
+                target,
+                Some("file"),
+                Some(7),
+                1,
+                2,
+                args,
+            )
+        };
+        let mut stream = Vec::new();
+        record("first", b"hello")
+            .encode_framed(&mut stream)
+            .unwrap();
+        record("second", b"world")
+            .encode_framed(&mut stream)
+            .unwrap();
+
+        // Feed the stream in reads splitting the length prefixes and the records
+        let mut codec = LogStreamCodec::default();
+        let mut buffer = BytesMut::new();
+        let mut decoded = Vec::new();
+        let mut remaining = &stream[..];
+        for len in [1, 5, 3, 11].into_iter().cycle() {
+            if remaining.is_empty() {
+                break;
+            }
+            let (chunk, rest) = remaining.split_at(len.min(remaining.len()));
+            remaining = rest;
+            buffer.extend_from_slice(chunk);
+            while let Some(record) = codec.decode(&mut buffer).unwrap() {
+                decoded.push(record);
+            }
+        }
+        assert!(buffer.is_empty());
+
+        let decoded: Vec<_> = decoded
+            .iter()
+            .map(|r| (r.target.as_str(), r.args.as_str()))
+            .collect();
+        assert_eq!(decoded, [("first", "hello"), ("second", "world")]);
+    }
+}
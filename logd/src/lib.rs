@@ -5,27 +5,66 @@
 //! Central trace collector
 
 use anyhow::{bail, Error};
-use feo_logger::fmt::format_owned;
 use feo_logger::record::OwnedRecord;
+use reorder::Reorder;
+use sink::{Alignment, FileSink, Sink};
+use std::time::{Duration, Instant};
+use tokio::select;
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
+use tokio::time::sleep_until;
 
 mod input;
+mod reorder;
+pub mod sink;
 
 pub const MAX_RECORD_SIZE: usize = feo_logger::MAX_RECORD_SIZE;
 const RECORD_CHANNEL_SIZE: usize = 100;
 pub const UNIX_PACKET_PATH: &str = "/tmp/logd.sock";
 pub const UNIX_STREAM_PATH: &str = "/tmp/logd.stream.sock";
+/// Default time window in which records are sorted by their timestamp
+pub const DEFAULT_REORDER_WINDOW: Duration = Duration::from_millis(100);
 
 type RecordSender = mpsc::Sender<OwnedRecord>;
 type RecordReceiver = mpsc::Receiver<OwnedRecord>;
 
+/// Options of logd
+#[derive(Debug)]
+pub struct Options {
+    /// Write records to a file instead of stdout
+    pub file: Option<sink::FileConfig>,
+    /// Time window in which records are sorted by their timestamp
+    pub reorder_window: Duration,
+    /// Fixed width of the target column. Adapts to the longest target received if not given.
+    pub target_width: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            file: None,
+            reorder_window: DEFAULT_REORDER_WINDOW,
+            target_width: None,
+        }
+    }
+}
+
 /// Start tasks for each input source. Start a task that processes records.
-pub async fn run() -> Result<(), Error> {
+pub async fn run(options: Options) -> Result<(), Error> {
+    let sink = match options.file {
+        Some(config) => Sink::File(FileSink::new(config)?),
+        None => Sink::Stdout,
+    };
+
     let (record_sender, record_receiver) = mpsc::channel(RECORD_CHANNEL_SIZE);
     let mut tasks = JoinSet::new();
 
-    tasks.spawn(process_records(record_receiver));
+    tasks.spawn(process_records(
+        record_receiver,
+        sink,
+        options.reorder_window,
+        options.target_width,
+    ));
     tasks.spawn(input::stream(record_sender.clone()));
     tasks.spawn(input::packet(record_sender));
 
@@ -36,10 +75,40 @@ pub async fn run() -> Result<(), Error> {
     }
 }
 
-/// Process records. Placeholder - just print to stdout.
-async fn process_records(mut record_receiver: RecordReceiver) -> Result<(), Error> {
-    while let Some(record) = record_receiver.recv().await {
-        format_owned(record, std::io::stdout())?;
+/// Process records until all senders are gone. Records are written in timestamp order
+/// within `reorder_window`. The columns are aligned over the records of all processes.
+async fn process_records(
+    mut record_receiver: RecordReceiver,
+    mut sink: Sink,
+    reorder_window: Duration,
+    target_width: Option<usize>,
+) -> Result<(), Error> {
+    let mut reorder = Reorder::new(reorder_window);
+    let mut alignment = Alignment::new(target_width);
+
+    loop {
+        // Write the records held back long enough
+        while let Some(record) = reorder.pop(Instant::now()) {
+            sink.write(record, alignment.widths())?;
+        }
+
+        let expiry = reorder.next_expiry();
+        let record = select! {
+            record = record_receiver.recv() => record,
+            _ = sleep_until(expiry.unwrap_or_else(Instant::now).into()), if expiry.is_some() => continue,
+        };
+        match record {
+            Some(record) => {
+                alignment.fit(&record);
+                reorder.push(record, Instant::now());
+            }
+            None => break,
+        }
+    }
+
+    // Write whatever is left
+    while let Some(record) = reorder.pop_any() {
+        sink.write(record, alignment.widths())?;
     }
-    unreachable!("record receiver closed");
+    Ok(())
 }
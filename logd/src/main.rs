@@ -5,19 +5,81 @@
 //! Placeholder logging daemon that collects logs from various sources. Minimal effort implementation.
 
 use anyhow::Error;
+use argh::FromArgs;
 use feo_log::{info, LevelFilter};
+use logd::sink::{FileConfig, FileFormat};
+use logd::Options;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::runtime;
 
+#[derive(FromArgs)]
+#[argh(help_triggers("-h", "--help", "help"))]
+/// Logd arguments
+struct Args {
+    #[argh(description = "write records to this file instead of stdout")]
+    #[argh(option, short = 'o')]
+    out: Option<PathBuf>,
+
+    #[argh(description = "file format: fmt (default) or raw")]
+    #[argh(option, short = 'f', default = "FileFormat::Fmt")]
+    format: FileFormat,
+
+    #[argh(description = "rotate the file once it exceeds this size in bytes")]
+    #[argh(option)]
+    max_size: Option<u64>,
+
+    #[argh(description = "rotate the file once it is older than this many seconds")]
+    #[argh(option)]
+    max_age: Option<u64>,
+
+    #[argh(description = "number of rotated files to keep")]
+    #[argh(option, default = "1")]
+    keep: usize,
+
+    #[argh(description = "time window in milliseconds in which records are sorted by timestamp")]
+    #[argh(option, default = "logd::DEFAULT_REORDER_WINDOW.as_millis() as u64")]
+    reorder_window_ms: u64,
+
+    #[argh(
+        description = "fixed width of the target column, adapts to the longest target if not given"
+    )]
+    #[argh(option)]
+    target_width: Option<usize>,
+}
+
 fn main() -> Result<(), Error> {
+    let Args {
+        out,
+        format,
+        max_size,
+        max_age,
+        keep,
+        reorder_window_ms,
+        target_width,
+    } = argh::from_env();
+
     // Initialize the logger *without* the logd part logger.
     feo_logger::init(LevelFilter::Debug, true, false);
 
     info!("Starting logd");
 
-    let logd = logd::run();
+    let file = out.map(|path| FileConfig {
+        format,
+        max_size,
+        max_age: max_age.map(Duration::from_secs),
+        keep,
+        ..FileConfig::new(path)
+    });
+    let logd = logd::run(Options {
+        file,
+        reorder_window: Duration::from_millis(reorder_window_ms),
+        target_width,
+    });
 
     runtime::Builder::new_current_thread()
         .enable_io()
+        .enable_time()
         .build()?
         .block_on(logd)
 }
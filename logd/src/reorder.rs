@@ -0,0 +1,164 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reorder records from multiple processes by their timestamp
+//!
+//! Records of different processes arrive interleaved and slightly out of order. Each record
+//! is held back for a time window after its arrival. Records are emitted in timestamp order
+//! once held back for the window, so a stalled source never blocks the output.
+
+use feo_logger::record::OwnedRecord;
+use feo_time::SystemTime;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// Maximum number of records held back. The oldest record is emitted beyond that.
+const MAX_RECORDS: usize = 4096;
+
+/// Record held back in the reorder buffer
+#[derive(Debug)]
+struct Entry {
+    /// Timestamp of the record
+    timestamp: SystemTime,
+    /// Arrival counter to keep records with equal timestamps in arrival order
+    seq: u64,
+    /// Point in time the record may be emitted
+    expiry: Instant,
+    record: OwnedRecord,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.timestamp, self.seq).cmp(&(other.timestamp, other.seq))
+    }
+}
+
+/// Buffer sorting records by timestamp within a time window
+#[derive(Debug)]
+pub(crate) struct Reorder {
+    window: Duration,
+    records: BinaryHeap<Reverse<Entry>>,
+    seq: u64,
+}
+
+impl Reorder {
+    /// Create a buffer holding back records for `window`
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            records: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    /// Add a record arrived at `now`
+    pub(crate) fn push(&mut self, record: OwnedRecord, now: Instant) {
+        self.seq += 1;
+        self.records.push(Reverse(Entry {
+            timestamp: record.timestamp,
+            seq: self.seq,
+            expiry: now + self.window,
+            record,
+        }));
+    }
+
+    /// Take the record with the earliest timestamp if it has been held back long enough
+    /// at `now` or the buffer is full
+    pub(crate) fn pop(&mut self, now: Instant) -> Option<OwnedRecord> {
+        let Reverse(next) = self.records.peek()?;
+        if next.expiry <= now || self.records.len() > MAX_RECORDS {
+            self.records.pop().map(|Reverse(entry)| entry.record)
+        } else {
+            None
+        }
+    }
+
+    /// Take the record with the earliest timestamp regardless of its expiry
+    pub(crate) fn pop_any(&mut self) -> Option<OwnedRecord> {
+        self.records.pop().map(|Reverse(entry)| entry.record)
+    }
+
+    /// Point in time the record with the earliest timestamp may be emitted
+    pub(crate) fn next_expiry(&self) -> Option<Instant> {
+        self.records.peek().map(|Reverse(entry)| entry.expiry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Reorder;
+    use feo_log::Level;
+    use feo_logger::record::OwnedRecord;
+    use feo_time::SystemTime;
+    use std::time::{Duration, Instant};
+
+    fn record(secs: u64, tgid: u32) -> OwnedRecord {
+        OwnedRecord {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            level: Level::Info,
+            target: "test".into(),
+            file: None,
+            line: None,
+            tgid,
+            tid: tgid,
+            args: format!("{secs}"),
+        }
+    }
+
+    #[test]
+    fn test_reorder_within_window() {
+        let window = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut reorder = Reorder::new(window);
+
+        // Two processes delivering interleaved records
+        for (n, (secs, tgid)) in [(3, 1), (1, 2), (4, 1), (2, 2), (5, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            reorder.push(record(secs, tgid), start + Duration::from_millis(n as u64));
+        }
+
+        // Nothing is emitted before the window expired
+        assert!(reorder.pop(start + Duration::from_millis(50)).is_none());
+        assert_eq!(
+            reorder.next_expiry(),
+            Some(start + Duration::from_millis(101))
+        );
+
+        // All records are emitted in timestamp order after the window
+        let now = start + window + Duration::from_millis(10);
+        let emitted: Vec<String> = std::iter::from_fn(|| reorder.pop(now))
+            .map(|record| record.args)
+            .collect();
+        assert_eq!(emitted, ["1", "2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn test_stalled_source_does_not_block() {
+        let window = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut reorder = Reorder::new(window);
+
+        reorder.push(record(2, 1), start);
+        // The record is emitted once the window expired, even if no other record arrives
+        assert!(reorder.pop(start + window).is_some());
+        assert!(reorder.pop_any().is_none());
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright 2025 Accenture.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Output of the collected records
+
+use feo_log::info;
+use feo_logger::fmt::{format_owned_with, Widths};
+use feo_logger::record::{OwnedRecord, Record};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Format of the records written to a file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Human readable format as printed to the console
+    #[default]
+    Fmt,
+    /// Encoded records, each prefixed with its length as a big endian u32. This is the format
+    /// of the stream input and allows re-processing the records later.
+    Raw,
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fmt" => Ok(Self::Fmt),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("invalid format {s}, expected fmt or raw")),
+        }
+    }
+}
+
+/// Configuration of a file sink
+#[derive(Debug, Clone)]
+pub struct FileConfig {
+    /// Path of the current log file. Rotated files get the suffix `.1`, `.2`, ...
+    pub path: PathBuf,
+    /// Format of the records
+    pub format: FileFormat,
+    /// Rotate once the file exceeds this size in bytes
+    pub max_size: Option<u64>,
+    /// Rotate once the file is older than this
+    pub max_age: Option<Duration>,
+    /// Number of rotated files to keep
+    pub keep: usize,
+}
+
+impl FileConfig {
+    /// Create a configuration writing formatted records to `path` without rotation
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: FileFormat::default(),
+            max_size: None,
+            max_age: None,
+            keep: 1,
+        }
+    }
+}
+
+/// Alignment of the columns of formatted records over the merged stream of all processes
+#[derive(Debug)]
+pub(crate) struct Alignment {
+    /// Fixed width of the target, if any
+    target_width: Option<usize>,
+    widths: Widths,
+}
+
+impl Alignment {
+    /// Create an alignment with a fixed target width or adapting to the longest target
+    pub(crate) fn new(target_width: Option<usize>) -> Self {
+        let widths = Widths {
+            target: target_width.unwrap_or_default(),
+            ..Widths::default()
+        };
+        Self {
+            target_width,
+            widths,
+        }
+    }
+
+    /// Grow the columns to fit the given record. A fixed target width is kept.
+    pub(crate) fn fit(&mut self, record: &OwnedRecord) {
+        self.widths.fit(&record.target, record.tgid, record.tid);
+        if let Some(target_width) = self.target_width {
+            self.widths.target = target_width;
+        }
+    }
+
+    /// Current column widths
+    pub(crate) fn widths(&self) -> &Widths {
+        &self.widths
+    }
+}
+
+/// Destination of the collected records
+pub(crate) enum Sink {
+    /// Print formatted records to stdout
+    Stdout,
+    /// Write records to a rotated file
+    File(FileSink),
+}
+
+impl Sink {
+    /// Write a record with formatted columns of the given widths
+    pub(crate) fn write(&mut self, record: OwnedRecord, widths: &Widths) -> io::Result<()> {
+        match self {
+            Sink::Stdout => format_owned_with(record, widths, io::stdout()),
+            Sink::File(file) => file.write(record, widths),
+        }
+    }
+}
+
+/// File sink with size and time based rotation
+pub(crate) struct FileSink {
+    config: FileConfig,
+    file: BufWriter<File>,
+    /// Bytes written to the current file
+    written: u64,
+    /// Point in time the current file has been opened
+    opened: Instant,
+}
+
+impl FileSink {
+    /// Create a sink. An existing file at the configured path is rotated.
+    pub(crate) fn new(config: FileConfig) -> io::Result<Self> {
+        if config.path.exists() {
+            rotate(&config.path, config.keep)?;
+        }
+        let file = create(&config.path)?;
+        Ok(Self {
+            config,
+            file,
+            written: 0,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Write a record and rotate the file if needed
+    fn write(&mut self, record: OwnedRecord, widths: &Widths) -> io::Result<()> {
+        let expired = self
+            .config
+            .max_age
+            .is_some_and(|max_age| self.opened.elapsed() >= max_age);
+        if expired && self.written > 0 {
+            self.rotate()?;
+        }
+
+        let mut buf = Vec::new();
+        match self.config.format {
+            FileFormat::Fmt => format_owned_with(record, widths, &mut buf)?,
+            FileFormat::Raw => {
+                let record = Record::new(
+                    record.timestamp,
+                    record.level,
+                    &record.target,
+                    record.file.as_deref(),
+                    record.line,
+                    record.tgid,
+                    record.tid,
+                    record.args.as_bytes(),
+                );
+                record.encode_framed(&mut buf)?;
+            }
+        }
+        self.file.write_all(&buf)?;
+        self.file.flush()?;
+        self.written += buf.len() as u64;
+
+        if self
+            .config
+            .max_size
+            .is_some_and(|max_size| self.written >= max_size)
+        {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Rotate the current file and open a new one
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        rotate(&self.config.path, self.config.keep)?;
+        self.file = create(&self.config.path)?;
+        self.written = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+/// Create a new log file
+fn create(path: &Path) -> io::Result<BufWriter<File>> {
+    info!("Writing records to {}", path.display());
+    File::create(path).map(BufWriter::new)
+}
+
+/// Shift `path.1` .. `path.{keep - 1}` by one, move `path` to `path.1` and drop the oldest file
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    for n in (1..keep).rev() {
+        let from = rotated(path, n);
+        if from.exists() {
+            fs::rename(from, rotated(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(path, 1))
+}
+
+/// Path of the `n`th rotated file
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{n}"));
+    path.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rotated, FileConfig, FileFormat, FileSink, Sink};
+    use crate::process_records;
+    use feo_log::Level;
+    use feo_logger::record::OwnedRecord;
+    use feo_time::SystemTime;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::runtime;
+    use tokio::sync::mpsc;
+
+    fn record(args: &str) -> OwnedRecord {
+        record_with_target("test", args)
+    }
+
+    fn record_with_target(target: &str, args: &str) -> OwnedRecord {
+        OwnedRecord {
+            timestamp: SystemTime::now(),
+            level: Level::Info,
+            target: target.into(),
+            file: None,
+            line: None,
+            tgid: 1,
+            tid: 2,
+            args: args.into(),
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("logd-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_and_rotate() {
+        let dir = test_dir("rotate");
+        let path = dir.join("logd.log");
+        let config = FileConfig {
+            format: FileFormat::Raw,
+            max_size: Some(100),
+            keep: 1,
+            ..FileConfig::new(&path)
+        };
+        let sink = Sink::File(FileSink::new(config).unwrap());
+
+        let (sender, receiver) = mpsc::channel(10);
+        let run = async move {
+            for n in 0..5 {
+                // Each encoded record takes 55 bytes
+                sender.send(record(&format!("{n:010}"))).await.unwrap();
+            }
+            drop(sender);
+            process_records(receiver, sink, Duration::ZERO, None).await
+        };
+        runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(run)
+            .unwrap();
+
+        // Rotated after the second and fourth record, only one rotated file is kept
+        let sizes: Vec<u64> = [path.clone(), rotated(&path, 1)]
+            .iter()
+            .map(|p| fs::metadata(p).unwrap().len())
+            .collect();
+        assert_eq!(sizes, [55, 110]);
+        assert!(!rotated(&path, 2).exists());
+
+        let current = fs::read(&path).unwrap();
+        let decoded = OwnedRecord::decode(&current[4..]).unwrap();
+        assert_eq!(decoded.args, "0000000004");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Write records of varying target lengths as formatted file and return the column of the
+    /// process id in each line
+    fn pid_columns(name: &str, target_width: Option<usize>, targets: &[&str]) -> Vec<usize> {
+        let dir = test_dir(name);
+        let path = dir.join("logd.log");
+        let sink = Sink::File(FileSink::new(FileConfig::new(&path)).unwrap());
+
+        let (sender, receiver) = mpsc::channel(10);
+        let records: Vec<_> = targets
+            .iter()
+            .map(|target| record_with_target(target, "message"))
+            .collect();
+        let run = async move {
+            for record in records {
+                sender.send(record).await.unwrap();
+            }
+            drop(sender);
+            process_records(receiver, sink, Duration::from_secs(1), target_width).await
+        };
+        runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(run)
+            .unwrap();
+
+        let formatted = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        formatted
+            .lines()
+            .map(|line| line.find('(').unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_aligned_targets() {
+        let targets = ["a", "feo::agent::primary", "logd", "feo::com"];
+
+        // Records within the reorder window are aligned to the longest target among them
+        let columns = pid_columns("align-adaptive", None, &targets);
+        assert_eq!(columns.len(), targets.len());
+        assert!(columns.iter().all(|c| *c == columns[0]));
+
+        // A fixed width aligns all targets up to that width
+        let columns = pid_columns("align-fixed", Some(24), &targets);
+        assert!(columns.iter().all(|c| *c == columns[0]));
+        let widest = pid_columns("align-fixed-widest", Some(24), &["x".repeat(24).as_str()]);
+        assert_eq!(columns[0], widest[0]);
+    }
+}